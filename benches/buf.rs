@@ -0,0 +1,36 @@
+//! Measures the per-write cost of recycling buffers, which depends on
+//! how buffers get reference counted.
+//!
+//! Run with `cargo bench --bench buf`, and again with
+//! `--features triomphe` to compare the two.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use simple_triple_buffer::new_pod;
+
+const ITERS: u32 = 2_000_000;
+
+fn measure<const N: usize>() {
+    let (mut w, mut r) = new_pod([0u8; N]);
+    let start = Instant::now();
+    for i in 0..ITERS {
+        w.write_update(|s| s[0] = i as u8);
+        if i % 2 == 0 {
+            black_box(r.read_newest());
+        }
+    }
+    let per_write = start.elapsed().as_nanos() as f64 / ITERS as f64;
+    println!("{:>6} bytes: {:>8.1} ns per write", N, per_write);
+}
+
+fn main() {
+    if cfg!(feature = "triomphe") {
+        println!("buffers: triomphe::Arc");
+    } else {
+        println!("buffers: std::sync::Arc");
+    }
+    measure::<8>();
+    measure::<64>();
+    measure::<1024>();
+}
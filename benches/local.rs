@@ -0,0 +1,31 @@
+//! Compares the single threaded `local` pair against `new_clone`.
+//!
+//! Run with `cargo bench --bench local`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use simple_triple_buffer::{local, new_clone};
+
+const ITERS: u32 = 1_000_000;
+
+fn main() {
+    let (mut w, mut r) = new_clone(0u64);
+    let start = Instant::now();
+    for i in 0..ITERS {
+        w.write_new(|_, new| *new = i as u64);
+        black_box(r.read_newest());
+    }
+    let threaded = start.elapsed().as_nanos() as f64 / ITERS as f64;
+
+    let (mut w, mut r) = local::new_clone(0u64);
+    let start = Instant::now();
+    for i in 0..ITERS {
+        w.write_new(|_, new| *new = i as u64);
+        black_box(r.read_newest());
+    }
+    let local = start.elapsed().as_nanos() as f64 / ITERS as f64;
+
+    println!("new_clone: {:>6.1} ns/iteration", threaded);
+    println!("    local: {:>6.1} ns/iteration", local);
+}
@@ -0,0 +1,55 @@
+//! Compares `persistent::new` against `new_clone` for a map of 100k
+//! entries, of which every publish changes a few.
+//!
+//! Run with `cargo bench --features im --bench persistent`.
+
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::Instant;
+
+use simple_triple_buffer::{new_clone, persistent};
+
+const ENTRIES: u64 = 100_000;
+const ITERS: u64 = 2_000;
+
+/// Publish `ITERS` states changing `delta` entries each, picking up
+/// every one, and return the time per publish in microseconds.
+fn std_map(delta: u64) -> f64 {
+    let (mut w, mut r) = new_clone((0..ENTRIES).map(|i| (i, i)).collect::<HashMap<_, _>>());
+    let start = Instant::now();
+    for i in 0..ITERS {
+        w.write_update(|map| {
+            for j in 0..delta {
+                map.insert((i * delta + j) % ENTRIES, i);
+            }
+        });
+        black_box(r.read_newest().get(&i));
+    }
+    start.elapsed().as_secs_f64() * 1e6 / ITERS as f64
+}
+
+fn im_map(delta: u64) -> f64 {
+    let (mut w, mut r) =
+        persistent::new((0..ENTRIES).map(|i| (i, i)).collect::<im::HashMap<_, _>>());
+    let start = Instant::now();
+    for i in 0..ITERS {
+        w.update_persistent(|map| {
+            for j in 0..delta {
+                map.insert((i * delta + j) % ENTRIES, i);
+            }
+        });
+        black_box(r.read_newest().get(&i));
+    }
+    start.elapsed().as_secs_f64() * 1e6 / ITERS as f64
+}
+
+fn main() {
+    for delta in [1, 10, 100, 1000] {
+        println!(
+            "{:>5} changes per publish: new_clone {:>8.1} us, persistent {:>8.1} us",
+            delta,
+            std_map(delta),
+            im_map(delta)
+        );
+    }
+}
@@ -0,0 +1,54 @@
+//! Compares buffer creation and state syncing of `new_pod`
+//! against `new_clone` for a multi-kilobyte `Copy` state.
+//!
+//! Run with `cargo bench --bench pod`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use simple_triple_buffer::{new_clone, new_pod, Reader, Writer};
+
+type Bones = [[f32; 16]; 256];
+
+const ITERS: u32 = 20_000;
+
+fn run(name: &str, (mut w, mut r): (Writer<Bones>, Reader<Bones>)) -> Duration {
+    let start = Instant::now();
+    for i in 0..ITERS {
+        w.write_update(|bones| bones[i as usize % 256][0] = i as f32);
+        if i % 3 == 0 {
+            black_box(r.read_newest());
+        }
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "{:>10}: {:>8.1} ns/write",
+        name,
+        elapsed.as_nanos() as f64 / ITERS as f64
+    );
+    elapsed
+}
+
+fn fresh_pairs(name: &str, make: fn() -> (Writer<Bones>, Reader<Bones>)) {
+    let start = Instant::now();
+    for _ in 0..ITERS / 10 {
+        let (mut w, mut r) = make();
+        w.write_new(|old, new| *new = *old);
+        w.write_new(|old, new| *new = *old);
+        black_box(r.read_newest());
+    }
+    println!(
+        "{:>10}: {:>8.1} ns/pair setup",
+        name,
+        start.elapsed().as_nanos() as f64 / (ITERS / 10) as f64
+    );
+}
+
+fn main() {
+    let init = [[0.0; 16]; 256];
+    run("new_clone", new_clone(init));
+    run("new_pod", new_pod(init));
+
+    fresh_pairs("new_clone", || new_clone([[0.0; 16]; 256]));
+    fresh_pairs("new_pod", || new_pod([[0.0; 16]; 256]));
+}
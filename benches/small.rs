@@ -0,0 +1,43 @@
+//! Compares the `small` sequence lock pair against `new_pod`
+//! for `Copy` states of different sizes.
+//!
+//! Run with `cargo bench --bench small`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use simple_triple_buffer::{new_pod, small};
+
+const ITERS: u32 = 200_000;
+
+fn compare<const N: usize>() {
+    let (mut w, mut r) = new_pod([0u8; N]);
+    let start = Instant::now();
+    for i in 0..ITERS {
+        w.write_update(|s| s[0] = i as u8);
+        black_box(r.read_newest());
+    }
+    let pod = start.elapsed().as_nanos() as f64 / ITERS as f64;
+
+    let (mut w, mut r) = small::new([0u8; N]);
+    let start = Instant::now();
+    for i in 0..ITERS {
+        w.write_update(|s| s[0] = i as u8);
+        black_box(r.read_newest());
+    }
+    let seqlock = start.elapsed().as_nanos() as f64 / ITERS as f64;
+
+    println!(
+        "{:>6} bytes: new_pod {:>8.1} ns, small {:>8.1} ns",
+        N, pod, seqlock
+    );
+}
+
+fn main() {
+    compare::<16>();
+    compare::<64>();
+    compare::<256>();
+    compare::<1024>();
+    compare::<4096>();
+    compare::<16384>();
+}
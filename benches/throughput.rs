@@ -0,0 +1,61 @@
+//! Measures writes and reads per second with the writer and the
+//! readers spinning on separate threads, which is where writer and
+//! reader state sharing cache lines shows up.
+//!
+//! Run with `cargo bench --bench throughput`. The numbers only mean
+//! something on a machine with a core for every thread.
+
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use simple_triple_buffer::new_pod;
+
+const DURATION: Duration = Duration::from_secs(1);
+
+fn measure(readers: usize) {
+    let (mut w, r) = new_pod([0u64; 8]);
+    let stop = Arc::new(AtomicBool::new(false));
+    let threads: Vec<_> = (0..readers)
+        .map(|_| {
+            let mut r = r.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let mut reads = 0u64;
+                while !stop.load(Ordering::Relaxed) {
+                    black_box(r.read_newest());
+                    reads += 1;
+                }
+                reads
+            })
+        })
+        .collect();
+    drop(r);
+
+    let start = Instant::now();
+    let mut writes = 0u64;
+    while start.elapsed() < DURATION {
+        for _ in 0..1000 {
+            w.write_update(|s| s[0] += 1);
+        }
+        writes += 1000;
+    }
+    stop.store(true, Ordering::Relaxed);
+    let reads: u64 = threads.into_iter().map(|t| t.join().unwrap()).sum();
+    let secs = start.elapsed().as_secs_f64();
+
+    println!(
+        "{} reader(s): {:>6.2} M writes/s, {:>6.2} M reads/s",
+        readers,
+        writes as f64 / secs / 1e6,
+        reads as f64 / secs / 1e6
+    );
+}
+
+fn main() {
+    for readers in [1, 2, 4] {
+        measure(readers);
+    }
+}
@@ -0,0 +1,38 @@
+//! Measures a tight `write_new` loop like the one in
+//! `examples/counter.rs`, where publishing dominates the cost.
+//!
+//! Run with `cargo bench --bench write_new`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use simple_triple_buffer::new_clone;
+
+const ITERS: u64 = 5_000_000;
+
+#[derive(Clone)]
+struct State {
+    v: u64,
+}
+
+/// Publish `ITERS` states, picking up every `read_every`th one.
+fn measure(read_every: u64) {
+    let (mut w, mut r) = new_clone(State { v: 0 });
+    let start = Instant::now();
+    for i in 0..ITERS {
+        w.write_new(|last, new| new.v = last.v + 1);
+        if i % read_every == 0 {
+            black_box(r.read_newest().v);
+        }
+    }
+    let per_write = start.elapsed().as_nanos() as f64 / ITERS as f64;
+    println!(
+        "read every {:>5}: {:>6.1} ns per write",
+        read_every, per_write
+    );
+}
+
+fn main() {
+    measure(1);
+    measure(1000);
+}
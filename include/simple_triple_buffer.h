@@ -0,0 +1,115 @@
+#ifndef SIMPLE_TRIPLE_BUFFER_H
+#define SIMPLE_TRIPLE_BUFFER_H
+
+/* Generated with cbindgen, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The call succeeded.
+ */
+#define STB_OK 0
+
+/**
+ * A required handle or pointer was null.
+ */
+#define STB_ERR_NULL -1
+
+/**
+ * The output buffer is too small for the state, see `stb_read`.
+ */
+#define STB_ERR_TOO_SMALL -2
+
+/**
+ * The library panicked, which is a bug in it.
+ */
+#define STB_ERR_PANIC -3
+
+/**
+ * The read side of a pair of byte buffers.
+ */
+typedef struct stb_reader_t stb_reader_t;
+
+/**
+ * The write side of a pair of byte buffers.
+ */
+typedef struct stb_writer_t stb_writer_t;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Create a new pair, whose initial state is `len` zero bytes
+ * with version 0, and store its halves in `writer` and `reader`.
+ *
+ * # Safety
+ * `writer` and `reader` have to be null, or valid for writes.
+ */
+int stb_new(size_t len, stb_writer_t **writer, stb_reader_t **reader);
+
+/**
+ * Publish the `len` bytes at `data` as the new state.
+ *
+ * `data` may be null if `len` is 0.
+ *
+ * # Safety
+ * `writer` has to be null, or a writer from `stb_new` that was not
+ * destroyed yet, used by one thread at a time. `data` has to be null,
+ * or valid for reading `len` bytes.
+ */
+int stb_write(stb_writer_t *writer, const uint8_t *data, size_t len);
+
+/**
+ * Copy the newest state into the `cap` bytes at `out`.
+ *
+ * Stores the length of the state in `written`, and its version in
+ * `version`, unless that is null. If the state is longer than `cap`,
+ * nothing gets copied, `written` is set to the length needed, and
+ * `STB_ERR_TOO_SMALL` returned. The state counts as read either way.
+ *
+ * # Safety
+ * `reader` has to be null, or a reader from `stb_new` that was not
+ * destroyed yet, used by one thread at a time. `out` has to be null,
+ * or valid for writing `cap` bytes. `written` and `version` have to
+ * be null, or valid for writes.
+ */
+int stb_read(stb_reader_t *reader, uint8_t *out, size_t cap, size_t *written, uint64_t *version);
+
+/**
+ * Check whether a state was published that `stb_read` did not return
+ * yet. Returns 1 if so, 0 if not, or a negative status code.
+ *
+ * # Safety
+ * `reader` has to be null, or a reader from `stb_new`
+ * that was not destroyed yet.
+ */
+int stb_has_update(const stb_reader_t *reader);
+
+/**
+ * Destroy a writer. Does nothing if `writer` is null.
+ *
+ * # Safety
+ * `writer` has to be null, or a writer from `stb_new`
+ * that was not destroyed yet.
+ */
+int stb_writer_free(stb_writer_t *writer);
+
+/**
+ * Destroy a reader. Does nothing if `reader` is null.
+ *
+ * # Safety
+ * `reader` has to be null, or a reader from `stb_new`
+ * that was not destroyed yet.
+ */
+int stb_reader_free(stb_reader_t *reader);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SIMPLE_TRIPLE_BUFFER_H */
@@ -0,0 +1,185 @@
+use alloc::alloc::{alloc_zeroed, dealloc, handle_alloc_error, Layout};
+use core::ops::{Deref, DerefMut};
+use core::ptr::NonNull;
+use core::{fmt, slice};
+
+use crate::{new_with, Reader, Writer};
+
+/// Create a new buffer pair of `len` zeroed bytes, whose buffers
+/// all start at an address that is a multiple of `align`.
+///
+/// This includes every buffer the pair creates later on,
+/// as they are clones of an existing one.
+///
+/// # Panics
+/// Panics if `align` is not a power of two, or if `len`
+/// rounded up to it overflows `isize`.
+///
+/// # Example
+/// ```
+/// let (mut writer, mut reader) = simple_triple_buffer::new_aligned(256, 64);
+/// writer.write_new(|_, new| new.fill(1));
+///
+/// let state = reader.read_newest();
+/// assert_eq!(state.as_ptr() as usize % 64, 0);
+/// assert_eq!(state.len(), 256);
+/// ````
+pub fn new_aligned(len: usize, align: usize) -> (Writer<AlignedBytes>, Reader<AlignedBytes>) {
+    new_with(AlignedBytes::new(len, align), AlignedBytes::clone)
+}
+
+/// A heap allocated byte slice with a custom alignment.
+///
+/// Clones have the same alignment.
+pub struct AlignedBytes {
+    ptr: NonNull<u8>,
+    layout: Layout,
+}
+
+// SAFETY: `AlignedBytes` owns its allocation, like a `Box<[u8]>`.
+unsafe impl Send for AlignedBytes {}
+// SAFETY: Shared access only hands out `&[u8]`.
+unsafe impl Sync for AlignedBytes {}
+
+impl AlignedBytes {
+    /// Allocate `len` zeroed bytes, aligned to `align`.
+    ///
+    /// # Panics
+    /// Panics if `align` is not a power of two, or if `len`
+    /// rounded up to it overflows `isize`.
+    pub fn new(len: usize, align: usize) -> Self {
+        let layout = match Layout::from_size_align(len, align) {
+            Ok(layout) => layout,
+            Err(_) => panic!("invalid layout of {} bytes aligned to {}", len, align),
+        };
+        Self {
+            ptr: Self::allocate(layout),
+            layout,
+        }
+    }
+
+    fn allocate(layout: Layout) -> NonNull<u8> {
+        if layout.size() == 0 {
+            // A dangling pointer at the alignment itself is well aligned.
+            return NonNull::new(layout.align() as *mut u8).unwrap();
+        }
+        // SAFETY: The layout has a non-zero size.
+        let ptr = unsafe { alloc_zeroed(layout) };
+        match NonNull::new(ptr) {
+            Some(ptr) => ptr,
+            None => handle_alloc_error(layout),
+        }
+    }
+
+    /// Get the alignment of the bytes.
+    pub fn align(&self) -> usize {
+        self.layout.align()
+    }
+}
+
+impl Deref for AlignedBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        // SAFETY: `ptr` points to `layout.size()` initialized bytes.
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.layout.size()) }
+    }
+}
+
+impl DerefMut for AlignedBytes {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // SAFETY: `ptr` points to `layout.size()` initialized bytes
+        // that are owned by `self`.
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.layout.size()) }
+    }
+}
+
+impl Clone for AlignedBytes {
+    fn clone(&self) -> Self {
+        let mut clone = Self {
+            ptr: Self::allocate(self.layout),
+            layout: self.layout,
+        };
+        clone.copy_from_slice(self);
+        clone
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        if self.layout == source.layout {
+            self.copy_from_slice(source);
+        } else {
+            *self = source.clone();
+        }
+    }
+}
+
+impl Drop for AlignedBytes {
+    fn drop(&mut self) {
+        if self.layout.size() > 0 {
+            // SAFETY: `ptr` was allocated with `layout`.
+            unsafe { dealloc(self.ptr.as_ptr(), self.layout) }
+        }
+    }
+}
+
+impl fmt::Debug for AlignedBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AlignedBytes")
+            .field("align", &self.align())
+            .field("bytes", &&**self)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{new_aligned, AlignedBytes};
+
+    fn is_aligned(bytes: &AlignedBytes, align: usize) -> bool {
+        (bytes.as_ptr() as usize).is_multiple_of(align)
+    }
+
+    #[test]
+    fn test_every_buffer_is_aligned_under_load() {
+        for align in [64, 4096] {
+            let (mut w, mut r) = new_aligned(100, align);
+            let t = std::thread::spawn(move || {
+                for i in 0..10_000u32 {
+                    w.write_new(|old, new| {
+                        assert!(is_aligned(old, align));
+                        assert!(is_aligned(new, align));
+                        new.copy_from_slice(old);
+                        new[0] = i as u8;
+                    });
+                }
+            });
+            while !r.is_disconnected() {
+                let state = r.read_newest();
+                assert!(is_aligned(state, align));
+                assert_eq!(state.align(), align);
+            }
+            t.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_clone_from_keeps_allocation() {
+        let a = AlignedBytes::new(8, 32);
+        let mut b = AlignedBytes::new(8, 32);
+        b[0] = 1;
+        let ptr = b.as_ptr();
+        b.clone_from(&a);
+        assert_eq!(b.as_ptr(), ptr);
+        assert_eq!(*b, [0; 8]);
+
+        let mut c = AlignedBytes::new(0, 1);
+        c.clone_from(&b);
+        assert!(is_aligned(&c, 32));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_invalid_alignment() {
+        AlignedBytes::new(8, 3);
+    }
+}
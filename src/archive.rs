@@ -0,0 +1,136 @@
+use core::mem;
+
+use rkyv::api::high::{HighDeserializer, HighSerializer};
+use rkyv::rancor;
+use rkyv::ser::allocator::ArenaHandle;
+use rkyv::util::AlignedVec;
+use rkyv::{Archive, Archived, Deserialize, Serialize};
+
+use crate::{Reader, Writer};
+
+impl<T> Reader<T>
+where
+    T: for<'a> Serialize<HighSerializer<AlignedVec, ArenaHandle<'a>, rancor::Error>>,
+{
+    /// Serialize the newest state into `buf` in rkyv's archived format,
+    /// returning the number of bytes written.
+    ///
+    /// The previous contents of `buf` get replaced, but its capacity is
+    /// kept, so exporting every state through the same buffer only
+    /// allocates while the archives keep growing. Consumers can access
+    /// the archive in place with `rkyv::access`, without parsing it.
+    ///
+    /// Only available with the `rkyv` feature.
+    ///
+    /// # Example
+    /// ```
+    /// use rkyv::{rancor, util::AlignedVec, Archive, Serialize};
+    ///
+    /// #[derive(Clone, Archive, Serialize)]
+    /// struct State {
+    ///     names: Vec<String>,
+    /// }
+    ///
+    /// let (mut writer, mut reader) =
+    ///     simple_triple_buffer::new_clone(State { names: Vec::new() });
+    /// writer.write_update(|state| state.names.push("a".into()));
+    ///
+    /// let mut buf = AlignedVec::new();
+    /// let len = reader.archive_newest(&mut buf).unwrap();
+    /// let archived = rkyv::access::<ArchivedState, rancor::Error>(&buf[..len]).unwrap();
+    /// assert_eq!(archived.names[0], "a");
+    /// ````
+    pub fn archive_newest(&mut self, buf: &mut AlignedVec) -> Result<usize, rancor::Error> {
+        let mut bytes = mem::take(buf);
+        bytes.clear();
+        *buf = rkyv::api::high::to_bytes_in(self.read_newest(), bytes)?;
+        Ok(buf.len())
+    }
+}
+
+impl<T: Archive> Writer<T>
+where
+    Archived<T>: Deserialize<T, HighDeserializer<rancor::Error>>,
+{
+    /// Publish the state stored in an rkyv archive.
+    ///
+    /// The deserialized state gets moved into a recycled buffer,
+    /// like with `write_new`, replacing what it held before.
+    ///
+    /// Only available with the `rkyv` feature.
+    ///
+    /// # Example
+    /// ```
+    /// use rkyv::rancor;
+    ///
+    /// let bytes = rkyv::to_bytes::<rancor::Error>(&vec![1u32, 2]).unwrap();
+    /// let archived = rkyv::access::<rkyv::Archived<Vec<u32>>, rancor::Error>(&bytes).unwrap();
+    ///
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(Vec::<u32>::new());
+    /// writer.publish_archived(archived).unwrap();
+    /// assert_eq!(*reader.read_newest(), [1, 2]);
+    /// ````
+    pub fn publish_archived(&mut self, archived: &Archived<T>) -> Result<(), rancor::Error> {
+        let state = rkyv::deserialize::<T, rancor::Error>(archived)?;
+        self.write_new(|_, new| *new = state);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rkyv::util::AlignedVec;
+    use rkyv::{rancor, Archive, Deserialize, Serialize};
+
+    use crate::new_clone;
+
+    #[derive(Debug, Clone, PartialEq, Archive, Serialize, Deserialize)]
+    struct Entity {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Archive, Serialize, Deserialize)]
+    struct World {
+        tick: u64,
+        entities: Vec<Entity>,
+    }
+
+    fn world(tick: u64) -> World {
+        World {
+            tick,
+            entities: (0..tick)
+                .map(|i| Entity {
+                    name: format!("entity {}", i),
+                    tags: vec!["a".into(); i as usize],
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let (mut w, mut r) = new_clone(world(0));
+        let (mut w2, mut r2) = new_clone(world(0));
+        let mut buf = AlignedVec::new();
+        for tick in 1..10 {
+            w.write_new(|_, new| *new = world(tick));
+            let len = r.archive_newest(&mut buf).unwrap();
+            let archived = rkyv::access::<ArchivedWorld, rancor::Error>(&buf[..len]).unwrap();
+            assert_eq!(archived.tick, tick);
+            w2.publish_archived(archived).unwrap();
+            assert_eq!(*r2.read_newest(), world(tick));
+        }
+    }
+
+    #[test]
+    fn test_export_reuses_buffer() {
+        let (mut w, mut r) = new_clone(world(10));
+        let mut buf = AlignedVec::new();
+        let len = r.archive_newest(&mut buf).unwrap();
+        let ptr = buf.as_ptr();
+        w.write_update(|world| world.tick = 5);
+        assert_eq!(r.archive_newest(&mut buf).unwrap(), len);
+        assert_eq!(buf.as_ptr(), ptr);
+    }
+}
@@ -0,0 +1,421 @@
+//! Buffer pairs that can fall back to a single state behind a mutex.
+//!
+//! A triple buffer never blocks, but keeps 2-3 copies of the state
+//! around. For states so large that this is unaffordable, sharing a
+//! single copy behind a mutex trades that memory for blocking instead.
+//! `new_backend` picks between the two at runtime, behind one API,
+//! so the tradeoff can be made per deployment without changing
+//! any call sites.
+//!
+//! With `Backend::SharedMutex`:
+//! - `BackendWriter::write_update` updates the state in place, holding
+//!   the lock for the duration of the closure. This is the intended way
+//!   to write, as it never copies the state. Like the lock, it ignores
+//!   panics, so a panicking closure leaves the state half updated.
+//! - `BackendWriter::write_new` has to give its closure a previous
+//!   state next to the one it writes, so the writer keeps a second
+//!   state around for it, created as a clone on the first call, and
+//!   swaps the two once the closure returns. A panicking closure leaves
+//!   the published state as it was.
+//! - `BackendWriter::swap` and `BackendWriter::feed` move the states
+//!   they get into place, without copying any.
+//! - `BackendReader::read_newest` returns a guard holding the lock,
+//!   which blocks the writer until it gets dropped.
+//!
+//! Both halves have the same methods as `Writer` and `Reader`, returning
+//! the same types, except for the guard, which derefs to the state. So
+//! code written against one of them only has to change the call that
+//! creates the pair, and `BackendWriter` implements `StatePublisher`,
+//! just like `Writer`.
+//!
+//! Only available with the `std` feature.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::backend::{new_backend, Backend};
+//!
+//! for backend in [Backend::TripleBuffer, Backend::SharedMutex] {
+//!     let (mut writer, mut reader) = new_backend(vec![0u8; 16], backend);
+//!     let receipt = writer.write_update(|state| state[0] = 1);
+//!     assert!(reader.has_update());
+//!     assert_eq!(reader.read_newest()[0], 1);
+//!     assert_eq!(reader.version(), receipt.version);
+//! }
+//! ````
+
+use std::mem;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::sync::{Mutex, MutexGuard};
+use crate::{new_clone, PublishReceipt, Reader, StatePublisher, Writer};
+
+/// How a pair created with `new_backend` shares its state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// A regular triple buffer, see `Writer` and `Reader`.
+    TripleBuffer,
+    /// A single state behind a mutex.
+    ///
+    /// The first `write_new` clones the state, which its
+    /// receipt reports as `allocated`, see the module docs.
+    SharedMutex,
+}
+
+/// The state of a pair using `Backend::SharedMutex`.
+struct Shared<T> {
+    state: Mutex<T>,
+    /// Incremented on every write.
+    version: AtomicU64,
+}
+
+impl<T> Shared<T> {
+    /// Count a write, which has to happen while holding the lock.
+    fn published(&self, allocated: bool) -> PublishReceipt {
+        // Readers load the version while holding the lock.
+        let version = self.version.fetch_add(1, Ordering::Release) + 1;
+        PublishReceipt { version, allocated }
+    }
+}
+
+/// Create a new buffer pair using `backend`.
+pub fn new_backend<T: Clone>(init: T, backend: Backend) -> (BackendWriter<T>, BackendReader<T>) {
+    match backend {
+        Backend::TripleBuffer => {
+            let (writer, reader) = new_clone(init);
+            (
+                BackendWriter(WriterInner::TripleBuffer(Box::new(writer))),
+                BackendReader(ReaderInner::TripleBuffer(reader)),
+            )
+        }
+        Backend::SharedMutex => {
+            let shared = Arc::new(Shared {
+                state: Mutex::new(init),
+                version: AtomicU64::new(0),
+            });
+            (
+                BackendWriter(WriterInner::SharedMutex {
+                    shared: shared.clone(),
+                    scratch: None,
+                }),
+                BackendReader(ReaderInner::SharedMutex { shared, seen: 0 }),
+            )
+        }
+    }
+}
+
+/// Write side of a pair created with `new_backend`.
+pub struct BackendWriter<T>(WriterInner<T>);
+
+enum WriterInner<T> {
+    TripleBuffer(Box<Writer<T>>),
+    SharedMutex {
+        shared: Arc<Shared<T>>,
+        /// The state `write_new` writes into, once it got called.
+        scratch: Option<T>,
+    },
+}
+
+impl<T: Clone> BackendWriter<T> {
+    /// Write the next state, see `Writer::write_new`.
+    ///
+    /// With `Backend::SharedMutex`, the closure writes into a second state
+    /// kept by the writer, while the lock is held. The first call creates
+    /// it as a clone, later ones get the state the previous call replaced.
+    pub fn write_new(&mut self, write_op: impl FnOnce(&T, &mut T)) -> PublishReceipt {
+        match &mut self.0 {
+            WriterInner::TripleBuffer(writer) => writer.write_new(write_op),
+            WriterInner::SharedMutex { shared, scratch } => {
+                let mut state = shared.state.lock();
+                let allocated = scratch.is_none();
+                let next = scratch.get_or_insert_with(|| T::clone(&state));
+                write_op(&state, next);
+                mem::swap(&mut *state, next);
+                shared.published(allocated)
+            }
+        }
+    }
+
+    /// Write the next state by updating the previous one,
+    /// see `Writer::write_update`.
+    ///
+    /// With `Backend::SharedMutex`, this updates the state in place,
+    /// holding the lock, and a panicking `update_op` leaves it half updated.
+    pub fn write_update(&mut self, update_op: impl FnOnce(&mut T)) -> PublishReceipt {
+        match &mut self.0 {
+            WriterInner::TripleBuffer(writer) => writer.write_update(update_op),
+            WriterInner::SharedMutex { shared, .. } => {
+                let mut state = shared.state.lock();
+                // Counted first, so readers notice even a half update.
+                let receipt = shared.published(false);
+                update_op(&mut state);
+                receipt
+            }
+        }
+    }
+}
+
+impl<T> BackendWriter<T> {
+    /// Publish `next` as the new state, and get back an older one,
+    /// see `Writer::swap`.
+    ///
+    /// With `Backend::SharedMutex`, this is always the previous state.
+    pub fn swap(&mut self, next: T) -> T {
+        match &mut self.0 {
+            WriterInner::TripleBuffer(writer) => writer.swap(next),
+            WriterInner::SharedMutex { shared, .. } => {
+                let mut state = shared.state.lock();
+                let prev = mem::replace(&mut *state, next);
+                shared.published(false);
+                prev
+            }
+        }
+    }
+
+    /// Publish every state of `states` in order, see `Writer::feed`.
+    pub fn feed(&mut self, states: impl IntoIterator<Item = T>) -> usize {
+        let mut published = 0;
+        for state in states {
+            self.swap(state);
+            published += 1;
+        }
+        published
+    }
+
+    /// Run `f` on the state published last, see `Writer::with_current`.
+    ///
+    /// With `Backend::SharedMutex`, this holds the lock while `f` runs.
+    pub fn with_current<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        match &self.0 {
+            WriterInner::TripleBuffer(writer) => writer.with_current(f),
+            WriterInner::SharedMutex { shared, .. } => f(&shared.state.lock()),
+        }
+    }
+
+    /// Get the version of the last published state, see `Writer::version`.
+    pub fn version(&self) -> u64 {
+        match &self.0 {
+            WriterInner::TripleBuffer(writer) => writer.version(),
+            WriterInner::SharedMutex { shared, .. } => shared.version.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Get the backend of the pair.
+    pub fn backend(&self) -> Backend {
+        match &self.0 {
+            WriterInner::TripleBuffer(_) => Backend::TripleBuffer,
+            WriterInner::SharedMutex { .. } => Backend::SharedMutex,
+        }
+    }
+
+    /// Check whether the `BackendReader` has been dropped.
+    pub fn is_closed(&self) -> bool {
+        match &self.0 {
+            WriterInner::TripleBuffer(writer) => writer.is_closed(),
+            WriterInner::SharedMutex { shared, .. } => Arc::strong_count(shared) == 1,
+        }
+    }
+}
+
+impl<T> Extend<T> for BackendWriter<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, states: I) {
+        self.feed(states);
+    }
+}
+
+impl<T: Clone> StatePublisher<T> for BackendWriter<T> {
+    fn publish_with(&mut self, write_op: &mut dyn FnMut(&T, &mut T)) {
+        self.write_new(write_op);
+    }
+}
+
+/// Read side of a pair created with `new_backend`.
+pub struct BackendReader<T>(ReaderInner<T>);
+
+enum ReaderInner<T> {
+    TripleBuffer(Reader<T>),
+    SharedMutex {
+        shared: Arc<Shared<T>>,
+        /// The version returned by the last `read_newest`.
+        seen: u64,
+    },
+}
+
+/// The state returned by `BackendReader::read_newest`.
+///
+/// With `Backend::SharedMutex`, this holds the lock on the state,
+/// so the writer blocks until it gets dropped.
+pub struct ReadGuard<'a, T>(GuardInner<'a, T>);
+
+enum GuardInner<'a, T> {
+    TripleBuffer(&'a T),
+    SharedMutex(MutexGuard<'a, T>),
+}
+
+impl<T> Deref for ReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        match &self.0 {
+            GuardInner::TripleBuffer(state) => state,
+            GuardInner::SharedMutex(guard) => guard,
+        }
+    }
+}
+
+impl<T> BackendReader<T> {
+    /// Get the newest state, see `Reader::read_newest`.
+    pub fn read_newest(&mut self) -> ReadGuard<'_, T> {
+        match &mut self.0 {
+            ReaderInner::TripleBuffer(reader) => {
+                ReadGuard(GuardInner::TripleBuffer(reader.read_newest()))
+            }
+            ReaderInner::SharedMutex { shared, seen } => {
+                let guard = shared.state.lock();
+                // Writes bump the version while holding the lock.
+                *seen = shared.version.load(Ordering::Acquire);
+                ReadGuard(GuardInner::SharedMutex(guard))
+            }
+        }
+    }
+
+    /// Check whether a state has been written
+    /// that `read_newest` has not returned yet.
+    pub fn has_update(&self) -> bool {
+        match &self.0 {
+            ReaderInner::TripleBuffer(reader) => reader.has_update(),
+            ReaderInner::SharedMutex { shared, seen } => {
+                shared.version.load(Ordering::Acquire) != *seen
+            }
+        }
+    }
+
+    /// Get the version of the state last returned by `read_newest`,
+    /// see `Reader::version`.
+    pub fn version(&self) -> u64 {
+        match &self.0 {
+            ReaderInner::TripleBuffer(reader) => reader.version(),
+            ReaderInner::SharedMutex { seen, .. } => *seen,
+        }
+    }
+
+    /// Check whether the `BackendWriter` has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        match &self.0 {
+            ReaderInner::TripleBuffer(reader) => reader.is_disconnected(),
+            ReaderInner::SharedMutex { shared, .. } => Arc::strong_count(shared) == 1,
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use std::sync::Arc;
+
+    use super::{new_backend, Backend};
+    use crate::StatePublisher;
+
+    #[test]
+    fn test_backends_behave_alike() {
+        for backend in [Backend::TripleBuffer, Backend::SharedMutex] {
+            let (mut w, mut r) = new_backend(vec![0u32; 4], backend);
+            assert_eq!(w.backend(), backend);
+            assert!(!r.has_update());
+            w.write_new(|old, new| {
+                assert_eq!(*old, [0; 4]);
+                new.clone_from(old);
+                new[0] = 1;
+            });
+            let receipt = w.write_update(|state| state[1] = 2);
+            assert!(r.has_update());
+            assert_eq!(*r.read_newest(), [1, 2, 0, 0]);
+            assert!(!r.has_update());
+            assert_eq!(r.version(), receipt.version);
+
+            let old = w.swap(vec![3; 4]);
+            assert_eq!(old.len(), 4);
+            w.extend([vec![4; 4], vec![5; 4]]);
+            assert_eq!(w.version(), 5);
+            assert_eq!(w.with_current(|state| state[0]), 5);
+            w.publish_with(&mut |old, new| *new = vec![old[0] + 1; 4]);
+            assert_eq!(*r.read_newest(), [6; 4]);
+            assert_eq!(r.version(), 6);
+            assert!(!r.is_disconnected());
+            drop(w);
+            assert!(r.is_disconnected());
+        }
+    }
+
+    #[test]
+    fn test_shared_mutex_copies_at_most_once() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct Counted(Arc<AtomicUsize>, Vec<u32>);
+
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Counted(self.0.clone(), self.1.clone())
+            }
+        }
+
+        let clones = Arc::new(AtomicUsize::new(0));
+        let (mut w, mut r) = new_backend(Counted(clones.clone(), vec![0]), Backend::SharedMutex);
+        for i in 1..=100 {
+            assert!(!w.write_update(|state| state.1[0] = i).allocated);
+        }
+        assert_eq!(clones.load(Ordering::Relaxed), 0);
+        for i in 101..=200 {
+            let receipt = w.write_new(|old, new| new.1[0] = old.1[0].max(i));
+            assert_eq!(receipt.allocated, i == 101);
+        }
+        assert_eq!(clones.load(Ordering::Relaxed), 1);
+        assert_eq!(r.read_newest().1, [200]);
+
+        // A panicking `write_new` leaves the state, `write_update` does not.
+        let new = catch_unwind(AssertUnwindSafe(|| {
+            w.write_new(|_, new| {
+                new.1[0] = 0;
+                panic!("write_new")
+            })
+        }));
+        assert!(new.is_err());
+        assert!(!r.has_update());
+        assert_eq!(r.read_newest().1, [200]);
+        let update = catch_unwind(AssertUnwindSafe(|| {
+            w.write_update(|state| {
+                state.1.push(0);
+                panic!("write_update")
+            })
+        }));
+        assert!(update.is_err());
+        assert!(r.has_update());
+        assert_eq!(r.read_newest().1, [200, 0]);
+        w.write_new(|old, new| new.1.clone_from(&old.1));
+        assert_eq!(r.read_newest().1, [200, 0]);
+        assert_eq!(clones.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_shared_mutex_across_threads() {
+        let (mut w, mut r) = new_backend([0u64; 64], Backend::SharedMutex);
+        let t = std::thread::spawn(move || {
+            for i in 1..=1000 {
+                w.write_update(|state| state.fill(i));
+            }
+        });
+        loop {
+            let disconnected = r.is_disconnected();
+            let state = r.read_newest();
+            assert!(state.iter().all(|v| *v == state[0]));
+            if disconnected {
+                assert_eq!(state[0], 1000);
+                break;
+            }
+        }
+        t.join().unwrap();
+    }
+}
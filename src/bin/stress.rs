@@ -0,0 +1,419 @@
+//! Runs a writer and readers against each other for a while, and prints
+//! what happened: how many states got published and observed, how stale
+//! they were when readers observed them, and how many buffers it took.
+//!
+//! Run with `cargo run --release --features stress --bin stress -- --help`.
+//!
+//! The writer of a triple buffer gets `2 + readers` buffers, and the
+//! summary tells how often it found none of them unused. It also checks
+//! that the pair upholds its invariants, exiting with a failure if it
+//! does not: readers never see states go backwards, no buffer is ever
+//! found still in use when reused, and dropping either side first shuts
+//! the other one down cleanly.
+
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use simple_triple_buffer::backend::{new_backend, Backend, BackendReader, BackendWriter};
+use simple_triple_buffer::{Reader, TripleBufferBuilder, Writer};
+
+const USAGE: &str = "\
+Usage: stress [OPTIONS]
+
+Options:
+  --payload <BYTES>      Size of the payload of every state [default: 1024]
+  --write-rate <HZ>      Publishes per second, 0 for unthrottled [default: 0]
+  --read-rate <HZ>       Reads per second of every reader, 0 for unthrottled [default: 0]
+  --readers <N>          Number of readers [default: 1]
+  --duration <SECS>      How long to run [default: 5]
+  --backend <BACKEND>    `triple` or `mutex`, which only supports one reader [default: triple]
+  --timestamps           Stamp every publish with its time
+  --readers-first        Drop the readers first at the end, instead of the writer
+  --help                 Print this";
+
+#[derive(Clone)]
+struct State {
+    seq: u64,
+    written: Instant,
+    payload: Vec<u8>,
+}
+
+struct Options {
+    payload: usize,
+    write_rate: f64,
+    read_rate: f64,
+    readers: usize,
+    duration: Duration,
+    backend: Backend,
+    timestamps: bool,
+    readers_first: bool,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            payload: 1024,
+            write_rate: 0.0,
+            read_rate: 0.0,
+            readers: 1,
+            duration: Duration::from_secs(5),
+            backend: Backend::TripleBuffer,
+            timestamps: false,
+            readers_first: false,
+        };
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().ok_or(format!("missing value for {}", arg));
+            match arg.as_str() {
+                "--payload" => options.payload = parse(&value()?)?,
+                "--write-rate" => options.write_rate = parse(&value()?)?,
+                "--read-rate" => options.read_rate = parse(&value()?)?,
+                "--readers" => options.readers = parse(&value()?)?,
+                "--duration" => options.duration = Duration::from_secs_f64(parse(&value()?)?),
+                "--backend" => {
+                    options.backend = match value()?.as_str() {
+                        "triple" => Backend::TripleBuffer,
+                        "mutex" => Backend::SharedMutex,
+                        other => return Err(format!("unknown backend {}", other)),
+                    }
+                }
+                "--timestamps" => options.timestamps = true,
+                "--readers-first" => options.readers_first = true,
+                "--help" => return Err(String::new()),
+                other => return Err(format!("unknown option {}", other)),
+            }
+        }
+        if options.readers == 0 {
+            return Err("at least one reader is needed".into());
+        }
+        if options.backend == Backend::SharedMutex && options.readers != 1 {
+            return Err("the mutex backend only supports one reader".into());
+        }
+        Ok(options)
+    }
+}
+
+fn parse<V: std::str::FromStr>(value: &str) -> Result<V, String> {
+    value
+        .parse()
+        .map_err(|_| format!("invalid value {}", value))
+}
+
+/// Sleeps until the next tick of a rate, if there is one.
+struct Ticker {
+    interval: Option<Duration>,
+    next: Instant,
+}
+
+impl Ticker {
+    fn new(rate: f64) -> Self {
+        Self {
+            interval: (rate > 0.0).then(|| Duration::from_secs_f64(1.0 / rate)),
+            next: Instant::now(),
+        }
+    }
+
+    fn wait(&mut self) {
+        if let Some(interval) = self.interval {
+            self.next += interval;
+            if let Some(sleep) = self.next.checked_duration_since(Instant::now()) {
+                thread::sleep(sleep);
+            }
+        }
+    }
+}
+
+/// Staleness counts in buckets growing by a quarter, from 100ns up.
+struct Histogram {
+    counts: Vec<u64>,
+}
+
+impl Histogram {
+    const FIRST: f64 = 100e-9;
+    const GROWTH: f64 = 1.25;
+
+    fn new() -> Self {
+        Self {
+            counts: vec![0; 128],
+        }
+    }
+
+    fn record(&mut self, staleness: Duration) {
+        let ratio = staleness.as_secs_f64() / Self::FIRST;
+        let bucket = if ratio <= 1.0 {
+            0
+        } else {
+            (ratio.ln() / Self::GROWTH.ln()).ceil() as usize
+        };
+        let last = self.counts.len() - 1;
+        self.counts[bucket.min(last)] += 1;
+    }
+
+    fn merge(&mut self, other: &Histogram) {
+        for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+            *count += other;
+        }
+    }
+
+    /// Get the upper bound of the bucket holding the `q` quantile.
+    fn quantile(&self, q: f64) -> Option<Duration> {
+        let total: u64 = self.counts.iter().sum();
+        let target = (total as f64 * q).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (bucket, count) in self.counts.iter().enumerate() {
+            seen += count;
+            if seen >= target && total > 0 {
+                let bound = Self::FIRST * Self::GROWTH.powi(bucket as i32);
+                return Some(Duration::from_secs_f64(bound));
+            }
+        }
+        None
+    }
+}
+
+enum PairWriter {
+    Triple(Box<Writer<State>>),
+    Mutex(BackendWriter<State>),
+}
+
+impl PairWriter {
+    fn write(&mut self, write_op: impl FnOnce(&State, &mut State)) -> bool {
+        match self {
+            PairWriter::Triple(writer) => writer.try_write_new(write_op).is_ok(),
+            PairWriter::Mutex(writer) => {
+                writer.write_new(write_op);
+                true
+            }
+        }
+    }
+
+    fn is_closed(&self) -> bool {
+        match self {
+            PairWriter::Triple(writer) => writer.is_closed(),
+            PairWriter::Mutex(writer) => writer.is_closed(),
+        }
+    }
+}
+
+enum PairReader {
+    Triple(Reader<State>),
+    Mutex(BackendReader<State>),
+}
+
+impl PairReader {
+    /// Get the sequence number and write time of the newest state.
+    fn read(&mut self) -> (u64, Instant) {
+        match self {
+            PairReader::Triple(reader) => {
+                let state = reader.read_newest();
+                (state.seq, state.written)
+            }
+            PairReader::Mutex(reader) => {
+                let state = reader.read_newest();
+                (state.seq, state.written)
+            }
+        }
+    }
+
+    fn has_update(&self) -> bool {
+        match self {
+            PairReader::Triple(reader) => reader.has_update(),
+            PairReader::Mutex(reader) => reader.has_update(),
+        }
+    }
+
+    fn is_disconnected(&self) -> bool {
+        match self {
+            PairReader::Triple(reader) => reader.is_disconnected(),
+            PairReader::Mutex(reader) => reader.is_disconnected(),
+        }
+    }
+}
+
+/// What a reader saw.
+struct Observed {
+    states: u64,
+    skipped: u64,
+    went_back: u64,
+    staleness: Histogram,
+}
+
+fn read(mut reader: PairReader, options: &Options, deadline: Instant) -> Observed {
+    let mut observed = Observed {
+        states: 0,
+        skipped: 0,
+        went_back: 0,
+        staleness: Histogram::new(),
+    };
+    let mut last = 0;
+    let mut ticker = Ticker::new(options.read_rate);
+    loop {
+        // Checked first, as the writer might publish a last state before going.
+        let disconnected = reader.is_disconnected();
+        if reader.has_update() {
+            let (seq, written) = reader.read();
+            observed.staleness.record(written.elapsed());
+            if seq <= last {
+                observed.went_back += 1;
+            } else {
+                observed.states += 1;
+                observed.skipped += seq - last - 1;
+                last = seq;
+            }
+        } else if disconnected {
+            break;
+        }
+        if options.readers_first && Instant::now() >= deadline {
+            break;
+        }
+        ticker.wait();
+    }
+    observed
+}
+
+fn main() -> ExitCode {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) if e.is_empty() => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let init = State {
+        seq: 0,
+        written: Instant::now(),
+        payload: vec![0; options.payload],
+    };
+    let make_buf_calls = Arc::new(AtomicUsize::new(0));
+    let (mut writer, readers) = match options.backend {
+        Backend::TripleBuffer => {
+            let calls = make_buf_calls.clone();
+            let (writer, reader) = TripleBufferBuilder::new(init)
+                .clone_with(move |state: &State| {
+                    calls.fetch_add(1, Ordering::Relaxed);
+                    state.clone()
+                })
+                .max_buffers(2 + options.readers)
+                .timestamps(options.timestamps)
+                .build()
+                .unwrap();
+            let mut readers: Vec<_> = (1..options.readers)
+                .map(|_| PairReader::Triple(reader.clone()))
+                .collect();
+            readers.push(PairReader::Triple(reader));
+            (PairWriter::Triple(Box::new(writer)), readers)
+        }
+        Backend::SharedMutex => {
+            let (writer, reader) = new_backend(init, Backend::SharedMutex);
+            (PairWriter::Mutex(writer), vec![PairReader::Mutex(reader)])
+        }
+    };
+
+    let start = Instant::now();
+    let deadline = start + options.duration;
+    let mut published = 0u64;
+    let mut exhausted = 0u64;
+    let mut skipped_buffers = 0;
+    let observed: Vec<_> = thread::scope(|s| {
+        let threads: Vec<_> = readers
+            .into_iter()
+            .map(|reader| s.spawn(|| read(reader, &options, deadline)))
+            .collect();
+
+        let mut ticker = Ticker::new(options.write_rate);
+        loop {
+            let done = if options.readers_first {
+                writer.is_closed()
+            } else {
+                Instant::now() >= deadline
+            };
+            if done {
+                break;
+            }
+            let seq = published + 1;
+            let written = writer.write(|_, new| {
+                new.seq = seq;
+                new.written = Instant::now();
+                new.payload.resize(options.payload, 0);
+                new.payload.fill(seq as u8);
+            });
+            if written {
+                published = seq;
+            } else {
+                exhausted += 1;
+            }
+            ticker.wait();
+        }
+        if let PairWriter::Triple(writer) = &writer {
+            skipped_buffers = writer.skipped_buffers();
+        }
+        drop(writer);
+
+        threads
+            .into_iter()
+            .map(|thread| thread.join().expect("reader panicked"))
+            .collect()
+    });
+    let elapsed = start.elapsed();
+
+    let mut staleness = Histogram::new();
+    for observed in &observed {
+        staleness.merge(&observed.staleness);
+    }
+    let states: u64 = observed.iter().map(|observed| observed.states).sum();
+    let skipped: u64 = observed.iter().map(|observed| observed.skipped).sum();
+    let went_back: u64 = observed.iter().map(|observed| observed.went_back).sum();
+    let per_second = |count: u64| count as f64 / elapsed.as_secs_f64();
+    let quantile = |q| match staleness.quantile(q) {
+        Some(bound) => format!("<= {:?}", bound),
+        None => "-".into(),
+    };
+
+    println!("backend:           {:?}", options.backend);
+    println!("ran for:           {:?}", elapsed);
+    println!(
+        "published:         {} ({:.0}/s)",
+        published,
+        per_second(published)
+    );
+    println!(
+        "observed:          {} over {} readers ({:.0}/s)",
+        states,
+        observed.len(),
+        per_second(states)
+    );
+    println!("skipped:           {}", skipped);
+    println!(
+        "make_buf calls:    {}",
+        make_buf_calls.load(Ordering::Relaxed)
+    );
+    println!("writes w/o buffer: {}", exhausted);
+    println!("staleness p50:     {}", quantile(0.5));
+    println!("staleness p99:     {}", quantile(0.99));
+
+    let mut violations = Vec::new();
+    if went_back > 0 {
+        violations.push(format!("readers saw {} states go backwards", went_back));
+    }
+    if skipped_buffers > 0 {
+        violations.push(format!(
+            "{} reused buffers were still in use",
+            skipped_buffers
+        ));
+    }
+    for violation in &violations {
+        eprintln!("violation: {}", violation);
+    }
+    if violations.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,187 @@
+//! Reads that wait for the writer to publish.
+//!
+//! Publishing stays lock-free while no reader waits: the writer only
+//! takes the lock to wake readers up if the count of waiting ones is
+//! non-zero. Readers count themselves before checking for a new state,
+//! and the writer checks the count after publishing, with a fence on
+//! both sides, so one of them always sees the other.
+
+use core::sync::atomic::Ordering;
+use std::time::{Duration, Instant};
+
+use crate::sync::atomic::{fence, AtomicUsize};
+use crate::sync::{Condvar, Mutex};
+use crate::{ReadError, Reader, Writer};
+
+pub(crate) struct Wakeup {
+    waiting: AtomicUsize,
+    lock: Mutex<()>,
+    published: Condvar,
+}
+
+impl Wakeup {
+    pub(crate) fn new() -> Self {
+        Self {
+            waiting: AtomicUsize::new(0),
+            lock: Mutex::new(()),
+            published: Condvar::new(),
+        }
+    }
+
+    fn notify(&self) {
+        fence(Ordering::SeqCst);
+        if self.waiting.load(Ordering::Relaxed) > 0 {
+            drop(self.lock.lock());
+            self.published.notify_all();
+        }
+    }
+
+    /// Wait until `ready` returns `true`, or the deadline passes,
+    /// returning whether it did.
+    fn wait(&self, deadline: Option<Instant>, ready: impl Fn() -> bool) -> bool {
+        self.waiting.fetch_add(1, Ordering::Relaxed);
+        fence(Ordering::SeqCst);
+        let mut guard = self.lock.lock();
+        let ready = loop {
+            if ready() {
+                break true;
+            }
+            match deadline {
+                None => guard = self.published.wait(guard),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break false;
+                    }
+                    guard = self.published.wait_timeout(guard, deadline - now);
+                }
+            }
+        };
+        drop(guard);
+        self.waiting.fetch_sub(1, Ordering::Relaxed);
+        ready
+    }
+}
+
+impl<T> Writer<T> {
+    /// Wake up readers waiting for a new state, or for the writer to go.
+    pub(crate) fn wake_readers(&self) {
+        self.read_update.shared.wakeup.notify();
+    }
+}
+
+impl<T> Reader<T> {
+    /// Wait until the writer publishes a state `read_newest`
+    /// has not returned yet, and return it.
+    ///
+    /// Returns right away if there already is one. Fails with
+    /// `ReadError::Disconnected` once the writer has been dropped
+    /// and its last state was picked up.
+    ///
+    /// Only available with the `std` feature.
+    ///
+    /// # Example
+    /// ```
+    /// use simple_triple_buffer::ReadError;
+    ///
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// std::thread::spawn(move || writer.write_new(|_, new| *new = 1));
+    ///
+    /// assert_eq!(reader.read_blocking(), Ok(&1));
+    /// assert_eq!(reader.read_blocking(), Err(ReadError::Disconnected));
+    /// ````
+    pub fn read_blocking(&mut self) -> Result<&T, ReadError> {
+        self.read_until(None)
+    }
+
+    /// Like `read_blocking`, but fails with `ReadError::Timeout`
+    /// if no new state gets published within `timeout`.
+    ///
+    /// Only available with the `std` feature.
+    pub fn read_timeout(&mut self, timeout: Duration) -> Result<&T, ReadError> {
+        self.read_until(Some(Instant::now() + timeout))
+    }
+
+    fn read_until(&mut self, deadline: Option<Instant>) -> Result<&T, ReadError> {
+        let ready = || self.has_update() || self.is_disconnected();
+        if !ready() && !self.read_update.shared.wakeup.wait(deadline, ready) {
+            return Err(ReadError::Timeout);
+        }
+        // The writer may have published a last state before it went.
+        if self.has_update() {
+            Ok(self.read_newest())
+        } else {
+            Err(ReadError::Disconnected)
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::{new_clone, ReadError};
+
+    #[test]
+    fn test_wakes_on_publish() {
+        let (mut w, mut r) = new_clone(0u32);
+        let writer = std::thread::spawn(move || {
+            for i in 1..=1000 {
+                w.write_new(|_, new| *new = i);
+                if i % 100 == 0 {
+                    std::thread::sleep(Duration::from_millis(1));
+                }
+            }
+        });
+        let mut last = 0;
+        loop {
+            match r.read_blocking() {
+                Ok(&i) => {
+                    assert!(i > last);
+                    last = i;
+                }
+                Err(e) => {
+                    assert_eq!(e, ReadError::Disconnected);
+                    break;
+                }
+            }
+        }
+        assert_eq!(last, 1000);
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_timeout() {
+        let (mut w, mut r) = new_clone(0u32);
+        let start = Instant::now();
+        assert_eq!(
+            r.read_timeout(Duration::from_millis(20)),
+            Err(ReadError::Timeout)
+        );
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        w.write_new(|_, new| *new = 1);
+        assert_eq!(r.read_timeout(Duration::ZERO), Ok(&1));
+        let waiter = std::thread::spawn(move || r.read_timeout(Duration::from_secs(60)).err());
+        std::thread::sleep(Duration::from_millis(10));
+        drop(w);
+        assert_eq!(waiter.join().unwrap(), Some(ReadError::Disconnected));
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use loom::thread;
+
+    use crate::new_clone;
+
+    #[test]
+    fn test_no_lost_wakeup() {
+        loom::model(|| {
+            let (mut w, mut r) = new_clone(0u32);
+            let writer = thread::spawn(move || w.write_new(|_, new| *new = 1));
+            assert_eq!(r.read_blocking(), Ok(&1));
+            writer.join().unwrap();
+        });
+    }
+}
@@ -0,0 +1,317 @@
+//! Forwarding the values of a channel into a `Writer`.
+//!
+//! `from_receiver` forwards a `std::sync::mpsc` channel on a thread of its
+//! own, and with the `tokio` feature, `from_watch` forwards a tokio watch
+//! channel from a task.
+//!
+//! # Example
+//! ```
+//! use std::sync::mpsc;
+//! use simple_triple_buffer::bridge;
+//!
+//! let (tx, rx) = mpsc::channel();
+//! let (writer, mut reader) = simple_triple_buffer::new_clone(0);
+//! let bridge = bridge::from_receiver(rx, writer);
+//!
+//! for i in 1..=10 {
+//!     tx.send(i).unwrap();
+//! }
+//! drop(tx);
+//! while !reader.is_disconnected() {
+//!     std::thread::yield_now();
+//! }
+//! assert_eq!(*reader.read_newest(), 10);
+//! assert_eq!(bridge.received(), 10);
+//! assert!(bridge.published() <= 10);
+//! ````
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+#[cfg(feature = "tokio")]
+use tokio::sync::watch;
+
+#[cfg(feature = "tokio")]
+use crate::Reader;
+use crate::Writer;
+
+/// How long the thread waits for a value before checking
+/// whether it should stop, which bounds how long `stop` takes.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Default)]
+struct Counters {
+    received: AtomicU64,
+    published: AtomicU64,
+    stop: AtomicBool,
+}
+
+impl Counters {
+    /// Count the publish of the last of `received` values. Received
+    /// values are counted last, so whoever sees them counted also
+    /// sees the state they ended up in published.
+    fn published(&self, received: u64) {
+        self.published.fetch_add(1, Ordering::Release);
+        self.received.fetch_add(received, Ordering::Release);
+    }
+}
+
+/// Spawn a thread that publishes the values received on `rx` with `writer`.
+///
+/// The thread waits for a value, then drains all others already sent, and
+/// only publishes the last of them, so bursts are coalesced into one state
+/// that readers see. It ends once all senders are gone, or the returned
+/// handle stops it, dropping the writer.
+pub fn from_receiver<T>(rx: Receiver<T>, mut writer: Writer<T>) -> BridgeHandle
+where
+    T: Send + Sync + 'static,
+{
+    let counters = Arc::new(Counters::default());
+    let thread = {
+        let counters = counters.clone();
+        thread::spawn(move || {
+            while !counters.stop.load(Ordering::Relaxed) {
+                let mut value = match rx.recv_timeout(POLL_INTERVAL) {
+                    Ok(value) => value,
+                    Err(RecvTimeoutError::Timeout) => continue,
+                    Err(RecvTimeoutError::Disconnected) => return,
+                };
+                let mut received = 1;
+                while let Ok(newer) = rx.try_recv() {
+                    value = newer;
+                    received += 1;
+                }
+                writer.write_new(|_, new| *new = value);
+                counters.published(received);
+            }
+        })
+    };
+    BridgeHandle {
+        counters,
+        worker: Some(Worker::Thread(thread)),
+    }
+}
+
+/// Spawn a task that publishes the values of the watch channel `rx` into
+/// a new pair, returning its reader.
+///
+/// The pair starts out with the value `rx` currently holds, and creates
+/// buffers with `make_buf`, like `new_with`. The task waits for the value
+/// to change, and then publishes a clone of it. The watch channel only
+/// keeps the newest value, so changes in between get coalesced on their
+/// own, and `received` and `published` of the handle stay the same.
+///
+/// The task ends once the sender of the channel is gone, which readers
+/// see as the writer being disconnected, or once all readers are gone.
+///
+/// Only available with the `tokio` feature.
+///
+/// # Panics
+/// Panics if called outside of a tokio runtime.
+///
+/// # Example
+/// ```
+/// use simple_triple_buffer::bridge;
+///
+/// let rt = tokio::runtime::Builder::new_current_thread().build().unwrap();
+/// rt.block_on(async {
+///     let (tx, rx) = tokio::sync::watch::channel(vec![1]);
+///     let (mut reader, _bridge) = bridge::from_watch(rx, |v: &Vec<i32>| v.clone());
+///     assert_eq!(*reader.read_newest(), [1]);
+///
+///     tx.send(vec![1, 2]).unwrap();
+///     drop(tx);
+///     while !reader.is_disconnected() {
+///         tokio::task::yield_now().await;
+///     }
+///     assert_eq!(*reader.read_newest(), [1, 2]);
+/// });
+/// ````
+#[cfg(feature = "tokio")]
+pub fn from_watch<T>(
+    mut rx: watch::Receiver<T>,
+    make_buf: impl FnMut(&T) -> T + Send + 'static,
+) -> (Reader<T>, BridgeHandle)
+where
+    T: Clone + Send + Sync + 'static,
+{
+    let init = rx.borrow_and_update().clone();
+    let (mut writer, reader) = crate::new_with(init, make_buf);
+    let counters = Arc::new(Counters::default());
+    let task = {
+        let counters = counters.clone();
+        tokio::spawn(async move {
+            while rx.changed().await.is_ok() && !writer.is_closed() {
+                writer.write_new(|_, new| new.clone_from(&rx.borrow_and_update()));
+                counters.published(1);
+            }
+        })
+    };
+    let handle = BridgeHandle {
+        counters,
+        worker: Some(Worker::Task(task)),
+    };
+    (reader, handle)
+}
+
+enum Worker {
+    Thread(JoinHandle<()>),
+    #[cfg(feature = "tokio")]
+    Task(tokio::task::JoinHandle<()>),
+}
+
+/// Handle to the thread of `from_receiver`, or the task of
+/// `from_watch`, which stops it when dropped.
+pub struct BridgeHandle {
+    counters: Arc<Counters>,
+    worker: Option<Worker>,
+}
+
+impl BridgeHandle {
+    /// Get the number of values received so far.
+    ///
+    /// Values only count once the state they ended up in is published.
+    pub fn received(&self) -> u64 {
+        self.counters.received.load(Ordering::Acquire)
+    }
+
+    /// Get the number of states published so far.
+    ///
+    /// The difference to `received` is the number of values
+    /// that were replaced by a newer one before being published.
+    pub fn published(&self) -> u64 {
+        self.counters.published.load(Ordering::Acquire)
+    }
+
+    /// Check whether the thread or task has ended,
+    /// because all senders are gone.
+    pub fn is_finished(&self) -> bool {
+        match &self.worker {
+            None => true,
+            Some(Worker::Thread(thread)) => thread.is_finished(),
+            #[cfg(feature = "tokio")]
+            Some(Worker::Task(task)) => task.is_finished(),
+        }
+    }
+
+    /// Stop the thread, and wait for it to end.
+    ///
+    /// Values still in the channel are not published.
+    ///
+    /// The task of `from_watch` gets aborted instead, which drops
+    /// its writer the next time the runtime gets to it.
+    pub fn stop(mut self) {
+        self.shut_down();
+    }
+
+    fn shut_down(&mut self) {
+        self.counters.stop.store(true, Ordering::Relaxed);
+        match self.worker.take() {
+            None => {}
+            Some(Worker::Thread(thread)) => {
+                if let Err(panic) = thread.join() {
+                    if !thread::panicking() {
+                        std::panic::resume_unwind(panic);
+                    }
+                }
+            }
+            #[cfg(feature = "tokio")]
+            Some(Worker::Task(task)) => task.abort(),
+        }
+    }
+}
+
+impl Drop for BridgeHandle {
+    fn drop(&mut self) {
+        self.shut_down();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::mpsc;
+    use std::time::{Duration, Instant};
+
+    use super::from_receiver;
+    use crate::new_clone;
+
+    #[test]
+    fn test_bursts_are_coalesced() {
+        let (tx, rx) = mpsc::channel();
+        let (w, mut r) = new_clone(0u32);
+        for i in 1..=100 {
+            tx.send(i).unwrap();
+        }
+        let bridge = from_receiver(rx, w);
+        while bridge.received() < 100 {
+            std::thread::yield_now();
+        }
+        // Everything was sent before the thread started, and `received`
+        // only counts values once the state they ended up in is published.
+        assert_eq!(bridge.published(), 1);
+        assert_eq!(*r.read_newest(), 100);
+
+        tx.send(101).unwrap();
+        while bridge.published() < 2 {
+            std::thread::yield_now();
+        }
+        assert_eq!(*r.read_newest(), 101);
+        assert!(!bridge.is_finished());
+        drop(tx);
+        while !bridge.is_finished() {
+            std::thread::yield_now();
+        }
+        assert!(r.is_disconnected());
+    }
+
+    #[test]
+    fn test_stop_is_prompt() {
+        let (tx, rx) = mpsc::channel::<u32>();
+        let (w, r) = new_clone(0);
+        let bridge = from_receiver(rx, w);
+        let start = Instant::now();
+        bridge.stop();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(r.is_disconnected());
+        // Sending after the thread is gone fails like for any dropped receiver.
+        assert!(tx.send(1).is_err());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn test_watch() {
+        use tokio::sync::watch;
+        use tokio::task::yield_now;
+
+        use super::from_watch;
+
+        let rt = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        rt.block_on(async {
+            let (tx, rx) = watch::channel(0u32);
+            let (mut r, bridge) = from_watch(rx, |v| *v);
+            for i in 1..=10 {
+                tx.send(i).unwrap();
+            }
+            while bridge.published() == 0 {
+                yield_now().await;
+            }
+            // The watch channel only kept the last value.
+            assert_eq!(bridge.published(), 1);
+            assert_eq!(*r.read_newest(), 10);
+
+            // Aborting the task drops the writer.
+            bridge.stop();
+            while !r.is_disconnected() {
+                yield_now().await;
+            }
+            // The receiver went along with it.
+            assert!(tx.send(11).is_err());
+            assert_eq!(*r.read_newest(), 10);
+        });
+    }
+}
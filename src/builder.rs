@@ -0,0 +1,628 @@
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+#[cfg(feature = "stats")]
+use std::time::Duration;
+
+#[cfg(feature = "std")]
+use crate::changes::{self, ChangeMask, Diff};
+#[cfg(feature = "stats")]
+use crate::drop_rate;
+#[cfg(feature = "latency")]
+use crate::latency;
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use crate::ring;
+use crate::source::CloneWith;
+use crate::sync;
+#[cfg(feature = "trace-export")]
+use crate::trace;
+use crate::{
+    Buf, BufferPool, BufferSource, BuildError, MakeBuf, Reader, Recycler, Refresh, Scrub, Writer,
+};
+
+/// Builder for configuring a buffer pair.
+///
+/// # Example
+/// ```
+/// use simple_triple_buffer::TripleBufferBuilder;
+///
+/// let (mut writer, mut reader) = TripleBufferBuilder::new(0)
+///     .clone_with(|v| *v)
+///     .preallocate(2)
+///     .max_buffers(3)
+///     .label("physics")
+///     .build()
+///     .unwrap();
+///
+/// writer.write_new(|old, new| *new = *old + 1);
+/// assert_eq!(*reader.read_newest(), 1);
+/// assert_eq!(reader.label(), Some("physics"));
+/// ````
+pub struct TripleBufferBuilder<T> {
+    init: Init<T>,
+    make_buf: MakeBuf<T>,
+    refresh: Option<Refresh<T>>,
+    spares: Vec<T>,
+    preallocate: usize,
+    max_buffers: Option<usize>,
+    max_readers: Option<usize>,
+    #[cfg(feature = "std")]
+    timestamps: bool,
+    #[cfg(feature = "std")]
+    detach_laggards: Option<u64>,
+    #[cfg(feature = "stats")]
+    drop_rate_window: Option<(Duration, usize)>,
+    #[cfg(feature = "latency")]
+    latency_buckets: Option<Vec<Duration>>,
+    #[cfg(feature = "trace-export")]
+    trace_events: Option<usize>,
+    label: Option<Cow<'static, str>>,
+    #[cfg(feature = "std")]
+    change_diff: Option<Diff<T>>,
+    #[cfg(feature = "metrics")]
+    metrics_default_label: Option<Cow<'static, str>>,
+    pool: Option<BufferPool<T>>,
+    scrub: Option<Scrub<T>>,
+}
+
+/// The state a builder publishes initially.
+enum Init<T> {
+    Buf(Buf<T>),
+    /// An `Arc` shared with others, which can not be turned into a
+    /// `triomphe::Arc`, so the first buffer gets created from it.
+    #[cfg(feature = "triomphe")]
+    Shared(Arc<T>),
+}
+
+impl<T> Init<T> {
+    fn into_buf(self, make_buf: &mut MakeBuf<T>) -> Buf<T> {
+        #[cfg(not(feature = "triomphe"))]
+        let _ = make_buf;
+        match self {
+            Init::Buf(buf) => buf,
+            #[cfg(feature = "triomphe")]
+            Init::Shared(shared) => match make_buf.make(&shared) {
+                Some(init) => Buf::new(init),
+                None => panic!("a shared initial Arc requires clone_with or copy_buffers"),
+            },
+        }
+    }
+}
+
+impl<T> TripleBufferBuilder<T> {
+    /// Start configuring a pair that initially publishes `init`.
+    pub fn new(init: T) -> Self {
+        Self::from_init(Init::Buf(Buf::new(init)))
+    }
+
+    /// Start configuring a pair that initially publishes an existing `Arc`.
+    ///
+    /// See `new_from_arc` for how such a shared state is treated.
+    pub fn from_arc(init: Arc<T>) -> Self {
+        #[cfg(not(feature = "triomphe"))]
+        let init = Init::Buf(init);
+        #[cfg(feature = "triomphe")]
+        let init = match Arc::try_unwrap(init) {
+            Ok(init) => Init::Buf(Buf::new(init)),
+            Err(shared) => Init::Shared(shared),
+        };
+        Self::from_init(init)
+    }
+
+    fn from_init(init: Init<T>) -> Self {
+        Self {
+            init,
+            make_buf: MakeBuf::Never,
+            refresh: None,
+            spares: Vec::new(),
+            preallocate: 0,
+            max_buffers: None,
+            max_readers: None,
+            #[cfg(feature = "std")]
+            timestamps: false,
+            #[cfg(feature = "std")]
+            detach_laggards: None,
+            #[cfg(feature = "stats")]
+            drop_rate_window: None,
+            #[cfg(feature = "latency")]
+            latency_buckets: None,
+            #[cfg(feature = "trace-export")]
+            trace_events: None,
+            label: None,
+            #[cfg(feature = "std")]
+            change_diff: None,
+            #[cfg(feature = "metrics")]
+            metrics_default_label: None,
+            pool: None,
+            scrub: None,
+        }
+    }
+
+    /// Create additional buffer instances with a custom clone function.
+    pub fn clone_with(mut self, make_buf: impl FnMut(&T) -> T + 'static + Send) -> Self {
+        self.make_buf = MakeBuf::Custom(Box::new(CloneWith(make_buf)));
+        self
+    }
+
+    /// Create and manage buffers with a custom source, see `new_with_source`.
+    pub fn source(mut self, source: impl BufferSource<T> + 'static + Send) -> Self {
+        self.make_buf = MakeBuf::Source(Box::new(source));
+        self
+    }
+
+    /// Create additional buffer instances by copying the previous state.
+    ///
+    /// This is like `clone_with(|v| *v)`, but without a boxed closure.
+    pub fn copy_buffers(mut self) -> Self
+    where
+        T: Copy,
+    {
+        fn copy<T: Copy>(v: &T) -> T {
+            *v
+        }
+        self.make_buf = MakeBuf::Copy(copy::<T>);
+        self
+    }
+
+    /// Bring reused buffers up to date with a custom refresh function,
+    /// see `new_with_refresh`.
+    pub fn refresh_with(mut self, refresh: impl FnMut(&T, &mut T) + 'static + Send) -> Self {
+        self.refresh = Some(Box::new(refresh));
+        self
+    }
+
+    /// Start out with the given spare buffers in the pool.
+    ///
+    /// Their contents do not matter, as every write overwrites them.
+    pub fn spare_buffers(mut self, spares: Vec<T>) -> Self {
+        self.spares = spares;
+        self
+    }
+
+    /// Create `n` spare buffers up front, instead of
+    /// lazily the first time a write needs them.
+    ///
+    /// Requires `clone_with` or `copy_buffers`.
+    pub fn preallocate(mut self, n: usize) -> Self {
+        self.preallocate = n;
+        self
+    }
+
+    /// Limit the total number of buffers the pair may own,
+    /// including the initial state.
+    ///
+    /// Writes that would need to exceed the limit fail
+    /// with `PoolExhausted`, see `Writer::try_write_new`.
+    /// A limit of 3 is enough for the writer to never have to wait.
+    pub fn max_buffers(mut self, n: usize) -> Self {
+        self.max_buffers = Some(n);
+        self
+    }
+
+    /// Limit the number of readers the pair may have at the same
+    /// time, including the initial one.
+    ///
+    /// Adding more fails with `TooManyReaders`, see
+    /// `Writer::try_subscribe` and `Reader::try_clone`.
+    pub fn max_readers(mut self, n: usize) -> Self {
+        self.max_readers = Some(n);
+        self
+    }
+
+    /// Record the time of each publish, see `Reader::published_at`.
+    ///
+    /// Only available with the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn timestamps(mut self, enabled: bool) -> Self {
+        self.timestamps = enabled;
+        self
+    }
+
+    /// Detach readers once they fall more than `generations` states
+    /// behind the newest one, see `Writer::laggards`.
+    ///
+    /// The writer checks all readers whenever it publishes, which
+    /// takes a lock only readers being cloned or dropped contend on.
+    ///
+    /// Only available with the `std` feature.
+    ///
+    /// # Panics
+    /// Panics if `generations` is 0.
+    #[cfg(feature = "std")]
+    pub fn detach_laggards(mut self, generations: u64) -> Self {
+        assert!(generations > 0, "readers can not lag behind by 0 states");
+        self.detach_laggards = Some(generations);
+        self
+    }
+
+    /// Count the states that got replaced before any reader picked them
+    /// up, in `intervals` intervals of length `interval`, see
+    /// `Writer::drop_rate`.
+    ///
+    /// Only available with the `stats` feature.
+    #[cfg(feature = "stats")]
+    pub fn drop_rate_window(mut self, interval: Duration, intervals: usize) -> Self {
+        self.drop_rate_window = Some((interval, intervals));
+        self
+    }
+
+    /// Collect how long published states wait for a reader to pick them
+    /// up, see `Reader::latency_histogram`.
+    ///
+    /// `bounds` are the upper bounds of the buckets the latencies get
+    /// counted in, with one more bucket for anything above the largest.
+    /// This implies `timestamps`.
+    ///
+    /// Only available with the `latency` feature.
+    #[cfg(feature = "latency")]
+    pub fn latency_buckets(mut self, bounds: impl IntoIterator<Item = Duration>) -> Self {
+        self.latency_buckets = Some(bounds.into_iter().collect());
+        self.timestamps = true;
+        self
+    }
+
+    /// Keep the last `capacity` events of the pair, for a timeline
+    /// of it, see `Writer::export_chrome_trace`.
+    ///
+    /// The events are the `write_new` closures, buffers getting created,
+    /// states getting published, and readers picking them up. Each costs
+    /// a timestamp and a push into a ring allocated up front.
+    ///
+    /// Only available with the `trace-export` feature.
+    #[cfg(feature = "trace-export")]
+    pub fn trace_events(mut self, capacity: usize) -> Self {
+        self.trace_events = Some(capacity);
+        self
+    }
+
+    /// Share unused buffers with other pairs using the same pool,
+    /// see `new_with_pool`.
+    pub fn pool(mut self, pool: BufferPool<T>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Zeroize buffers that no longer hold a needed state.
+    ///
+    /// This happens whenever a buffer gets recycled, which includes
+    /// buffers put into a `BufferPool` and ones it drops when full,
+    /// and for all buffers left behind when the `Writer` and `Reader`
+    /// get dropped. See also `Writer::scrub`.
+    ///
+    /// Reused buffers then no longer contain an older state,
+    /// so this can not be combined with `refresh_with`.
+    ///
+    /// Only available with the `zeroize` feature.
+    #[cfg(feature = "zeroize")]
+    pub fn zeroize(self) -> Self
+    where
+        T: zeroize::Zeroize,
+    {
+        self.scrub_with(T::zeroize)
+    }
+
+    /// Run `scrub` on buffers that no longer hold a needed state,
+    /// at the same points as `zeroize`.
+    pub(crate) fn scrub_with(mut self, scrub: Scrub<T>) -> Self {
+        self.scrub = Some(scrub);
+        self
+    }
+
+    /// Attach a label to both halves of the pair, for diagnostics.
+    ///
+    /// It shows up in their `Debug` output, and in the messages of
+    /// panics and errors about the pair. Unlabeled pairs store nothing.
+    pub fn label(mut self, label: impl Into<Cow<'static, str>>) -> Self {
+        self.label = Some(label.into());
+        self
+    }
+
+    /// Compare every state about to be published with the previous one
+    /// using `diff`, so readers can tell which sections of the state
+    /// changed, see `Reader::changes_since_last_read`.
+    ///
+    /// `diff` runs on the thread of the writer. Updating the pending
+    /// state in place would leave nothing to compare with, so
+    /// `Writer::write_update` always writes into another buffer.
+    ///
+    /// Only available with the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn change_mask(mut self, diff: impl Fn(&T, &T) -> ChangeMask + Send + 'static) -> Self {
+        self.change_diff = Some(Box::new(diff));
+        self
+    }
+
+    /// Report the metrics of the pair under `label` if it has none,
+    /// instead of not reporting them at all.
+    ///
+    /// Labeled pairs report `stb_publishes_total`,
+    /// `stb_updates_observed_total` and `stb_buffers_allocated_total`
+    /// counters and a `stb_pool_size` gauge of the buffers they hold,
+    /// with the label as `pair`, to the recorder of the `metrics` crate
+    /// that was installed when the pair got built.
+    ///
+    /// Only available with the `metrics` feature.
+    #[cfg(feature = "metrics")]
+    pub fn metrics_default_label(mut self, label: impl Into<Cow<'static, str>>) -> Self {
+        self.metrics_default_label = Some(label.into());
+        self
+    }
+
+    /// Create the buffer pair.
+    ///
+    /// Fails if the options are inconsistent, for example if the pair
+    /// would have no way to ever get a buffer to write into.
+    pub fn build(self) -> Result<(Writer<T>, Reader<T>), BuildError> {
+        let initial = 1 + self.spares.len() + self.preallocate;
+        if let MakeBuf::Never = self.make_buf {
+            if self.preallocate > 0 {
+                return Err(BuildError::new(
+                    "preallocate requires clone_with or copy_buffers",
+                ));
+            }
+            if self.spares.is_empty() {
+                return Err(BuildError::new(
+                    "one of clone_with, copy_buffers or spare_buffers is required to get buffers to write into",
+                ));
+            }
+            #[cfg(feature = "triomphe")]
+            if let Init::Shared(_) = self.init {
+                return Err(BuildError::new(
+                    "a shared initial Arc requires clone_with or copy_buffers",
+                ));
+            }
+        }
+        if self.scrub.is_some() && self.refresh.is_some() {
+            return Err(BuildError::new(
+                "zeroize can not be combined with refresh_with",
+            ));
+        }
+        if let Some(max) = self.max_buffers {
+            if max < 2 {
+                return Err(BuildError::new(
+                    "max_buffers must be at least 2 to have a buffer to write into",
+                ));
+            }
+            if initial > max {
+                return Err(BuildError::new(
+                    "spare_buffers and preallocate exceed max_buffers",
+                ));
+            }
+        }
+        if self.max_readers == Some(0) {
+            return Err(BuildError::new(
+                "max_readers must be at least 1 for the initial reader",
+            ));
+        }
+        Ok(self.finish())
+    }
+
+    /// Create the buffer pair without validating the options.
+    pub(crate) fn finish(self) -> (Writer<T>, Reader<T>) {
+        let mut make_buf = self.make_buf;
+        let init = self.init.into_buf(&mut make_buf);
+        // Pairs with a bounded number of buffers can never overflow the ring.
+        let capacity = self
+            .max_buffers
+            .unwrap_or(ring::DEFAULT_CAPACITY)
+            .max(1 + self.spares.len() + self.preallocate);
+        #[cfg(feature = "metrics")]
+        let metrics_label = self.label.clone().or(self.metrics_default_label);
+        let mut w = Writer::new(init, make_buf, self.label, self.scrub, capacity);
+        if let Some(max) = self.max_readers {
+            let shared = sync::Arc::get_mut(&mut w.read_update.shared).unwrap();
+            shared.max_readers = max;
+        }
+        #[cfg(feature = "std")]
+        if self.detach_laggards.is_some() {
+            let shared = sync::Arc::get_mut(&mut w.read_update.shared).unwrap();
+            shared.reader_list.detach_after = self.detach_laggards;
+        }
+        #[cfg(feature = "stats")]
+        if let Some((interval, intervals)) = self.drop_rate_window {
+            let shared = sync::Arc::get_mut(&mut w.read_update.shared).unwrap();
+            shared.drop_window = Some(drop_rate::DropWindow::new(interval, intervals));
+        }
+        #[cfg(feature = "latency")]
+        if let Some(bounds) = self.latency_buckets {
+            let shared = sync::Arc::get_mut(&mut w.read_update.shared).unwrap();
+            shared.latency = Some(latency::Histogram::new(bounds));
+        }
+        #[cfg(feature = "std")]
+        if let Some(diff) = self.change_diff {
+            let shared = sync::Arc::get_mut(&mut w.read_update.shared).unwrap();
+            shared.changes = Some(changes::ChangeLog::new());
+            w.diff = Some(diff);
+        }
+        #[cfg(feature = "trace-export")]
+        if let Some(capacity) = self.trace_events {
+            let shared = sync::Arc::get_mut(&mut w.read_update.shared).unwrap();
+            shared.trace = Some(trace::TraceRing::new(capacity));
+        }
+        w.refresh = self.refresh;
+        #[cfg(feature = "std")]
+        {
+            w.timestamps = self.timestamps;
+        }
+        w.max_buffers = self.max_buffers.unwrap_or(usize::MAX);
+        if let Some(pool) = self.pool {
+            w.unused_bufs_tx = Recycler::Pool(pool);
+        }
+        for spare in self.spares {
+            w.created += 1;
+            w.recycle(Buf::new(spare));
+        }
+        for _ in 0..self.preallocate {
+            if let Some(buf) = w.make_buf.make(&w.prev_buf) {
+                w.created += 1;
+                w.recycle(Buf::new(buf));
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(label) = metrics_label {
+            let shared = sync::Arc::get_mut(&mut w.read_update.shared).unwrap();
+            shared.observed_metric = Some(metrics::observed_counter(label.clone()));
+            w.metrics = Some(Box::new(metrics::WriterMetrics::new(label)));
+            w.metrics_allocated(self.preallocate as u64);
+        }
+
+        let r = w.new_reader();
+        (w, r)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::TripleBufferBuilder;
+
+    #[test]
+    fn test_invalid_options() {
+        let no_buffers = TripleBufferBuilder::new(0).build();
+        assert!(no_buffers.is_err());
+
+        let prealloc_without_clone = TripleBufferBuilder::new(0)
+            .spare_buffers(vec![0])
+            .preallocate(1)
+            .build();
+        assert!(prealloc_without_clone.is_err());
+
+        let too_small = TripleBufferBuilder::new(0)
+            .clone_with(|v| *v)
+            .max_buffers(1)
+            .build();
+        assert!(too_small.is_err());
+
+        let too_many = TripleBufferBuilder::new(0)
+            .clone_with(|v| *v)
+            .preallocate(3)
+            .max_buffers(3)
+            .build();
+        let err = too_many.err().unwrap();
+        assert!(err.to_string().contains("exceed max_buffers"));
+    }
+
+    #[test]
+    fn test_preallocate() {
+        let count = Arc::new(Mutex::new(0));
+        let c = count.clone();
+        let (mut w, mut r) = TripleBufferBuilder::new(0)
+            .clone_with(move |v| {
+                *c.lock().unwrap() += 1;
+                *v
+            })
+            .preallocate(2)
+            .build()
+            .unwrap();
+        assert_eq!(*count.lock().unwrap(), 2);
+
+        for i in 1..10 {
+            w.write_new(|_, new| *new = i);
+            w.write_new(|_, new| *new = i);
+            assert_eq!(*r.read_newest(), i);
+        }
+        assert_eq!(*count.lock().unwrap(), 2);
+    }
+
+    #[test]
+    fn test_copy_buffers_compose() {
+        let (mut w, mut r) = TripleBufferBuilder::new([0u64; 512])
+            .copy_buffers()
+            .preallocate(2)
+            .max_buffers(3)
+            .build()
+            .unwrap();
+        for i in 1..100 {
+            w.try_write_new(|old, new| {
+                *new = *old;
+                new[i % 512] = i as u64;
+            })
+            .unwrap();
+            if i % 2 == 0 {
+                assert_eq!(r.read_newest()[i % 512], i as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_max_buffers() {
+        let (mut w, mut r) = TripleBufferBuilder::new(0)
+            .clone_with(|v| *v)
+            .max_buffers(2)
+            .build()
+            .unwrap();
+        assert!(w.try_write_new(|_, new| *new = 1).is_ok());
+        assert!(w.try_write_new(|_, new| *new = 2).is_err());
+        assert_eq!(*r.read_newest(), 1);
+        assert!(w.try_write_new(|_, new| *new = 2).is_ok());
+        assert_eq!(*r.read_newest(), 2);
+    }
+
+    #[test]
+    fn test_label() {
+        let (w, r) = TripleBufferBuilder::new(0)
+            .clone_with(|v| *v)
+            .label(String::from("physics"))
+            .build()
+            .unwrap();
+        assert_eq!(w.label(), Some("physics"));
+        assert_eq!(r.label(), Some("physics"));
+        assert_eq!(
+            format!("{:?}", r),
+            r#"Reader { label: Some("physics"), disconnected: false, .. }"#
+        );
+
+        let (w2, r2) = crate::new_clone(0);
+        assert_eq!(w2.label(), None);
+        let err = crate::TripleBuffer::join(w, r2).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "writer physics and reader (unlabeled) belong to different buffer pairs"
+        );
+        let (w, _) = err.into_halves();
+        assert!(format!("{:?}", w).starts_with(r#"Writer { label: Some("physics")"#));
+        drop((r, w2));
+    }
+
+    #[test]
+    #[should_panic(expected = "physics: no unused buffer available")]
+    fn test_label_in_panics() {
+        let (mut w, _r) = TripleBufferBuilder::new(0)
+            .copy_buffers()
+            .max_buffers(2)
+            .label("physics")
+            .build()
+            .unwrap();
+        // The reader holds the initial state.
+        w.write_new(|_, new| *new = 1);
+        w.write_new(|_, new| *new = 2);
+    }
+
+    #[cfg(feature = "std")]
+    #[test]
+    fn test_timestamps() {
+        use std::time::Instant;
+
+        let (mut w, mut r) = TripleBufferBuilder::new(0)
+            .clone_with(|v| *v)
+            .timestamps(true)
+            .build()
+            .unwrap();
+        assert_eq!(r.published_at(), None);
+
+        let before = Instant::now();
+        w.write_new(|_, new| *new = 1);
+        r.read_newest();
+        let at = r.published_at().unwrap();
+        assert!(at >= before && at <= Instant::now());
+
+        let (mut w, mut r) = crate::new_clone(0);
+        w.write_new(|_, new| *new = 1);
+        r.read_newest();
+        assert_eq!(r.published_at(), None);
+    }
+}
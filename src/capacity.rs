@@ -0,0 +1,149 @@
+//! Growing buffers up front, for states about to hold a lot more.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+#[cfg(feature = "std")]
+use std::hash::{BuildHasher, Hash};
+
+use crate::{BufferPool, PublishReceipt, Writer};
+
+/// States that can make room for a number of elements ahead of time,
+/// see `Writer::write_new_with_capacity`.
+pub trait Reserve {
+    /// Make room for at least `capacity` elements in total.
+    fn reserve_total(&mut self, capacity: usize);
+
+    /// Get the number of elements there is room for.
+    fn capacity(&self) -> usize;
+}
+
+impl<T> Reserve for Vec<T> {
+    fn reserve_total(&mut self, capacity: usize) {
+        self.reserve(capacity.saturating_sub(self.len()));
+    }
+
+    fn capacity(&self) -> usize {
+        Vec::capacity(self)
+    }
+}
+
+impl<T> Reserve for VecDeque<T> {
+    fn reserve_total(&mut self, capacity: usize) {
+        self.reserve(capacity.saturating_sub(self.len()));
+    }
+
+    fn capacity(&self) -> usize {
+        VecDeque::capacity(self)
+    }
+}
+
+impl Reserve for String {
+    fn reserve_total(&mut self, capacity: usize) {
+        self.reserve(capacity.saturating_sub(self.len()));
+    }
+
+    fn capacity(&self) -> usize {
+        String::capacity(self)
+    }
+}
+
+/// Only available with the `std` feature.
+#[cfg(feature = "std")]
+impl<K: Eq + Hash, V, S: BuildHasher> Reserve for HashMap<K, V, S> {
+    fn reserve_total(&mut self, capacity: usize) {
+        self.reserve(capacity.saturating_sub(self.len()));
+    }
+
+    fn capacity(&self) -> usize {
+        HashMap::capacity(self)
+    }
+}
+
+impl<T: Reserve> Writer<T> {
+    /// Write the next state like `write_new`, after making room in the
+    /// buffer for as many elements as `capacity` returns for the
+    /// previous state.
+    ///
+    /// For a state about to grow a lot in one write, like when loading
+    /// a level, this grows the buffer once instead of reallocating it
+    /// again and again while `write_op` fills it. The buffer keeps that
+    /// capacity when it gets reused, see `unused_capacity`.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(Vec::new());
+    /// writer.write_new_with_capacity(
+    ///     |_| 1000,
+    ///     |_, new| {
+    ///         new.clear();
+    ///         new.extend(0..1000);
+    ///     },
+    /// );
+    /// assert_eq!(reader.read_newest().len(), 1000);
+    /// ````
+    ///
+    /// # Panics
+    /// Panics if the pair was created without a way to create
+    /// new buffers, and all existing ones are in use.
+    pub fn write_new_with_capacity(
+        &mut self,
+        capacity: impl FnOnce(&T) -> usize,
+        write_op: impl FnOnce(&T, &mut T),
+    ) -> PublishReceipt {
+        self.write_new(|old, new| {
+            new.reserve_total(capacity(old));
+            write_op(old, new);
+        })
+    }
+
+    /// Get the total capacity of the unused buffers
+    /// waiting to be written into.
+    ///
+    /// Buffers put into a shared `BufferPool` are not counted,
+    /// see `BufferPool::pooled_capacity` for those.
+    pub fn unused_capacity(&self) -> usize {
+        let mut capacity = self.spare.as_ref().map_or(0, |buf| buf.capacity());
+        self.unused_bufs_rx
+            .for_each(|buf| capacity += buf.capacity());
+        capacity
+    }
+}
+
+impl<T: Reserve> BufferPool<T> {
+    /// Get the total capacity of the buffers in the pool.
+    pub fn pooled_capacity(&self) -> usize {
+        let mut capacity = 0;
+        self.for_each(|buf| capacity += buf.capacity());
+        capacity
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::new_clone;
+
+    #[test]
+    fn test_reused_buffers_keep_capacity() {
+        let (mut w, mut r) = new_clone(Vec::<u32>::new());
+        for _ in 0..3 {
+            w.write_new_with_capacity(
+                |_| 100,
+                |_, new| {
+                    assert!(new.capacity() >= 100);
+                    new.clear();
+                    new.push(1);
+                },
+            );
+            r.read_newest();
+        }
+        // Both buffers in rotation got grown, and stay that way.
+        assert!(w.unused_capacity() >= 100);
+        for _ in 0..10 {
+            w.write_new(|_, new| assert!(new.capacity() >= 100));
+            r.read_newest();
+        }
+    }
+}
@@ -0,0 +1,311 @@
+//! A C interface to pairs of byte buffers.
+//!
+//! Writers and readers are opaque handles, created together by
+//! `stb_new`. Every state is a byte string, and every publish gets a
+//! version number, starting at 1, so C code can tell states apart.
+//!
+//! All functions return one of the `STB_*` status codes, or a count for
+//! `stb_has_update`. Null handles and pointers get rejected with
+//! `STB_ERR_NULL`, and a panic inside the library gets reported as
+//! `STB_ERR_PANIC` instead of unwinding into the caller. Handles that
+//! were already destroyed, or never came from `stb_new`, can not be
+//! detected, and are undefined behavior like in any other C API.
+//!
+//! The header `include/simple_triple_buffer.h` is generated with
+//! `cbindgen --config cbindgen.toml --output include/simple_triple_buffer.h`.
+//! To get a shared library, build with
+//! `cargo rustc --release --features capi --crate-type cdylib`.
+//!
+//! ```c
+//! stb_writer_t *writer;
+//! stb_reader_t *reader;
+//! stb_new(64, &writer, &reader);
+//!
+//! const uint8_t data[3] = {1, 2, 3};
+//! stb_write(writer, data, sizeof data);
+//!
+//! uint8_t out[64];
+//! size_t written;
+//! uint64_t version;
+//! stb_read(reader, out, sizeof out, &written, &version);
+//!
+//! stb_writer_free(writer);
+//! stb_reader_free(reader);
+//! ```
+
+#![allow(non_camel_case_types)]
+
+use std::os::raw::c_int;
+use std::panic::{self, AssertUnwindSafe};
+use std::{ptr, slice};
+
+use crate::{new_clone, Reader, Writer};
+
+/// The call succeeded.
+pub const STB_OK: c_int = 0;
+/// A required handle or pointer was null.
+pub const STB_ERR_NULL: c_int = -1;
+/// The output buffer is too small for the state, see `stb_read`.
+pub const STB_ERR_TOO_SMALL: c_int = -2;
+/// The library panicked, which is a bug in it.
+pub const STB_ERR_PANIC: c_int = -3;
+
+#[derive(Clone)]
+struct Frame {
+    version: u64,
+    bytes: Vec<u8>,
+}
+
+/// The write side of a pair of byte buffers.
+pub struct stb_writer_t {
+    writer: Writer<Frame>,
+}
+
+/// The read side of a pair of byte buffers.
+pub struct stb_reader_t {
+    reader: Reader<Frame>,
+}
+
+/// Run `f`, turning a panic into `STB_ERR_PANIC`.
+fn guard(f: impl FnOnce() -> c_int) -> c_int {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(STB_ERR_PANIC)
+}
+
+/// Create a new pair, whose initial state is `len` zero bytes
+/// with version 0, and store its halves in `writer` and `reader`.
+///
+/// # Safety
+/// `writer` and `reader` have to be null, or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn stb_new(
+    len: usize,
+    writer: *mut *mut stb_writer_t,
+    reader: *mut *mut stb_reader_t,
+) -> c_int {
+    if writer.is_null() || reader.is_null() {
+        return STB_ERR_NULL;
+    }
+    guard(|| {
+        let (w, r) = new_clone(Frame {
+            version: 0,
+            bytes: vec![0; len],
+        });
+        // SAFETY: Both pointers were checked for null above, and
+        // the caller guarantees that they are valid otherwise.
+        unsafe {
+            *writer = Box::into_raw(Box::new(stb_writer_t { writer: w }));
+            *reader = Box::into_raw(Box::new(stb_reader_t { reader: r }));
+        }
+        STB_OK
+    })
+}
+
+/// Publish the `len` bytes at `data` as the new state.
+///
+/// `data` may be null if `len` is 0.
+///
+/// # Safety
+/// `writer` has to be null, or a writer from `stb_new` that was not
+/// destroyed yet, used by one thread at a time. `data` has to be null,
+/// or valid for reading `len` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn stb_write(
+    writer: *mut stb_writer_t,
+    data: *const u8,
+    len: usize,
+) -> c_int {
+    if writer.is_null() || (data.is_null() && len > 0) {
+        return STB_ERR_NULL;
+    }
+    let data = if len == 0 {
+        &[][..]
+    } else {
+        // SAFETY: Checked for null above, valid as guaranteed by the caller.
+        unsafe { slice::from_raw_parts(data, len) }
+    };
+    // SAFETY: Checked for null above, valid as guaranteed by the caller.
+    let writer = unsafe { &mut (*writer).writer };
+    guard(|| {
+        writer.write_new(|old, new| {
+            new.version = old.version + 1;
+            new.bytes.clear();
+            new.bytes.extend_from_slice(data);
+        });
+        STB_OK
+    })
+}
+
+/// Copy the newest state into the `cap` bytes at `out`.
+///
+/// Stores the length of the state in `written`, and its version in
+/// `version`, unless that is null. If the state is longer than `cap`,
+/// nothing gets copied, `written` is set to the length needed, and
+/// `STB_ERR_TOO_SMALL` returned. The state counts as read either way.
+///
+/// # Safety
+/// `reader` has to be null, or a reader from `stb_new` that was not
+/// destroyed yet, used by one thread at a time. `out` has to be null,
+/// or valid for writing `cap` bytes. `written` and `version` have to
+/// be null, or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn stb_read(
+    reader: *mut stb_reader_t,
+    out: *mut u8,
+    cap: usize,
+    written: *mut usize,
+    version: *mut u64,
+) -> c_int {
+    if reader.is_null() || written.is_null() || (out.is_null() && cap > 0) {
+        return STB_ERR_NULL;
+    }
+    // SAFETY: Checked for null above, valid as guaranteed by the caller.
+    let reader = unsafe { &mut (*reader).reader };
+    guard(|| {
+        let frame = reader.read_newest();
+        // SAFETY: Checked for null above, valid as guaranteed by the caller.
+        unsafe {
+            *written = frame.bytes.len();
+            if !version.is_null() {
+                *version = frame.version;
+            }
+        }
+        if frame.bytes.len() > cap {
+            return STB_ERR_TOO_SMALL;
+        }
+        // SAFETY: `out` has room for `cap` bytes, which is enough, and
+        // can not overlap with the state, which the library allocated.
+        unsafe { ptr::copy_nonoverlapping(frame.bytes.as_ptr(), out, frame.bytes.len()) };
+        STB_OK
+    })
+}
+
+/// Check whether a state was published that `stb_read` did not return
+/// yet. Returns 1 if so, 0 if not, or a negative status code.
+///
+/// # Safety
+/// `reader` has to be null, or a reader from `stb_new`
+/// that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn stb_has_update(reader: *const stb_reader_t) -> c_int {
+    if reader.is_null() {
+        return STB_ERR_NULL;
+    }
+    // SAFETY: Checked for null above, valid as guaranteed by the caller.
+    let reader = unsafe { &(*reader).reader };
+    guard(|| c_int::from(reader.has_update()))
+}
+
+/// Destroy a writer. Does nothing if `writer` is null.
+///
+/// # Safety
+/// `writer` has to be null, or a writer from `stb_new`
+/// that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn stb_writer_free(writer: *mut stb_writer_t) -> c_int {
+    if writer.is_null() {
+        return STB_OK;
+    }
+    // SAFETY: The caller guarantees that this is a live handle,
+    // which `stb_new` created with `Box::into_raw`.
+    let writer = unsafe { Box::from_raw(writer) };
+    guard(|| {
+        drop(writer);
+        STB_OK
+    })
+}
+
+/// Destroy a reader. Does nothing if `reader` is null.
+///
+/// # Safety
+/// `reader` has to be null, or a reader from `stb_new`
+/// that was not destroyed yet.
+#[no_mangle]
+pub unsafe extern "C" fn stb_reader_free(reader: *mut stb_reader_t) -> c_int {
+    if reader.is_null() {
+        return STB_OK;
+    }
+    // SAFETY: The caller guarantees that this is a live handle,
+    // which `stb_new` created with `Box::into_raw`.
+    let reader = unsafe { Box::from_raw(reader) };
+    guard(|| {
+        drop(reader);
+        STB_OK
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ptr;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let (mut w, mut r) = (ptr::null_mut(), ptr::null_mut());
+        unsafe {
+            assert_eq!(stb_new(2, &mut w, &mut r), STB_OK);
+            let (mut out, mut written, mut version) = ([0xffu8; 4], 0, 99);
+            assert_eq!(stb_has_update(r), 0);
+            assert_eq!(
+                stb_read(r, out.as_mut_ptr(), 4, &mut written, &mut version),
+                STB_OK
+            );
+            assert_eq!((&out[..written], version), (&[0, 0][..], 0));
+
+            assert_eq!(stb_write(w, [1, 2, 3].as_ptr(), 3), STB_OK);
+            assert_eq!(stb_has_update(r), 1);
+            assert_eq!(
+                stb_read(r, out.as_mut_ptr(), 2, &mut written, ptr::null_mut()),
+                STB_ERR_TOO_SMALL
+            );
+            assert_eq!(written, 3);
+            assert_eq!(
+                stb_read(r, out.as_mut_ptr(), 4, &mut written, &mut version),
+                STB_OK
+            );
+            assert_eq!((&out[..written], version), (&[1, 2, 3][..], 1));
+
+            assert_eq!(stb_write(w, ptr::null(), 0), STB_OK);
+            assert_eq!(
+                stb_read(r, ptr::null_mut(), 0, &mut written, &mut version),
+                STB_OK
+            );
+            assert_eq!((written, version), (0, 2));
+
+            assert_eq!(stb_writer_free(w), STB_OK);
+            assert_eq!(stb_reader_free(r), STB_OK);
+        }
+    }
+
+    #[test]
+    fn test_null_handles() {
+        let mut written = 0;
+        unsafe {
+            assert_eq!(stb_new(1, ptr::null_mut(), ptr::null_mut()), STB_ERR_NULL);
+            assert_eq!(stb_write(ptr::null_mut(), ptr::null(), 0), STB_ERR_NULL);
+            assert_eq!(
+                stb_read(
+                    ptr::null_mut(),
+                    ptr::null_mut(),
+                    0,
+                    &mut written,
+                    ptr::null_mut()
+                ),
+                STB_ERR_NULL
+            );
+            assert_eq!(stb_has_update(ptr::null()), STB_ERR_NULL);
+            assert_eq!(stb_writer_free(ptr::null_mut()), STB_OK);
+            assert_eq!(stb_reader_free(ptr::null_mut()), STB_OK);
+
+            let (mut w, mut r) = (ptr::null_mut(), ptr::null_mut());
+            stb_new(0, &mut w, &mut r);
+            assert_eq!(stb_write(w, ptr::null(), 1), STB_ERR_NULL);
+            assert_eq!(
+                stb_read(r, ptr::null_mut(), 1, &mut written, ptr::null_mut()),
+                STB_ERR_NULL
+            );
+            stb_writer_free(w);
+            stb_reader_free(r);
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Which sections of the state changed between the states a reader read.
+
+use alloc::boxed::Box;
+use core::ops::{BitOr, BitOrAssign};
+use core::sync::atomic::Ordering;
+
+use crate::sync::atomic::AtomicU64;
+use crate::{Buf, Reader, Writer};
+
+/// The sections of a state that changed, one bit for each,
+/// see `TripleBufferBuilder::change_mask`.
+///
+/// What the bits stand for is up to the pair, like bit 0 for the
+/// player and bit 1 for the map of a game state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ChangeMask(pub u64);
+
+impl ChangeMask {
+    /// No section changed.
+    pub const NONE: ChangeMask = ChangeMask(0);
+    /// Every section might have changed.
+    pub const ALL: ChangeMask = ChangeMask(u64::MAX);
+
+    /// Get the mask of only section `section`, which is below 64.
+    pub const fn bit(section: u32) -> ChangeMask {
+        ChangeMask(1 << section)
+    }
+
+    /// Check whether no section changed.
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Check whether any of the sections of `other` changed.
+    pub fn intersects(self, other: ChangeMask) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for ChangeMask {
+    type Output = ChangeMask;
+
+    fn bitor(self, other: ChangeMask) -> ChangeMask {
+        ChangeMask(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for ChangeMask {
+    fn bitor_assign(&mut self, other: ChangeMask) {
+        self.0 |= other.0;
+    }
+}
+
+/// Compares the previous state with the one about to be published.
+pub(crate) type Diff<T> = Box<dyn Fn(&T, &T) -> ChangeMask + Send>;
+
+/// The version of the last publish that changed each section.
+///
+/// Readers can skip any number of publishes, so instead of keeping
+/// the mask of each of them around, every section remembers when it
+/// changed last. A reader that last read version `v` then knows that
+/// every section changed after `v` is changed for it.
+pub(crate) struct ChangeLog {
+    changed_at: [AtomicU64; 64],
+}
+
+impl ChangeLog {
+    pub(crate) fn new() -> Self {
+        Self {
+            changed_at: core::array::from_fn(|_| AtomicU64::new(0)),
+        }
+    }
+
+    /// Record the changes of the publish of `version`, before it
+    /// gets published, so readers picking it up see them.
+    fn record(&self, version: u64, changes: ChangeMask) {
+        for (section, changed_at) in self.changed_at.iter().enumerate() {
+            if changes.0 & (1 << section) != 0 {
+                changed_at.store(version, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Get the sections that changed after `version`.
+    ///
+    /// The writer may have published more since the state read last,
+    /// so this may include sections that only changed after it, which
+    /// the next read reports again. It never misses any, though.
+    fn since(&self, version: u64) -> ChangeMask {
+        let mut changes = ChangeMask::NONE;
+        for (section, changed_at) in self.changed_at.iter().enumerate() {
+            if changed_at.load(Ordering::Relaxed) > version {
+                changes |= ChangeMask::bit(section as u32);
+            }
+        }
+        changes
+    }
+}
+
+impl<T> Writer<T> {
+    /// Record the changes of `new_state`, about to be published.
+    pub(crate) fn record_changes(&self, new_state: &Buf<T>) {
+        if let (Some(diff), Some(log)) = (&self.diff, &self.read_update.shared.changes) {
+            log.record(self.version + 1, diff(&self.prev_buf, new_state));
+        }
+    }
+}
+
+impl<T> Reader<T> {
+    /// Get the sections that changed between the state returned by the
+    /// read before the last one and the state the last read returned.
+    ///
+    /// A reader that skipped some publishes gets all sections any of
+    /// them changed. If the last read returned the same state as the
+    /// one before, nothing changed. Pairs built without
+    /// `TripleBufferBuilder::change_mask` report `ChangeMask::ALL`
+    /// for every new state.
+    ///
+    /// Only available with the `std` feature.
+    ///
+    /// # Example
+    /// ```
+    /// use simple_triple_buffer::{ChangeMask, TripleBufferBuilder};
+    ///
+    /// const A: ChangeMask = ChangeMask::bit(0);
+    /// const B: ChangeMask = ChangeMask::bit(1);
+    ///
+    /// let (mut writer, mut reader) = TripleBufferBuilder::new((0, 0))
+    ///     .copy_buffers()
+    ///     .change_mask(|old: &(i32, i32), new: &(i32, i32)| {
+    ///         let mut changes = ChangeMask::NONE;
+    ///         if old.0 != new.0 { changes |= A; }
+    ///         if old.1 != new.1 { changes |= B; }
+    ///         changes
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    ///
+    /// writer.write_update(|state| state.0 = 1);
+    /// reader.read_newest();
+    /// assert_eq!(reader.changes_since_last_read(), A);
+    ///
+    /// writer.write_update(|state| state.1 = 1);
+    /// reader.read_newest();
+    /// assert_eq!(reader.changes_since_last_read(), B);
+    ///
+    /// reader.read_newest();
+    /// assert!(reader.changes_since_last_read().is_empty());
+    /// ````
+    pub fn changes_since_last_read(&self) -> ChangeMask {
+        self.changes
+    }
+
+    /// Get the changes since `version`, for a state about to be picked up.
+    pub(crate) fn changes_since(&self, version: u64) -> ChangeMask {
+        match &self.read_update.shared.changes {
+            Some(log) => log.since(version),
+            None => ChangeMask::ALL,
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::ChangeMask;
+    use crate::{Reader, TripleBufferBuilder, Writer};
+
+    /// A state of three sections, and the bit of each that changed.
+    fn pair() -> (Writer<[u32; 3]>, Reader<[u32; 3]>) {
+        TripleBufferBuilder::new([0; 3])
+            .copy_buffers()
+            .change_mask(|old: &[u32; 3], new: &[u32; 3]| {
+                let mut changes = ChangeMask::NONE;
+                for section in 0..3 {
+                    if old[section] != new[section] {
+                        changes |= ChangeMask::bit(section as u32);
+                    }
+                }
+                changes
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_coalesced_publishes_are_the_union() {
+        let (mut w, mut r) = pair();
+        w.write_update(|state| state[0] = 1);
+        w.write_update(|state| state[2] = 1);
+        w.write_update(|state| state[0] = 2);
+        assert_eq!(*r.read_newest(), [2, 0, 1]);
+        assert_eq!(
+            r.changes_since_last_read(),
+            ChangeMask::bit(0) | ChangeMask::bit(2)
+        );
+
+        w.write_update(|state| state[1] = 1);
+        r.read_newest();
+        assert_eq!(r.changes_since_last_read(), ChangeMask::bit(1));
+        r.read_newest();
+        assert_eq!(r.changes_since_last_read(), ChangeMask::NONE);
+    }
+
+    #[test]
+    fn test_readers_keep_their_own_masks() {
+        let (mut w, mut r1) = pair();
+        let mut r2 = r1.clone();
+        w.write_update(|state| state[0] = 1);
+        r1.read_newest();
+        w.write_update(|state| state[1] = 1);
+        r1.read_newest();
+        r2.read_newest();
+        assert_eq!(r1.changes_since_last_read(), ChangeMask::bit(1));
+        assert_eq!(
+            r2.changes_since_last_read(),
+            ChangeMask::bit(0) | ChangeMask::bit(1)
+        );
+        // A republished state that did not change reports nothing.
+        w.write_update(|_| {});
+        r1.read_newest();
+        assert!(r1.changes_since_last_read().is_empty());
+    }
+
+    #[test]
+    fn test_pairs_without_diff() {
+        let (mut w, mut r) = crate::new_clone(0);
+        w.write_new(|_, new| *new = 1);
+        r.read_newest();
+        assert_eq!(r.changes_since_last_read(), ChangeMask::ALL);
+        r.read_newest();
+        assert_eq!(r.changes_since_last_read(), ChangeMask::NONE);
+    }
+}
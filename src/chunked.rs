@@ -0,0 +1,359 @@
+//! A large state split into chunks that are buffered independently.
+//!
+//! The state is a list of chunks, and a write only copies the chunks
+//! it changes. Chunks written with `ChunkedWriter::write_chunk` become
+//! visible together on the next `ChunkedWriter::commit`, which publishes
+//! them as one generation. Readers always see a complete generation,
+//! never a mix of chunks from different commits.
+//!
+//! Generations reference their chunks, so unchanged chunks are shared
+//! between all of them. A written chunk gets copied into a spare chunk
+//! first, taken from a pool shared by all chunks, which gets refilled
+//! with the chunks that new generations replaced, once no generation
+//! references them anymore.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::chunked::ChunkedTripleBuffer;
+//!
+//! let (mut writer, mut reader) = ChunkedTripleBuffer::new(vec![0u32; 4]).split();
+//!
+//! writer.write_chunk(1, |chunk| *chunk = 1);
+//! writer.write_chunk(3, |chunk| *chunk = 3);
+//! assert_eq!(reader.read_newest().chunk(1), &0);
+//!
+//! writer.commit();
+//! let generation = reader.read_newest();
+//! assert_eq!(generation.number(), 1);
+//! assert_eq!(generation.iter().copied().collect::<Vec<_>>(), [0, 1, 0, 3]);
+//! ````
+
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::{Reader, TripleBufferBuilder, Writer};
+
+/// Both halves of a chunked buffer pair, before they get split.
+pub struct ChunkedTripleBuffer<T> {
+    writer: ChunkedWriter<T>,
+    reader: ChunkedReader<T>,
+}
+
+impl<T: Clone> ChunkedTripleBuffer<T> {
+    /// Create a new chunked buffer pair, starting with `chunks`
+    /// as generation 0.
+    pub fn new(chunks: Vec<T>) -> Self {
+        let chunks: Vec<Arc<T>> = chunks.into_iter().map(Arc::new).collect();
+        let init = Generation {
+            number: 0,
+            chunks: chunks.clone(),
+        };
+        let (writer, reader) = TripleBufferBuilder::new(init)
+            .clone_with(|generation| generation.clone())
+            .scrub_with(Generation::release)
+            .finish();
+        let writer = ChunkedWriter {
+            writer,
+            dirty: vec![false; chunks.len()],
+            chunks,
+            written: Vec::new(),
+            spares: VecDeque::new(),
+            max_spares: 0,
+            number: 0,
+            created: 0,
+        };
+        Self {
+            writer,
+            reader: ChunkedReader { reader },
+        }
+    }
+}
+
+impl<T> ChunkedTripleBuffer<T> {
+    /// Split into the two halves.
+    pub fn split(self) -> (ChunkedWriter<T>, ChunkedReader<T>) {
+        (self.writer, self.reader)
+    }
+}
+
+/// A committed state, made up of references to its chunks.
+pub struct Generation<T> {
+    number: u64,
+    chunks: Vec<Arc<T>>,
+}
+
+impl<T> Clone for Generation<T> {
+    fn clone(&self) -> Self {
+        Self {
+            number: self.number,
+            chunks: self.chunks.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.number = source.number;
+        self.chunks.clone_from(&source.chunks);
+    }
+}
+
+impl<T> Generation<T> {
+    /// Drop the references of a generation no longer in use,
+    /// so that the chunks only it references can be reused.
+    fn release(&mut self) {
+        self.chunks.clear();
+    }
+
+    /// Get the number of commits that lead to this generation.
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    /// Get the number of chunks.
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    /// Check whether the state has no chunks at all.
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Get the chunk at `idx`.
+    ///
+    /// # Panics
+    /// Panics if `idx` is out of bounds.
+    pub fn chunk(&self, idx: usize) -> &T {
+        &self.chunks[idx]
+    }
+
+    /// Iterate over all chunks.
+    pub fn iter(&self) -> impl Iterator<Item = &T> + '_ {
+        self.chunks.iter().map(|chunk| &**chunk)
+    }
+}
+
+/// Write side of a chunked buffer pair.
+pub struct ChunkedWriter<T> {
+    writer: Writer<Generation<T>>,
+    /// The chunks of the next generation.
+    chunks: Vec<Arc<T>>,
+    /// Whether a chunk was written since the last commit,
+    /// in which case only the writer references it.
+    dirty: Vec<bool>,
+    /// The indices of the chunks written since the last commit.
+    written: Vec<usize>,
+    /// Chunks replaced by a commit, oldest first.
+    spares: VecDeque<Arc<T>>,
+    /// The number of spares to keep around, see `commit`.
+    max_spares: usize,
+    /// The number of the last committed generation.
+    number: u64,
+    /// The number of chunks created in addition to the initial ones.
+    created: usize,
+}
+
+impl<T: Clone> ChunkedWriter<T> {
+    /// Update the chunk at `idx` for the next generation.
+    ///
+    /// The first write to a chunk after a commit copies it into a spare
+    /// chunk, and further ones update that copy in place. Readers only
+    /// see the changes once they get committed.
+    ///
+    /// # Panics
+    /// Panics if `idx` is out of bounds.
+    pub fn write_chunk(&mut self, idx: usize, write_op: impl FnOnce(&mut T)) {
+        if !self.dirty[idx] {
+            let mut spare = self.take_spare(idx);
+            Arc::get_mut(&mut spare)
+                .unwrap()
+                .clone_from(&self.chunks[idx]);
+            let replaced = core::mem::replace(&mut self.chunks[idx], spare);
+            self.spares.push_back(replaced);
+            self.dirty[idx] = true;
+            self.written.push(idx);
+        }
+        write_op(Arc::get_mut(&mut self.chunks[idx]).unwrap());
+    }
+
+    /// Take a chunk no generation references anymore,
+    /// or create one from the chunk at `idx`.
+    fn take_spare(&mut self, idx: usize) -> Arc<T> {
+        let unused = self
+            .spares
+            .iter_mut()
+            .position(|spare| Arc::get_mut(spare).is_some());
+        match unused.and_then(|pos| self.spares.remove(pos)) {
+            Some(spare) => spare,
+            None => {
+                self.created += 1;
+                Arc::new((*self.chunks[idx]).clone())
+            }
+        }
+    }
+}
+
+impl<T> ChunkedWriter<T> {
+    /// Publish all chunks written since the last commit
+    /// as a new generation, returning its number.
+    ///
+    /// Readers either see all of them, or none.
+    ///
+    /// The pool of spare chunks keeps up to 3 times as many chunks as the
+    /// largest commit so far changed, which is enough for their replacements
+    /// to come back into use as readers move on to newer generations.
+    pub fn commit(&mut self) -> u64 {
+        self.number += 1;
+        let (number, chunks) = (self.number, &self.chunks);
+        self.writer.write_new(|_, new| {
+            new.number = number;
+            new.chunks.clone_from(chunks);
+        });
+        self.max_spares = self.max_spares.max(3 * self.written.len());
+        for idx in self.written.drain(..) {
+            self.dirty[idx] = false;
+        }
+        while self.spares.len() > self.max_spares {
+            self.spares.pop_front();
+        }
+        number
+    }
+
+    /// Get the number of chunks written since the last commit.
+    pub fn written(&self) -> usize {
+        self.written.len()
+    }
+
+    /// Check whether the `ChunkedReader` has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.writer.is_closed()
+    }
+}
+
+/// Read side of a chunked buffer pair.
+///
+/// Cloning it creates another reader for the same pair, see `Reader`.
+pub struct ChunkedReader<T> {
+    reader: Reader<Generation<T>>,
+}
+
+impl<T> Clone for ChunkedReader<T> {
+    fn clone(&self) -> Self {
+        Self {
+            reader: self.reader.clone(),
+        }
+    }
+}
+
+impl<T> ChunkedReader<T> {
+    /// Get the newest committed generation.
+    pub fn read_newest(&mut self) -> &Generation<T> {
+        self.reader.read_newest()
+    }
+
+    /// Check whether a generation has been committed
+    /// that `read_newest` has not returned yet.
+    pub fn has_update(&self) -> bool {
+        self.reader.has_update()
+    }
+
+    /// Check whether the `ChunkedWriter` has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.reader.is_disconnected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChunkedTripleBuffer;
+
+    #[test]
+    fn test_uncommitted_writes_are_invisible() {
+        let (mut w, mut r) = ChunkedTripleBuffer::new(vec![0u32; 8]).split();
+        for idx in 0..4 {
+            w.write_chunk(idx, |chunk| *chunk = 1);
+            w.write_chunk(idx, |chunk| *chunk += 1);
+            assert!(!r.has_update());
+            assert!(r.read_newest().iter().all(|chunk| *chunk == 0));
+        }
+        assert_eq!(w.written(), 4);
+        assert_eq!(w.commit(), 1);
+        assert_eq!(w.written(), 0);
+        let generation = r.read_newest();
+        assert_eq!(generation.number(), 1);
+        assert_eq!(
+            generation.iter().copied().collect::<Vec<_>>(),
+            [2, 2, 2, 2, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_spares_are_reused() {
+        let (mut w, mut r) = ChunkedTripleBuffer::new(vec![[0u8; 256]; 1024]).split();
+        for g in 1..=1000 {
+            for idx in (0..1024).step_by(97) {
+                w.write_chunk((idx + g) % 1024, |chunk| chunk[0] = g as u8);
+            }
+            w.commit();
+            if g % 3 == 0 {
+                r.read_newest();
+            }
+        }
+        // 11 chunks per commit, and a few generations of them in use.
+        assert!(w.created <= 11 * 4, "{}", w.created);
+        assert!(w.spares.len() <= 33);
+    }
+
+    /// Commit `g` writes `g` into every third chunk, so the value
+    /// every chunk of a generation must have is known.
+    fn expected(idx: usize, generation: u64) -> u64 {
+        let since = (idx as u64 + generation) % 3;
+        generation.saturating_sub(since)
+    }
+
+    #[test]
+    fn test_readers_observe_whole_generations() {
+        const CHUNKS: usize = 64;
+        const COMMITS: u64 = 2000;
+        let (mut w, r) = ChunkedTripleBuffer::new(vec![[0u64; 8]; CHUNKS]).split();
+        let readers: Vec<_> = (0..3)
+            .map(|_| {
+                let mut r = r.clone();
+                std::thread::spawn(move || {
+                    let mut last = 0;
+                    loop {
+                        let disconnected = r.is_disconnected();
+                        let generation = r.read_newest();
+                        let g = generation.number();
+                        assert!(g >= last);
+                        last = g;
+                        for (idx, chunk) in generation.iter().enumerate() {
+                            assert_eq!(*chunk, [expected(idx, g); 8], "chunk {} of {}", idx, g);
+                        }
+                        if disconnected {
+                            return g;
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(r);
+        for g in 1..=COMMITS {
+            for idx in 0..CHUNKS {
+                if (idx as u64 + g).is_multiple_of(3) {
+                    // Written one element at a time, to give
+                    // readers a chance to catch half-written chunks.
+                    for i in 0..8 {
+                        w.write_chunk(idx, |chunk| chunk[i] = g);
+                    }
+                }
+            }
+            w.commit();
+        }
+        drop(w);
+        for reader in readers {
+            assert_eq!(reader.join().unwrap(), COMMITS);
+        }
+    }
+}
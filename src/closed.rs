@@ -0,0 +1,294 @@
+use core::sync::atomic::Ordering;
+#[cfg(feature = "async")]
+use core::{
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use crate::{scrub_unique, Buf, ReadUpdate, Reader, Writer};
+
+impl<T> Writer<T> {
+    /// Check whether the `Reader` has been dropped.
+    ///
+    /// Once this returns `true`, nothing will observe the states
+    /// written by this `Writer` again, unless `Writer::subscribe`
+    /// adds a new reader.
+    pub fn is_closed(&self) -> bool {
+        self.read_update.shared.readers.load(Ordering::Acquire) == 0
+    }
+
+    /// Wait until the `Reader` has been dropped.
+    ///
+    /// The returned future resolves as soon as the `Reader` is dropped,
+    /// even if the `Writer` does not publish anything in the meantime.
+    /// This is useful for stopping a producer once nobody consumes
+    /// its states anymore.
+    #[cfg(feature = "async")]
+    pub fn closed(&mut self) -> Closed<'_, T> {
+        Closed { writer: self }
+    }
+}
+
+impl<T> Reader<T> {
+    /// Check whether the `Writer` has been dropped.
+    ///
+    /// Once this returns `true`, no new states will be published,
+    /// though the last one might not have been picked up yet.
+    ///
+    /// Readers detached with `Writer::detach` count as disconnected too.
+    pub fn is_disconnected(&self) -> bool {
+        #[cfg(feature = "std")]
+        if self.entry.is_detached() {
+            return true;
+        }
+        !self.read_update.shared.writer_alive.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Reader<T> {
+    /// Take the newest state out of the pair, if the writer is gone
+    /// and nothing else references it anymore, or get the reader back.
+    ///
+    /// This moves the state on without cloning it, at the end of a
+    /// processing chain. It fails while other readers still hold the
+    /// newest state, the writer keeps a history of states, or the pair
+    /// returns its buffers to a `BufferSource`, without changing anything.
+    /// Other readers that did not pick up the newest state yet keep the
+    /// one they hold.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, reader) = simple_triple_buffer::new_clone(vec![0; 1024]);
+    /// writer.write_new(|_, new| new[0] = 1);
+    ///
+    /// // The writer still has the state published.
+    /// let reader = reader.take_if_unique().unwrap_err();
+    /// drop(writer);
+    /// assert_eq!(reader.take_if_unique().ok().unwrap()[0], 1);
+    /// ````
+    pub fn take_if_unique(mut self) -> Result<T, Self> {
+        let shared = &self.read_update.shared;
+        if shared.source_hooks || shared.writer_alive.load(Ordering::Acquire) {
+            return Err(self);
+        }
+        self.read_newest();
+        let shared = &self.read_update.shared;
+        let Some(taken) = shared.pending.take_unique(&self.prev_buf) else {
+            return Err(self);
+        };
+        // Leaves `taken` as the only reference. Scrubbing skips the
+        // buffer for that reason too, as it is still referenced here.
+        drop(self);
+        match Buf::try_unwrap(taken) {
+            Ok(state) => Ok(state),
+            Err(_) => unreachable!("the reader held the only other reference"),
+        }
+    }
+}
+
+impl<T> Drop for Writer<T> {
+    fn drop(&mut self) {
+        self.read_update
+            .shared
+            .writer_alive
+            .store(false, Ordering::Release);
+        #[cfg(all(feature = "readiness", unix))]
+        self.raise_readiness();
+        #[cfg(feature = "std")]
+        self.wake_readers();
+        #[cfg(feature = "async")]
+        self.wake_streams();
+        // `prev_buf` belongs to the pending slot, and gets
+        // scrubbed and retired along with the shared state.
+        self.orphan_source();
+    }
+}
+
+impl<T> Clone for Reader<T> {
+    fn clone(&self) -> Self {
+        self.try_clone().unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl<T> Reader<T> {
+    /// Create another reader for the same pair, which
+    /// has already been counted in `SharedState::readers`.
+    pub(crate) fn clone_counted(&self) -> Self {
+        Self {
+            prev_buf: self.prev_buf.clone(),
+            unused_bufs_tx: self.unused_bufs_tx.clone(),
+            read_update: ReadUpdate {
+                shared: self.read_update.shared.clone(),
+            },
+            rewound: None,
+            rejected: None,
+            #[cfg(feature = "std")]
+            prev_time: self.prev_time,
+            #[cfg(feature = "std")]
+            prev_version: self.prev_version,
+            #[cfg(feature = "std")]
+            changes: crate::ChangeMask::NONE,
+            #[cfg(feature = "std")]
+            entry: self
+                .read_update
+                .shared
+                .reader_list
+                .register(self.prev_version, self.entry.is_detached()),
+        }
+    }
+}
+
+impl<T> Drop for Reader<T> {
+    fn drop(&mut self) {
+        if let Some(rewound) = self.rewound.take() {
+            self.recycle(rewound);
+        }
+        if let Some(rejected) = self.rejected.take() {
+            self.recycle(rejected);
+        }
+        // The writer already gave up on the buffer of a detached reader.
+        #[cfg(feature = "std")]
+        let detached = self.is_detached();
+        #[cfg(not(feature = "std"))]
+        let detached = false;
+        let shared = &self.read_update.shared;
+        scrub_unique(shared.scrub, &mut self.prev_buf);
+        if shared.source_hooks {
+            // Returned or retired once nothing else references it.
+            shared.released.lock().push(self.prev_buf.clone());
+        } else if !detached && Buf::get_mut(&mut self.prev_buf).is_some() {
+            // Gone with this reader, so the writer may create another one.
+            shared.freed.fetch_add(1, Ordering::Relaxed);
+        }
+        #[cfg(feature = "std")]
+        shared.reader_list.unregister(&self.entry);
+        if shared.readers.fetch_sub(1, Ordering::AcqRel) == 1 {
+            if let Some(waker) = shared.closed_waker.lock().take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+/// Future returned by `Writer::closed`.
+#[cfg(feature = "async")]
+#[must_use = "futures do nothing unless polled"]
+pub struct Closed<'a, T> {
+    writer: &'a Writer<T>,
+}
+
+#[cfg(feature = "async")]
+impl<T> Future for Closed<'_, T> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.writer.is_closed() {
+            return Poll::Ready(());
+        }
+        *self.writer.read_update.shared.closed_waker.lock() = Some(cx.waker().clone());
+
+        // The last reader might have gone away before the waker was stored.
+        if self.writer.is_closed() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::new_clone;
+
+    #[test]
+    fn test_is_closed() {
+        let (w, r) = new_clone(0);
+        assert!(!w.is_closed());
+        drop(r);
+        assert!(w.is_closed());
+    }
+
+    #[test]
+    fn test_is_disconnected() {
+        let (w, r) = new_clone(0);
+        assert!(!r.is_disconnected());
+        drop(w);
+        assert!(r.is_disconnected());
+    }
+
+    #[test]
+    fn test_take_after_writer() {
+        let (mut w, r) = new_clone(vec![0u8; 16]);
+        let mut r2 = r.clone();
+        w.write_new(|_, new| new[0] = 1);
+        let ptr = r2.read_newest().as_ptr();
+        drop(w);
+        // `r2` still holds the newest state.
+        let r = r.take_if_unique().unwrap_err();
+        drop(r2);
+        let state = r.take_if_unique().ok().unwrap();
+        assert_eq!(state[0], 1);
+        assert_eq!(state.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_take_before_writer() {
+        let (mut w, r) = new_clone(vec![0u8; 16]);
+        w.write_new(|_, new| new[0] = 1);
+        let mut r = r.take_if_unique().unwrap_err();
+        // Failing left the reader untouched.
+        assert!(r.has_update());
+        assert_eq!(r.read_newest()[0], 1);
+        let mut r2 = r.clone();
+        w.write_new(|_, new| new[0] = 2);
+        drop(w);
+        assert_eq!(r.take_if_unique().ok().unwrap()[0], 2);
+        // Taken before `r2` got to it.
+        assert!(!r2.has_update());
+        assert_eq!(r2.read_newest()[0], 1);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_closed_resolves_on_reader_drop() {
+        use std::future::Future;
+        use std::sync::Arc;
+        use std::task::{Context, Poll, Wake};
+        use std::thread::{self, Thread};
+        use std::time::Duration;
+
+        struct ThreadWaker(Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        fn block_on<F: Future>(fut: F) -> F::Output {
+            let mut fut = Box::pin(fut);
+            let waker = Arc::new(ThreadWaker(thread::current())).into();
+            let mut cx = Context::from_waker(&waker);
+            loop {
+                if let Poll::Ready(v) = fut.as_mut().poll(&mut cx) {
+                    return v;
+                }
+                thread::park();
+            }
+        }
+
+        let (mut w, r) = new_clone(0);
+        w.write_new(|_, new| *new = 1);
+        let t = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            drop(r);
+        });
+        block_on(w.closed());
+        assert!(w.is_closed());
+        t.join().unwrap();
+
+        // Resolves immediately once closed.
+        block_on(w.closed());
+    }
+}
@@ -0,0 +1,138 @@
+use core::fmt;
+
+use crate::sync::Arc;
+
+use crate::{new_clone, new_with, JoinError, PublishReceipt, Reader, Writer};
+
+/// Both halves of a buffer pair in one value.
+///
+/// This is convenient for using the pair from a single thread,
+/// for example in tests, or for storing it before the halves
+/// get moved to their threads with `split`.
+///
+/// # Example
+/// ```
+/// use simple_triple_buffer::TripleBuffer;
+///
+/// let mut buffer = TripleBuffer::new_clone(0);
+/// buffer.write_new(|old, new| *new = *old + 1);
+/// assert_eq!(*buffer.read_newest(), 1);
+///
+/// let (writer, reader) = buffer.split();
+/// let buffer = TripleBuffer::join(writer, reader).unwrap();
+/// ````
+pub struct TripleBuffer<T> {
+    writer: Writer<T>,
+    reader: Reader<T>,
+}
+
+impl<T> fmt::Debug for TripleBuffer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TripleBuffer")
+            .field("label", &self.writer.label())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> TripleBuffer<T> {
+    /// Create a new buffer pair that creates additional
+    /// buffer instances with a custom clone function.
+    ///
+    /// See `new_with`.
+    pub fn new_with(init: T, make_buf: impl FnMut(&T) -> T + 'static + Send) -> Self {
+        let (writer, reader) = new_with(init, make_buf);
+        Self { writer, reader }
+    }
+
+    /// Create a new buffer pair that creates additional
+    /// buffer instances by cloning a previous state.
+    ///
+    /// See `new_clone`.
+    pub fn new_clone(init: T) -> Self
+    where
+        T: Clone,
+    {
+        let (writer, reader) = new_clone(init);
+        Self { writer, reader }
+    }
+
+    /// Reassemble the two halves of a pair.
+    ///
+    /// Fails, returning both halves, if they
+    /// do not belong to the same pair.
+    // The error just hands back the same halves the success case holds.
+    #[allow(clippy::result_large_err)]
+    pub fn join(writer: Writer<T>, reader: Reader<T>) -> Result<Self, JoinError<T>> {
+        if Arc::ptr_eq(&writer.read_update.shared, &reader.read_update.shared) {
+            Ok(Self { writer, reader })
+        } else {
+            Err(JoinError { writer, reader })
+        }
+    }
+
+    /// Separate the pair into its two halves.
+    pub fn split(self) -> (Writer<T>, Reader<T>) {
+        (self.writer, self.reader)
+    }
+
+    /// Access the write side of the pair.
+    pub fn writer(&mut self) -> &mut Writer<T> {
+        &mut self.writer
+    }
+
+    /// Access the read side of the pair.
+    pub fn reader(&mut self) -> &mut Reader<T> {
+        &mut self.reader
+    }
+
+    /// Write the next state into the buffer.
+    ///
+    /// See `Writer::write_new`.
+    pub fn write_new(&mut self, write_op: impl FnOnce(&T, &mut T)) -> PublishReceipt {
+        self.writer.write_new(write_op)
+    }
+
+    /// Get a view to the newest state currently in the buffer.
+    ///
+    /// See `Reader::read_newest`.
+    pub fn read_newest(&mut self) -> &T {
+        self.reader.read_newest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TripleBuffer;
+
+    #[test]
+    fn test_join_checks_pairs() {
+        let (w1, r1) = TripleBuffer::new_clone(1).split();
+        let (w2, r2) = TripleBuffer::new_clone(2).split();
+
+        let err = TripleBuffer::join(w1, r2).err().unwrap();
+        let (w1, r2) = err.into_halves();
+        let err = TripleBuffer::join(w2, r1).err().unwrap();
+        let (w2, r1) = err.into_halves();
+
+        let mut b1 = TripleBuffer::join(w1, r1).unwrap();
+        let mut b2 = TripleBuffer::join(w2, r2).unwrap();
+        assert_eq!(*b1.read_newest(), 1);
+        assert_eq!(*b2.read_newest(), 2);
+    }
+
+    #[test]
+    fn test_split_across_threads() {
+        let mut buffer = TripleBuffer::new_clone(0);
+        buffer.write_new(|_, new| *new = 1);
+        assert_eq!(*buffer.read_newest(), 1);
+
+        let (mut w, r) = buffer.split();
+        std::thread::spawn(move || w.write_new(|_, new| *new = 2))
+            .join()
+            .unwrap();
+        let (w, _) = TripleBuffer::new_clone(0).split();
+        let err = TripleBuffer::join(w, r).err().unwrap();
+        let (_, mut r) = err.into_halves();
+        assert_eq!(*r.read_newest(), 2);
+    }
+}
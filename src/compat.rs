@@ -0,0 +1,221 @@
+//! Wrappers with the vocabulary of the `triple_buffer` crate.
+//!
+//! `Input` and `Output` mirror the API of the same name in
+//! `triple_buffer`, so migrating can mostly be done by swapping
+//! imports. They are thin wrappers around `Writer` and `Reader`,
+//! and the differences between the two crates show up as follows:
+//!
+//! - `Input::write` publishes a value. This needs a buffer to move
+//!   the value into, which is cloned from the previous state until
+//!   enough buffers are around to be recycled, so prefer
+//!   `Writer::write_new` to avoid building a whole new `T` per write.
+//! - `Input::input_buffer` gives access to an unused buffer, which,
+//!   like in `triple_buffer`, contains some older state, not
+//!   necessarily the last published one. `Input::publish` then
+//!   publishes it. The buffer is taken from the pool of unused buffers
+//!   when first accessed, and returned to it if the `Input` gets
+//!   turned back into a `Writer` without publishing it.
+//! - States can be shared between both halves, so `Output` can not
+//!   hand out a mutable reference to its state in place.
+//!   `Output::output_buffer` is read only, and `output_buffer_mut`
+//!   first clones the state if the `Writer` still references it.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::compat::TripleBuffer;
+//!
+//! let buf = TripleBuffer::new(&0);
+//! let (mut input, mut output) = buf.split();
+//!
+//! input.write(42);
+//! assert!(output.updated());
+//! assert_eq!(*output.read(), 42);
+//!
+//! *input.input_buffer() = 43;
+//! input.publish();
+//! assert!(output.update());
+//! assert_eq!(*output.output_buffer(), 43);
+//! ````
+
+use crate::{new_clone, Buf, Reader, Writer};
+
+/// Both halves of a buffer pair, before they get split.
+pub struct TripleBuffer<T> {
+    input: Input<T>,
+    output: Output<T>,
+}
+
+impl<T: Clone> TripleBuffer<T> {
+    /// Create a new buffer pair starting with a clone of `initial`.
+    pub fn new(initial: &T) -> Self {
+        let (input, output) = triple_buffer(initial);
+        Self { input, output }
+    }
+}
+
+impl<T> TripleBuffer<T> {
+    /// Split into the two halves.
+    pub fn split(self) -> (Input<T>, Output<T>) {
+        (self.input, self.output)
+    }
+}
+
+/// Create a new buffer pair starting with a clone of `initial`.
+pub fn triple_buffer<T: Clone>(initial: &T) -> (Input<T>, Output<T>) {
+    let (writer, reader) = new_clone(initial.clone());
+    (Input::from(writer), Output::from(reader))
+}
+
+/// The write side, see `Writer`.
+pub struct Input<T> {
+    writer: Writer<T>,
+    /// The buffer handed out by `input_buffer`, if any.
+    scratch: Option<Buf<T>>,
+}
+
+impl<T> Input<T> {
+    /// Publish `value` as the new state.
+    pub fn write(&mut self, value: T) {
+        *self.input_buffer() = value;
+        self.publish();
+    }
+
+    /// Check whether the reader has picked up the last published state.
+    pub fn consumed(&self) -> bool {
+        self.writer.read_update.shared.pending.consumed()
+    }
+
+    /// Get mutable access to the buffer that `publish` will publish.
+    ///
+    /// It contains some older state, not necessarily
+    /// the last published one.
+    ///
+    /// # Panics
+    /// If no unused buffer is available, see `Writer::write_new`.
+    pub fn input_buffer(&mut self) -> &mut T {
+        let writer = &mut self.writer;
+        let scratch = self
+            .scratch
+            .get_or_insert_with(|| writer.next_unused_buffer());
+        // Buffers handed out as unused have no other references.
+        Buf::get_mut(scratch).unwrap()
+    }
+
+    /// Publish the contents of `input_buffer`.
+    ///
+    /// Returns whether this replaced a state the reader
+    /// has not picked up yet.
+    pub fn publish(&mut self) -> bool {
+        let buf = match self.scratch.take() {
+            Some(buf) => buf,
+            None => self.writer.next_unused_buffer(),
+        };
+        self.writer.publish(buf)
+    }
+
+    /// Unwrap the underlying `Writer`.
+    pub fn into_writer(mut self) -> Writer<T> {
+        if let Some(buf) = self.scratch.take() {
+            self.writer.discard(buf);
+        }
+        self.writer
+    }
+}
+
+impl<T> From<Writer<T>> for Input<T> {
+    fn from(writer: Writer<T>) -> Self {
+        Self {
+            writer,
+            scratch: None,
+        }
+    }
+}
+
+/// The read side, see `Reader`.
+pub struct Output<T> {
+    reader: Reader<T>,
+}
+
+impl<T> Output<T> {
+    /// Get the newest published state.
+    pub fn read(&mut self) -> &T {
+        self.reader.read_newest()
+    }
+
+    /// Check whether a new state has been published
+    /// since the last call to `read` or `update`.
+    pub fn updated(&self) -> bool {
+        self.reader.has_update()
+    }
+
+    /// Pick up the newest published state, if any,
+    /// returning whether there was one.
+    pub fn update(&mut self) -> bool {
+        let updated = self.reader.has_update();
+        self.reader.read_newest();
+        updated
+    }
+
+    /// Get the state picked up by the last `read` or `update`.
+    pub fn output_buffer(&self) -> &T {
+        &self.reader.prev_buf
+    }
+
+    /// Get mutable access to the state picked
+    /// up by the last `read` or `update`.
+    ///
+    /// If the `Writer` still references that state,
+    /// this first replaces it with a clone.
+    pub fn output_buffer_mut(&mut self) -> &mut T
+    where
+        T: Clone,
+    {
+        Buf::make_mut(&mut self.reader.prev_buf)
+    }
+
+    /// Unwrap the underlying `Reader`.
+    pub fn into_reader(self) -> Reader<T> {
+        self.reader
+    }
+}
+
+impl<T> From<Reader<T>> for Output<T> {
+    fn from(reader: Reader<T>) -> Self {
+        Self { reader }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::triple_buffer;
+
+    #[test]
+    fn test_publish_flags() {
+        let (mut input, mut output) = triple_buffer(&0);
+        assert!(input.consumed());
+        assert!(!output.updated());
+        assert!(!output.update());
+
+        assert!(!input.publish());
+        assert!(!input.consumed());
+        input.write(1);
+        assert!(output.updated());
+        assert!(output.update());
+        assert!(input.consumed());
+        assert_eq!(*output.output_buffer(), 1);
+    }
+
+    #[test]
+    fn test_output_buffer_mut() {
+        let (mut input, mut output) = triple_buffer(&vec![0]);
+        input.write(vec![1]);
+        output.read();
+        output.output_buffer_mut().push(2);
+        assert_eq!(*output.output_buffer(), [1, 2]);
+
+        // The writer still sees its own state as the previous one.
+        let mut writer = input.into_writer();
+        writer.write_new(|old, new| new.clone_from(old));
+        assert_eq!(*output.read(), [1]);
+    }
+}
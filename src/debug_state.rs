@@ -0,0 +1,266 @@
+//! A snapshot of where the buffers of a pair are, see `Writer::debug_state`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem;
+use core::sync::atomic::Ordering;
+
+use crate::{ref_count, Buf, Writer};
+
+/// Where a buffer is, as seen by `Writer::debug_state`.
+#[non_exhaustive]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Location {
+    /// The newest published state, waiting in the pending slot.
+    Published {
+        /// Whether a reader has picked it up.
+        read: bool,
+    },
+    /// An unread state the writer kept for its next write.
+    Spare,
+    /// Returned to the writer, waiting to be reused.
+    Returned,
+    /// Kept for `Reader::rewind`, see `Writer::keep_history`.
+    History,
+    /// Let go of by a dropped reader, waiting
+    /// for its other references to go away.
+    Released,
+}
+
+/// A buffer in a `PoolDebug`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferDebug {
+    /// Identifies the buffer, stable for as long as it exists.
+    pub id: usize,
+    /// Where the buffer is.
+    pub location: Location,
+    /// The number of references to it, including the one of its location.
+    pub refs: usize,
+}
+
+/// A snapshot of the buffers of a pair, returned by `Writer::debug_state`.
+///
+/// Buffers held by readers only are not listed, but the difference
+/// between `created` and the listed buffers tells how many there are.
+/// The counts of the listed ones tell which of them readers hold too.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PoolDebug {
+    /// The label of the pair, if any.
+    pub label: Option<String>,
+    /// The number of buffers the writer created or was given,
+    /// and that still exist, as far as it knows.
+    pub created: usize,
+    /// The limit set with `TripleBufferBuilder::max_buffers`, if any.
+    pub max_buffers: Option<usize>,
+    /// The number of readers.
+    pub readers: usize,
+    /// The buffers the writer can see, by location.
+    /// Buffers kept by the history are listed once more for that.
+    pub buffers: Vec<BufferDebug>,
+    /// The size of a single state, not counting any heap memory it owns.
+    pub state_size: usize,
+}
+
+impl PoolDebug {
+    /// Get the number of buffers only held by readers.
+    pub fn reader_held(&self) -> usize {
+        let mut ids: Vec<_> = self.buffers.iter().map(|buf| buf.id).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        self.created.saturating_sub(ids.len())
+    }
+}
+
+impl fmt::Display for PoolDebug {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = self.label.as_deref().unwrap_or("(unlabeled)");
+        writeln!(
+            f,
+            "pair {}: {} buffers of {} bytes, {} readers",
+            label, self.created, self.state_size, self.readers
+        )?;
+        writeln!(f, "{:>18}  {:<18}  {:>4}", "id", "location", "refs")?;
+        for buf in &self.buffers {
+            let location = match buf.location {
+                Location::Published { read: true } => "published, read",
+                Location::Published { read: false } => "published, unread",
+                Location::Spare => "spare",
+                Location::Returned => "returned",
+                Location::History => "history",
+                Location::Released => "released",
+            };
+            writeln!(f, "{:>#18x}  {:<18}  {:>4}", buf.id, location, buf.refs)?;
+        }
+        write!(f, "{} more held by readers only", self.reader_held())
+    }
+}
+
+/// A summary of a pair small enough to log often, returned by
+/// `Writer::status`.
+///
+/// Unlike `PoolDebug`, it takes no locks and allocates nothing.
+/// With the `defmt` feature, it implements `defmt::Format`.
+#[non_exhaustive]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PairStatus {
+    /// The number of states published, see `Writer::version`.
+    pub version: u64,
+    /// The number of buffers the writer created or was given,
+    /// and that still exist, as far as it knows.
+    pub created: usize,
+    /// The number of readers.
+    pub readers: usize,
+    /// Whether a reader picked up the newest state.
+    pub read: bool,
+}
+
+impl fmt::Display for PairStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "version {} ({}), {} buffers, {} readers",
+            self.version,
+            if self.read { "read" } else { "unread" },
+            self.created,
+            self.readers
+        )
+    }
+}
+
+impl<T> Writer<T> {
+    /// Get a summary of the pair, see `PairStatus`.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, _reader) = simple_triple_buffer::new_clone(0u64);
+    /// writer.write_new(|_, new| *new = 1);
+    ///
+    /// let status = writer.status();
+    /// assert_eq!(status.version, 1);
+    /// assert_eq!(status.to_string(), "version 1 (unread), 2 buffers, 1 readers");
+    /// ````
+    pub fn status(&self) -> PairStatus {
+        let shared = &self.read_update.shared;
+        PairStatus {
+            version: self.version,
+            created: self.created,
+            readers: shared.readers.load(Ordering::Relaxed),
+            read: shared.pending.consumed(),
+        }
+    }
+
+    /// Get a snapshot of where the buffers of the pair currently are.
+    ///
+    /// Readers may keep reading meanwhile, so the snapshot is only
+    /// exact if they do not. This takes the same locks that keeping
+    /// a history and dropping readers take. Buffers put into a shared
+    /// `BufferPool` are not listed, see `BufferPool::stats` for those.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0u64);
+    /// writer.write_new(|_, new| *new = 1);
+    /// reader.read_newest();
+    ///
+    /// let state = writer.debug_state();
+    /// assert_eq!(state.created, 2);
+    /// assert_eq!(state.reader_held(), 0);
+    /// println!("{}", state);
+    /// ````
+    pub fn debug_state(&self) -> PoolDebug {
+        let shared = &self.read_update.shared;
+        let mut buffers = Vec::new();
+        let mut push = |buf: &Buf<T>, location| {
+            buffers.push(BufferDebug {
+                id: Buf::as_ptr(buf) as usize,
+                location,
+                refs: ref_count(buf),
+            })
+        };
+        // The pending slot always holds the last published state.
+        let read = shared.pending.consumed();
+        push(&self.prev_buf, Location::Published { read });
+        if let Some(spare) = &self.spare {
+            push(spare, Location::Spare);
+        }
+        self.unused_bufs_rx
+            .for_each(|buf| push(buf, Location::Returned));
+        for buf in shared.history.lock().iter() {
+            push(buf, Location::History);
+        }
+        for buf in shared.released.lock().iter() {
+            push(buf, Location::Released);
+        }
+        PoolDebug {
+            label: self.label().map(String::from),
+            created: self.created,
+            max_buffers: (self.max_buffers != usize::MAX).then_some(self.max_buffers),
+            readers: shared.readers.load(Ordering::Relaxed),
+            buffers,
+            state_size: mem::size_of::<T>(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Location;
+    use crate::new_clone;
+
+    #[test]
+    fn test_locations() {
+        let (mut w, mut r) = new_clone([0u8; 16]);
+        w.write_new(|_, new| new[0] = 1);
+        let state = w.debug_state();
+        assert_eq!(state.created, 2);
+        assert_eq!(state.buffers.len(), 1);
+        assert_eq!(
+            state.buffers[0].location,
+            Location::Published { read: false }
+        );
+        // The reader still holds the initial state.
+        assert_eq!(state.reader_held(), 1);
+
+        r.read_newest();
+        let state = w.debug_state();
+        let locations: Vec<_> = state.buffers.iter().map(|buf| buf.location).collect();
+        assert_eq!(
+            locations,
+            [Location::Published { read: true }, Location::Returned]
+        );
+        assert_eq!(state.buffers[0].refs, 2);
+        assert_eq!(state.buffers[1].refs, 1);
+        assert_eq!(state.reader_held(), 0);
+
+        w.keep_history(1);
+        w.write_new(|_, new| new[0] = 2);
+        w.write_new(|_, new| new[0] = 3);
+        let state = w.debug_state();
+        assert_eq!(state.state_size, 16);
+        assert!(state
+            .buffers
+            .iter()
+            .any(|buf| buf.location == Location::History));
+        let table = state.to_string();
+        assert!(table.starts_with("pair (unlabeled): 3 buffers of 16 bytes, 1 readers\n"));
+        assert!(table.contains("published, unread"));
+        // The reader still holds the state it read last.
+        assert!(table.ends_with("1 more held by readers only"));
+    }
+
+    #[test]
+    fn test_status() {
+        let (mut w, mut r) = new_clone(0u8);
+        let _r2 = r.clone();
+        w.write_new(|_, new| *new = 1);
+        r.read_newest();
+        let status = w.status();
+        assert_eq!((status.version, status.readers), (1, 2));
+        assert!(status.read);
+        assert_eq!(status.created, w.debug_state().created);
+    }
+}
@@ -0,0 +1,202 @@
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as HashMap;
+use alloc::collections::VecDeque;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{new_clone, Buf, Reader, Writer};
+
+/// Number of past operations that are kept for replaying.
+///
+/// Buffers that fell further behind than this get fully resynced.
+const JOURNAL_LEN: usize = 32;
+
+type Apply<T, Op> = Box<dyn FnMut(&mut T, &Op) + Send>;
+
+fn key<T>(buf: &Buf<T>) -> usize {
+    Buf::as_ptr(buf) as usize
+}
+
+/// Create a new buffer pair whose states are written
+/// by applying operations with `DeltaWriter::write_delta`.
+///
+/// Instead of copying the whole previous state into a reused buffer,
+/// the writer replays the operations the buffer has missed since it
+/// was last published onto it. Only buffers that fell behind by more
+/// than the last 32 operations get fully resynced with `Clone::clone_from`.
+///
+/// # Example
+/// ```
+/// enum Op {
+///     Push(u32),
+///     Pop,
+/// }
+///
+/// let (mut writer, mut reader) =
+///     simple_triple_buffer::new_with_delta(vec![], |v: &mut Vec<u32>, op: &Op| match op {
+///         Op::Push(x) => v.push(*x),
+///         Op::Pop => drop(v.pop()),
+///     });
+///
+/// writer.write_delta(Op::Push(1));
+/// writer.write_delta(Op::Push(2));
+/// writer.write_delta(Op::Pop);
+/// assert_eq!(*reader.read_newest(), [1]);
+/// ````
+pub fn new_with_delta<T, Op>(
+    init: T,
+    apply: impl FnMut(&mut T, &Op) + 'static + Send,
+) -> (DeltaWriter<T, Op>, Reader<T>)
+where
+    T: Clone,
+{
+    let (writer, reader) = new_clone(init);
+    let mut synced = HashMap::new();
+    synced.insert(key(&writer.prev_buf), 0);
+    let writer = DeltaWriter {
+        writer,
+        apply: Box::new(apply),
+        journal: VecDeque::with_capacity(JOURNAL_LEN),
+        generation: 0,
+        synced,
+    };
+    (writer, reader)
+}
+
+/// Write side of a pair created with `new_with_delta`.
+pub struct DeltaWriter<T, Op> {
+    writer: Writer<T>,
+    apply: Apply<T, Op>,
+    /// The last operations, with the generation they produced.
+    journal: VecDeque<(u64, Op)>,
+    generation: u64,
+    /// The generation each buffer was last brought up to, by address.
+    synced: HashMap<usize, u64>,
+}
+
+impl<T: Clone, Op> DeltaWriter<T, Op> {
+    /// Publish the previous state with `op` applied to it.
+    pub fn write_delta(&mut self, op: Op) {
+        let created = self.writer.created;
+        let mut new_state = self.writer.next_unused_buffer();
+        // Fresh buffers are clones of the previous state, and might
+        // reuse the address of a buffer that got freed in the meantime.
+        let synced = if self.writer.created != created {
+            Some(self.generation)
+        } else {
+            self.synced.remove(&key(&new_state))
+        };
+
+        // This Arc will have no other clones at this point,
+        // so we can get a mutable reference into it.
+        let new = Buf::get_mut(&mut new_state).unwrap();
+        match synced {
+            Some(synced) if synced + self.journal.len() as u64 >= self.generation => {
+                for (_, missed) in self.journal.iter().filter(|(gen, _)| *gen > synced) {
+                    (self.apply)(new, missed);
+                }
+            }
+            _ => new.clone_from(&self.writer.prev_buf),
+        }
+        (self.apply)(new, &op);
+
+        self.generation += 1;
+        if self.journal.len() == JOURNAL_LEN {
+            self.journal.pop_front();
+        }
+        self.journal.push_back((self.generation, op));
+        self.synced.insert(key(&new_state), self.generation);
+        let oldest = self.generation - self.journal.len() as u64;
+        self.synced.retain(|_, gen| *gen >= oldest);
+
+        self.writer.publish(new_state);
+    }
+}
+
+impl<T, Op> DeltaWriter<T, Op> {
+    /// Check whether the `Reader` has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.writer.is_closed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::new_with_delta;
+
+    /// A state that counts how often it gets fully copied.
+    #[derive(Default)]
+    struct State {
+        values: Vec<u32>,
+        copies: Arc<AtomicUsize>,
+    }
+
+    impl Clone for State {
+        fn clone(&self) -> Self {
+            self.copies.fetch_add(1, Ordering::Relaxed);
+            State {
+                values: self.values.clone(),
+                copies: self.copies.clone(),
+            }
+        }
+    }
+
+    fn push(state: &mut State, op: &u32) {
+        state.values.push(*op);
+    }
+
+    #[test]
+    fn test_replays_missed_ops() {
+        let copies = Arc::new(AtomicUsize::new(0));
+        let init = State {
+            values: vec![],
+            copies: copies.clone(),
+        };
+        let (mut w, mut r) = new_with_delta(init, push);
+        for i in 0..100 {
+            w.write_delta(i);
+            if i % 3 == 0 {
+                assert_eq!(r.read_newest().values, (0..=i).collect::<Vec<_>>());
+            }
+        }
+        assert_eq!(r.read_newest().values, (0..100).collect::<Vec<_>>());
+        // Only the buffers that got created needed a full copy.
+        assert!(copies.load(Ordering::Relaxed) <= 3);
+    }
+
+    #[test]
+    fn test_pinned_buffer_falls_back_to_clone() {
+        let copies = Arc::new(AtomicUsize::new(0));
+        let init = State {
+            values: vec![],
+            copies: copies.clone(),
+        };
+        let (mut w, mut r) = new_with_delta(init, push);
+        for round in 0..5 {
+            // The reader pins its buffer across more ops than
+            // the journal remembers.
+            let pinned = r.read_newest().values.clone();
+            for i in 0..50 {
+                w.write_delta(round * 50 + i);
+            }
+            assert_eq!(r.read_newest().values.len(), pinned.len() + 50);
+        }
+        assert_eq!(r.read_newest().values, (0..250).collect::<Vec<_>>());
+        assert!(copies.load(Ordering::Relaxed) >= 4);
+    }
+
+    #[test]
+    fn test_lagging_within_journal() {
+        let (mut w, mut r) = new_with_delta(Vec::new(), |v: &mut Vec<u32>, op: &u32| v.push(*op));
+        for round in 0..20 {
+            for i in 0..10 {
+                w.write_delta(round * 10 + i);
+            }
+            assert_eq!(*r.read_newest(), (0..(round + 1) * 10).collect::<Vec<_>>());
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! A double buffer, for when a third copy of the state is too much.
+//!
+//! Exactly two buffers exist: the one the reader sees, and the one
+//! the writer writes into. A write gets published as soon as the
+//! reader is not looking at the current state, or else once it
+//! drops its `ReadGuard`. Until then, a further write has to wait.
+//!
+//! Apart from `read_newest` returning a guard, the API mirrors the
+//! one of the triple buffer.
+//!
+//! Only available with the `std` feature.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::double;
+//!
+//! let (mut writer, mut reader) = double::new_clone(0);
+//! writer.write_new(|old, new| *new = *old + 1);
+//! assert_eq!(*reader.read_newest(), 1);
+//! ````
+
+use std::cell::UnsafeCell;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::sync::{Condvar, Mutex, MutexGuard};
+use crate::WouldBlock;
+
+struct State {
+    /// The buffer the reader sees.
+    front: usize,
+    /// Set while a `ReadGuard` exists.
+    reading: bool,
+    /// Set if the writer published while the reader was reading,
+    /// in which case dropping the guard swaps the buffers.
+    swap_pending: bool,
+}
+
+struct Shared<T> {
+    bufs: [UnsafeCell<T>; 2],
+    state: Mutex<State>,
+    swapped: Condvar,
+}
+
+// The writer only writes to the buffer that is not `front`,
+// and the buffers only get swapped while nobody reads `front`.
+unsafe impl<T: Send + Sync> Sync for Shared<T> {}
+
+impl<T> Shared<T> {
+    fn swap(&self, state: &mut State) {
+        state.front = 1 - state.front;
+        state.swap_pending = false;
+        self.swapped.notify_one();
+    }
+}
+
+/// Create a new double buffer, with the second buffer
+/// created by cloning `init`.
+pub fn new_clone<T: Clone>(init: T) -> (Writer<T>, Reader<T>) {
+    let shared = Arc::new(Shared {
+        bufs: [UnsafeCell::new(init.clone()), UnsafeCell::new(init)],
+        state: Mutex::new(State {
+            front: 0,
+            reading: false,
+            swap_pending: false,
+        }),
+        swapped: Condvar::new(),
+    });
+    let writer = Writer {
+        shared: shared.clone(),
+    };
+    (writer, Reader { shared })
+}
+
+/// Write side of the double buffer.
+pub struct Writer<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Writer<T> {
+    /// Write a new state and publish it.
+    ///
+    /// The closure gets a reference to the previously published state
+    /// and to an older state it has to overwrite.
+    ///
+    /// If the previous write has not been published yet because
+    /// the reader is still holding its `ReadGuard`, this blocks
+    /// until the guard is dropped.
+    pub fn write_new(&mut self, f: impl FnOnce(&T, &mut T)) {
+        let mut state = self.shared.state.lock();
+        while state.swap_pending {
+            state = self.shared.swapped.wait(state);
+        }
+        self.write_unpublished(state, f);
+    }
+
+    /// Write a new state and publish it, or fail without calling `f`
+    /// if that would have to wait for the reader.
+    pub fn try_write_new(&mut self, f: impl FnOnce(&T, &mut T)) -> Result<(), WouldBlock> {
+        let state = self.shared.state.lock();
+        if state.swap_pending {
+            return Err(WouldBlock);
+        }
+        self.write_unpublished(state, f);
+        Ok(())
+    }
+
+    fn write_unpublished(&self, state: MutexGuard<'_, State>, f: impl FnOnce(&T, &mut T)) {
+        let front = state.front;
+        // The reader can not swap the buffers, so there is
+        // no need to hold the lock while writing.
+        drop(state);
+
+        let bufs = &self.shared.bufs;
+        // SAFETY: Only the writer writes, and only to the back buffer.
+        let (old, new) = unsafe { (&*bufs[front].get(), &mut *bufs[1 - front].get()) };
+        f(old, new);
+
+        let mut state = self.shared.state.lock();
+        if state.reading {
+            state.swap_pending = true;
+        } else {
+            self.shared.swap(&mut state);
+        }
+    }
+}
+
+/// Read side of the double buffer.
+pub struct Reader<T> {
+    shared: Arc<Shared<T>>,
+}
+
+impl<T> Reader<T> {
+    /// Get a view to the newest published state.
+    ///
+    /// While the returned guard is alive, the writer can
+    /// complete one more write, but then has to wait.
+    pub fn read_newest(&mut self) -> ReadGuard<'_, T> {
+        let front = {
+            let mut state = self.shared.state.lock();
+            state.reading = true;
+            state.front
+        };
+        ReadGuard {
+            reader: self,
+            front,
+        }
+    }
+}
+
+/// A view to the state returned by `Reader::read_newest`.
+pub struct ReadGuard<'a, T> {
+    reader: &'a mut Reader<T>,
+    front: usize,
+}
+
+impl<T> Deref for ReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The buffers do not get swapped while the guard is alive.
+        unsafe { &*self.reader.shared.bufs[self.front].get() }
+    }
+}
+
+impl<T> Drop for ReadGuard<'_, T> {
+    fn drop(&mut self) {
+        let shared = &self.reader.shared;
+        let mut state = shared.state.lock();
+        state.reading = false;
+        if state.swap_pending {
+            shared.swap(&mut state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use super::new_clone;
+
+    #[test]
+    fn test_write_while_reading() {
+        let (mut w, mut r) = new_clone(0);
+        let guard = r.read_newest();
+        assert!(w.try_write_new(|_, new| *new = 1).is_ok());
+        assert!(w.try_write_new(|_, new| *new = 2).is_err());
+        assert_eq!(*guard, 0);
+        drop(guard);
+
+        assert_eq!(*r.read_newest(), 1);
+        assert!(w.try_write_new(|old, new| *new = *old + 1).is_ok());
+        assert!(w.try_write_new(|old, new| *new = *old + 1).is_ok());
+        assert_eq!(*r.read_newest(), 3);
+    }
+
+    #[test]
+    fn test_writer_waits_for_long_read() {
+        let (mut w, mut r) = new_clone(0);
+        let written = Arc::new(AtomicUsize::new(0));
+        let guard = r.read_newest();
+
+        let t = {
+            let written = written.clone();
+            std::thread::spawn(move || {
+                for _ in 0..2 {
+                    w.write_new(|old, new| *new = *old + 1);
+                    written.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        };
+        std::thread::sleep(Duration::from_millis(50));
+        // The first write went through, the second one waits for the guard.
+        assert_eq!(written.load(Ordering::SeqCst), 1);
+        assert_eq!(*guard, 0);
+        drop(guard);
+
+        t.join().unwrap();
+        assert_eq!(written.load(Ordering::SeqCst), 2);
+        assert_eq!(*r.read_newest(), 2);
+    }
+
+    #[test]
+    fn test_threads() {
+        let (mut w, mut r) = new_clone([0u64; 8]);
+        let writer = std::thread::spawn(move || {
+            for _ in 0..10_000 {
+                w.write_new(|old, new| *new = [old[0] + 1; 8]);
+            }
+        });
+        let mut last = 0;
+        while last < 10_000 {
+            let state = r.read_newest();
+            assert!(state.iter().all(|v| *v == state[0]));
+            assert!(state[0] >= last);
+            last = state[0];
+        }
+        writer.join().unwrap();
+    }
+}
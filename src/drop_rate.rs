@@ -0,0 +1,163 @@
+//! The share of published states no reader ever picked up, over a
+//! sliding window of time.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::{Reader, Writer};
+
+/// The counts of one interval.
+struct Interval {
+    /// The number of the interval since `DropWindow::start`
+    /// the counts belong to, plus one, or 0 if unused.
+    number: AtomicU64,
+    published: AtomicU64,
+    dropped: AtomicU64,
+}
+
+/// A ring of per-interval counts of published and dropped states,
+/// see `TripleBufferBuilder::drop_rate_window`.
+///
+/// Only the writer updates it, moving on to the next interval
+/// lazily when it counts a state, so there is no timer involved.
+/// Readers only ever read it.
+pub(crate) struct DropWindow {
+    start: Instant,
+    interval: Duration,
+    intervals: Box<[Interval]>,
+}
+
+impl DropWindow {
+    pub(crate) fn new(interval: Duration, intervals: usize) -> Self {
+        assert!(
+            interval > Duration::ZERO,
+            "drop rate interval must not be zero"
+        );
+        Self {
+            start: Instant::now(),
+            interval,
+            intervals: (0..intervals.max(1))
+                .map(|_| Interval {
+                    number: AtomicU64::new(0),
+                    published: AtomicU64::new(0),
+                    dropped: AtomicU64::new(0),
+                })
+                .collect(),
+        }
+    }
+
+    fn current(&self) -> u64 {
+        let elapsed = self.start.elapsed().as_nanos() / self.interval.as_nanos();
+        elapsed as u64 + 1
+    }
+
+    /// Get the interval `number` belongs to, clearing it if it still
+    /// holds the counts of an older one.
+    fn interval(&self, number: u64) -> &Interval {
+        let interval = &self.intervals[(number % self.intervals.len() as u64) as usize];
+        if interval.number.load(Ordering::Relaxed) != number {
+            interval.published.store(0, Ordering::Relaxed);
+            interval.dropped.store(0, Ordering::Relaxed);
+            interval.number.store(number, Ordering::Relaxed);
+        }
+        interval
+    }
+
+    /// Count a published state.
+    pub(crate) fn published(&self) {
+        let interval = self.interval(self.current());
+        interval.published.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a state that got replaced without any reader picking it up.
+    pub(crate) fn dropped(&self) {
+        let interval = self.interval(self.current());
+        interval.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn rate(&self, window: Duration) -> f32 {
+        let current = self.current();
+        let covered = (window.as_nanos() / self.interval.as_nanos()).max(1) as u64;
+        let first = current.saturating_sub(covered.min(self.intervals.len() as u64) - 1);
+        let (mut published, mut dropped) = (0, 0);
+        for interval in self.intervals.iter() {
+            if (first..=current).contains(&interval.number.load(Ordering::Relaxed)) {
+                published += interval.published.load(Ordering::Relaxed);
+                dropped += interval.dropped.load(Ordering::Relaxed);
+            }
+        }
+        if published == 0 {
+            return 0.0;
+        }
+        // States published before the window may get dropped within it.
+        (dropped as f32 / published as f32).min(1.0)
+    }
+}
+
+impl<T> Writer<T> {
+    /// Get the share of states published within the last `window` that
+    /// got replaced before any reader picked them up, from 0 to 1, if
+    /// the pair was created with `TripleBufferBuilder::drop_rate_window`.
+    ///
+    /// The window gets rounded down to whole intervals, and is at most
+    /// as long as all of them together. Without any publishes within
+    /// the window, the rate is 0.
+    ///
+    /// Only available with the `stats` feature.
+    pub fn drop_rate(&self, window: Duration) -> Option<f32> {
+        let drops = self.read_update.shared.drop_window.as_ref()?;
+        Some(drops.rate(window))
+    }
+}
+
+impl<T> Reader<T> {
+    /// Get the share of states published within the last `window` that
+    /// got replaced before any reader picked them up, see
+    /// `Writer::drop_rate`.
+    ///
+    /// Only available with the `stats` feature.
+    pub fn drop_rate(&self, window: Duration) -> Option<f32> {
+        let drops = self.read_update.shared.drop_window.as_ref()?;
+        Some(drops.rate(window))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    use crate::TripleBufferBuilder;
+
+    #[test]
+    fn test_drop_rate() {
+        let interval = Duration::from_millis(50);
+        let (mut w, mut r) = TripleBufferBuilder::new(0)
+            .copy_buffers()
+            .drop_rate_window(interval, 4)
+            .build()
+            .unwrap();
+        assert_eq!(r.drop_rate(interval * 4), Some(0.0));
+
+        // Every other state gets picked up, with both write paths.
+        for i in 1..=10 {
+            if i % 4 < 2 {
+                w.write_new(|_, new| *new = i);
+            } else {
+                w.write_update(|state| *state = i);
+            }
+            if i % 2 == 0 {
+                r.read_newest();
+            }
+        }
+        let rate = w.drop_rate(interval * 4).unwrap();
+        assert!((rate - 0.5).abs() < 0.11, "{}", rate);
+
+        // Idle periods report no drops, instead of dividing by zero.
+        sleep(interval * 5);
+        assert_eq!(r.drop_rate(interval * 4), Some(0.0));
+
+        let (w, _r) = TripleBufferBuilder::new(0).copy_buffers().build().unwrap();
+        assert_eq!(w.drop_rate(interval), None);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{new_clone, Reader, Writer};
+
+/// Create two connected endpoints, each of which publishes
+/// states of one type to the other, and reads states of
+/// the other type from it.
+///
+/// The first endpoint writes `A` states starting at `init_a`,
+/// and reads `B` states starting at `init_b`.
+///
+/// # Example
+/// ```
+/// let (mut sim, mut ui) = simple_triple_buffer::duplex(0u64, String::new());
+///
+/// ui.write_new(|_, intent| *intent = "jump".into());
+/// let intent = sim.exchange(|tick, new| *new = tick + 1);
+/// assert_eq!(intent, "jump");
+/// assert_eq!(*ui.read_newest(), 1);
+///
+/// drop(sim);
+/// assert!(ui.is_disconnected());
+/// ````
+pub fn duplex<A: Clone, B: Clone>(init_a: A, init_b: B) -> (Endpoint<A, B>, Endpoint<B, A>) {
+    let (writer_a, reader_a) = new_clone(init_a);
+    let (writer_b, reader_b) = new_clone(init_b);
+    let first = Endpoint {
+        writer: writer_a,
+        reader: reader_b,
+    };
+    let second = Endpoint {
+        writer: writer_b,
+        reader: reader_a,
+    };
+    (first, second)
+}
+
+/// One end of a bidirectional connection created with `duplex`.
+///
+/// It writes `Out` states and reads `In` states.
+pub struct Endpoint<Out, In> {
+    writer: Writer<Out>,
+    reader: Reader<In>,
+}
+
+impl<Out, In> Endpoint<Out, In> {
+    /// Write the next outgoing state, see `Writer::write_new`.
+    pub fn write_new(&mut self, write_op: impl FnOnce(&Out, &mut Out)) {
+        self.writer.write_new(write_op);
+    }
+
+    /// Get the newest incoming state, see `Reader::read_newest`.
+    pub fn read_newest(&mut self) -> &In {
+        self.reader.read_newest()
+    }
+
+    /// Write the next outgoing state, then get the newest incoming one.
+    pub fn exchange(&mut self, write_op: impl FnOnce(&Out, &mut Out)) -> &In {
+        self.writer.write_new(write_op);
+        self.reader.read_newest()
+    }
+
+    /// Check whether the other endpoint has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.writer.is_closed() || self.reader.is_disconnected()
+    }
+
+    /// Get the writer for outgoing states.
+    pub fn writer(&mut self) -> &mut Writer<Out> {
+        &mut self.writer
+    }
+
+    /// Get the reader for incoming states.
+    pub fn reader(&mut self) -> &mut Reader<In> {
+        &mut self.reader
+    }
+
+    /// Split the endpoint into its writer and reader.
+    pub fn split(self) -> (Writer<Out>, Reader<In>) {
+        (self.writer, self.reader)
+    }
+}
+
+impl<Out: Clone, In> Endpoint<Out, In> {
+    /// Write the next outgoing state by updating
+    /// a copy of the previous one, see `Writer::write_update`.
+    pub fn write_update(&mut self, update_op: impl FnOnce(&mut Out)) {
+        self.writer.write_update(update_op);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::duplex;
+
+    #[test]
+    fn test_both_directions_across_threads() {
+        let (mut sim, mut ui) = duplex(0u32, 0u8);
+        let t = std::thread::spawn(move || {
+            let mut intent = 0;
+            while intent < 10 {
+                intent = *sim.exchange(|tick, new| *new = tick + 1);
+            }
+            sim
+        });
+        let mut tick = 0;
+        for i in 1..=10 {
+            ui.write_new(|_, new| *new = i);
+            tick = *ui.read_newest();
+        }
+        let sim = t.join().unwrap();
+        assert!(tick <= *ui.read_newest());
+        assert!(!ui.is_disconnected());
+        drop(sim);
+        assert!(ui.is_disconnected());
+    }
+
+    #[test]
+    fn test_split_halves_disconnect() {
+        let (a, b) = duplex(0, 0);
+        let (writer, reader) = a.split();
+        drop(reader);
+        assert!(b.is_disconnected());
+        drop(writer);
+        assert!(b.is_disconnected());
+    }
+}
@@ -0,0 +1,326 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::error::Error;
+
+use crate::{Reader, Writer};
+
+/// Error returned when a write needs an unused buffer,
+/// but none is available and no new one may be created.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolExhausted;
+
+impl fmt::Display for PoolExhausted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("no unused buffer available and creating new ones is not allowed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for PoolExhausted {}
+
+/// Error returned when adding a reader would exceed
+/// `TripleBufferBuilder::max_readers`.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooManyReaders;
+
+impl fmt::Display for TooManyReaders {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the pair already has as many readers as allowed")
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for TooManyReaders {}
+
+/// Error returned by `watch_compat::Sender::send` once all receivers
+/// are gone, handing back the state that was not sent.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct SendError<T>(pub T);
+
+// Like for channels, without requiring `T: Debug`.
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendError").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("all receivers have been dropped")
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<T> defmt::Format for SendError<T> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "SendError {{ .. }}")
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Error for SendError<T> {}
+
+/// Error returned by `Inbox::submit` when all slots of the inbox are
+/// occupied, or the writer is gone, handing back the state.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Full<T>(pub T);
+
+// Like `SendError`, without requiring `T: Debug`.
+impl<T> fmt::Debug for Full<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Full").finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Display for Full<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the inbox is full or its writer is gone")
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<T> defmt::Format for Full<T> {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(f, "Full {{ .. }}")
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Error for Full<T> {}
+
+/// Error returned by `double::Writer::try_write_new` when the
+/// write would have to wait for the reader.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WouldBlock;
+
+impl fmt::Display for WouldBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the reader still holds the buffer the write needs")
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for WouldBlock {}
+
+/// Error returned by reads that can fail.
+#[non_exhaustive]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReadError {
+    /// The writer has been dropped, and the newest state was picked up.
+    Disconnected,
+    /// The reader has been closed.
+    Closed,
+    /// No new state was published in time.
+    Timeout,
+    /// The read was cancelled before a new state was published.
+    Cancelled,
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ReadError::Disconnected => "the writer has been dropped",
+            ReadError::Closed => "the reader has been closed",
+            ReadError::Timeout => "no new state was published in time",
+            ReadError::Cancelled => "the read was cancelled",
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for ReadError {}
+
+/// Error returned by writes that can fail, possibly because
+/// the closure computing the new state failed with `E`.
+///
+/// `PoolExhausted` and `WouldBlock` convert into it,
+/// so `?` works on them in functions returning it.
+#[non_exhaustive]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WriteError<E> {
+    /// See `PoolExhausted`.
+    PoolExhausted,
+    /// See `WouldBlock`.
+    WouldBlock,
+    /// The closure failed, and nothing was published.
+    User(E),
+}
+
+impl<E> From<PoolExhausted> for WriteError<E> {
+    fn from(_: PoolExhausted) -> Self {
+        WriteError::PoolExhausted
+    }
+}
+
+impl<E> From<WouldBlock> for WriteError<E> {
+    fn from(_: WouldBlock) -> Self {
+        WriteError::WouldBlock
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for WriteError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::PoolExhausted => PoolExhausted.fmt(f),
+            WriteError::WouldBlock => WouldBlock.fmt(f),
+            WriteError::User(e) => write!(f, "failed to write the new state: {}", e),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<E: Error + 'static> Error for WriteError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WriteError::User(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Error returned by `TripleBufferBuilder::build`
+/// for an invalid combination of options.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BuildError {
+    reason: &'static str,
+}
+
+impl BuildError {
+    pub(crate) fn new(reason: &'static str) -> Self {
+        Self { reason }
+    }
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid triple buffer configuration: {}", self.reason)
+    }
+}
+
+#[cfg(feature = "std")]
+impl Error for BuildError {}
+
+/// Error returned by `TripleBuffer::join` when the
+/// two halves do not belong to the same pair.
+pub struct JoinError<T> {
+    pub(crate) writer: Writer<T>,
+    pub(crate) reader: Reader<T>,
+}
+
+impl<T> JoinError<T> {
+    /// Get back the two halves that could not be joined.
+    pub fn into_halves(self) -> (Writer<T>, Reader<T>) {
+        (self.writer, self.reader)
+    }
+}
+
+impl<T> fmt::Debug for JoinError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinError")
+            .field("writer", &self.writer)
+            .field("reader", &self.reader)
+            .finish()
+    }
+}
+
+impl<T> fmt::Display for JoinError<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.writer.label(), self.reader.label()) {
+            (None, None) => f.write_str("writer and reader belong to different buffer pairs"),
+            (writer, reader) => write!(
+                f,
+                "writer {} and reader {} belong to different buffer pairs",
+                writer.unwrap_or("(unlabeled)"),
+                reader.unwrap_or("(unlabeled)")
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T> Error for JoinError<T> {}
+
+/// Error returned when saving or loading a snapshot fails,
+/// see `Writer::load_published`.
+///
+/// Only available with the `serde` feature.
+#[cfg(feature = "serde")]
+#[non_exhaustive]
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// Reading or writing the snapshot failed.
+    Io(std::io::Error),
+    /// The data does not start like a snapshot.
+    NotASnapshot,
+    /// The snapshot was saved with a different format.
+    FormatMismatch {
+        /// The name of the format loading it.
+        expected: &'static str,
+        /// The name of the format it was saved with.
+        found: String,
+    },
+    /// The snapshot was saved with a different version of the state type.
+    VersionMismatch {
+        /// The version loading it.
+        expected: u32,
+        /// The version it was saved with.
+        found: u32,
+    },
+    /// The format failed to serialize or deserialize the state.
+    Format(Box<dyn Error + Send + Sync>),
+    /// No unused buffer was available to load the state into.
+    PoolExhausted,
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "snapshot I/O failed: {}", e),
+            SnapshotError::NotASnapshot => f.write_str("the data is not a snapshot"),
+            SnapshotError::FormatMismatch { expected, found } => write!(
+                f,
+                "the snapshot was saved as {}, but is loaded as {}",
+                found, expected
+            ),
+            SnapshotError::VersionMismatch { expected, found } => write!(
+                f,
+                "the snapshot has version {}, but version {} was expected",
+                found, expected
+            ),
+            SnapshotError::Format(e) => write!(f, "snapshot serialization failed: {}", e),
+            SnapshotError::PoolExhausted => PoolExhausted.fmt(f),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Error for SnapshotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SnapshotError::Io(e) => Some(e),
+            SnapshotError::Format(e) => Some(&**e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<std::io::Error> for SnapshotError {
+    fn from(e: std::io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl From<PoolExhausted> for SnapshotError {
+    fn from(_: PoolExhausted) -> Self {
+        SnapshotError::PoolExhausted
+    }
+}
@@ -0,0 +1,67 @@
+use crate::Writer;
+
+/// Write side of the triple buffer restricted to a single part of the state.
+///
+/// Created with `Writer::field`.
+pub struct FieldWriter<'a, T, L> {
+    writer: &'a mut Writer<T>,
+    lens: L,
+}
+
+impl<'a, T: Clone, L> FieldWriter<'a, T, L> {
+    pub(crate) fn new(writer: &'a mut Writer<T>, lens: L) -> Self {
+        Self { writer, lens }
+    }
+
+    /// Write the next state by updating this part of the previous one.
+    ///
+    /// Everything outside of the part selected by the lens is
+    /// synced from the previous state before the closure runs,
+    /// and the resulting complete state gets published.
+    pub fn write<U>(&mut self, update_op: impl FnOnce(&mut U))
+    where
+        U: ?Sized,
+        L: Fn(&mut T) -> &mut U,
+    {
+        let lens = &self.lens;
+        self.writer.write_update(|state| update_op(lens(state)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::new_clone;
+
+    #[derive(Clone, Default)]
+    struct State {
+        a: Vec<u32>,
+        b: String,
+    }
+
+    #[test]
+    fn test_fields_keep_remainder() {
+        let (mut w, mut r) = new_clone(State::default());
+
+        let mut a = w.field(|s: &mut State| &mut s.a);
+        a.write(|a| a.push(1));
+        a.write(|a| a.push(2));
+        w.field(|s: &mut State| &mut s.b).write(|b| b.push('x'));
+        w.field(|s: &mut State| &mut s.a).write(|a| a.push(3));
+
+        let state = r.read_newest();
+        assert_eq!(state.a, [1, 2, 3]);
+        assert_eq!(state.b, "x");
+    }
+
+    #[test]
+    fn test_field_overlapping_read() {
+        let (mut w, mut r) = new_clone(State::default());
+        let mut field = w.field(|s: &mut State| &mut s.a);
+        field.write(|a| a.push(1));
+        assert_eq!(r.read_newest().a, [1]);
+        for i in 2..6 {
+            field.write(|a| a.push(i));
+        }
+        assert_eq!(r.read_newest().a, [1, 2, 3, 4, 5]);
+    }
+}
@@ -0,0 +1,143 @@
+//! Buffer pairs over slices whose length is fixed at creation.
+
+use alloc::boxed::Box;
+
+use crate::{new_with, PublishReceipt, Reader, Writer};
+
+/// Create a new buffer pair whose state is a slice of `len` elements,
+/// starting out with `fill(i)` at every index `i`.
+///
+/// Writes get the previous and the new state as slices of that same
+/// length, and nothing a write does can change it, unlike with a `Vec`.
+///
+/// # Example
+/// ```
+/// let (mut writer, mut reader) = simple_triple_buffer::new_slice(4, |i| i as f32);
+/// writer.write_new(|old, new| {
+///     for (new, old) in new.iter_mut().zip(old) {
+///         *new = old * 2.0;
+///     }
+/// });
+/// assert_eq!(reader.read_newest(), [0.0, 2.0, 4.0, 6.0]);
+/// ````
+pub fn new_slice<U: Clone + 'static>(
+    len: usize,
+    fill: impl Fn(usize) -> U,
+) -> (SliceWriter<U>, SliceReader<U>) {
+    let (writer, reader) = new_with(FixedSlice((0..len).map(fill).collect()), FixedSlice::clone);
+    (SliceWriter(writer), SliceReader(reader))
+}
+
+/// A boxed slice only ever handed out as a slice.
+///
+/// The states can not be unsized themselves, as the slot publishing
+/// them holds a single pointer-sized word. As this never leaves the
+/// pair, every state of it has the length it was created with.
+struct FixedSlice<U>(Box<[U]>);
+
+impl<U: Clone> Clone for FixedSlice<U> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        // All states of a pair have the same length.
+        self.0.clone_from_slice(&source.0);
+    }
+}
+
+/// Write side of a pair created with `new_slice`.
+pub struct SliceWriter<U>(Writer<FixedSlice<U>>);
+
+impl<U> SliceWriter<U> {
+    /// Write the next state, see `Writer::write_new`.
+    pub fn write_new(&mut self, write_op: impl FnOnce(&[U], &mut [U])) -> PublishReceipt {
+        self.0.write_new(|old, new| write_op(&old.0, &mut new.0))
+    }
+
+    /// Run `f` on the state published last, see `Writer::with_current`.
+    pub fn with_current<R>(&self, f: impl FnOnce(&[U]) -> R) -> R {
+        self.0.with_current(|state| f(&state.0))
+    }
+
+    /// Get the length of every state of the pair.
+    pub fn len(&self) -> usize {
+        self.with_current(<[U]>::len)
+    }
+
+    /// Check whether the states of the pair are empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Get the version of the last published state, see `Writer::version`.
+    pub fn version(&self) -> u64 {
+        self.0.version()
+    }
+
+    /// Check whether all readers have been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.0.is_closed()
+    }
+}
+
+impl<U: Clone> SliceWriter<U> {
+    /// Write the next state by updating the previous one,
+    /// see `Writer::write_update`.
+    pub fn write_update(&mut self, update_op: impl FnOnce(&mut [U])) -> PublishReceipt {
+        self.0.write_update(|state| update_op(&mut state.0))
+    }
+}
+
+/// Read side of a pair created with `new_slice`.
+///
+/// Cloning it creates another reader, see `Reader`.
+pub struct SliceReader<U>(Reader<FixedSlice<U>>);
+
+impl<U> SliceReader<U> {
+    /// Get the newest state, see `Reader::read_newest`.
+    pub fn read_newest(&mut self) -> &[U] {
+        &self.0.read_newest().0
+    }
+
+    /// Check whether a state has been published
+    /// that `read_newest` has not returned yet.
+    pub fn has_update(&self) -> bool {
+        self.0.has_update()
+    }
+
+    /// Check whether the `SliceWriter` has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.0.is_disconnected()
+    }
+}
+
+impl<U> Clone for SliceReader<U> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::new_slice;
+
+    #[test]
+    fn test_states_keep_their_length() {
+        let (mut w, mut r) = new_slice(3, |i| vec![i]);
+        for i in 0..10 {
+            w.write_new(|old, new| {
+                assert_eq!(new.len(), 3);
+                new.clone_from_slice(old);
+                new[0].push(i);
+            });
+            w.write_update(|state| state[2].clear());
+            let state = r.read_newest();
+            assert_eq!(state.len(), 3);
+            assert_eq!(state[0].len(), i + 2);
+            assert!(state[2].is_empty());
+        }
+        assert_eq!(w.len(), 3);
+        assert_eq!(r.clone().read_newest()[1], [1]);
+    }
+}
@@ -0,0 +1,174 @@
+use std::io::{self, Write};
+
+use crate::{Buf, Writer};
+
+/// What `IoFrameWriter` does with a frame that was not flushed
+/// by the time it gets dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PartialFrame {
+    /// Drop the bytes written so far, publishing nothing.
+    #[default]
+    Discard,
+    /// Publish the bytes written so far as a frame of their own.
+    Publish,
+}
+
+/// An `io::Write` that publishes a frame on every flush,
+/// see `Writer::as_frame_writer`.
+pub struct IoFrameWriter<'a> {
+    writer: &'a mut Writer<Vec<u8>>,
+    /// The frame being written, once anything was written since the last flush.
+    frame: Option<Buf<Vec<u8>>>,
+    on_drop: PartialFrame,
+}
+
+impl Writer<Vec<u8>> {
+    /// Get an `io::Write` that writes frames of bytes into unused buffers,
+    /// and publishes one on every `flush`.
+    ///
+    /// Each frame starts out empty, but keeps the capacity of the buffer
+    /// it gets written into, so once the buffers grew large enough for the
+    /// frames, writing them no longer allocates. A flush without any writes
+    /// since the previous one publishes nothing, and an unflushed frame is
+    /// discarded when the returned writer gets dropped, unless changed with
+    /// `IoFrameWriter::on_drop`.
+    ///
+    /// Writes fail if the pair can not create additional
+    /// buffers, and all existing ones are in use.
+    ///
+    /// Only available with the `std` feature.
+    ///
+    /// # Example
+    /// ```
+    /// use std::io::Write;
+    ///
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(Vec::new());
+    ///
+    /// let mut frames = writer.as_frame_writer();
+    /// write!(frames, "frame {}", 1).unwrap();
+    /// frames.flush().unwrap();
+    /// assert_eq!(reader.read_newest(), b"frame 1");
+    ///
+    /// frames.write_all(b"unfinished").unwrap();
+    /// drop(frames);
+    /// assert!(!reader.has_update());
+    /// ````
+    pub fn as_frame_writer(&mut self) -> IoFrameWriter<'_> {
+        IoFrameWriter {
+            writer: self,
+            frame: None,
+            on_drop: PartialFrame::default(),
+        }
+    }
+}
+
+impl IoFrameWriter<'_> {
+    /// Set what happens to an unflushed frame when this gets dropped.
+    pub fn on_drop(mut self, partial: PartialFrame) -> Self {
+        self.on_drop = partial;
+        self
+    }
+
+    /// Get the number of bytes written since the last flush.
+    pub fn pending_len(&self) -> usize {
+        self.frame.as_ref().map_or(0, |frame| frame.len())
+    }
+
+    fn frame(&mut self) -> io::Result<&mut Vec<u8>> {
+        if self.frame.is_none() {
+            let mut buf = self
+                .writer
+                .try_next_unused_buffer()
+                .map_err(io::Error::other)?;
+            Buf::get_mut(&mut buf).unwrap().clear();
+            self.frame = Some(buf);
+        }
+        Ok(Buf::get_mut(self.frame.as_mut().unwrap()).unwrap())
+    }
+}
+
+impl Write for IoFrameWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.frame()?.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.frame()?.extend_from_slice(buf);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(frame) = self.frame.take() {
+            self.writer.publish(frame);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for IoFrameWriter<'_> {
+    fn drop(&mut self) {
+        if let Some(frame) = self.frame.take() {
+            match self.on_drop {
+                PartialFrame::Discard => self.writer.discard(frame),
+                PartialFrame::Publish => drop(self.writer.publish(frame)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::PartialFrame;
+    use crate::{new_clone, new_with_buffers};
+
+    #[test]
+    fn test_capacity_is_reused() {
+        let (mut w, mut r) = new_clone(Vec::new());
+        let mut frames = w.as_frame_writer();
+        let mut seen = Vec::new();
+        for i in 0..100u8 {
+            for _ in 0..64 {
+                frames.write_all(&[i]).unwrap();
+            }
+            assert_eq!(frames.pending_len(), 64);
+            frames.flush().unwrap();
+            let frame = r.read_newest();
+            assert_eq!(frame, &[i; 64]);
+            seen.push(frame.as_ptr());
+        }
+        // An empty flush publishes nothing.
+        frames.flush().unwrap();
+        assert!(!r.has_update());
+        drop(frames);
+        seen.sort();
+        seen.dedup();
+        assert!(seen.len() <= 3, "{}", seen.len());
+    }
+
+    #[test]
+    fn test_partial_frames() {
+        let (mut w, mut r) = new_clone(b"init".to_vec());
+        let mut frames = w.as_frame_writer();
+        frames.write_all(b"discarded").unwrap();
+        drop(frames);
+        assert!(!r.has_update());
+
+        let mut frames = w.as_frame_writer().on_drop(PartialFrame::Publish);
+        frames.write_all(b"published").unwrap();
+        drop(frames);
+        assert_eq!(r.read_newest(), b"published");
+    }
+
+    #[test]
+    fn test_exhausted_pool_fails_writes() {
+        let (mut w, r) = new_with_buffers(Vec::new(), vec![Vec::new()]);
+        let _held = r.clone();
+        w.write_new(|_, new| new.push(1));
+        let mut frames = w.as_frame_writer();
+        let err = frames.write_all(b"x").unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Other);
+    }
+}
@@ -0,0 +1,190 @@
+//! Buffer pairs for fixed-size byte frames, like raw video or audio.
+//!
+//! Every buffer is a `Box<[u8]>` of exactly the current frame length,
+//! so writing a frame never reallocates, and there is no state type
+//! or clone function to set up.
+//!
+//! # Example
+//! ```
+//! let (mut writer, mut reader) = simple_triple_buffer::frames::new_frames(4);
+//!
+//! writer.fill_frame(|frame| frame.copy_from_slice(b"abcd"));
+//! writer.write_frame(|frame| frame[0] = b'x');
+//! assert_eq!(reader.read_frame(), b"xbcd");
+//!
+//! writer.resize(2);
+//! writer.write_frame(|frame| frame[1] = b'y');
+//! assert_eq!(reader.read_frame(), b"xy");
+//! ````
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use alloc::vec;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{new_with, Buf, Reader, Writer};
+
+type Frame = Box<[u8]>;
+
+/// Create a new buffer pair for frames of `frame_len` bytes,
+/// starting with a frame of zeroes.
+pub fn new_frames(frame_len: usize) -> (FrameWriter, FrameReader) {
+    let len = Arc::new(AtomicUsize::new(frame_len));
+    let make_len = len.clone();
+    let (writer, reader) = new_with(vec![0; frame_len].into_boxed_slice(), move |_| {
+        vec![0; make_len.load(Ordering::Relaxed)].into_boxed_slice()
+    });
+    (FrameWriter { writer, len }, FrameReader { reader })
+}
+
+/// Write side of a pair created with `new_frames`.
+pub struct FrameWriter {
+    writer: Writer<Frame>,
+    /// The current frame length, shared with the function creating new buffers.
+    len: Arc<AtomicUsize>,
+}
+
+impl FrameWriter {
+    /// Publish a frame, starting out as a copy of the previous one.
+    ///
+    /// The closure may overwrite any part of the frame. If the frame
+    /// length changed since the previous frame, only the overlapping
+    /// prefix gets copied, and the rest is zeroed.
+    pub fn write_frame(&mut self, write_op: impl FnOnce(&mut [u8])) {
+        let mut new_frame = self.next_frame();
+        let frame = Buf::get_mut(&mut new_frame).unwrap();
+        let prev = &self.writer.prev_buf;
+        let copied = prev.len().min(frame.len());
+        frame[..copied].copy_from_slice(&prev[..copied]);
+        frame[copied..].fill(0);
+        write_op(frame);
+        self.writer.publish(new_frame);
+    }
+
+    /// Publish a frame that the closure fills completely.
+    ///
+    /// This skips copying the previous frame in first, so the initial
+    /// contents are some older frame, and any part the closure
+    /// does not overwrite will show stale data.
+    pub fn fill_frame(&mut self, fill_op: impl FnOnce(&mut [u8])) {
+        let mut new_frame = self.next_frame();
+        fill_op(Buf::get_mut(&mut new_frame).unwrap());
+        self.writer.publish(new_frame);
+    }
+
+    /// Get the length of the frames written from now on.
+    pub fn frame_len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Change the length of the frames written from now on.
+    ///
+    /// Unused buffers of the old length are dropped right away,
+    /// and the ones still held by the reader once they come back.
+    /// The reader keeps seeing frames of the old length until
+    /// the next one gets published.
+    pub fn resize(&mut self, frame_len: usize) {
+        if self.len.swap(frame_len, Ordering::Relaxed) == frame_len {
+            return;
+        }
+        while let Some(buf) = self.writer.unused_bufs_rx.try_recv() {
+            self.drop_frame(buf);
+        }
+    }
+
+    /// Check whether the `FrameReader` has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.writer.is_closed()
+    }
+
+    fn next_frame(&mut self) -> Buf<Frame> {
+        let len = self.frame_len();
+        loop {
+            let buf = self.writer.next_unused_buffer();
+            if buf.len() == len {
+                return buf;
+            }
+            // Left over from before a resize.
+            self.drop_frame(buf);
+        }
+    }
+
+    fn drop_frame(&mut self, buf: Buf<Frame>) {
+        self.writer.created -= 1;
+        drop(buf);
+    }
+}
+
+/// Read side of a pair created with `new_frames`.
+pub struct FrameReader {
+    reader: Reader<Frame>,
+}
+
+impl FrameReader {
+    /// Get the newest frame, see `Reader::read_newest`.
+    pub fn read_frame(&mut self) -> &[u8] {
+        self.reader.read_newest()
+    }
+
+    /// Check whether a frame has been published
+    /// that `read_frame` has not returned yet.
+    pub fn has_update(&self) -> bool {
+        self.reader.has_update()
+    }
+
+    /// Check whether the `FrameWriter` has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        self.reader.is_disconnected()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::new_frames;
+
+    #[test]
+    fn test_frames_are_reused() {
+        let (mut w, mut r) = new_frames(1024);
+        for i in 0..100u8 {
+            w.fill_frame(|frame| frame.fill(i));
+            if i % 2 == 0 {
+                assert!(r.read_frame().iter().all(|b| *b == i));
+            }
+        }
+        assert!(w.writer.created <= 3);
+    }
+
+    #[test]
+    fn test_write_frame_copies_previous() {
+        let (mut w, mut r) = new_frames(3);
+        w.fill_frame(|frame| frame.copy_from_slice(&[1, 2, 3]));
+        r.read_frame();
+        w.write_frame(|frame| frame[2] = 4);
+        w.write_frame(|frame| frame[0] = 5);
+        assert_eq!(r.read_frame(), [5, 2, 4]);
+    }
+
+    #[test]
+    fn test_resize() {
+        let (mut w, mut r) = new_frames(4);
+        for i in 0..4 {
+            w.fill_frame(|frame| frame.fill(i));
+            r.read_frame();
+        }
+        w.resize(8);
+        assert_eq!(w.frame_len(), 8);
+        assert_eq!(r.read_frame().len(), 4);
+        w.write_frame(|frame| frame[7] = 9);
+        assert_eq!(r.read_frame(), [3, 3, 3, 3, 0, 0, 0, 9]);
+
+        for i in 0..10 {
+            w.fill_frame(|frame| frame.fill(i));
+            assert_eq!(r.read_frame(), [i; 8]);
+        }
+        assert!(w.writer.created <= 3);
+
+        w.resize(2);
+        w.write_frame(|_| {});
+        assert_eq!(r.read_frame(), [9, 9]);
+    }
+}
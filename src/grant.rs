@@ -0,0 +1,130 @@
+use alloc::vec::Vec;
+use core::ops::{Deref, DerefMut};
+
+use crate::{Buf, Writer};
+
+/// A writable byte region backed by a recycled buffer of the triple buffer.
+///
+/// Created with `Writer::grant`. Calling `commit` publishes the
+/// written bytes, while dropping the grant without committing
+/// returns the buffer to the writer unpublished.
+pub struct ByteGrant<'a> {
+    writer: &'a mut Writer<Vec<u8>>,
+    buf: Option<Buf<Vec<u8>>>,
+}
+
+impl Writer<Vec<u8>> {
+    /// Get a writable region of `max_len` bytes for the next state.
+    ///
+    /// The region is backed by the capacity of a recycled buffer,
+    /// so repeated grants of similar size do not reallocate.
+    /// Its initial contents are unspecified.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(Vec::new());
+    ///
+    /// let mut grant = writer.grant(16);
+    /// grant[..3].copy_from_slice(b"abc");
+    /// grant.commit(3);
+    ///
+    /// assert_eq!(*reader.read_newest(), b"abc");
+    /// ````
+    pub fn grant(&mut self, max_len: usize) -> ByteGrant<'_> {
+        let mut buf = self.next_unused_buffer();
+        Buf::get_mut(&mut buf).unwrap().resize(max_len, 0);
+        ByteGrant {
+            writer: self,
+            buf: Some(buf),
+        }
+    }
+}
+
+impl ByteGrant<'_> {
+    /// Publish the first `len` bytes of the granted region as the new state.
+    ///
+    /// # Panics
+    /// Panics if `len` is larger than the granted region.
+    pub fn commit(mut self, len: usize) {
+        let mut buf = self.buf.take().unwrap();
+        let bytes = Buf::get_mut(&mut buf).unwrap();
+        assert!(
+            len <= bytes.len(),
+            "commit length {} exceeds granted length {}",
+            len,
+            bytes.len()
+        );
+        bytes.truncate(len);
+        self.writer.publish(buf);
+    }
+}
+
+impl Deref for ByteGrant<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.buf.as_ref().unwrap()
+    }
+}
+
+impl DerefMut for ByteGrant<'_> {
+    fn deref_mut(&mut self) -> &mut [u8] {
+        // The buffer came out of the unused pool,
+        // so this grant holds the only reference to it.
+        Buf::get_mut(self.buf.as_mut().unwrap()).unwrap()
+    }
+}
+
+impl Drop for ByteGrant<'_> {
+    fn drop(&mut self) {
+        if let Some(buf) = self.buf.take() {
+            self.writer.discard(buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::new_clone;
+
+    #[test]
+    fn test_commit_exact_len() {
+        let (mut w, mut r) = new_clone(vec![1, 2, 3]);
+        let mut grant = w.grant(8);
+        assert_eq!(grant.len(), 8);
+        grant[..2].copy_from_slice(&[7, 8]);
+        grant.commit(2);
+        assert_eq!(*r.read_newest(), [7, 8]);
+    }
+
+    #[test]
+    fn test_dropped_grant_is_not_published() {
+        let (mut w, mut r) = new_clone(vec![1]);
+        let ptr = {
+            let mut grant = w.grant(1024);
+            grant[0] = 9;
+            grant.as_ptr()
+        };
+        assert_eq!(*r.read_newest(), [1]);
+
+        // The dropped buffer is reused with its capacity intact.
+        let grant = w.grant(1024);
+        assert_eq!(grant.as_ptr(), ptr);
+    }
+
+    #[test]
+    fn test_grants_reuse_capacity() {
+        let (mut w, mut r) = new_clone(Vec::with_capacity(4096));
+        let mut ptrs = Vec::new();
+        for i in 0..16 {
+            let mut grant = w.grant(4096);
+            grant[0] = i;
+            if !ptrs.contains(&grant.as_ptr()) {
+                ptrs.push(grant.as_ptr());
+            }
+            grant.commit(1);
+            assert_eq!(*r.read_newest(), [i]);
+        }
+        assert!(ptrs.len() <= 3);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::{Allocation, Buf, Reader, Writer};
+
+impl<T> Writer<T> {
+    /// Keep the last `k` published states available to `Reader::rewind`.
+    ///
+    /// The kept states are excluded from buffer recycling,
+    /// so this costs up to `k` additional copies of `T`
+    /// on top of the usual steady state.
+    /// With `k = 0`, which is the default, no history is kept.
+    pub fn keep_history(&mut self, k: usize) {
+        self.history_len = k;
+        let mut history = self.read_update.shared.history.lock();
+        while history.len() > k {
+            let buf = history.pop_front().unwrap();
+            self.recycle(buf);
+        }
+    }
+
+    pub(crate) fn record_history(&mut self, new_state: &Buf<T>) {
+        let mut history = self.read_update.shared.history.lock();
+        let capacity = history.capacity();
+        history.push_back(new_state.clone());
+        let grew = history.capacity() != capacity;
+        while history.len() > self.history_len {
+            let buf = history.pop_front().unwrap();
+            self.recycle(buf);
+        }
+        drop(history);
+        if grew {
+            self.allocated(Allocation::History);
+        }
+    }
+}
+
+impl<T> Reader<T> {
+    /// Get a view of the state that was published `n` publishes
+    /// before the newest one, as kept by `Writer::keep_history`.
+    ///
+    /// `rewind(0)` is the newest published state.
+    /// Returns `None` if the history does not reach back that far.
+    ///
+    /// The returned state stays pinned until the next call
+    /// to `rewind` or `newest`.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// writer.keep_history(3);
+    /// for i in 1..=5 {
+    ///     writer.write_new(|_, new| *new = i);
+    /// }
+    ///
+    /// assert_eq!(reader.rewind(0), Some(&5));
+    /// assert_eq!(reader.rewind(2), Some(&3));
+    /// assert_eq!(reader.rewind(3), None);
+    /// assert_eq!(*reader.newest(), 5);
+    /// ````
+    pub fn rewind(&mut self, n: usize) -> Option<&T> {
+        let buf = {
+            let history = self.read_update.shared.history.lock();
+            let idx = history.len().checked_sub(n + 1)?;
+            history[idx].clone()
+        };
+        if let Some(old) = self.rewound.replace(buf) {
+            self.recycle(old);
+        }
+        self.rewound.as_deref()
+    }
+
+    /// Release any state pinned by `rewind` and
+    /// get a view to the newest state currently in the buffer.
+    ///
+    /// Otherwise this behaves exactly like `read_newest`.
+    pub fn newest(&mut self) -> &T {
+        if let Some(old) = self.rewound.take() {
+            self.recycle(old);
+        }
+        self.read_newest()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::new_with;
+
+    #[test]
+    fn test_rewind_steps_back() {
+        let (mut w, mut r) = new_with(0, |v| *v);
+        w.keep_history(4);
+        assert_eq!(r.rewind(0), None);
+
+        for i in 1..=10 {
+            w.write_new(|_, new| *new = i);
+        }
+        for n in 0..4 {
+            assert_eq!(r.rewind(n), Some(&(10 - n)));
+        }
+        assert_eq!(r.rewind(4), None);
+
+        w.write_new(|_, new| *new = 11);
+        assert_eq!(r.rewind(1), Some(&10));
+        assert_eq!(*r.newest(), 11);
+    }
+
+    #[test]
+    fn test_history_survives_recycling() {
+        let (mut w, mut r) = new_with(vec![0], |v| v.clone());
+        w.keep_history(2);
+        for i in 1..50 {
+            w.write_new(|_, new| {
+                new.clear();
+                new.push(i);
+            });
+            if i % 3 == 0 {
+                assert_eq!(*r.read_newest(), [i]);
+            }
+            assert_eq!(r.rewind(1), Some(&vec![i - 1]).filter(|_| i > 1));
+        }
+    }
+
+    #[test]
+    fn test_history_copy_count() {
+        let count = Arc::new(Mutex::new(0));
+        let c = count.clone();
+        let (mut w, mut r) = new_with(0, move |v| {
+            *c.lock().unwrap() += 1;
+            *v
+        });
+        w.keep_history(3);
+        for i in 1..100 {
+            w.write_new(|_, new| *new = i);
+            assert_eq!(*r.read_newest(), i);
+        }
+        // The history pins 3 copies on top of the usual ones.
+        assert!(*count.lock().unwrap() <= 5);
+
+        w.keep_history(0);
+        assert_eq!(r.rewind(0), None);
+    }
+}
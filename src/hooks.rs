@@ -0,0 +1,275 @@
+//! Callbacks for the events of a pair, for feeding metrics backends.
+
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::sync::Mutex;
+use crate::{Buf, Reader, Writer};
+
+/// A callback for one kind of event, see `Hooks`.
+pub type Hook = Box<dyn FnMut(&HookCtx) + Send>;
+
+/// What a hook gets told about an event.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HookCtx {
+    /// The number of states published so far, counting the one
+    /// published in `on_publish`.
+    ///
+    /// In `on_reader_observed` this was read after picking up the state,
+    /// so if the writer published again meanwhile, it is larger than
+    /// the version of the state the reader got.
+    pub version: u64,
+    /// The buffer the event is about, as in `BufferDebug::id`.
+    pub buffer: usize,
+    /// When the event happened.
+    pub time: Instant,
+    /// In `on_reader_observed`, how long ago the state was published,
+    /// if the pair records timestamps. `None` for all other events.
+    pub lag: Option<Duration>,
+}
+
+/// Callbacks for the events of a pair, see `Writer::set_hooks`.
+///
+/// # Example
+/// ```
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use std::sync::Arc;
+/// use simple_triple_buffer::Hooks;
+///
+/// let published = Arc::new(AtomicU64::new(0));
+/// let counter = published.clone();
+///
+/// let (mut writer, _reader) = simple_triple_buffer::new_clone(0);
+/// writer.set_hooks(Hooks {
+///     on_publish: Some(Box::new(move |ctx| counter.store(ctx.version, Ordering::Relaxed))),
+///     ..Hooks::default()
+/// });
+/// writer.write_new(|_, new| *new = 1);
+/// writer.write_new(|_, new| *new = 2);
+/// assert_eq!(published.load(Ordering::Relaxed), 2);
+/// ````
+#[derive(Default)]
+pub struct Hooks {
+    /// Called by the writer after publishing a state.
+    pub on_publish: Option<Hook>,
+    /// Called by the writer after creating a new buffer.
+    pub on_buffer_created: Option<Hook>,
+    /// Called by the writer when it reuses a returned buffer.
+    pub on_buffer_recycled: Option<Hook>,
+    /// Called by a reader after picking up a newly published state.
+    pub on_reader_observed: Option<Hook>,
+}
+
+/// Call `hook`, dropping it if it panics.
+fn call(hook: &mut Option<Hook>, ctx: &HookCtx) {
+    if let Some(f) = hook {
+        if panic::catch_unwind(AssertUnwindSafe(|| f(ctx))).is_err() {
+            *hook = None;
+        }
+    }
+}
+
+fn buffer_id<T>(buf: &Buf<T>) -> usize {
+    Buf::as_ptr(buf) as usize
+}
+
+/// The hooks the writer calls itself.
+pub(crate) struct WriterHooks {
+    on_publish: Option<Hook>,
+    on_buffer_created: Option<Hook>,
+    on_buffer_recycled: Option<Hook>,
+}
+
+/// The hook readers call, shared by all of them.
+///
+/// It has a lock of its own, so a slow hook only ever holds up
+/// the other readers calling it, never the writer.
+pub(crate) struct ObservedHook {
+    set: AtomicBool,
+    /// The version of the last published state, only kept up to date
+    /// while the hook is set.
+    version: AtomicU64,
+    hook: Mutex<Option<Hook>>,
+}
+
+impl ObservedHook {
+    pub(crate) fn new() -> Self {
+        Self {
+            set: AtomicBool::new(false),
+            version: AtomicU64::new(0),
+            hook: Mutex::new(None),
+        }
+    }
+}
+
+impl<T> Writer<T> {
+    /// Install callbacks for the events of the pair,
+    /// replacing the ones installed before.
+    ///
+    /// All hooks but `on_reader_observed` run on the thread of the
+    /// writer, and that one on the thread of the reader that picked up
+    /// the state. None of them runs while the pair holds a lock, so a
+    /// slow hook only slows down the half calling it. A hook that
+    /// panics gets dropped, without affecting the pair. Events without
+    /// a hook cost a single check.
+    ///
+    /// Only available with the `std` feature.
+    pub fn set_hooks(&mut self, hooks: Hooks) {
+        let observed = &self.read_update.shared.observed_hook;
+        observed.version.store(self.version, Ordering::Relaxed);
+        let set = hooks.on_reader_observed.is_some();
+        *observed.hook.lock() = hooks.on_reader_observed;
+        observed.set.store(set, Ordering::Relaxed);
+
+        let Hooks {
+            on_publish,
+            on_buffer_created,
+            on_buffer_recycled,
+            ..
+        } = hooks;
+        self.hooks =
+            (on_publish.is_some() || on_buffer_created.is_some() || on_buffer_recycled.is_some())
+                .then(|| {
+                    Box::new(WriterHooks {
+                        on_publish,
+                        on_buffer_created,
+                        on_buffer_recycled,
+                    })
+                });
+    }
+
+    fn writer_ctx(&self, buf: &Buf<T>) -> HookCtx {
+        HookCtx {
+            version: self.version,
+            buffer: buffer_id(buf),
+            time: Instant::now(),
+            lag: None,
+        }
+    }
+
+    pub(crate) fn hook_published(&mut self) {
+        let observed = &self.read_update.shared.observed_hook;
+        if observed.set.load(Ordering::Relaxed) {
+            observed.version.store(self.version, Ordering::Relaxed);
+        }
+        if let Some(hooks) = &self.hooks {
+            if hooks.on_publish.is_some() {
+                let ctx = self.writer_ctx(&self.prev_buf);
+                call(&mut self.hooks.as_mut().unwrap().on_publish, &ctx);
+            }
+        }
+    }
+
+    pub(crate) fn hook_created(&mut self, buf: &Buf<T>) {
+        if let Some(hooks) = &self.hooks {
+            if hooks.on_buffer_created.is_some() {
+                let ctx = self.writer_ctx(buf);
+                call(&mut self.hooks.as_mut().unwrap().on_buffer_created, &ctx);
+            }
+        }
+    }
+
+    pub(crate) fn hook_recycled(&mut self, buf: &Buf<T>) {
+        if let Some(hooks) = &self.hooks {
+            if hooks.on_buffer_recycled.is_some() {
+                let ctx = self.writer_ctx(buf);
+                call(&mut self.hooks.as_mut().unwrap().on_buffer_recycled, &ctx);
+            }
+        }
+    }
+}
+
+impl<T> Reader<T> {
+    pub(crate) fn hook_observed(&self, publish_time: Option<Instant>) {
+        let observed = &self.read_update.shared.observed_hook;
+        if !observed.set.load(Ordering::Relaxed) {
+            return;
+        }
+        let time = Instant::now();
+        let ctx = HookCtx {
+            version: observed.version.load(Ordering::Relaxed),
+            buffer: buffer_id(&self.prev_buf),
+            time,
+            lag: publish_time.map(|published| time.saturating_duration_since(published)),
+        };
+        let mut hook = observed.hook.lock();
+        call(&mut hook, &ctx);
+        if hook.is_none() {
+            observed.set.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::{HookCtx, Hooks};
+    use crate::TripleBufferBuilder;
+
+    type Log = Arc<Mutex<Vec<(&'static str, u64)>>>;
+
+    fn hook(log: &Log, name: &'static str) -> Option<super::Hook> {
+        let log = log.clone();
+        Some(Box::new(move |ctx: &HookCtx| {
+            log.lock().unwrap().push((name, ctx.version))
+        }))
+    }
+
+    #[test]
+    fn test_hooks_see_events() {
+        let log = Log::default();
+        let (mut w, mut r) = TripleBufferBuilder::new(0)
+            .copy_buffers()
+            .timestamps(true)
+            .build()
+            .unwrap();
+        w.set_hooks(Hooks {
+            on_publish: hook(&log, "publish"),
+            on_buffer_created: hook(&log, "created"),
+            on_buffer_recycled: hook(&log, "recycled"),
+            on_reader_observed: hook(&log, "observed"),
+        });
+        w.write_new(|_, new| *new = 1);
+        r.read_newest();
+        r.read_newest();
+        w.write_new(|_, new| *new = 2);
+        assert_eq!(
+            *log.lock().unwrap(),
+            [
+                ("created", 0),
+                ("publish", 1),
+                ("observed", 1),
+                ("recycled", 1),
+                ("publish", 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_panicking_hook_is_dropped() {
+        let log = Log::default();
+        let (mut w, mut r) = crate::new_clone(0);
+        w.set_hooks(Hooks {
+            on_publish: Some(Box::new(|ctx: &HookCtx| assert!(ctx.version < 2))),
+            on_reader_observed: Some(Box::new(|_: &HookCtx| panic!("hook failed"))),
+            on_buffer_created: hook(&log, "created"),
+            ..Hooks::default()
+        });
+        for i in 1..=3 {
+            w.write_new(|_, new| *new = i);
+            assert_eq!(*r.read_newest(), i);
+        }
+        // The other hooks keep running.
+        assert_eq!(*log.lock().unwrap(), [("created", 0)]);
+        assert!(!r
+            .read_update
+            .shared
+            .observed_hook
+            .set
+            .load(std::sync::atomic::Ordering::Relaxed));
+        assert!(w.hooks.as_ref().unwrap().on_publish.is_none());
+    }
+}
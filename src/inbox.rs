@@ -0,0 +1,473 @@
+//! Candidate states submitted by other threads, for the writer to publish.
+//!
+//! Like the ring of unused buffers, the inbox is a fixed set of slots,
+//! each a single atomic word holding the address of a buffer, or 0.
+//! Submitters claim an empty slot by marking it `CLAIMED`, store the
+//! sequence number of the submission next to it, and only then the
+//! address, so the writer never sees a buffer without its number.
+//! Only the writer empties occupied slots again.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::sync::atomic::Ordering;
+
+use crate::sync::atomic::{AtomicBool, AtomicUsize};
+use crate::sync::{Arc, CachePadded};
+use crate::{Buf, Full, Recycler, Writer};
+
+/// Marks a slot a submitter is about to fill. Buffer
+/// addresses are aligned, so none of them is 1.
+const CLAIMED: usize = 1;
+
+struct Slot {
+    buf: AtomicUsize,
+    seq: AtomicUsize,
+}
+
+struct Slots<T> {
+    slots: Box<[CachePadded<Slot>]>,
+    next_seq: AtomicUsize,
+    writer_alive: AtomicBool,
+    /// Occupied slots own one reference to their buffer.
+    _buf: PhantomData<Buf<T>>,
+}
+
+impl<T> Slots<T> {
+    /// Take the buffers out of all occupied slots, with their
+    /// sequence numbers.
+    fn take_all(&self, taken: &mut Vec<(usize, Buf<T>)>) {
+        for slot in self.slots.iter() {
+            let ptr = slot.buf.load(Ordering::Acquire);
+            if ptr == 0 || ptr == CLAIMED {
+                continue;
+            }
+            // Only the writer empties occupied slots, so neither
+            // the number nor the address changes until then.
+            let seq = slot.seq.load(Ordering::Relaxed);
+            slot.buf.store(0, Ordering::Relaxed);
+            // SAFETY: The reference the slot owned is ours now.
+            taken.push((seq, unsafe { Buf::from_raw(ptr as *const T) }));
+        }
+    }
+}
+
+impl<T> Drop for Slots<T> {
+    fn drop(&mut self) {
+        self.take_all(&mut Vec::new());
+    }
+}
+
+/// Submits candidate states for the writer to publish,
+/// see `Writer::inbox`.
+///
+/// Cloning it creates another handle to the same inbox, so any
+/// number of threads can submit states into it.
+pub struct Inbox<T> {
+    slots: Arc<Slots<T>>,
+}
+
+// SAFETY: Submitted states get moved to the thread of the writer,
+// and nothing else ever gets access to them.
+unsafe impl<T: Send> Send for Inbox<T> {}
+// SAFETY: See above, all `submit` does is move a `T` into a slot.
+unsafe impl<T: Send> Sync for Inbox<T> {}
+
+impl<T> Clone for Inbox<T> {
+    fn clone(&self) -> Self {
+        Self {
+            slots: self.slots.clone(),
+        }
+    }
+}
+
+impl<T> Inbox<T> {
+    /// Submit a candidate state for the writer to pick up the next
+    /// time it calls `Writer::drain_inbox`.
+    ///
+    /// This does not wait for anything. It hands `state` back if
+    /// all slots of the inbox are occupied, or if the writer is gone.
+    pub fn submit(&self, state: T) -> Result<(), Full<T>> {
+        if self.is_closed() {
+            return Err(Full(state));
+        }
+        for slot in self.slots.slots.iter() {
+            if slot.buf.load(Ordering::Relaxed) == 0
+                && slot
+                    .buf
+                    .compare_exchange(0, CLAIMED, Ordering::Relaxed, Ordering::Relaxed)
+                    .is_ok()
+            {
+                let seq = self.slots.next_seq.fetch_add(1, Ordering::Relaxed);
+                slot.seq.store(seq, Ordering::Relaxed);
+                let ptr = Buf::into_raw(Buf::new(state));
+                slot.buf.store(ptr as usize, Ordering::Release);
+                return Ok(());
+            }
+        }
+        Err(Full(state))
+    }
+
+    /// Check whether the writer has been dropped, so
+    /// nothing drains the inbox anymore.
+    pub fn is_closed(&self) -> bool {
+        !self.slots.writer_alive.load(Ordering::Relaxed)
+    }
+}
+
+/// Which state `Writer::drain_inbox` keeps, see there.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Keep {
+    /// Keep the state kept so far.
+    Current,
+    /// Keep the submitted one instead.
+    Submitted,
+}
+
+impl Keep {
+    /// Always keep the submitted state, so the one submitted
+    /// last gets published.
+    pub fn newest<T>(_: &T, _: &T) -> Keep {
+        Keep::Submitted
+    }
+}
+
+/// The end of the inbox the writer drains.
+pub(crate) struct Drain<T>(Arc<Slots<T>>);
+
+impl<T> Drop for Drain<T> {
+    fn drop(&mut self) {
+        self.0.writer_alive.store(false, Ordering::Relaxed);
+        // Submissions racing with this get dropped
+        // along with the slots, by the last inbox.
+        self.0.take_all(&mut Vec::new());
+    }
+}
+
+impl<T> Writer<T> {
+    /// Get an inbox other threads can submit candidate states into,
+    /// with room for `capacity` of them at a time.
+    ///
+    /// The writer stays the only one publishing: whenever it calls
+    /// `drain_inbox`, that picks one of the submitted states and
+    /// publishes it. Submitting only takes an atomic operation and
+    /// moving the state into a new buffer, so producers that can not
+    /// share a thread with the writer need no lock, unlike with a
+    /// `SharedWriter`.
+    ///
+    /// Calling this again returns another handle to the same inbox,
+    /// whose capacity stays the one it was created with.
+    ///
+    /// # Example
+    /// ```
+    /// use simple_triple_buffer::Keep;
+    ///
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// let inbox = writer.inbox(4);
+    ///
+    /// let producers: Vec<_> = (1..=3)
+    ///     .map(|i| {
+    ///         let inbox = inbox.clone();
+    ///         std::thread::spawn(move || inbox.submit(i * 10).unwrap())
+    ///     })
+    ///     .collect();
+    /// for producer in producers {
+    ///     producer.join().unwrap();
+    /// }
+    ///
+    /// // Publish the largest submission.
+    /// assert!(writer.drain_inbox(|current, submitted| {
+    ///     if submitted > current { Keep::Submitted } else { Keep::Current }
+    /// }));
+    /// assert_eq!(*reader.read_newest(), 30);
+    /// ````
+    pub fn inbox(&mut self, capacity: usize) -> Inbox<T> {
+        let drain = self.inbox.get_or_insert_with(|| {
+            Drain(Arc::new(Slots {
+                slots: (0..capacity)
+                    .map(|_| {
+                        CachePadded(Slot {
+                            buf: AtomicUsize::new(0),
+                            seq: AtomicUsize::new(0),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+                    .into(),
+                next_seq: AtomicUsize::new(0),
+                writer_alive: AtomicBool::new(true),
+                _buf: PhantomData,
+            }))
+        });
+        Inbox {
+            slots: drain.0.clone(),
+        }
+    }
+
+    /// Pick one of the states submitted to the inbox since the last
+    /// call and publish it, returning whether there was one to publish.
+    ///
+    /// The submitted states get passed to `select` in the order they
+    /// were submitted in, along with the state kept so far, which starts
+    /// out as the published one. If `select` never keeps a submitted
+    /// state, nothing gets published. Pass `Keep::newest` to publish
+    /// the state submitted last.
+    ///
+    /// The kept state gets published in the buffer it was submitted in,
+    /// and the others join the unused buffers, to be written into by
+    /// later writes. Buffers that would exceed
+    /// `TripleBufferBuilder::max_buffers`, or the room for unused ones,
+    /// get dropped instead, and the kept state then gets moved into an
+    /// unused buffer, like with `swap`.
+    ///
+    /// # Panics
+    /// Panics if the kept state has to be moved, and the pair was
+    /// created without a way to create new buffers, and all existing
+    /// ones are in use.
+    pub fn drain_inbox(&mut self, mut select: impl FnMut(&T, &T) -> Keep) -> bool {
+        let Some(drain) = &self.inbox else {
+            return false;
+        };
+        let mut submitted = Vec::new();
+        drain.0.take_all(&mut submitted);
+        // The numbers wrap around, but there are never more of them
+        // at a time than slots, so their differences do not.
+        if let Some(&(first, _)) = submitted.first() {
+            submitted.sort_by_key(|(seq, _)| seq.wrapping_sub(first) as isize);
+        }
+
+        let mut kept: Option<Buf<T>> = None;
+        for (_, buf) in submitted {
+            let current = kept.as_deref().unwrap_or(&self.prev_buf);
+            match select(current, &buf) {
+                Keep::Current => self.adopt(buf),
+                Keep::Submitted => {
+                    if let Some(replaced) = kept.replace(buf) {
+                        self.adopt(replaced);
+                    }
+                }
+            }
+        }
+        let Some(mut kept) = kept else {
+            return false;
+        };
+        if self.count_adopted() {
+            self.publish(kept);
+        } else {
+            let mut new_state = self.next_unused_buffer();
+            core::mem::swap(
+                Buf::get_mut(&mut new_state).unwrap(),
+                Buf::get_mut(&mut kept).unwrap(),
+            );
+            self.publish(new_state);
+        }
+        true
+    }
+
+    /// Add a submitted buffer to the unused ones, if there is room.
+    fn adopt(&mut self, buf: Buf<T>) {
+        if self.count_adopted() {
+            // It may have the address of a freed buffer `write_regions`
+            // still tracks, which says nothing about its contents.
+            self.discard(buf);
+        }
+    }
+
+    /// Count a submitted buffer as one of the pair, if there is room.
+    fn count_adopted(&mut self) -> bool {
+        // Buffers of a `BufferPool` are not counted.
+        if let Recycler::Pool(_) = &self.unused_bufs_tx {
+            return true;
+        }
+        // With no more buffers than slots, the ring never drops any.
+        if self.created >= self.max_buffers.min(self.unused_bufs_rx.capacity()) {
+            return false;
+        }
+        self.created += 1;
+        true
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::Keep;
+    use crate::{new_clone, Full, TripleBufferBuilder};
+
+    #[test]
+    fn test_newest_wins() {
+        let (mut w, mut r) = new_clone(0);
+        assert!(!w.drain_inbox(Keep::newest));
+        let inbox = w.inbox(4);
+        for i in 1..=4 {
+            inbox.submit(i).unwrap();
+        }
+        assert_eq!(inbox.submit(5), Err(Full(5)));
+        assert!(w.drain_inbox(Keep::newest));
+        assert_eq!(*r.read_newest(), 4);
+        assert!(!w.drain_inbox(Keep::newest));
+
+        // The other submissions got reused.
+        let created = w.debug_state().created;
+        for i in 0..3 {
+            w.write_new(|_, new| *new = i);
+            r.read_newest();
+        }
+        assert_eq!(w.debug_state().created, created);
+    }
+
+    #[test]
+    fn test_select_can_keep_published() {
+        let (mut w, mut r) = new_clone(10);
+        let inbox = w.inbox(4);
+        inbox.submit(5).unwrap();
+        inbox.submit(7).unwrap();
+        let larger = |current: &i32, submitted: &i32| {
+            if submitted > current {
+                Keep::Submitted
+            } else {
+                Keep::Current
+            }
+        };
+        assert!(!w.drain_inbox(larger));
+        assert!(!r.has_update());
+        inbox.submit(12).unwrap();
+        assert!(w.drain_inbox(larger));
+        assert_eq!(*r.read_newest(), 12);
+    }
+
+    #[test]
+    fn test_bounded_pairs_stay_bounded() {
+        let (mut w, mut r) = TripleBufferBuilder::new(0)
+            .copy_buffers()
+            .max_buffers(3)
+            .build()
+            .unwrap();
+        let inbox = w.inbox(8);
+        for round in 0..10 {
+            for i in 0..8 {
+                inbox.submit(round * 8 + i).unwrap();
+            }
+            assert!(w.drain_inbox(Keep::newest));
+            assert_eq!(*r.read_newest(), round * 8 + 7);
+            assert!(w.debug_state().created <= 3);
+        }
+    }
+
+    #[test]
+    #[allow(clippy::single_range_in_vec_init)]
+    fn test_submitted_buffers_are_synced_by_write_regions() {
+        // Inline, so the buffers are the only allocations of their size.
+        #[derive(Clone)]
+        struct Cells([u32; 60]);
+
+        impl AsRef<[u32]> for Cells {
+            fn as_ref(&self) -> &[u32] {
+                &self.0
+            }
+        }
+
+        impl AsMut<[u32]> for Cells {
+            fn as_mut(&mut self) -> &mut [u32] {
+                &mut self.0
+            }
+        }
+
+        let (mut w, mut r) = new_clone(Cells([0; 60]));
+        let inbox = w.inbox(4);
+        let mut held = r.clone();
+        w.write_regions(&[1..2], |_, new| new[1] = 1);
+        held.read_newest();
+        for i in 2..=4 {
+            w.write_regions(&[i..i + 1], |_, new| new[i] = 1);
+            r.read_newest();
+        }
+        // Only `held` references its buffer, which the region log still
+        // tracks, so the allocator may hand its address to the next
+        // submission once it is gone.
+        drop(held);
+        inbox.submit(Cells([7; 60])).unwrap();
+        assert!(!w.drain_inbox(|_, _| Keep::Current));
+        for i in 5..=8 {
+            w.write_regions(&[i..i + 1], |_, new| new[i] = 1);
+        }
+        let state = &r.read_newest().0;
+        assert_eq!(state[0], 0);
+        assert!(state[1..=8].iter().all(|v| *v == 1));
+        assert!(state[9..].iter().all(|v| *v == 0));
+    }
+
+    #[test]
+    fn test_submitters_race() {
+        let (mut w, mut r) = new_clone((0, 0));
+        let inbox = w.inbox(4);
+        let producers: Vec<_> = (0..4)
+            .map(|p| {
+                let inbox = inbox.clone();
+                std::thread::spawn(move || {
+                    for i in 1..=200 {
+                        while inbox.submit((p, i)).is_err() {
+                            std::thread::yield_now();
+                        }
+                    }
+                })
+            })
+            .collect();
+        let mut last = [0; 4];
+        while producers.iter().any(|p| !p.is_finished()) {
+            // Keep the newest state of the producer of the published one.
+            w.drain_inbox(|current, submitted| {
+                if submitted.0 == current.0 && submitted.1 < current.1 {
+                    Keep::Current
+                } else {
+                    Keep::Submitted
+                }
+            });
+            let (p, i) = *r.read_newest();
+            assert!(i >= last[p]);
+            last[p] = i;
+        }
+        for producer in producers {
+            producer.join().unwrap();
+        }
+        drop(w);
+        assert!(inbox.is_closed());
+        assert!(inbox.submit((0, 0)).is_err());
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use loom::thread;
+    use std::sync::Arc;
+
+    use super::Keep;
+    use crate::new_clone;
+
+    #[test]
+    fn test_submit_while_draining() {
+        loom::model(|| {
+            let live = Arc::new(());
+            let (mut w, mut r) = new_clone((0, live.clone()));
+            let inbox = w.inbox(2);
+            let submitters: Vec<_> = (1..=2)
+                .map(|i| {
+                    let inbox = inbox.clone();
+                    let state = (i, live.clone());
+                    thread::spawn(move || inbox.submit(state).unwrap())
+                })
+                .collect();
+            thread::yield_now();
+            w.drain_inbox(Keep::newest);
+            let first = r.read_newest().0;
+            for submitter in submitters {
+                submitter.join().unwrap();
+            }
+            let drained = w.drain_inbox(Keep::newest);
+            let last = r.read_newest().0;
+            // Whatever the first drain missed, the second one publishes.
+            assert_ne!(last, 0);
+            assert_eq!(drained, first != last || first == 0);
+            drop((w, r, inbox));
+            assert_eq!(Arc::strong_count(&live), 1);
+        });
+    }
+}
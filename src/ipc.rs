@@ -0,0 +1,217 @@
+//! Buffer pairs shared between processes.
+//!
+//! One process creates a named segment with `create` and writes to it,
+//! and another one attaches to it with `open` and reads from it. The
+//! segment is a file in `/dev/shm` where available, and in the temporary
+//! directory otherwise, mapped into both processes. It uses the layout
+//! of the `mmap` module, so `mmap::recover` works on it as well.
+//!
+//! All coordination happens through atomics in the segment, so there are
+//! no locks a crashing process could leave behind. The writer only ever
+//! writes to a slot it owns, and hands it over with a single atomic swap
+//! once the state is complete. If the writing process dies in the middle
+//! of a write, the slot never gets handed over, and the reader keeps
+//! seeing the last complete state. Nothing the reader does can tear
+//! the state the writer is working on either.
+//!
+//! A process that exits without dropping its half can not mark it as
+//! detached, so `IpcReader::is_disconnected` and `IpcWriter::has_reader`
+//! keep reporting it as attached. Applications that need to detect
+//! crashed peers should publish a counter or timestamp as part of the
+//! state, and check that it advances.
+//!
+//! Only available with the `ipc` feature.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::ipc;
+//!
+//! // In the producing process:
+//! let mut writer = ipc::create("doc-example", [0u32; 4]).unwrap();
+//!
+//! // In the consuming process:
+//! let mut reader = ipc::open::<[u32; 4]>("doc-example").unwrap();
+//!
+//! writer.write([1; 4]);
+//! assert_eq!(*reader.read_newest(), [1; 4]);
+//! # ipc::remove("doc-example").unwrap();
+//! ````
+
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use bytemuck::Pod;
+
+use crate::mmap::{Flush, Mapping, MmapReader, MmapWriter};
+
+const WRITER: u8 = 0b01;
+const READER: u8 = 0b10;
+
+/// Get the path of the file backing the segment `name`.
+///
+/// Fails with `InvalidInput` if `name` is empty or contains
+/// a path separator.
+pub fn segment_path(name: &str) -> io::Result<PathBuf> {
+    if name.is_empty() || name.contains(['/', '\\']) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "segment names must be non-empty and not contain path separators",
+        ));
+    }
+    let shm = PathBuf::from("/dev/shm");
+    let dir = match shm.is_dir() {
+        true => shm,
+        false => std::env::temp_dir(),
+    };
+    Ok(dir.join(format!("simple_triple_buffer.{}", name)))
+}
+
+/// Create the segment `name` with the initial state `init`,
+/// and attach to it as the writer.
+///
+/// An existing segment of the same name gets replaced. Readers still
+/// attached to it keep it alive, but never see another update.
+pub fn create<T: Pod + Send + Sync>(name: &str, init: T) -> io::Result<IpcWriter<T>> {
+    let path = segment_path(name)?;
+    match std::fs::remove_file(&path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    let mapping = Arc::new(Mapping::create(&path, init)?);
+    mapping.attached().fetch_or(WRITER, Ordering::Release);
+    Ok(IpcWriter {
+        writer: MmapWriter::new(mapping.clone(), Flush::Manual),
+        mapping,
+    })
+}
+
+/// Attach to the segment `name` as its reader.
+///
+/// Fails with `InvalidData` if the segment was not created for
+/// states of type `T`, and with `AlreadyExists` if another reader is
+/// attached to it. A reader that crashed still counts as attached,
+/// until the writer creates the segment anew.
+pub fn open<T: Pod + Send + Sync>(name: &str) -> io::Result<IpcReader<T>> {
+    let mapping = Arc::new(Mapping::<T>::open(&segment_path(name)?)?);
+    if mapping.attached().fetch_or(READER, Ordering::Acquire) & READER != 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "the segment already has a reader",
+        ));
+    }
+    Ok(IpcReader {
+        reader: MmapReader::attach(mapping.clone()),
+        mapping,
+    })
+}
+
+/// Remove the segment `name`.
+///
+/// Halves still attached to it keep working.
+pub fn remove(name: &str) -> io::Result<()> {
+    std::fs::remove_file(segment_path(name)?)
+}
+
+/// Write side of a segment, see `create`.
+pub struct IpcWriter<T: Pod> {
+    writer: MmapWriter<T>,
+    mapping: Arc<Mapping<T>>,
+}
+
+impl<T: Pod> IpcWriter<T> {
+    /// Write a new state and publish it, see `MmapWriter::write_new`.
+    pub fn write_new(&mut self, f: impl FnOnce(&T, &mut T)) {
+        self.writer
+            .write_new(f)
+            .expect("publishing without flushing can not fail");
+    }
+
+    /// Publish `value` as the new state.
+    pub fn write(&mut self, value: T) {
+        self.write_new(|_, new| *new = value);
+    }
+
+    /// Check whether a reader is attached to the segment.
+    pub fn has_reader(&self) -> bool {
+        self.mapping.attached().load(Ordering::Acquire) & READER != 0
+    }
+}
+
+impl<T: Pod> Drop for IpcWriter<T> {
+    fn drop(&mut self) {
+        self.mapping
+            .attached()
+            .fetch_and(!WRITER, Ordering::Release);
+    }
+}
+
+/// Read side of a segment, see `open`.
+pub struct IpcReader<T: Pod> {
+    reader: MmapReader<T>,
+    mapping: Arc<Mapping<T>>,
+}
+
+impl<T: Pod> IpcReader<T> {
+    /// Get the newest published state.
+    pub fn read_newest(&mut self) -> &T {
+        self.reader.read_newest()
+    }
+
+    /// Check whether a state has been published
+    /// that `read_newest` has not returned yet.
+    pub fn has_update(&self) -> bool {
+        self.reader.has_update()
+    }
+
+    /// Check whether the writer has detached from the segment.
+    ///
+    /// Once this returns `true`, the next `read_newest`
+    /// returns the final state.
+    pub fn is_disconnected(&self) -> bool {
+        self.mapping.attached().load(Ordering::Acquire) & WRITER == 0
+    }
+}
+
+impl<T: Pod> Drop for IpcReader<T> {
+    fn drop(&mut self) {
+        self.mapping
+            .attached()
+            .fetch_and(!READER, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{create, open, remove, segment_path};
+
+    #[test]
+    fn test_single_reader() {
+        let name = format!("unit-{}", std::process::id());
+        let mut w = create(&name, 0u64).unwrap();
+        assert!(!w.has_reader());
+        let mut r = open::<u64>(&name).unwrap();
+        assert!(w.has_reader());
+        assert!(open::<u64>(&name).is_err());
+        assert!(open::<u32>(&name).is_err());
+
+        w.write(1);
+        drop(r);
+        assert!(!w.has_reader());
+
+        // A new reader continues where the previous one left off.
+        r = open(&name).unwrap();
+        assert!(r.has_update());
+        assert_eq!(*r.read_newest(), 1);
+        drop(w);
+        assert!(r.is_disconnected());
+        remove(&name).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_names() {
+        assert!(segment_path("").is_err());
+        assert!(segment_path("a/b").is_err());
+    }
+}
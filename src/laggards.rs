@@ -0,0 +1,224 @@
+//! Readers that fall behind, and detaching them.
+//!
+//! Each reader pins exactly one buffer, the state it last picked up, on
+//! top of the one pending in the slot, however many readers there are.
+//! A reader that stops reading keeps that buffer forever though, which
+//! the writer has to replace with a new one, and which counts towards
+//! `TripleBufferBuilder::max_buffers`.
+//!
+//! So every reader has an entry in a list shared by the pair, with the
+//! version of the state it last picked up. The writer can list the
+//! readers that fell behind, and detach them, giving up on the buffer
+//! they pin: a detached reader keeps its last state, but never picks up
+//! another one, and reports itself as disconnected.
+
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::sync::Mutex;
+use crate::{Reader, Writer};
+
+/// Identifies a reader of a pair, see `Writer::laggards`.
+///
+/// Clones of a reader get their own ids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ReaderId(u64);
+
+pub(crate) struct ReaderEntry {
+    id: ReaderId,
+    /// The version of the state the reader last picked up.
+    version: AtomicU64,
+    detached: AtomicBool,
+}
+
+impl ReaderEntry {
+    /// Record that the reader picked up the state with `version`.
+    pub(crate) fn picked_up(&self, version: u64) {
+        self.version.store(version, Ordering::Relaxed);
+    }
+
+    pub(crate) fn is_detached(&self) -> bool {
+        self.detached.load(Ordering::Relaxed)
+    }
+}
+
+/// The entries of all attached readers of a pair.
+pub(crate) struct ReaderList {
+    entries: Mutex<Vec<Arc<ReaderEntry>>>,
+    next_id: AtomicU64,
+    /// Detach readers that many states behind when publishing,
+    /// see `TripleBufferBuilder::detach_laggards`.
+    pub(crate) detach_after: Option<u64>,
+}
+
+impl ReaderList {
+    pub(crate) fn new() -> Self {
+        Self {
+            entries: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(0),
+            detach_after: None,
+        }
+    }
+
+    /// Add the entry of a new reader, which holds the state with `version`.
+    pub(crate) fn register(&self, version: u64, detached: bool) -> Arc<ReaderEntry> {
+        let entry = Arc::new(ReaderEntry {
+            id: ReaderId(self.next_id.fetch_add(1, Ordering::Relaxed)),
+            version: AtomicU64::new(version),
+            detached: AtomicBool::new(detached),
+        });
+        if !detached {
+            self.entries.lock().push(entry.clone());
+        }
+        entry
+    }
+
+    pub(crate) fn unregister(&self, entry: &ReaderEntry) {
+        self.entries.lock().retain(|e| e.id != entry.id);
+    }
+
+    /// Detach the readers more than `generations` states behind `version`,
+    /// returning how many there were.
+    fn detach_behind(&self, version: u64, generations: u64) -> usize {
+        let mut entries = self.entries.lock();
+        let before = entries.len();
+        entries.retain(|entry| {
+            let behind = version - entry.version.load(Ordering::Relaxed) > generations;
+            if behind {
+                entry.detached.store(true, Ordering::Relaxed);
+            }
+            !behind
+        });
+        before - entries.len()
+    }
+}
+
+impl<T> Writer<T> {
+    /// List the readers more than `generations` states behind the newest
+    /// one, which have not picked up a state for that many publishes.
+    ///
+    /// Only available with the `std` feature.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// let mut wedged = reader.clone();
+    /// wedged.read_newest();
+    ///
+    /// for i in 1..=10 {
+    ///     writer.write_new(|_, new| *new = i);
+    ///     reader.read_newest();
+    /// }
+    /// assert_eq!(writer.laggards(5), [wedged.id()]);
+    ///
+    /// assert!(writer.detach(wedged.id()));
+    /// assert!(wedged.is_disconnected());
+    /// assert_eq!(*wedged.read_newest(), 0);
+    /// ````
+    pub fn laggards(&self, generations: u64) -> Vec<ReaderId> {
+        let version = self.version;
+        self.read_update
+            .shared
+            .reader_list
+            .entries
+            .lock()
+            .iter()
+            .filter(|entry| version - entry.version.load(Ordering::Relaxed) > generations)
+            .map(|entry| entry.id)
+            .collect()
+    }
+
+    /// Detach the reader `id`, so it never picks up another state,
+    /// returning whether it was still attached.
+    ///
+    /// The buffer the reader holds no longer counts towards
+    /// `TripleBufferBuilder::max_buffers`.
+    ///
+    /// Only available with the `std` feature.
+    pub fn detach(&mut self, id: ReaderId) -> bool {
+        let mut entries = self.read_update.shared.reader_list.entries.lock();
+        let Some(pos) = entries.iter().position(|entry| entry.id == id) else {
+            return false;
+        };
+        entries
+            .swap_remove(pos)
+            .detached
+            .store(true, Ordering::Relaxed);
+        drop(entries);
+        self.created -= 1;
+        true
+    }
+
+    /// Detach the readers too far behind, if the pair was
+    /// created with `TripleBufferBuilder::detach_laggards`.
+    pub(crate) fn detach_laggards(&mut self) {
+        let list = &self.read_update.shared.reader_list;
+        if let Some(generations) = list.detach_after {
+            self.created -= list.detach_behind(self.version, generations);
+        }
+    }
+}
+
+impl<T> Reader<T> {
+    /// Get the id of this reader, see `Writer::laggards`.
+    ///
+    /// Only available with the `std` feature.
+    pub fn id(&self) -> ReaderId {
+        self.entry.id
+    }
+
+    /// Check whether the writer detached this reader,
+    /// see `Writer::detach`.
+    ///
+    /// Only available with the `std` feature.
+    pub fn is_detached(&self) -> bool {
+        self.entry.is_detached()
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::{new_clone, ReadError, TripleBufferBuilder};
+
+    #[test]
+    fn test_laggards_get_detached() {
+        let (mut w, mut r) = TripleBufferBuilder::new(0u32)
+            .copy_buffers()
+            .max_buffers(3)
+            .detach_laggards(1)
+            .build()
+            .unwrap();
+        let mut wedged = r.clone();
+        let mut stuck = r.clone();
+        for i in 1..=100 {
+            if i == 2 {
+                stuck.read_newest();
+            }
+            // Without detaching, the buffers `wedged` and `stuck`
+            // hold would keep counting towards the limit forever.
+            w.try_write_new(|_, new| *new = i).unwrap();
+            assert_eq!(*r.read_newest(), i);
+        }
+        assert!(w.laggards(0).is_empty());
+        assert!(stuck.is_detached());
+        assert!(wedged.is_detached());
+        assert!(!wedged.has_update());
+        assert_eq!(*wedged.read_newest(), 0);
+        assert_eq!(wedged.read_blocking(), Err(ReadError::Disconnected));
+        assert!(!r.is_detached());
+        // Clones of detached readers are detached as well.
+        assert!(wedged.clone().is_detached());
+    }
+
+    #[test]
+    fn test_dropped_readers_are_not_listed() {
+        let (mut w, r) = new_clone(0u32);
+        let ids: Vec<_> = (0..3).map(|_| r.clone()).map(|r| r.id()).collect();
+        assert_eq!(ids.len(), 3);
+        w.write_new(|_, new| *new = 1);
+        assert_eq!(w.laggards(0), [r.id()]);
+        assert!(!w.detach(ids[0]));
+        assert_eq!(w.laggards(1), []);
+    }
+}
@@ -0,0 +1,253 @@
+//! How long published states wait before a reader picks them up.
+
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use crate::{Reader, Writer};
+
+/// Fixed buckets of publish-to-read latencies, shared by a pair.
+///
+/// Readers add a sample whenever `read_newest` returns a newly published
+/// state, which only takes a binary search over the bounds and two
+/// atomic updates.
+pub(crate) struct Histogram {
+    /// The inclusive upper bounds of all but the last bucket, in
+    /// nanoseconds, ascending.
+    bounds: Box<[u64]>,
+    counts: Box<[AtomicU64]>,
+    max: AtomicU64,
+}
+
+impl Histogram {
+    pub(crate) fn new(bounds: impl IntoIterator<Item = Duration>) -> Self {
+        let mut bounds: Vec<u64> = bounds.into_iter().map(nanos).collect();
+        bounds.sort_unstable();
+        bounds.dedup();
+        Self {
+            counts: (0..=bounds.len()).map(|_| AtomicU64::new(0)).collect(),
+            bounds: bounds.into(),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn record(&self, latency: Duration) {
+        let latency = nanos(latency);
+        let bucket = self.bounds.partition_point(|bound| *bound < latency);
+        self.counts[bucket].fetch_add(1, Ordering::Relaxed);
+        self.max.fetch_max(latency, Ordering::Relaxed);
+    }
+
+    fn snapshot(&self) -> LatencyHistogram {
+        LatencyHistogram {
+            bounds: self
+                .bounds
+                .iter()
+                .map(|b| Duration::from_nanos(*b))
+                .collect(),
+            counts: self
+                .counts
+                .iter()
+                .map(|c| c.load(Ordering::Relaxed))
+                .collect(),
+            max: Duration::from_nanos(self.max.load(Ordering::Relaxed)),
+        }
+    }
+
+    fn reset(&self) {
+        for count in self.counts.iter() {
+            count.store(0, Ordering::Relaxed);
+        }
+        self.max.store(0, Ordering::Relaxed);
+    }
+}
+
+fn nanos(d: Duration) -> u64 {
+    u64::try_from(d.as_nanos()).unwrap_or(u64::MAX)
+}
+
+/// A snapshot of the publish-to-read latencies of a pair,
+/// see `TripleBufferBuilder::latency_buckets`.
+///
+/// Samples taken while the snapshot was made may be
+/// missing from some of the numbers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LatencyHistogram {
+    bounds: Vec<Duration>,
+    counts: Vec<u64>,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    /// Get the number of samples.
+    pub fn count(&self) -> u64 {
+        self.counts.iter().sum()
+    }
+
+    /// Iterate over the buckets, as their inclusive upper bound and the
+    /// number of samples in them. The last bucket has no upper bound.
+    pub fn buckets(&self) -> impl Iterator<Item = (Option<Duration>, u64)> + '_ {
+        let bounds = self.bounds.iter().copied().map(Some).chain([None]);
+        bounds.zip(self.counts.iter().copied())
+    }
+
+    /// Get the latency that a fraction `q` of the samples did not exceed,
+    /// or `None` if there are no samples.
+    ///
+    /// This is the upper bound of the bucket the sample falls into, or
+    /// the largest sample, if that is lower, so it overestimates by at
+    /// most the width of a bucket.
+    ///
+    /// # Panics
+    /// Panics if `q` is not between 0 and 1.
+    pub fn percentile(&self, q: f64) -> Option<Duration> {
+        assert!((0.0..=1.0).contains(&q), "percentile out of range");
+        let count = self.count();
+        if count == 0 {
+            return None;
+        }
+        let rank = ((q * count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (bound, n) in self.buckets() {
+            seen += n;
+            if seen >= rank {
+                return Some(bound.map_or(self.max, |bound| bound.min(self.max)));
+            }
+        }
+        Some(self.max)
+    }
+
+    /// Get the median latency, see `percentile`.
+    pub fn p50(&self) -> Option<Duration> {
+        self.percentile(0.5)
+    }
+
+    /// Get the 99th percentile latency, see `percentile`.
+    pub fn p99(&self) -> Option<Duration> {
+        self.percentile(0.99)
+    }
+
+    /// Get the largest latency, or `None` if there are no samples.
+    pub fn max(&self) -> Option<Duration> {
+        (self.count() > 0).then_some(self.max)
+    }
+}
+
+impl<T> Writer<T> {
+    /// Get the publish-to-read latencies of the pair so far, if it was
+    /// created with `TripleBufferBuilder::latency_buckets`.
+    ///
+    /// Only available with the `latency` feature.
+    pub fn latency_histogram(&self) -> Option<LatencyHistogram> {
+        self.read_update
+            .shared
+            .latency
+            .as_ref()
+            .map(Histogram::snapshot)
+    }
+
+    /// Drop all latency samples taken so far.
+    ///
+    /// Only available with the `latency` feature.
+    pub fn reset_latency_histogram(&self) {
+        if let Some(latency) = &self.read_update.shared.latency {
+            latency.reset();
+        }
+    }
+}
+
+impl<T> Reader<T> {
+    /// Get the publish-to-read latencies of the pair so far, if it was
+    /// created with `TripleBufferBuilder::latency_buckets`.
+    ///
+    /// All readers of a pair add to the same histogram.
+    ///
+    /// Only available with the `latency` feature.
+    pub fn latency_histogram(&self) -> Option<LatencyHistogram> {
+        self.read_update
+            .shared
+            .latency
+            .as_ref()
+            .map(Histogram::snapshot)
+    }
+
+    /// Drop all latency samples taken so far.
+    ///
+    /// Only available with the `latency` feature.
+    pub fn reset_latency_histogram(&self) {
+        if let Some(latency) = &self.read_update.shared.latency {
+            latency.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::Histogram;
+    use crate::TripleBufferBuilder;
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let histogram = Histogram::new([ms(10), ms(1), ms(100)]);
+        assert_eq!(histogram.snapshot().p50(), None);
+        for _ in 0..90 {
+            histogram.record(Duration::from_micros(500));
+        }
+        for _ in 0..9 {
+            histogram.record(ms(50));
+        }
+        histogram.record(ms(250));
+
+        let snapshot = histogram.snapshot();
+        assert_eq!(snapshot.count(), 100);
+        assert_eq!(
+            snapshot.buckets().collect::<Vec<_>>(),
+            [
+                (Some(ms(1)), 90),
+                (Some(ms(10)), 0),
+                (Some(ms(100)), 9),
+                (None, 1)
+            ]
+        );
+        assert_eq!(snapshot.p50(), Some(ms(1)));
+        assert_eq!(snapshot.p99(), Some(ms(100)));
+        assert_eq!(snapshot.percentile(1.0), Some(ms(250)));
+        assert_eq!(snapshot.max(), Some(ms(250)));
+
+        histogram.reset();
+        assert_eq!(histogram.snapshot().count(), 0);
+    }
+
+    #[test]
+    fn test_only_new_states_are_sampled() {
+        let (mut w, mut r) = TripleBufferBuilder::new(0)
+            .copy_buffers()
+            .latency_buckets([ms(1), ms(10)])
+            .build()
+            .unwrap();
+        r.read_newest();
+        assert_eq!(r.latency_histogram().unwrap().count(), 0);
+
+        w.write_new(|_, new| *new = 1);
+        std::thread::sleep(ms(2));
+        for _ in 0..10 {
+            r.read_newest();
+        }
+        let histogram = w.latency_histogram().unwrap();
+        assert_eq!(histogram.count(), 1);
+        assert!(histogram.max().unwrap() >= ms(2));
+        assert_ne!(histogram.p50(), Some(ms(1)));
+
+        r.reset_latency_histogram();
+        assert_eq!(w.latency_histogram().unwrap().max(), None);
+
+        let (_w, r) = TripleBufferBuilder::new(0).copy_buffers().build().unwrap();
+        assert!(r.latency_histogram().is_none());
+    }
+}
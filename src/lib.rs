@@ -1,63 +1,617 @@
 #![warn(rust_2018_idioms)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-use std::sync::Arc;
-use std::sync::{
-    mpsc::{channel, Receiver, Sender},
-    Mutex,
+extern crate alloc;
+
+mod aligned;
+#[cfg(feature = "rkyv")]
+mod archive;
+#[cfg(feature = "std")]
+pub mod backend;
+#[cfg(feature = "std")]
+mod blocking;
+#[cfg(feature = "std")]
+pub mod bridge;
+mod builder;
+mod capacity;
+#[cfg(feature = "capi")]
+pub mod capi;
+#[cfg(feature = "std")]
+mod changes;
+pub mod chunked;
+mod closed;
+mod combined;
+pub mod compat;
+mod debug_state;
+mod delta;
+#[cfg(feature = "std")]
+pub mod double;
+#[cfg(feature = "stats")]
+mod drop_rate;
+mod duplex;
+mod error;
+mod field;
+mod fixed_slice;
+#[cfg(feature = "std")]
+mod frame_io;
+pub mod frames;
+mod grant;
+mod history;
+#[cfg(feature = "std")]
+mod hooks;
+mod inbox;
+#[cfg(feature = "ipc")]
+pub mod ipc;
+#[cfg(feature = "std")]
+mod laggards;
+#[cfg(feature = "latency")]
+mod latency;
+pub mod local;
+#[cfg(feature = "locked-slots")]
+pub mod locked_slots;
+#[cfg(feature = "std")]
+mod map;
+#[cfg(feature = "metrics")]
+mod metrics;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+#[cfg(feature = "std")]
+pub mod model;
+mod monotonic;
+mod owned;
+#[cfg(feature = "std")]
+mod periodic;
+#[cfg(feature = "im")]
+pub mod persistent;
+#[cfg(feature = "std")]
+mod pipe;
+mod pool;
+#[cfg(feature = "stats")]
+mod publish_rate;
+#[cfg(all(feature = "readiness", unix))]
+mod readiness;
+mod realtime;
+#[cfg(feature = "record")]
+pub mod record;
+mod regions;
+#[cfg(feature = "registry")]
+pub mod registry;
+#[cfg(feature = "record")]
+pub mod replay;
+mod reset;
+mod retire;
+mod ring;
+mod scoped;
+#[cfg(feature = "zeroize")]
+mod scrub;
+mod shared;
+mod shared_writer;
+mod slot;
+pub mod small;
+#[cfg(feature = "serde")]
+pub mod snapshot;
+mod source;
+mod state_traits;
+pub mod static_buffer;
+#[cfg(feature = "stats")]
+pub mod stats;
+#[cfg(feature = "async")]
+mod stream;
+mod subscribe;
+mod sync;
+#[cfg(feature = "tokio")]
+mod tee;
+pub mod test_util;
+#[cfg(feature = "trace-export")]
+mod trace;
+mod uninit;
+mod vec_pool;
+mod version;
+pub mod watch_compat;
+
+pub use aligned::{new_aligned, AlignedBytes};
+pub use builder::TripleBufferBuilder;
+pub use capacity::Reserve;
+#[cfg(feature = "std")]
+pub use changes::ChangeMask;
+#[cfg(feature = "async")]
+pub use closed::Closed;
+pub use combined::TripleBuffer;
+pub use debug_state::{BufferDebug, Location, PairStatus, PoolDebug};
+pub use delta::{new_with_delta, DeltaWriter};
+pub use duplex::{duplex, Endpoint};
+#[cfg(feature = "serde")]
+pub use error::SnapshotError;
+pub use error::{
+    BuildError, Full, JoinError, PoolExhausted, ReadError, TooManyReaders, WouldBlock, WriteError,
 };
+pub use field::FieldWriter;
+pub use fixed_slice::{new_slice, SliceReader, SliceWriter};
+#[cfg(feature = "std")]
+pub use frame_io::{IoFrameWriter, PartialFrame};
+pub use grant::ByteGrant;
+#[cfg(feature = "std")]
+pub use hooks::{Hook, HookCtx, Hooks};
+pub use inbox::{Inbox, Keep};
+#[cfg(feature = "std")]
+pub use laggards::ReaderId;
+#[cfg(feature = "latency")]
+pub use latency::LatencyHistogram;
+#[cfg(feature = "std")]
+pub use map::TripleBufferMap;
+pub use owned::{new_owned, OwnedReader, OwnedWriter};
+#[cfg(feature = "std")]
+pub use periodic::{spawn_periodic_writer, PeriodicHandle};
+#[cfg(feature = "std")]
+pub use pipe::{pipe, pipe_inline, PipeHandle};
+pub use pool::{BufferPool, PoolStats};
+pub use realtime::Allocation;
+pub use scoped::{new_scoped, ScopedWriter};
+#[cfg(feature = "zeroize")]
+pub use scrub::new_zeroizing;
+pub use shared::Shared;
+pub use shared_writer::SharedWriter;
+pub use source::{new_with_source, BufferSource};
+pub use state_traits::{StatePublisher, StateSubscriber};
+pub use static_buffer::StaticTripleBuffer;
+#[cfg(feature = "async")]
+pub use stream::{DiffStream, WaitFor};
+#[cfg(feature = "tokio")]
+pub use tee::{OverflowPolicy, TeeHandle};
+pub use uninit::{new_write_init, InitBuf, InitWriter, UninitBuf};
+pub use vec_pool::{new_vec_pool, VecWriter};
+pub use version::PublishReceipt;
 
+use alloc::borrow::Cow;
+use alloc::boxed::Box;
+use alloc::collections::VecDeque;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem::ManuallyDrop;
+use core::task::Waker;
+use regions::RegionLog;
+use ring::{channel, Receiver, Sender};
+use slot::Slot;
+#[cfg(feature = "std")]
+use std::time::Instant;
+use sync::atomic::{AtomicBool, AtomicUsize};
+use sync::Mutex;
+
+/// A shared handle to a buffer.
+#[cfg(not(feature = "triomphe"))]
 type Buf<T> = Arc<T>;
+/// A shared handle to a buffer.
+///
+/// Buffers never have `Weak` references, so
+/// this skips maintaining a weak count.
+#[cfg(feature = "triomphe")]
+type Buf<T> = triomphe::Arc<T>;
+
+/// Get the number of strong references to a buffer.
+#[cfg(not(feature = "triomphe"))]
+fn ref_count<T>(buf: &Buf<T>) -> usize {
+    Arc::strong_count(buf)
+}
+/// Get the number of references to a buffer.
+#[cfg(feature = "triomphe")]
+fn ref_count<T>(buf: &Buf<T>) -> usize {
+    Buf::count(buf)
+}
+
+/// Check whether exactly `count` references to a buffer exist,
+/// including `Weak` ones.
+#[cfg(not(feature = "triomphe"))]
+fn has_refs<T>(buf: &Buf<T>, count: usize) -> bool {
+    Arc::strong_count(buf) == count && Arc::weak_count(buf) == 0
+}
+/// Check whether exactly `count` references to a buffer exist.
+#[cfg(feature = "triomphe")]
+fn has_refs<T>(buf: &Buf<T>, count: usize) -> bool {
+    Buf::count(buf) == count
+}
+
+/// Get a handle to `buf` that does not own a reference of its own.
+///
+/// # Safety
+/// The handle must not be used once the last
+/// reference it was created from is gone.
+unsafe fn alias<T>(buf: &Buf<T>) -> ManuallyDrop<Buf<T>> {
+    // SAFETY: Deferred to the caller.
+    ManuallyDrop::new(unsafe { Buf::from_raw(Buf::as_ptr(buf)) })
+}
+
+/// How a pair creates additional buffer instances.
+enum MakeBuf<T> {
+    /// No new buffers may be created.
+    Never,
+    /// New buffers are plain copies of the previous state.
+    Copy(fn(&T) -> T),
+    /// New buffers come from a clone function.
+    Custom(Box<dyn BufferSource<T> + Send>),
+    /// New buffers come from a source that also
+    /// wants to know about recycled and retired buffers.
+    Source(Box<dyn BufferSource<T> + Send>),
+}
+impl<T> MakeBuf<T> {
+    fn make(&mut self, prev: &T) -> Option<T> {
+        match self {
+            MakeBuf::Never => None,
+            MakeBuf::Copy(copy) => Some(copy(prev)),
+            MakeBuf::Custom(source) | MakeBuf::Source(source) => Some(source.create(prev)),
+        }
+    }
+}
+/// Brings a stale buffer up to date with the previous state.
+type Refresh<T> = Box<dyn FnMut(&T, &mut T) + Send>;
+/// Wipes the contents of a buffer that is no longer needed.
+type Scrub<T> = fn(&mut T);
+
+struct Publication<T> {
+    buf: Buf<T>,
+    #[cfg(feature = "std")]
+    time: Option<Instant>,
+    /// The number of states published up to this one.
+    #[cfg(feature = "std")]
+    version: u64,
+}
+struct SharedState<T> {
+    pending: Slot<T>,
+    history: Mutex<VecDeque<Buf<T>>>,
+    readers: AtomicUsize,
+    /// The number of readers allowed, see `TripleBufferBuilder::max_readers`.
+    max_readers: usize,
+    /// The number of buffers dropped along with the last reader holding
+    /// them, for the writer to subtract from the ones it created.
+    freed: AtomicUsize,
+    writer_alive: AtomicBool,
+    closed_waker: Mutex<Option<Waker>>,
+    label: Option<Cow<'static, str>>,
+    scrub: Option<Scrub<T>>,
+    /// Whether the pair has a custom `BufferSource`.
+    source_hooks: bool,
+    /// Buffers held by dropped readers, for the writer to pick
+    /// up once nothing else references them.
+    released: Mutex<Vec<Buf<T>>>,
+    /// The custom source, once the writer has been dropped.
+    orphaned_source: Mutex<Option<Box<dyn BufferSource<T> + Send>>>,
+    #[cfg(feature = "registry")]
+    registration: registry::Registration,
+    #[cfg(feature = "latency")]
+    latency: Option<latency::Histogram>,
+    #[cfg(feature = "trace-export")]
+    trace: Option<trace::TraceRing>,
+    #[cfg(feature = "std")]
+    observed_hook: hooks::ObservedHook,
+    /// When each section last changed, see `TripleBufferBuilder::change_mask`.
+    #[cfg(feature = "std")]
+    changes: Option<changes::ChangeLog>,
+    #[cfg(feature = "metrics")]
+    observed_metric: Option<::metrics::Counter>,
+    #[cfg(feature = "stats")]
+    drop_window: Option<drop_rate::DropWindow>,
+    #[cfg(feature = "stats")]
+    counters: stats::Counters,
+    #[cfg(all(feature = "readiness", unix))]
+    readiness: std::sync::OnceLock<readiness::Readiness>,
+    #[cfg(feature = "std")]
+    wakeup: blocking::Wakeup,
+    #[cfg(feature = "std")]
+    reader_list: laggards::ReaderList,
+    #[cfg(feature = "async")]
+    publish_wakers: stream::PublishWakers,
+}
+impl<T> Drop for SharedState<T> {
+    fn drop(&mut self) {
+        self.retire_all();
+    }
+}
 struct ReadUpdate<T> {
-    shared: Arc<Mutex<Option<Buf<T>>>>,
+    shared: sync::Arc<SharedState<T>>,
 }
 impl<T> ReadUpdate<T> {
-    fn new() -> Self {
+    fn new(
+        init: Buf<T>,
+        label: Option<Cow<'static, str>>,
+        scrub: Option<Scrub<T>>,
+        source_hooks: bool,
+    ) -> Self {
         Self {
-            shared: Arc::new(Mutex::new(None)),
+            shared: sync::Arc::new(SharedState {
+                #[cfg(feature = "registry")]
+                registration: registry::Registration::new(label.clone()),
+                pending: Slot::new(init),
+                history: Mutex::new(VecDeque::new()),
+                readers: AtomicUsize::new(1),
+                max_readers: usize::MAX,
+                freed: AtomicUsize::new(0),
+                writer_alive: AtomicBool::new(true),
+                closed_waker: Mutex::new(None),
+                label,
+                scrub,
+                source_hooks,
+                released: Mutex::new(Vec::new()),
+                orphaned_source: Mutex::new(None),
+                #[cfg(feature = "latency")]
+                latency: None,
+                #[cfg(feature = "trace-export")]
+                trace: None,
+                #[cfg(feature = "std")]
+                observed_hook: hooks::ObservedHook::new(),
+                #[cfg(feature = "std")]
+                changes: None,
+                #[cfg(feature = "metrics")]
+                observed_metric: None,
+                #[cfg(feature = "stats")]
+                drop_window: None,
+                #[cfg(feature = "stats")]
+                counters: stats::Counters::new(),
+                #[cfg(all(feature = "readiness", unix))]
+                readiness: std::sync::OnceLock::new(),
+                #[cfg(feature = "std")]
+                wakeup: blocking::Wakeup::new(),
+                #[cfg(feature = "std")]
+                reader_list: laggards::ReaderList::new(),
+                #[cfg(feature = "async")]
+                publish_wakers: stream::PublishWakers::new(),
+            }),
         }
     }
-    fn replace(&self, v: Buf<T>) -> Option<Buf<T>> {
-        std::mem::replace(&mut self.shared.lock().unwrap(), Some(v))
+}
+
+/// Where unused buffers get returned to.
+enum Recycler<T> {
+    /// The writer of the pair.
+    Channel(Sender<T>),
+    /// A pool shared with other pairs.
+    Pool(BufferPool<T>),
+}
+impl<T> Clone for Recycler<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Recycler::Channel(tx) => Recycler::Channel(tx.clone()),
+            Recycler::Pool(pool) => Recycler::Pool(pool.clone()),
+        }
     }
-    fn take(&self) -> Option<Buf<T>> {
-        self.shared.lock().unwrap().take()
+}
+
+/// Return a buffer to the writer once nothing else references it,
+/// scrubbing it first if the pair zeroizes its buffers.
+///
+/// If other clones of it still exist, whoever releases
+/// the last one is responsible for returning it.
+fn recycle<T>(unused_bufs_tx: &Recycler<T>, scrub: Option<Scrub<T>>, mut buf: Buf<T>) {
+    // This also rules out buffers that have `Weak` references,
+    // which could otherwise be upgraded while being written to.
+    if let Some(unused) = Buf::get_mut(&mut buf) {
+        if let Some(scrub) = scrub {
+            scrub(unused);
+        }
+        match unused_bufs_tx {
+            // If the writer is gone there is nothing to return it to.
+            Recycler::Channel(tx) => drop(tx.send(buf)),
+            Recycler::Pool(pool) => pool.put(buf),
+        }
+    }
+}
+
+/// Scrub a buffer that is about to be dropped,
+/// if nothing else references it.
+fn scrub_unique<T>(scrub: Option<Scrub<T>>, buf: &mut Buf<T>) {
+    if let (Some(scrub), Some(buf)) = (scrub, Buf::get_mut(buf)) {
+        scrub(buf);
+    }
+}
+
+/// A buffer taken back out of the slot by `Writer::try_update_pending`,
+/// which goes back into it if the update panics, so `prev_buf` keeps
+/// pointing to the state in the slot.
+struct Reclaimed<'a, T> {
+    slot: &'a Slot<T>,
+    buf: Option<Buf<T>>,
+}
+impl<T> Drop for Reclaimed<'_, T> {
+    fn drop(&mut self) {
+        if let Some(buf) = self.buf.take() {
+            self.slot.restore(buf);
+        }
     }
 }
 
 /// Write side of the triple buffer.
 pub struct Writer<T> {
-    make_buf: Box<dyn FnMut(&T) -> T + Send>,
-    unused_bufs_rx: Receiver<Buf<T>>,
+    make_buf: MakeBuf<T>,
+    refresh: Option<Refresh<T>>,
+    unused_bufs_rx: Receiver<T>,
+    /// A replaced state no reader ever picked up, kept for
+    /// the next write instead of going through the ring.
+    spare: Option<Buf<T>>,
 
-    prev_buf: Buf<T>,
-    unused_bufs_tx: Sender<Buf<T>>,
+    /// The last published state. The pending slot always holds it while
+    /// the writer exists, so this borrows the reference of the slot
+    /// instead of keeping one of its own.
+    prev_buf: ManuallyDrop<Buf<T>>,
+    unused_bufs_tx: Recycler<T>,
     read_update: ReadUpdate<T>,
+
+    regions: Option<Box<RegionLog>>,
+    history_len: usize,
+    #[cfg(feature = "std")]
+    timestamps: bool,
+    created: usize,
+    /// The number of buffers dropped by `check_unused`.
+    skipped: usize,
+    max_buffers: usize,
+    #[cfg(feature = "record")]
+    recorder: Option<record::Hook<T>>,
+    #[cfg(feature = "tokio")]
+    tee: Option<Box<tee::Tee<T>>>,
+    /// The state `publish_default_in_place` copies from.
+    default_state: Option<Box<T>>,
+    inbox: Option<inbox::Drain<T>>,
+    /// The number of states published.
+    version: u64,
+    /// Whether allocating is a violation, see `warm_up`.
+    warmed_up: bool,
+    on_violation: Option<realtime::Violation>,
+    #[cfg(feature = "std")]
+    hooks: Option<Box<hooks::WriterHooks>>,
+    #[cfg(feature = "std")]
+    diff: Option<changes::Diff<T>>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Box<metrics::WriterMetrics>>,
+    #[cfg(feature = "stats")]
+    publish_times: Box<publish_rate::PublishTimes>,
+    /// The number of writes `publish_if_newer` turned down.
+    #[cfg(feature = "stats")]
+    rejected: u64,
+    /// The value of `created` at the last publish.
+    #[cfg(feature = "tracing")]
+    created_at_publish: usize,
 }
 
 /// Read side of the triple buffer.
+///
+/// Cloning a reader creates another reader for the same pair,
+/// starting at the same state. Each reader independently picks up
+/// the newest published state, so any number of threads can
+/// follow the writer. Every reader can hold on to one state that
+/// is older than the newest one, so the number of copies of T
+/// reaches a steady state around `2 + n_readers`.
 pub struct Reader<T> {
     prev_buf: Buf<T>,
-    unused_bufs_tx: Sender<Buf<T>>,
+    unused_bufs_tx: Recycler<T>,
     read_update: ReadUpdate<T>,
+    rewound: Option<Buf<T>>,
+    /// The pending state `read_if` turned down, kept alive until the
+    /// writer replaces it, so its address can not come back meanwhile.
+    rejected: Option<Buf<T>>,
+    #[cfg(feature = "std")]
+    prev_time: Option<Instant>,
+    /// The version of `prev_buf`.
+    #[cfg(feature = "std")]
+    prev_version: u64,
+    /// The sections that changed with the last read.
+    #[cfg(feature = "std")]
+    changes: ChangeMask,
+    #[cfg(feature = "std")]
+    entry: alloc::sync::Arc<laggards::ReaderEntry>,
 }
 
 /// Create a new buffer pair that creates additional
 /// buffer instances with a custom clone function.
 ///
+/// The clone function has to be `'static`, see `new_scoped`
+/// for one that borrows local data.
+///
 /// The number of copies of T will reach a steady state around 2-4.
 pub fn new_with<T>(
     init: T,
     make_buf: impl FnMut(&T) -> T + 'static + Send,
 ) -> (Writer<T>, Reader<T>) {
-    let w = Writer::new(init, make_buf);
-    let r = Reader {
-        prev_buf: w.prev_buf.clone(),
-        unused_bufs_tx: w.unused_bufs_tx.clone(),
-        read_update: ReadUpdate {
-            shared: w.read_update.shared.clone(),
-        },
-    };
-    (w, r)
+    TripleBufferBuilder::new(init).clone_with(make_buf).finish()
+}
+
+/// Create a new buffer pair that creates additional buffer instances
+/// with a custom clone function, and brings reused buffers up to date
+/// with a custom refresh function.
+///
+/// `refresh(prev, stale)` gets called by `Writer::write_update` in place
+/// of `Clone::clone_from`, and only needs to copy the parts of the state
+/// that can actually differ between `prev` and `stale`.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+///
+/// #[derive(Clone)]
+/// struct Level {
+///     assets: Arc<Vec<u8>>,
+///     positions: Vec<f32>,
+/// }
+///
+/// let init = Level {
+///     assets: Arc::new(vec![0; 1 << 20]),
+///     positions: vec![0.0; 4],
+/// };
+/// let (mut writer, mut reader) = simple_triple_buffer::new_with_refresh(
+///     init,
+///     |v| v.clone(),
+///     // The assets never change, so only sync the positions.
+///     |prev, stale| stale.positions.clone_from(&prev.positions),
+/// );
+///
+/// writer.write_update(|level| level.positions[0] = 1.0);
+/// assert_eq!(reader.read_newest().positions[0], 1.0);
+/// ````
+pub fn new_with_refresh<T>(
+    init: T,
+    make_buf: impl FnMut(&T) -> T + 'static + Send,
+    refresh: impl FnMut(&T, &mut T) + 'static + Send,
+) -> (Writer<T>, Reader<T>) {
+    TripleBufferBuilder::new(init)
+        .clone_with(make_buf)
+        .refresh_with(refresh)
+        .finish()
+}
+
+/// Create a new buffer pair that starts out publishing an existing `Arc`
+/// and creates additional buffer instances with a custom clone function.
+///
+/// Since other clones of `init` may exist, the pair never
+/// writes into it. As long as any such clone is alive,
+/// `init` simply drops out of rotation once a newer state
+/// gets published, instead of being reused as a buffer.
+/// With the `triomphe` feature, buffers are no `std::sync::Arc`s,
+/// so the pair publishes a copy of such an `init` made with
+/// `make_buf` instead.
+///
+/// The number of copies of T will reach a steady state around 2-4.
+///
+/// # Example
+/// ```
+/// use std::sync::Arc;
+///
+/// let config = Arc::new(vec![1, 2, 3]);
+/// let (mut writer, mut reader) =
+///     simple_triple_buffer::new_from_arc(config.clone(), |v| v.clone());
+/// assert_eq!(*reader.read_newest(), [1, 2, 3]);
+///
+/// writer.write_new(|_, new| new.push(4));
+/// assert_eq!(*config, [1, 2, 3]);
+/// ````
+pub fn new_from_arc<T>(
+    init: Arc<T>,
+    make_buf: impl FnMut(&T) -> T + 'static + Send,
+) -> (Writer<T>, Reader<T>) {
+    TripleBufferBuilder::from_arc(init)
+        .clone_with(make_buf)
+        .finish()
+}
+
+/// Create a new buffer pair that uses the given spare buffers
+/// and never creates any additional ones.
+///
+/// The contents of the spare buffers do not matter, as every write
+/// overwrites them. Writes that would need more buffers than
+/// `1 + spares.len()` fail with `PoolExhausted` instead of allocating,
+/// see `Writer::try_write_new`. With at least two spare buffers,
+/// that can never happen.
+///
+/// # Example
+/// ```
+/// let (mut writer, mut reader) = simple_triple_buffer::new_with_buffers(0, vec![0]);
+///
+/// writer.write_new(|old, new| *new = *old + 1);
+/// assert!(writer.try_write_new(|old, new| *new = *old + 1).is_err());
+///
+/// assert_eq!(*reader.read_newest(), 1);
+/// assert!(writer.try_write_new(|old, new| *new = *old + 1).is_ok());
+/// ````
+pub fn new_with_buffers<T>(init: T, spares: Vec<T>) -> (Writer<T>, Reader<T>) {
+    TripleBufferBuilder::new(init)
+        .spare_buffers(spares)
+        .finish()
 }
 
 /// Create a new buffer pair that creates additional
@@ -68,29 +622,312 @@ pub fn new_clone<T: Clone>(init: T) -> (Writer<T>, Reader<T>) {
     new_with(init, |v| v.clone())
 }
 
+/// Create a new buffer pair that creates additional
+/// buffer instances from scratch with a custom function.
+///
+/// Unlike with `new_with`, the function does not get to see the
+/// previous state, which fits states that are cheap to construct
+/// in an empty shape but expensive to copy.
+///
+/// The number of copies of T will reach a steady state around 2-4.
+///
+/// # Example
+/// ```
+/// let (mut writer, mut reader) =
+///     simple_triple_buffer::new_with_init_fn(Vec::new(), || Vec::with_capacity(1024));
+///
+/// writer.write_new(|old, new| {
+///     new.clear();
+///     new.extend(old);
+///     new.push(1);
+/// });
+/// assert_eq!(*reader.read_newest(), [1]);
+/// ````
+pub fn new_with_init_fn<T>(
+    init: T,
+    mut make: impl FnMut() -> T + 'static + Send,
+) -> (Writer<T>, Reader<T>) {
+    new_with(init, move |_| make())
+}
+
+/// Create a new buffer pair for a plain-old-data state, that creates
+/// additional buffer instances by copying the previous state.
+///
+/// Unlike `new_clone`, this involves no boxed clone function,
+/// all buffer creation is a plain `memcpy`. Use
+/// `TripleBufferBuilder::copy_buffers` to combine this with
+/// other options, like preallocation.
+///
+/// The number of copies of T will reach a steady state around 2-4.
+///
+/// # Example
+/// ```
+/// let (mut writer, mut reader) = simple_triple_buffer::new_pod([0.0f32; 4096]);
+///
+/// writer.write_update(|bones| bones[7] = 1.0);
+/// assert_eq!(reader.read_newest()[7], 1.0);
+/// ````
+pub fn new_pod<T: Copy>(init: T) -> (Writer<T>, Reader<T>) {
+    TripleBufferBuilder::new(init).copy_buffers().finish()
+}
+
+/// Create a new buffer pair that shares its unused buffers with other
+/// pairs using the same pool, and creates additional buffer
+/// instances by cloning a previous state.
+///
+/// Any pair using the pool may reuse buffers returned by
+/// any other, which keeps the total number of copies of T
+/// low when many pairs of the same state type exist.
+///
+/// # Example
+/// ```
+/// use simple_triple_buffer::{new_with_pool, BufferPool};
+///
+/// let pool = BufferPool::new(4);
+/// let (mut w1, mut r1) = new_with_pool(vec![0u8; 1024], pool.clone());
+/// let (mut w2, mut r2) = new_with_pool(vec![0u8; 1024], pool.clone());
+///
+/// w1.write_new(|_, new| new[0] = 1);
+/// w2.write_new(|_, new| new[0] = 2);
+/// assert_eq!(r1.read_newest()[0], 1);
+/// assert_eq!(r2.read_newest()[0], 2);
+/// assert_eq!(pool.stats().pooled, 2);
+/// ````
+pub fn new_with_pool<T: Clone>(init: T, pool: BufferPool<T>) -> (Writer<T>, Reader<T>) {
+    TripleBufferBuilder::new(init)
+        .clone_with(|v| v.clone())
+        .pool(pool)
+        .finish()
+}
+
+/// Create a new buffer pair that starts with `T::default()`
+/// and creates additional buffer instances the same way.
+///
+/// The number of copies of T will reach a steady state around 2-4.
+///
+/// # Example
+/// ```
+/// let (mut writer, mut reader) = simple_triple_buffer::new_default::<Vec<u32>>();
+/// assert!(reader.read_newest().is_empty());
+///
+/// writer.write_new(|old, new| {
+///     new.clone_from(old);
+///     new.push(1);
+/// });
+/// assert_eq!(*reader.read_newest(), [1]);
+/// ````
+pub fn new_default<T: Default>() -> (Writer<T>, Reader<T>) {
+    new_with(T::default(), |_| T::default())
+}
+
 impl<T> Writer<T> {
-    fn new(init: T, make_buf: impl FnMut(&T) -> T + 'static + Send) -> Self {
-        let prev_buf = Arc::new(init);
-        let make_buf = Box::new(make_buf);
-        let read_update = ReadUpdate::new();
-        let (unused_bufs_tx, unused_bufs_rx) = channel();
+    fn new(
+        prev_buf: Buf<T>,
+        make_buf: MakeBuf<T>,
+        label: Option<Cow<'static, str>>,
+        scrub: Option<Scrub<T>>,
+        capacity: usize,
+    ) -> Self {
+        let source_hooks = matches!(make_buf, MakeBuf::Source(_));
+        // SAFETY: The slot takes over `prev_buf`, see `Writer::prev_buf`.
+        let alias = unsafe { alias(&prev_buf) };
+        let read_update = ReadUpdate::new(prev_buf, label, scrub, source_hooks);
+        let (unused_bufs_tx, unused_bufs_rx) = channel(capacity);
         Self {
-            prev_buf,
+            prev_buf: alias,
             make_buf,
-            unused_bufs_tx,
+            refresh: None,
+            unused_bufs_tx: Recycler::Channel(unused_bufs_tx),
             unused_bufs_rx,
+            spare: None,
             read_update,
+            regions: None,
+            history_len: 0,
+            #[cfg(feature = "std")]
+            timestamps: false,
+            created: 1,
+            skipped: 0,
+            max_buffers: usize::MAX,
+            #[cfg(feature = "record")]
+            recorder: None,
+            #[cfg(feature = "tokio")]
+            tee: None,
+            default_state: None,
+            inbox: None,
+            version: 0,
+            warmed_up: false,
+            on_violation: None,
+            #[cfg(feature = "std")]
+            hooks: None,
+            #[cfg(feature = "std")]
+            diff: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+            #[cfg(feature = "stats")]
+            publish_times: publish_rate::PublishTimes::new(),
+            #[cfg(feature = "stats")]
+            rejected: 0,
+            #[cfg(feature = "tracing")]
+            created_at_publish: 1,
         }
     }
 
     fn next_unused_buffer(&mut self) -> Buf<T> {
-        if let Some(buf) = self.unused_bufs_rx.try_recv().ok() {
-            debug_assert!(Arc::strong_count(&buf) == 1);
-            debug_assert!(Arc::weak_count(&buf) == 0);
-            return buf;
+        match self.try_next_unused_buffer() {
+            Ok(buf) => buf,
+            Err(e) => self.fail(e),
+        }
+    }
+
+    fn try_next_unused_buffer(&mut self) -> Result<Buf<T>, PoolExhausted> {
+        while let Some(buf) = self.spare.take().or_else(|| self.unused_bufs_rx.try_recv()) {
+            let Some(mut buf) = self.check_unused(buf) else {
+                continue;
+            };
+            // Scrubbed buffers no longer contain the state they had.
+            if let (Some(_), Some(regions)) = (self.read_update.shared.scrub, &mut self.regions) {
+                regions.forget(&buf);
+            }
+            self.on_recycle(&mut buf);
+            return Ok(buf);
+        }
+        if let Recycler::Pool(pool) = &self.unused_bufs_tx {
+            let taken = pool.take();
+            if let Some(mut buf) = taken.and_then(|buf| self.check_unused(buf)) {
+                // Other pairs may have written into it in the meantime.
+                if let Some(regions) = &mut self.regions {
+                    regions.forget(&buf);
+                }
+                self.on_recycle(&mut buf);
+                return Ok(buf);
+            }
+        }
+        if self.read_update.shared.source_hooks {
+            let taken = self.read_update.shared.take_released();
+            if let Some(mut buf) = taken.and_then(|buf| self.check_unused(buf)) {
+                self.on_recycle(&mut buf);
+                return Ok(buf);
+            }
         }
-        let new_state = (self.make_buf)(&self.prev_buf);
-        Arc::new(new_state)
+        // Buffers taken from a `BufferPool` were never counted.
+        let freed = self
+            .read_update
+            .shared
+            .freed
+            .swap(0, core::sync::atomic::Ordering::Relaxed);
+        self.created = self.created.saturating_sub(freed);
+        if self.created >= self.max_buffers {
+            return Err(self.exhausted());
+        }
+        #[cfg(feature = "trace-export")]
+        if let Some(trace) = &self.read_update.shared.trace {
+            trace.begin(trace::Event::MakeBuf);
+        }
+        let made = {
+            #[cfg(feature = "tracing")]
+            let _span =
+                tracing::debug_span!("make_buf", label = self.label().unwrap_or("")).entered();
+            self.make_buf.make(&self.prev_buf)
+        };
+        #[cfg(feature = "trace-export")]
+        if let Some(trace) = &self.read_update.shared.trace {
+            trace.end(trace::Event::MakeBuf);
+        }
+        let Some(made) = made else {
+            return Err(self.exhausted());
+        };
+        let new_state = Buf::new(made);
+        self.created += 1;
+        self.allocated(Allocation::NewBuffer);
+        #[cfg(feature = "std")]
+        self.hook_created(&new_state);
+        #[cfg(feature = "metrics")]
+        self.metrics_allocated(1);
+        #[cfg(feature = "tracing")]
+        {
+            // Each reader holds one state, and the writer the published
+            // one, the one it writes into and possibly a spare.
+            let expected = 3 + self
+                .read_update
+                .shared
+                .readers
+                .load(core::sync::atomic::Ordering::Relaxed);
+            if self.created > expected {
+                tracing::warn!(
+                    label = self.label().unwrap_or(""),
+                    buffers = self.created,
+                    expected,
+                    "buffer pool grew beyond its steady state"
+                );
+            }
+        }
+        if let Some(regions) = &mut self.regions {
+            regions.forget(&new_state);
+        }
+        Ok(new_state)
+    }
+
+    /// Check that nothing else references a buffer about to be reused.
+    ///
+    /// That holds for every buffer that comes back to the writer, but
+    /// should it ever be violated, skipping the buffer and creating a new
+    /// one beats writing into a state someone else can see.
+    fn check_unused(&mut self, buf: Buf<T>) -> Option<Buf<T>> {
+        debug_assert!(has_refs(&buf, 1), "a returned buffer is still in use");
+        if has_refs(&buf, 1) {
+            return Some(buf);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            label = self.label().unwrap_or(""),
+            "skipped a returned buffer that is still in use"
+        );
+        self.skipped += 1;
+        self.created -= 1;
+        None
+    }
+
+    /// Give up on getting a buffer to write into,
+    /// so the write does not get published.
+    fn exhausted(&self) -> PoolExhausted {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(
+            label = self.label().unwrap_or(""),
+            buffers = self.created,
+            "no buffer to write into, dropping the write"
+        );
+        PoolExhausted
+    }
+
+    /// Get the number of returned buffers that turned out to still be
+    /// in use, and were dropped instead of being reused.
+    ///
+    /// This is always 0, unless there is a bug somewhere. Builds with
+    /// debug assertions panic instead of skipping them.
+    pub fn skipped_buffers(&self) -> usize {
+        self.skipped
+    }
+
+    /// Run `f` on the state published last, or the initial state
+    /// before the first write, and return what it returns.
+    ///
+    /// This is the preferred way for the producer to branch on the
+    /// current state, as no borrow of the writer outlives the call, so
+    /// it can write right after. Within `write_new`, the closure gets
+    /// the same state as its first argument.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, _reader) = simple_triple_buffer::new_clone(vec![1, 2]);
+    /// assert_eq!(writer.with_current(|state| state.len()), 2);
+    ///
+    /// if writer.with_current(|state| state.len() < 3) {
+    ///     writer.write_update(|state| state.push(3));
+    /// }
+    /// assert_eq!(writer.with_current(|state| state.clone()), [1, 2, 3]);
+    /// ````
+    pub fn with_current<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.prev_buf)
     }
 
     /// Write the next state into the buffer.
@@ -104,28 +941,541 @@ impl<T> Writer<T> {
     /// It is possible for multiple independent reads to happen
     /// while a single write is in process.
     ///
+    /// The closure needs the previous state next to the one it writes,
+    /// so unlike `write_update`, this never writes into the published
+    /// buffer in place, even if no reader picked it up yet. Instead, once
+    /// the new state replaces such an unread one, the writer keeps it for
+    /// the next write, so back-to-back writes no reader catches up with
+    /// take turns between the same buffers, without creating new ones.
+    ///
+    /// Returns the version of the new state, see `PublishReceipt`.
+    ///
     /// # Example
     /// ```
     /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
     /// writer.write_new(|old, new| *new = *old + 1);
     /// assert_eq!(*reader.read_newest(), 1);
     /// ````
-    pub fn write_new(&mut self, mut write_op: impl FnMut(&T, &mut T)) {
-        let mut new_state = self.next_unused_buffer();
+    ///
+    /// # Panics
+    /// Panics if the pair was created without a way to create
+    /// new buffers, and all existing ones are in use.
+    /// See `try_write_new` for a non-panicking version.
+    pub fn write_new(&mut self, write_op: impl FnOnce(&T, &mut T)) -> PublishReceipt {
+        match self.try_write_new(write_op) {
+            Ok(receipt) => receipt,
+            Err(e) => self.fail(e),
+        }
+    }
+
+    /// Write the next state into the buffer,
+    /// unless no unused buffer is available.
+    ///
+    /// This only fails for pairs that can not create additional
+    /// buffers, like ones created with `new_with_buffers`.
+    /// In that case, the closure is not called.
+    pub fn try_write_new(
+        &mut self,
+        write_op: impl FnOnce(&T, &mut T),
+    ) -> Result<PublishReceipt, PoolExhausted> {
+        let created = self.created;
+        let mut new_state = self.try_next_unused_buffer()?;
 
         // This Arc will have no other clones at this point,
         // so we can get a mutable reference into it.
-        let mut_ref = Arc::get_mut(&mut new_state).unwrap();
-        write_op(&self.prev_buf, mut_ref);
+        let mut_ref = Buf::get_mut(&mut new_state).unwrap();
+        #[cfg(feature = "trace-export")]
+        if let Some(trace) = &self.read_update.shared.trace {
+            trace.begin(trace::Event::Write);
+        }
+        {
+            #[cfg(feature = "tracing")]
+            let _span =
+                tracing::debug_span!("write_new", label = self.label().unwrap_or("")).entered();
+            write_op(&self.prev_buf, mut_ref);
+        }
+        #[cfg(feature = "trace-export")]
+        if let Some(trace) = &self.read_update.shared.trace {
+            trace.end(trace::Event::Write);
+        }
+
+        self.publish(new_state);
+        Ok(self.receipt(created))
+    }
+
+    /// Publish `next` as the new state, and get back the contents
+    /// of the unused buffer it got moved into.
+    ///
+    /// This lets the writer build each state in an owned `T`, and reuse
+    /// what comes back for the one after, without going through a
+    /// closure. The returned value is some older state, or whatever
+    /// `TripleBufferBuilder::scrub_with` left of it, never the one
+    /// currently published or one a reader holds. A new buffer only
+    /// gets created if no unused one is around, like for `write_new`,
+    /// so once the pair has enough of them, this does not allocate.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(vec![0; 4]);
+    ///
+    /// let mut next = vec![1; 4];
+    /// for i in 2..=10 {
+    ///     next = writer.swap(next);
+    ///     // Some older state, to be overwritten.
+    ///     next.clear();
+    ///     next.resize(4, i);
+    /// }
+    /// assert_eq!(*reader.read_newest(), [9; 4]);
+    /// assert_eq!(next, [10; 4]);
+    /// ````
+    ///
+    /// # Panics
+    /// Panics if the pair was created without a way to create
+    /// new buffers, and all existing ones are in use.
+    pub fn swap(&mut self, next: T) -> T {
+        let mut new_state = self.next_unused_buffer();
+        let old = core::mem::replace(Buf::get_mut(&mut new_state).unwrap(), next);
+        self.publish(new_state);
+        old
+    }
+
+    /// Publish every state of `states` in order, like `swap` does,
+    /// returning how many there were.
+    ///
+    /// Readers only see the states they pick up in time, as usual, and
+    /// the last one once this returns. Buffers get reused between the
+    /// states, so a long iterator does not allocate for each of them.
+    /// `Writer` implements `Extend` the same way.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// assert_eq!(writer.feed(1..=100), 100);
+    /// assert_eq!(*reader.read_newest(), 100);
+    ///
+    /// assert_eq!(writer.feed(None), 0);
+    /// assert!(!reader.has_update());
+    /// ````
+    ///
+    /// # Panics
+    /// Panics if the pair was created without a way to create
+    /// new buffers, and all existing ones are in use.
+    pub fn feed(&mut self, states: impl IntoIterator<Item = T>) -> usize {
+        let mut published = 0;
+        for state in states {
+            self.swap(state);
+            published += 1;
+        }
+        published
+    }
+
+    /// Write the next state into the buffer with a closure that can fail,
+    /// in which case nothing gets published.
+    ///
+    /// # Example
+    /// ```
+    /// use std::num::ParseIntError;
+    /// use simple_triple_buffer::WriteError;
+    ///
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// let result = writer.try_write_with(|_, new| {
+    ///     *new = "x".parse::<i32>()?;
+    ///     Ok::<_, ParseIntError>(())
+    /// });
+    /// assert!(matches!(result, Err(WriteError::User(_))));
+    /// assert!(!reader.has_update());
+    /// ````
+    pub fn try_write_with<E>(
+        &mut self,
+        write_op: impl FnOnce(&T, &mut T) -> Result<(), E>,
+    ) -> Result<(), WriteError<E>> {
+        let mut new_state = self.try_next_unused_buffer()?;
+        let result = {
+            #[cfg(feature = "tracing")]
+            let _span = tracing::debug_span!("try_write_with", label = self.label().unwrap_or(""))
+                .entered();
+            write_op(&self.prev_buf, Buf::get_mut(&mut new_state).unwrap())
+        };
+        match result {
+            Ok(()) => {
+                self.publish(new_state);
+                Ok(())
+            }
+            Err(e) => {
+                self.discard(new_state);
+                Err(WriteError::User(e))
+            }
+        }
+    }
+
+    /// Update the previous state in place and publish it again, if it
+    /// is still pending and no other reference to it exists, so no
+    /// reader can ever see the change happen.
+    ///
+    /// Hands `update_op` back otherwise.
+    fn try_update_pending<F: FnOnce(&mut T)>(&mut self, update_op: F) -> Result<(), F> {
+        // The change mask needs the previous state to compare against.
+        #[cfg(feature = "std")]
+        if self.diff.is_some() {
+            return Err(update_op);
+        }
+        let slot = &self.read_update.shared.pending;
+        if !slot.reclaim(&self.prev_buf) {
+            return Err(update_op);
+        }
+        // SAFETY: The reference of the slot is ours now.
+        let buf = unsafe { Buf::from_raw(Buf::as_ptr(&self.prev_buf)) };
+        let mut reclaimed = Reclaimed {
+            slot,
+            buf: Some(buf),
+        };
+        {
+            #[cfg(feature = "tracing")]
+            let _span =
+                tracing::debug_span!("write_update", label = self.label().unwrap_or("")).entered();
+            update_op(Buf::get_mut(reclaimed.buf.as_mut().unwrap()).unwrap());
+        }
+        let new_state = reclaimed.buf.take().unwrap();
+        drop(reclaimed);
+        #[cfg(feature = "stats")]
+        self.read_update.shared.counted_drop();
+        self.publish(new_state);
+        Ok(())
+    }
+
+    /// Publish a new state, returning whether it replaced
+    /// one the reader has not picked up yet.
+    fn publish(&mut self, new_state: Buf<T>) -> bool {
+        if let Some(regions) = &mut self.regions {
+            regions.record(&new_state, None);
+        }
+        self.publish_untracked(new_state)
+    }
+
+    fn publish_untracked(&mut self, new_state: Buf<T>) -> bool {
+        if self.history_len > 0 {
+            self.record_history(&new_state);
+        }
+        #[cfg(feature = "std")]
+        self.record_changes(&new_state);
+        // SAFETY: The slot takes over `new_state` below.
+        self.prev_buf = unsafe { alias(&new_state) };
+        self.version += 1;
+        // Without the `stats` feature, only timestamped pairs need the time.
+        #[cfg(feature = "std")]
+        let now = (cfg!(feature = "stats") || self.timestamps).then(Instant::now);
+        #[cfg(feature = "stats")]
+        if let Some(now) = now {
+            self.publish_times.record(now);
+        }
+        let publication = Publication {
+            buf: new_state,
+            #[cfg(feature = "std")]
+            time: now.filter(|_| self.timestamps),
+            #[cfg(feature = "std")]
+            version: self.version,
+        };
+        let (replaced, unread) = self.read_update.shared.pending.replace(publication);
+        #[cfg(feature = "registry")]
+        self.read_update.shared.registration.published(self.created);
+        #[cfg(feature = "trace-export")]
+        if let Some(trace) = &self.read_update.shared.trace {
+            trace.instant(trace::Event::Publish);
+        }
+        #[cfg(feature = "stats")]
+        {
+            let shared = &self.read_update.shared;
+            if let Some(drops) = &shared.drop_window {
+                drops.published();
+            }
+            if unread {
+                shared.counted_drop();
+            }
+        }
+        #[cfg(feature = "std")]
+        self.detach_laggards();
+        #[cfg(feature = "std")]
+        self.hook_published();
+        #[cfg(feature = "metrics")]
+        self.metrics_published();
+        #[cfg(all(feature = "readiness", unix))]
+        self.raise_readiness();
+        #[cfg(feature = "std")]
+        self.wake_readers();
+        #[cfg(feature = "async")]
+        self.wake_streams();
+        #[cfg(feature = "tracing")]
+        {
+            tracing::debug!(
+                label = self.label().unwrap_or(""),
+                version = self.version,
+                new_buffer = self.created > self.created_at_publish,
+                replaced_unread = unread,
+                "published state"
+            );
+            self.created_at_publish = self.created;
+        }
+        #[cfg(feature = "record")]
+        if let Some(recorder) = &mut self.recorder {
+            recorder(&self.prev_buf);
+        }
+        #[cfg(feature = "tokio")]
+        self.send_to_tee();
+        match replaced {
+            Some(mut unused) if unread && self.spare.is_none() => {
+                // Unless the history holds it, nothing else references it.
+                if let Some(state) = Buf::get_mut(&mut unused) {
+                    if let Some(scrub) = self.read_update.shared.scrub {
+                        scrub(state);
+                    }
+                    self.spare = Some(unused);
+                }
+            }
+            Some(unused) => self.recycle(unused),
+            None => {}
+        }
+        unread
+    }
+
+    fn recycle(&self, buf: Buf<T>) {
+        recycle(&self.unused_bufs_tx, self.read_update.shared.scrub, buf);
+    }
+
+    /// Return a buffer whose contents got modified without being published.
+    fn discard(&mut self, buf: Buf<T>) {
+        if let Some(regions) = &mut self.regions {
+            regions.forget(&buf);
+        }
+        self.recycle(buf);
+    }
+
+    /// Create a reader starting at the last published state.
+    ///
+    /// The caller is responsible for counting it in `readers`.
+    fn new_reader(&self) -> Reader<T> {
+        Reader {
+            prev_buf: Buf::clone(&self.prev_buf),
+            unused_bufs_tx: self.unused_bufs_tx.clone(),
+            read_update: ReadUpdate {
+                shared: self.read_update.shared.clone(),
+            },
+            rewound: None,
+            rejected: None,
+            #[cfg(feature = "std")]
+            prev_time: None,
+            #[cfg(feature = "std")]
+            prev_version: self.version,
+            #[cfg(feature = "std")]
+            changes: ChangeMask::NONE,
+            #[cfg(feature = "std")]
+            entry: self
+                .read_update
+                .shared
+                .reader_list
+                .register(self.version, false),
+        }
+    }
+}
+
+impl<T> Writer<T> {
+    /// Get the label attached with `TripleBufferBuilder::label`, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.read_update.shared.label.as_deref()
+    }
 
-        self.prev_buf = new_state.clone();
-        if let Some(unused_buf) = self.read_update.replace(new_state) {
-            self.unused_bufs_tx.send(unused_buf).unwrap();
+    /// Panic with `e`, prefixed with the label of the pair, if any.
+    fn fail(&self, e: impl fmt::Display) -> ! {
+        match self.label() {
+            Some(label) => panic!("{}: {}", label, e),
+            None => panic!("{}", e),
         }
     }
 }
 
+impl<T> Extend<T> for Writer<T> {
+    /// Publish every state in order, see `Writer::feed`.
+    fn extend<I: IntoIterator<Item = T>>(&mut self, states: I) {
+        self.feed(states);
+    }
+}
+
+impl<T> fmt::Debug for Writer<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Writer")
+            .field("label", &self.label())
+            .field("closed", &self.is_closed())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T> fmt::Debug for Reader<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reader")
+            .field("label", &self.label())
+            .field("disconnected", &self.is_disconnected())
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: Clone> Writer<T> {
+    /// Write the next state by updating a copy of the previous one.
+    ///
+    /// The closure receives a mutable reference to a buffer that
+    /// has been synced with the previous state via `Clone::clone_from`,
+    /// or the refresh function given to `new_with_refresh`,
+    /// so it only needs to apply the parts that actually change.
+    ///
+    /// If the `Reader` has not picked up the previous state yet,
+    /// nothing else can be looking at it, so it gets updated in place
+    /// without any syncing. This keeps a fast writer with a slow reader
+    /// at 2 copies of `T`, where `write_new` needs 3.
+    /// If `update_op` panics in that case, the state stays published
+    /// as far as `update_op` got with it, as restoring it would take
+    /// the copy this avoids. Readers and later writes pick it up from
+    /// there, like a state behind a lock that ignores poisoning.
+    ///
+    /// Returns the version of the new state, see `PublishReceipt`.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(vec![1, 2]);
+    /// writer.write_update(|state| state.push(3));
+    /// assert_eq!(*reader.read_newest(), [1, 2, 3]);
+    /// ````
+    pub fn write_update(&mut self, update_op: impl FnOnce(&mut T)) -> PublishReceipt {
+        let created = self.created;
+        let update_op = match self.try_update_pending(update_op) {
+            Ok(()) => return self.receipt(created),
+            Err(update_op) => update_op,
+        };
+        let mut new_state = self.next_unused_buffer();
+        let new = Buf::get_mut(&mut new_state).unwrap();
+        match &mut self.refresh {
+            Some(refresh) => refresh(&self.prev_buf, new),
+            None => new.clone_from(&self.prev_buf),
+        }
+        {
+            #[cfg(feature = "tracing")]
+            let _span =
+                tracing::debug_span!("write_update", label = self.label().unwrap_or("")).entered();
+            update_op(new);
+        }
+        self.publish(new_state);
+        self.receipt(created)
+    }
+
+    /// Get a writer that only has access to a single part of the state.
+    ///
+    /// The `lens` closure projects the state onto the part
+    /// the returned `FieldWriter` is responsible for.
+    /// Every write through it publishes a complete `T`,
+    /// with all other parts carried over from the previous state.
+    ///
+    /// # Example
+    /// ```
+    /// #[derive(Clone)]
+    /// struct State {
+    ///     physics: u32,
+    ///     audio: u32,
+    /// }
+    ///
+    /// let (mut writer, mut reader) =
+    ///     simple_triple_buffer::new_clone(State { physics: 0, audio: 0 });
+    ///
+    /// writer.field(|s: &mut State| &mut s.physics).write(|p| *p = 1);
+    /// writer.field(|s: &mut State| &mut s.audio).write(|a| *a = 2);
+    ///
+    /// let state = reader.read_newest();
+    /// assert_eq!((state.physics, state.audio), (1, 2));
+    /// ````
+    pub fn field<U, L>(&mut self, lens: L) -> FieldWriter<'_, T, L>
+    where
+        U: ?Sized,
+        L: Fn(&mut T) -> &mut U + Clone,
+    {
+        FieldWriter::new(self, lens)
+    }
+}
+
 impl<T> Reader<T> {
+    /// Get the label attached with `TripleBufferBuilder::label`, if any.
+    pub fn label(&self) -> Option<&str> {
+        self.read_update.shared.label.as_deref()
+    }
+
+    /// Get the time at which the state last returned
+    /// by `read_newest` was published.
+    ///
+    /// This is `None` for the initial state, and for pairs
+    /// created without `TripleBufferBuilder::timestamps`.
+    ///
+    /// Only available with the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn published_at(&self) -> Option<Instant> {
+        self.prev_time
+    }
+
+    /// Check whether the `Writer` has published a state
+    /// that `read_newest` has not returned yet.
+    pub fn has_update(&self) -> bool {
+        #[cfg(feature = "std")]
+        if self.entry.is_detached() {
+            return false;
+        }
+        !self.pending_rejected() && self.read_update.shared.pending.has_newer(&self.prev_buf)
+    }
+
+    /// Check whether the pending state is the one `read_if` turned down.
+    fn pending_rejected(&self) -> bool {
+        let pending = &self.read_update.shared.pending;
+        self.rejected
+            .as_ref()
+            .is_some_and(|rejected| !pending.has_newer(rejected))
+    }
+
+    /// Run `f` on the state pending in the buffer, if it is one that
+    /// `read_newest` has not returned yet, without picking it up.
+    ///
+    /// This lets a reader look at a new state before committing to it,
+    /// like to decide whether it is worth cutting the current frame
+    /// short. The state stays pending for `read_newest`, and does not
+    /// count as picked up, so `has_update` still reports it afterwards.
+    ///
+    /// The peeked state may be superseded at any time. The `Writer` is
+    /// not blocked while `f` runs, and can publish a newer state before
+    /// or right after it returns, so `read_newest` may well return a
+    /// different state than the one `f` got. The buffer of the peeked
+    /// state can not be reused by the writer until `f` returns, though,
+    /// so `f` should not take long.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// assert_eq!(reader.peek_pending(|state| state.copied()), None);
+    ///
+    /// writer.write_new(|_, new| *new = 1);
+    /// assert_eq!(reader.peek_pending(|state| state.copied()), Some(1));
+    /// assert!(reader.has_update());
+    /// assert_eq!(*reader.read_newest(), 1);
+    /// ````
+    pub fn peek_pending<R>(&self, f: impl FnOnce(Option<&T>) -> R) -> R {
+        #[cfg(feature = "std")]
+        if self.entry.is_detached() {
+            return f(None);
+        }
+        if self.pending_rejected() {
+            return f(None);
+        }
+        match self.read_update.shared.pending.peek(&self.prev_buf) {
+            Some(buf) => {
+                let result = f(Some(&buf));
+                self.recycle(buf);
+                result
+            }
+            None => f(None),
+        }
+    }
+
     /// Get a view to the newest state currently in the buffer.
     ///
     /// The `Writer` is not blocked while the returned borrow is held,
@@ -135,6 +1485,10 @@ impl<T> Reader<T> {
     /// It is possible for multiple write updates to happen
     /// while a single read is in process.
     ///
+    /// If nothing new has been published, this is a single atomic
+    /// load, without taking any lock, so readers can poll it
+    /// much more often than the writer publishes.
+    ///
     /// # Example
     /// ```
     /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
@@ -149,13 +1503,115 @@ impl<T> Reader<T> {
     /// assert_eq!(*guard, 1);
     /// ````
     pub fn read_newest(&mut self) -> &T {
-        match self.read_update.take() {
-            Some(new_buf) => {
-                let now_unused_buf = std::mem::replace(&mut self.prev_buf, new_buf);
-                self.unused_bufs_tx.send(now_unused_buf).unwrap();
-                &self.prev_buf
+        self.read_if(|_, _| true)
+    }
+
+    /// Pick up the newest state like `read_newest`, but only if `accept`
+    /// approves of it, given the current state and the new one.
+    ///
+    /// This keeps a reader from ever going backwards when the writer
+    /// republishes states from a source that can deliver them out of
+    /// order, or from picking up states it considers invalid. A rejected
+    /// state counts as consumed: `has_update` no longer reports it, and
+    /// neither this nor `read_newest` returns it, until the writer
+    /// publishes the next state, which `accept` gets to judge again.
+    /// The reader keeps its current state in the meantime.
+    ///
+    /// The rejected state is held on to until the writer replaces it,
+    /// so while it is pending, the reader holds one more buffer than
+    /// usual.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// let newer = |current: &i32, new: &i32| new > current;
+    ///
+    /// writer.write_new(|_, new| *new = 5);
+    /// assert_eq!(*reader.read_if(newer), 5);
+    ///
+    /// // Arrived late, so the reader keeps its state.
+    /// writer.write_new(|_, new| *new = 3);
+    /// assert_eq!(*reader.read_if(newer), 5);
+    /// assert!(!reader.has_update());
+    /// assert_eq!(*reader.read_newest(), 5);
+    ///
+    /// writer.write_new(|_, new| *new = 7);
+    /// assert_eq!(*reader.read_if(newer), 7);
+    /// ````
+    pub fn read_if(&mut self, accept: impl FnOnce(&T, &T) -> bool) -> &T {
+        let shared = &self.read_update.shared;
+        #[cfg(all(feature = "readiness", unix))]
+        if let Some(readiness) = shared.readiness.get() {
+            readiness.clear();
+        }
+        #[cfg(feature = "std")]
+        {
+            self.changes = ChangeMask::NONE;
+        }
+        if self.pending_rejected() {
+            return &self.prev_buf;
+        }
+        let Some(publication) = shared
+            .pending
+            .newer_than(&self.prev_buf, |stale| self.recycle(stale))
+        else {
+            return &self.prev_buf;
+        };
+        // The writer replaced the state rejected last time.
+        if let Some(rejected) = self.rejected.take() {
+            self.recycle(rejected);
+        }
+        #[cfg(feature = "std")]
+        {
+            if self.entry.is_detached() {
+                self.recycle(publication.buf);
+                return &self.prev_buf;
             }
-            None => &self.prev_buf,
+            // A reader turning states down is still keeping up.
+            self.entry.picked_up(publication.version);
+        }
+        if !accept(&self.prev_buf, &publication.buf) {
+            self.rejected = Some(publication.buf);
+            return &self.prev_buf;
+        }
+        #[cfg(feature = "std")]
+        {
+            self.changes = self.changes_since(self.prev_version);
+            self.prev_time = publication.time;
+            self.prev_version = publication.version;
+        }
+        #[cfg(feature = "stats")]
+        shared.counters.picked_up();
+        #[cfg(feature = "latency")]
+        if let (Some(latency), Some(time)) = (&shared.latency, publication.time) {
+            latency.record(time.elapsed());
+        }
+        #[cfg(feature = "trace-export")]
+        if let Some(trace) = &shared.trace {
+            trace.instant(trace::Event::Read);
+        }
+        #[cfg(feature = "tracing")]
+        tracing::debug!(
+            label = self.label().unwrap_or(""),
+            lag = ?publication.time.map(|time| time.elapsed()),
+            "picked up state"
+        );
+        let new_buf = publication.buf;
+        let now_unused_buf = core::mem::replace(&mut self.prev_buf, new_buf);
+        self.recycle(now_unused_buf);
+        #[cfg(feature = "std")]
+        self.hook_observed(publication.time);
+        #[cfg(feature = "metrics")]
+        self.metrics_observed();
+        &self.prev_buf
+    }
+
+    fn recycle(&self, buf: Buf<T>) {
+        let shared = &self.read_update.shared;
+        if shared.source_hooks {
+            shared.release(self, buf);
+        } else {
+            recycle(&self.unused_bufs_tx, shared.scrub, buf);
         }
     }
 }
@@ -163,6 +1619,7 @@ impl<T> Reader<T> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::Mutex;
 
     fn measure() -> [Arc<Mutex<usize>>; 2] {
         let p = Arc::new(Mutex::new(0));
@@ -177,6 +1634,93 @@ mod tests {
         *ptr.lock().unwrap()
     }
 
+    #[test]
+    fn test_swap_reuses_buffers() {
+        let (mut w, mut r) = new_clone(0u32);
+        let mut next = 1;
+        for i in 1..=100 {
+            let old = w.swap(next);
+            assert_eq!(*r.read_newest(), i);
+            // Neither the published state, nor the one `r` holds.
+            assert!(old < i, "{} {}", old, i);
+            next = i + 1;
+        }
+        assert!(w.created <= 3, "{}", w.created);
+    }
+
+    #[test]
+    fn test_peek_pending_keeps_buffers() {
+        let (mut w, r) = new_clone(0u64);
+        let peeker = std::thread::spawn(move || {
+            let mut last = 0;
+            while !r.is_disconnected() {
+                if let Some(state) = r.peek_pending(|state| state.copied()) {
+                    assert!(state >= last);
+                    last = state;
+                }
+            }
+            r
+        });
+        for i in 1..=10_000 {
+            w.write_new(|_, new| *new = i);
+        }
+        assert_eq!(w.skipped_buffers(), 0);
+        drop(w);
+        let mut r = peeker.join().unwrap();
+        // Peeking did not pick anything up.
+        assert!(r.has_update());
+        assert_eq!(*r.read_newest(), 10_000);
+    }
+
+    #[test]
+    fn test_with_current_follows_publishes() {
+        let (mut w, _r) = new_clone(0u32);
+        assert_eq!(w.with_current(|state| *state), 0);
+        for i in 1..=10 {
+            let next = w.with_current(|state| state + i);
+            if i % 2 == 0 {
+                w.write_new(|_, new| *new = next);
+            } else {
+                w.write_update(|state| *state = next);
+            }
+            assert_eq!(w.with_current(|state| *state), next);
+        }
+        assert_eq!(w.with_current(|state| *state), 55);
+    }
+
+    #[test]
+    fn test_read_if_never_goes_back() {
+        let (mut w, mut r) = TripleBufferBuilder::new(0u32)
+            .copy_buffers()
+            .max_buffers(4)
+            .build()
+            .unwrap();
+        let newer = |current: &u32, new: &u32| new > current;
+        for i in 1..=100 {
+            // Every other state arrives late.
+            let state = if i % 2 == 0 { i / 2 } else { i };
+            w.try_write_new(|_, new| *new = state).unwrap();
+            assert_eq!(*r.read_if(newer), i - 1 + i % 2);
+            assert!(!r.has_update());
+            assert_eq!(r.peek_pending(|state| state.copied()), None);
+        }
+        w.try_write_new(|_, new| *new = 1000).unwrap();
+        assert!(r.has_update());
+        assert_eq!(*r.read_newest(), 1000);
+    }
+
+    #[test]
+    fn test_feed_reuses_buffers() {
+        let (mut w, mut r) = new_clone(vec![0u32; 4]);
+        w.extend(core::iter::empty());
+        assert_eq!(w.version(), 0);
+        w.extend((1..=1000).map(|i| vec![i; 4]));
+        assert_eq!(w.version(), 1000);
+        // The initial state, the published one and one to write into.
+        assert_eq!(w.created, 3);
+        assert_eq!(*r.read_newest(), [1000; 4]);
+    }
+
     #[test]
     fn test_seq_1() {
         let [c, c2] = measure();
@@ -229,6 +1773,269 @@ mod tests {
         assert!(final_count(&c) <= 2);
     }
 
+    #[test]
+    fn test_foreign_arc_is_never_written() {
+        let init = Arc::new(vec![0]);
+        let (mut w, mut r) = new_from_arc(init.clone(), |v| v.clone());
+
+        for i in 1..20 {
+            w.write_new(|old, new| {
+                new.clone_from(old);
+                new[0] = i;
+            });
+            if i % 2 == 0 {
+                assert_eq!(*r.read_newest(), [i]);
+            }
+        }
+        assert_eq!(*init, [0]);
+        assert_eq!(Arc::strong_count(&init), 1);
+    }
+
+    #[test]
+    fn test_foreign_arc_with_weak_is_dropped() {
+        let init = Arc::new(0);
+        let weak = Arc::downgrade(&init);
+        let (mut w, mut r) = new_from_arc(init, |v| *v);
+
+        for i in 1..10 {
+            w.write_new(|_, new| *new = i);
+            assert_eq!(*r.read_newest(), i);
+        }
+        // Reusing it as a buffer would have kept it alive.
+        assert!(weak.upgrade().is_none());
+    }
+
+    #[test]
+    fn test_supplied_buffers_suffice() {
+        let (mut w, mut r) = new_with_buffers(vec![0], vec![Vec::new(), Vec::new()]);
+        for i in 1..100 {
+            w.try_write_new(|_, new| {
+                new.clear();
+                new.push(i);
+            })
+            .unwrap();
+            if i % 3 == 0 {
+                assert_eq!(*r.read_newest(), [i]);
+            }
+        }
+        assert_eq!(w.unused_bufs_rx.overflowed(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "no unused buffer available")]
+    fn test_exhausted_pool_panics() {
+        let (mut w, _r) = new_with_buffers(0, vec![]);
+        w.write_new(|_, new| *new = 1);
+    }
+
+    #[test]
+    fn test_pod_has_no_clone_fn() {
+        let (mut w, mut r) = new_pod([7u8; 4096]);
+        assert!(matches!(w.make_buf, MakeBuf::Copy(_)));
+        w.write_update(|v| v[0] = 1);
+        w.write_update(|v| v[1] = 2);
+        assert_eq!(r.read_newest()[..3], [1, 2, 7]);
+    }
+
+    #[test]
+    fn test_update_slow_reader_copies() {
+        let [c, c2] = measure();
+
+        let (mut w, mut r) = new_with(vec![0], move |v| {
+            count(&c2);
+            v.clone()
+        });
+        for i in 1..100 {
+            w.write_update(|v| v[0] = i);
+            if i % 10 == 0 {
+                assert_eq!(*r.read_newest(), [i]);
+            }
+        }
+        assert!(final_count(&c) <= 1);
+    }
+
+    #[test]
+    fn test_update_slow_writer_copies() {
+        let [c, c2] = measure();
+
+        let (mut w, mut r) = new_with(vec![0], move |v| {
+            count(&c2);
+            v.clone()
+        });
+        for i in 1..100 {
+            w.write_update(|v| v[0] = i);
+            for _ in 0..3 {
+                assert_eq!(*r.read_newest(), [i]);
+            }
+        }
+        assert!(final_count(&c) <= 1);
+    }
+
+    #[test]
+    fn test_unread_states_are_reused_directly() {
+        let (mut w, mut r) = new_clone(0);
+        for i in 1..=100 {
+            w.write_new(|_, new| *new = i);
+        }
+        assert_eq!(w.created, 3);
+        assert!(w.spare.is_some());
+        assert!(w.unused_bufs_rx.try_recv().is_none());
+        assert_eq!(*r.read_newest(), 100);
+    }
+
+    #[test]
+    fn test_unread_writes_do_not_clone() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        struct Counted(Arc<AtomicUsize>, u32);
+
+        impl Clone for Counted {
+            fn clone(&self) -> Self {
+                self.0.fetch_add(1, Ordering::Relaxed);
+                Counted(self.0.clone(), self.1)
+            }
+        }
+
+        let clones = Arc::new(AtomicUsize::new(0));
+        let (mut w, mut r) = new_clone(Counted(clones.clone(), 0));
+        w.write_new(|_, new| new.1 = 1);
+        w.write_new(|_, new| new.1 = 2);
+        let warmed_up = clones.load(Ordering::Relaxed);
+        for i in 3..=1000 {
+            let receipt = w.write_new(|_, new| new.1 = i);
+            assert!(!receipt.allocated);
+        }
+        assert_eq!(clones.load(Ordering::Relaxed), warmed_up);
+        assert_eq!(r.read_newest().1, 1000);
+    }
+
+    #[test]
+    fn test_readers_never_observe_mutation() {
+        let (mut w, r) = new_clone([0u64; 8]);
+        let readers: Vec<_> = (0..2)
+            .map(|_| {
+                let mut r = r.clone();
+                std::thread::spawn(move || {
+                    while !r.is_disconnected() {
+                        let state = r.read_newest();
+                        let seen = *state;
+                        assert!(seen.iter().all(|v| *v == seen[0]));
+                        for _ in 0..100 {
+                            std::hint::spin_loop();
+                            assert_eq!(*state, seen);
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(r);
+        for i in 1..=50_000 {
+            if i % 2 == 0 {
+                w.write_new(|_, new| *new = [i; 8]);
+            } else {
+                w.write_update(|state| *state = [i; 8]);
+            }
+        }
+        drop(w);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    #[test]
+    #[cfg_attr(
+        debug_assertions,
+        should_panic(expected = "a returned buffer is still in use")
+    )]
+    fn test_shared_returned_buffers_are_skipped() {
+        let (mut w, mut r) = new_clone(0);
+        let leaked = Buf::new(7);
+        match &w.unused_bufs_tx {
+            Recycler::Channel(tx) => assert!(tx.send(leaked.clone()).is_ok()),
+            Recycler::Pool(_) => unreachable!(),
+        }
+        w.write_new(|_, new| *new = 1);
+        assert_eq!(w.skipped_buffers(), 1);
+        assert_eq!(*leaked, 7);
+        assert_eq!(*r.read_newest(), 1);
+    }
+
+    #[test]
+    fn test_write_errors_convert() {
+        fn write_parsed(
+            w: &mut Writer<i32>,
+            s: &str,
+        ) -> Result<(), WriteError<std::num::ParseIntError>> {
+            w.try_write_new(|_, new| *new = 0)?;
+            w.try_write_with(|_, new| {
+                *new = s.parse()?;
+                Ok(())
+            })
+        }
+
+        let (mut w, mut r) = new_clone(0);
+        let failed = write_parsed(&mut w, "x").unwrap_err();
+        assert!(matches!(failed, WriteError::User(_)));
+        #[cfg(feature = "std")]
+        assert!(std::error::Error::source(&failed).is_some());
+        assert_eq!(*r.read_newest(), 0);
+        write_parsed(&mut w, "5").unwrap();
+        assert_eq!(*r.read_newest(), 5);
+
+        let (mut w, _r) = new_with_buffers(0, Vec::new());
+        assert_eq!(write_parsed(&mut w, "6"), Err(WriteError::PoolExhausted));
+    }
+
+    #[test]
+    fn test_reader_survives_panicking_writes() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let (mut w, mut r) = new_clone(vec![0]);
+        w.write_update(|v| v[0] = 1);
+        let new = catch_unwind(AssertUnwindSafe(|| w.write_new(|_, _| panic!("write_new"))));
+        assert!(new.is_err());
+        assert!(!r.is_disconnected());
+        // The unread state gets updated in place, so the panic leaves it
+        // published as far as the update got, for the writer and readers.
+        let update = catch_unwind(AssertUnwindSafe(|| {
+            w.write_update(|v| {
+                v.push(9);
+                panic!("write_update")
+            })
+        }));
+        assert!(update.is_err());
+        assert_eq!(w.version(), 1);
+        assert_eq!(w.with_current(|v| v.clone()), [1, 9]);
+        assert!(r.has_update());
+        assert_eq!(*r.read_newest(), [1, 9]);
+
+        w.write_update(|v| v[0] += 1);
+        assert_eq!(*r.read_newest(), [2, 9]);
+        w.write_new(|old, new| *new = vec![old[0] + 1]);
+        assert_eq!(*r.read_newest(), [3]);
+    }
+
+    #[test]
+    fn test_update_never_mutates_held_state() {
+        let (mut w, mut r) = new_clone(vec![0]);
+        {
+            let held = r.read_newest();
+            for i in 1..10 {
+                w.write_update(|v| v[0] = i);
+                assert_eq!(*held, [0]);
+            }
+        }
+        assert_eq!(*r.read_newest(), [9]);
+
+        // States pinned by the history are not updated in place either.
+        w.keep_history(2);
+        for i in 10..20 {
+            w.write_update(|v| v[0] = i);
+            assert_eq!(r.rewind(1), Some(&vec![i - 1]).filter(|_| i > 10));
+        }
+        assert_eq!(*r.newest(), [19]);
+    }
+
     #[test]
     fn test_long_overlapping_write() {
         let [c, c2] = measure();
@@ -250,4 +2057,286 @@ mod tests {
 
         assert!(final_count(&c) <= 2);
     }
+
+    #[test]
+    fn test_broadcast_readers_are_independent() {
+        let (mut w, mut r1) = new_clone(0);
+        let mut r2 = r1.clone();
+        w.write_new(|_, new| *new = 1);
+        assert!(r1.has_update() && r2.has_update());
+        assert_eq!(*r1.read_newest(), 1);
+        assert!(!r1.has_update() && r2.has_update());
+
+        w.write_new(|_, new| *new = 2);
+        assert_eq!(*r2.read_newest(), 2);
+        assert_eq!(*r1.read_newest(), 2);
+
+        let mut r3 = r2.clone();
+        assert!(!r3.has_update());
+        assert_eq!(*r3.read_newest(), 2);
+        drop((r1, r2));
+        assert!(!w.is_closed());
+        drop(r3);
+        assert!(w.is_closed());
+    }
+
+    #[test]
+    fn test_broadcast_steady_state() {
+        let [c, c2] = measure();
+        let (mut w, r) = new_with(vec![0u64; 8], move |v| {
+            count(&c2);
+            v.clone()
+        });
+
+        // Readers that pick up states at different rates,
+        // one of which lags far behind.
+        let mut readers: Vec<_> = (0..3).map(|_| r.clone()).collect();
+        readers.push(r);
+        for i in 1..1000u64 {
+            w.write_update(|v| v.iter_mut().for_each(|x| *x = i));
+            for (n, r) in readers.iter_mut().enumerate() {
+                if i % (1 + 7 * n as u64) == 0 {
+                    assert_eq!(r.read_newest()[0], i);
+                }
+            }
+        }
+        // At most 2 + n_readers copies, including the initial state.
+        assert!(final_count(&c) <= 1 + readers.len());
+        assert_eq!(w.unused_bufs_rx.overflowed(), 0);
+    }
+
+    #[test]
+    fn test_broadcast_threads() {
+        let (mut w, r) = new_clone([0u64; 8]);
+        let threads: Vec<_> = (0..3)
+            .map(|n| {
+                let mut r = r.clone();
+                std::thread::spawn(move || {
+                    let mut last = 0;
+                    while last < 10_000 {
+                        let state = r.read_newest();
+                        assert!(state.iter().all(|v| *v == state[0]));
+                        assert!(state[0] >= last);
+                        last = state[0];
+                        if n == 0 {
+                            std::thread::yield_now();
+                        }
+                    }
+                })
+            })
+            .collect();
+        drop(r);
+        for _ in 0..10_000 {
+            w.write_new(|old, new| *new = [old[0] + 1; 8]);
+        }
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert_eq!(w.unused_bufs_rx.overflowed(), 0);
+    }
+
+    #[test]
+    fn test_refresh_replaces_clone_from() {
+        #[derive(Debug, PartialEq)]
+        struct State {
+            dynamic: u32,
+            refreshed: u32,
+        }
+        impl Clone for State {
+            fn clone(&self) -> Self {
+                State { ..*self }
+            }
+            fn clone_from(&mut self, _: &Self) {
+                panic!("refresh should be used instead");
+            }
+        }
+
+        let init = State {
+            dynamic: 0,
+            refreshed: 0,
+        };
+        let (mut w, mut r) = new_with_refresh(init, State::clone, |prev, stale| {
+            stale.dynamic = prev.dynamic;
+            stale.refreshed += 1;
+        });
+        for i in 1..10 {
+            w.write_update(|s| s.dynamic += 1);
+            assert_eq!(r.read_newest().dynamic, i);
+        }
+        assert!(r.read_newest().refreshed > 0);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use loom::thread;
+    use std::sync::Arc;
+
+    use super::{new_clone, Reader, Writer};
+
+    /// A state that keeps `live` alive for as long as any buffer exists.
+    type State = (u32, Arc<()>);
+
+    fn pair() -> (Writer<State>, Reader<State>, Arc<()>) {
+        let live = Arc::new(());
+        let (w, r) = new_clone((0, live.clone()));
+        (w, r, live)
+    }
+
+    #[test]
+    fn test_concurrent_write_read() {
+        loom::model(|| {
+            let (mut w, mut r, live) = pair();
+            let writer = thread::spawn(move || {
+                w.write_new(|_, new| new.0 = 1);
+                w.write_update(|state| state.0 = 2);
+                w
+            });
+            let first = r.read_newest().0;
+            let second = r.read_newest().0;
+            assert!(first <= second);
+            let w = writer.join().unwrap();
+            assert_eq!(r.read_newest().0, 2);
+            drop((w, r));
+            assert_eq!(Arc::strong_count(&live), 1);
+        });
+    }
+
+    #[test]
+    fn test_writer_drop_during_read() {
+        loom::model(|| {
+            let (mut w, mut r, live) = pair();
+            let writer = thread::spawn(move || {
+                w.write_new(|_, new| new.0 = 1);
+            });
+            let seen = r.read_newest().0;
+            writer.join().unwrap();
+            assert!(r.is_disconnected());
+            assert!(seen <= 1);
+            assert_eq!(r.read_newest().0, 1);
+            drop(r);
+            assert_eq!(Arc::strong_count(&live), 1);
+        });
+    }
+
+    #[test]
+    fn test_reader_drop_during_write() {
+        loom::model(|| {
+            let (mut w, mut r, live) = pair();
+            let reader = thread::spawn(move || {
+                r.read_newest();
+            });
+            w.write_new(|_, new| new.0 = 1);
+            w.write_new(|_, new| new.0 = 2);
+            reader.join().unwrap();
+            assert!(w.is_closed());
+            drop(w);
+            assert_eq!(Arc::strong_count(&live), 1);
+        });
+    }
+
+    #[test]
+    fn test_recycled_buffers_are_unique() {
+        loom::model(|| {
+            let (mut w, mut r, _live) = pair();
+            let reader = thread::spawn(move || {
+                for _ in 0..2 {
+                    let state = r.read_newest();
+                    let seen = state.0;
+                    thread::yield_now();
+                    // The writer never reuses a buffer a reader holds.
+                    assert_eq!(state.0, seen);
+                }
+            });
+            for i in 1..=3 {
+                if i % 2 == 0 {
+                    w.write_new(|_, new| new.0 = i);
+                } else {
+                    w.write_update(|state| state.0 = i);
+                }
+            }
+            reader.join().unwrap();
+        });
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tracing_tests {
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Level, Metadata, Subscriber};
+
+    /// Collects the level and message of every event, and the names of spans.
+    #[derive(Clone, Default)]
+    struct Collect(Arc<Mutex<Vec<(Level, String)>>>);
+
+    struct Message<'a>(&'a mut String);
+
+    impl Visit for Message<'_> {
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            if field.name() == "message" {
+                *self.0 = format!("{:?}", value);
+            }
+        }
+    }
+
+    impl Subscriber for Collect {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &Attributes<'_>) -> Id {
+            let name = span.metadata().name();
+            self.0.lock().unwrap().push((Level::TRACE, name.into()));
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, event: &Event<'_>) {
+            let mut message = String::new();
+            event.record(&mut Message(&mut message));
+            self.0
+                .lock()
+                .unwrap()
+                .push((*event.metadata().level(), message));
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    #[test]
+    fn test_events() {
+        let collect = Collect::default();
+        tracing::subscriber::with_default(collect.clone(), || {
+            let (mut w, mut r) = crate::TripleBufferBuilder::new(0)
+                .copy_buffers()
+                .max_buffers(2)
+                .build()
+                .unwrap();
+            // Holds on to the initial state.
+            let _held = r.clone();
+            w.write_new(|_, new| *new = 1);
+            r.read_newest();
+            r.read_newest();
+            assert!(w.try_write_new(|_, new| *new = 2).is_err());
+        });
+        let events = collect.0.lock().unwrap().clone();
+        let expected = [
+            (Level::TRACE, "make_buf"),
+            (Level::TRACE, "write_new"),
+            (Level::DEBUG, "published state"),
+            (Level::DEBUG, "picked up state"),
+            (Level::WARN, "no buffer to write into, dropping the write"),
+        ];
+        assert_eq!(
+            events,
+            expected.map(|(level, message)| (level, message.to_string()))
+        );
+    }
 }
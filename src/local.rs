@@ -0,0 +1,145 @@
+//! A buffer pair for use within a single thread.
+//!
+//! This works like the regular pairs, for example to pass states
+//! between the phases of a game loop, but is built on `Rc` and `Cell`
+//! instead of atomics and locks. In exchange, neither half is `Send`.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::local;
+//!
+//! let (mut writer, mut reader) = local::new_clone(vec![0]);
+//! writer.write_new(|old, new| {
+//!     new.clone_from(old);
+//!     new.push(1);
+//! });
+//! assert_eq!(*reader.read_newest(), [0, 1]);
+//! ````
+//!
+//! Neither half can be sent to another thread:
+//! ```compile_fail
+//! fn assert_send<T: Send>(_: T) {}
+//!
+//! let (writer, _reader) = simple_triple_buffer::local::new_clone(0);
+//! assert_send(writer);
+//! ````
+
+use alloc::boxed::Box;
+use alloc::rc::Rc;
+use alloc::vec::Vec;
+use core::cell::{Cell, RefCell};
+
+type LocalBuf<T> = Rc<T>;
+
+struct Shared<T> {
+    pending: Cell<Option<LocalBuf<T>>>,
+    unused_bufs: RefCell<Vec<LocalBuf<T>>>,
+}
+
+impl<T> Shared<T> {
+    /// Return a buffer to the writer once nothing else references it,
+    /// see the function of the same name for the threaded pairs.
+    fn recycle(&self, mut buf: LocalBuf<T>) {
+        if Rc::get_mut(&mut buf).is_some() {
+            self.unused_bufs.borrow_mut().push(buf);
+        }
+    }
+}
+
+/// Create a new single threaded buffer pair that creates
+/// additional buffer instances with a custom clone function.
+///
+/// See `new_with`.
+pub fn new_with<T>(init: T, make_buf: impl FnMut(&T) -> T + 'static) -> (Writer<T>, Reader<T>) {
+    let prev_buf = Rc::new(init);
+    let shared = Rc::new(Shared {
+        pending: Cell::new(None),
+        unused_bufs: RefCell::new(Vec::new()),
+    });
+    let writer = Writer {
+        make_buf: Box::new(make_buf),
+        prev_buf: prev_buf.clone(),
+        shared: shared.clone(),
+    };
+    let reader = Reader { prev_buf, shared };
+    (writer, reader)
+}
+
+/// Create a new single threaded buffer pair that creates
+/// additional buffer instances by cloning a previous state.
+///
+/// See `new_clone`.
+pub fn new_clone<T: Clone>(init: T) -> (Writer<T>, Reader<T>) {
+    new_with(init, |v| v.clone())
+}
+
+/// Write side of a single threaded buffer pair.
+pub struct Writer<T> {
+    make_buf: Box<dyn FnMut(&T) -> T>,
+    prev_buf: LocalBuf<T>,
+    shared: Rc<Shared<T>>,
+}
+
+impl<T> Writer<T> {
+    /// Write a new state and publish it.
+    ///
+    /// See `Writer::write_new` of the threaded pairs.
+    pub fn write_new(&mut self, write_op: impl FnOnce(&T, &mut T)) {
+        let unused = self.shared.unused_bufs.borrow_mut().pop();
+        let mut new_buf = match unused {
+            Some(buf) => buf,
+            None => Rc::new((self.make_buf)(&self.prev_buf)),
+        };
+        write_op(&self.prev_buf, Rc::get_mut(&mut new_buf).unwrap());
+
+        self.prev_buf = new_buf.clone();
+        if let Some(unused) = self.shared.pending.replace(Some(new_buf)) {
+            self.shared.recycle(unused);
+        }
+    }
+}
+
+/// Read side of a single threaded buffer pair.
+pub struct Reader<T> {
+    prev_buf: LocalBuf<T>,
+    shared: Rc<Shared<T>>,
+}
+
+impl<T> Reader<T> {
+    /// Get a view to the newest state currently in the buffer.
+    ///
+    /// See `Reader::read_newest` of the threaded pairs.
+    pub fn read_newest(&mut self) -> &T {
+        if let Some(new_buf) = self.shared.pending.take() {
+            let now_unused_buf = core::mem::replace(&mut self.prev_buf, new_buf);
+            self.shared.recycle(now_unused_buf);
+        }
+        &self.prev_buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    use super::new_with;
+
+    #[test]
+    fn test_recycling() {
+        let count = Rc::new(Cell::new(0));
+        let c = count.clone();
+        let (mut w, mut r) = new_with(0, move |v| {
+            c.set(c.get() + 1);
+            *v
+        });
+        for i in 1..100 {
+            w.write_new(|old, new| *new = *old + 1);
+            if i % 2 == 0 {
+                assert_eq!(*r.read_newest(), i);
+            }
+        }
+        // Same steady state as the threaded pairs.
+        assert!(count.get() <= 3);
+    }
+}
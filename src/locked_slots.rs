@@ -0,0 +1,274 @@
+//! A buffer pair with a number of slots fixed at compile time,
+//! coordinated by a mutex.
+//!
+//! This is separate from `Writer` and `Reader`, and shares none of their
+//! lock-free machinery. Those pairs allocate additional buffers whenever
+//! readers hold on to states for longer. `new::<T, N>` instead allocates
+//! exactly `N` slots up front, and writes fail with `PoolExhausted` if
+//! the reader has pinned so many of them that none is left to write into.
+//! Keeping track of the pins takes a short lock on every write and read,
+//! so the halves can briefly block each other, unlike with `Writer` and
+//! `Reader`.
+//!
+//! With `N = 3` the writer can always make progress as long as the
+//! reader does not pin any `PinnedSlot`s, each of which costs one slot.
+//!
+//! `Writer` and `Reader` themselves are not generic over the number of
+//! buffers: their pool grows with the number of readers and the states
+//! they hold on to, which a fixed `N` could not preserve.
+//!
+//! Only available with the `locked-slots` feature.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::locked_slots;
+//!
+//! let (mut writer, mut reader) = locked_slots::new::<u32, 4>(0);
+//! writer.write_new(|old, new| *new = *old + 1);
+//! assert_eq!(*reader.read_newest(), 1);
+//!
+//! let pinned = reader.pin();
+//! writer.write_new(|old, new| *new = *old + 1);
+//! assert_eq!(*pinned, 1);
+//! assert_eq!(*reader.read_newest(), 2);
+//! ````
+
+use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::ops::Deref;
+
+use crate::sync::Mutex;
+use crate::PoolExhausted;
+
+/// Which half is using which slot.
+struct SlotTable<const N: usize> {
+    /// Number of references the reader holds to each slot.
+    pins: [usize; N],
+    /// The slot containing the newest published state.
+    latest: usize,
+    /// Set if the reader has not picked up `latest` yet.
+    pending: bool,
+}
+
+impl<const N: usize> SlotTable<N> {
+    /// Find a slot the writer may overwrite.
+    fn free_slot(&self) -> Option<usize> {
+        (0..N).find(|&i| i != self.latest && self.pins[i] == 0)
+    }
+}
+
+struct Shared<T, const N: usize> {
+    slots: [UnsafeCell<T>; N],
+    table: Mutex<SlotTable<N>>,
+}
+
+// SAFETY: Slots are only written by the writer while the table marks them
+// as unused by anyone else, otherwise they are only read.
+unsafe impl<T: Send + Sync, const N: usize> Sync for Shared<T, N> {}
+
+impl<T, const N: usize> Shared<T, N> {
+    fn unpin(&self, slot: usize) {
+        self.table.lock().pins[slot] -= 1;
+    }
+}
+
+/// Fails to compile for fewer than 2 slots, which would
+/// leave the writer without a slot to write into.
+struct AtLeastTwo<const N: usize>;
+
+impl<const N: usize> AtLeastTwo<N> {
+    const OK: () = assert!(N >= 2, "a locked slot pair needs at least 2 slots");
+}
+
+/// Create a new buffer pair with exactly `N` slots,
+/// all of which start out as a clone of `init`.
+///
+/// `N` has to be at least 2, which gets checked at compile time:
+/// ```compile_fail
+/// let (writer, reader) = simple_triple_buffer::locked_slots::new::<u32, 1>(0);
+/// ````
+pub fn new<T: Clone, const N: usize>(init: T) -> (LockedWriter<T, N>, LockedReader<T, N>) {
+    let () = AtLeastTwo::<N>::OK;
+    let mut pins = [0; N];
+    pins[0] = 1;
+    let shared = Arc::new(Shared {
+        slots: core::array::from_fn(|_| UnsafeCell::new(init.clone())),
+        table: Mutex::new(SlotTable {
+            pins,
+            latest: 0,
+            pending: false,
+        }),
+    });
+    let writer = LockedWriter {
+        shared: shared.clone(),
+    };
+    let reader = LockedReader { shared, front: 0 };
+    (writer, reader)
+}
+
+/// Write side of a pair with `N` slots.
+pub struct LockedWriter<T, const N: usize> {
+    shared: Arc<Shared<T, N>>,
+}
+
+impl<T, const N: usize> LockedWriter<T, N> {
+    /// Write a new state and publish it.
+    ///
+    /// The closure gets a reference to the previously published state
+    /// and to an older state it has to overwrite.
+    ///
+    /// # Panics
+    /// If the reader has pinned all slots the writer could write into.
+    pub fn write_new(&mut self, f: impl FnOnce(&T, &mut T)) {
+        if let Err(e) = self.try_write_new(f) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Write a new state and publish it, or fail without calling `f`
+    /// if the reader has pinned all slots the writer could write into.
+    pub fn try_write_new(&mut self, f: impl FnOnce(&T, &mut T)) -> Result<(), PoolExhausted> {
+        let shared = &*self.shared;
+        let (latest, slot) = {
+            let table = shared.table.lock();
+            (table.latest, table.free_slot().ok_or(PoolExhausted)?)
+        };
+        // SAFETY: Only the writer changes `latest` or adds pins to
+        // other slots, so `slot` stays unused by the reader until
+        // it is published, and `latest` is never written to.
+        let (old, new) = unsafe { (&*shared.slots[latest].get(), &mut *shared.slots[slot].get()) };
+        f(old, new);
+
+        let mut table = shared.table.lock();
+        table.latest = slot;
+        table.pending = true;
+        Ok(())
+    }
+
+    /// Get the number of slots the writer could currently write into.
+    pub fn free_slots(&self) -> usize {
+        let table = self.shared.table.lock();
+        (0..N)
+            .filter(|&i| i != table.latest && table.pins[i] == 0)
+            .count()
+    }
+}
+
+/// Read side of a pair with `N` slots.
+pub struct LockedReader<T, const N: usize> {
+    shared: Arc<Shared<T, N>>,
+    /// The slot returned by the last `read_newest`, pinned by the reader.
+    front: usize,
+}
+
+impl<T, const N: usize> LockedReader<T, N> {
+    /// Get a view to the newest state currently in the buffer.
+    pub fn read_newest(&mut self) -> &T {
+        {
+            let mut table = self.shared.table.lock();
+            if table.pending {
+                table.pending = false;
+                table.pins[self.front] -= 1;
+                self.front = table.latest;
+                table.pins[self.front] += 1;
+            }
+        }
+        // SAFETY: The writer never writes to a pinned slot.
+        unsafe { &*self.shared.slots[self.front].get() }
+    }
+
+    /// Pin the state last returned by `read_newest`, so it stays
+    /// readable independently of the reader.
+    ///
+    /// The writer can not reuse its slot until the pin is dropped.
+    pub fn pin(&self) -> PinnedSlot<T, N> {
+        self.shared.table.lock().pins[self.front] += 1;
+        PinnedSlot {
+            shared: self.shared.clone(),
+            slot: self.front,
+        }
+    }
+}
+
+impl<T, const N: usize> Drop for LockedReader<T, N> {
+    fn drop(&mut self) {
+        self.shared.unpin(self.front);
+    }
+}
+
+/// A pinned state of a pair with `N` slots, see `LockedReader::pin`.
+pub struct PinnedSlot<T, const N: usize> {
+    shared: Arc<Shared<T, N>>,
+    slot: usize,
+}
+
+impl<T, const N: usize> Deref for PinnedSlot<T, N> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // SAFETY: The writer never writes to a pinned slot.
+        unsafe { &*self.shared.slots[self.slot].get() }
+    }
+}
+
+impl<T, const N: usize> Drop for PinnedSlot<T, N> {
+    fn drop(&mut self) {
+        self.shared.unpin(self.slot);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::new;
+
+    #[test]
+    fn test_triple() {
+        let (mut w, mut r) = new::<u32, 3>(0);
+        for i in 1..10 {
+            w.write_new(|old, new| *new = *old + 1);
+            if i % 3 == 0 {
+                assert_eq!(*r.read_newest(), i);
+            }
+        }
+        assert_eq!(*r.read_newest(), 9);
+        assert_eq!(w.free_slots(), 2);
+    }
+
+    #[test]
+    fn test_pinned_slots() {
+        let (mut w, mut r) = new::<u32, 4>(0);
+        let mut pins = Vec::new();
+        for i in 1..=3 {
+            w.write_new(|_, new| *new = i);
+            r.read_newest();
+            pins.push(r.pin());
+        }
+        // The pins hold three slots, leaving one for the writer.
+        assert_eq!(w.free_slots(), 1);
+        assert!(w.try_write_new(|_, new| *new = 4).is_ok());
+        assert!(w.try_write_new(|_, new| *new = 5).is_err());
+        assert_eq!(pins.iter().map(|s| **s).collect::<Vec<_>>(), [1, 2, 3]);
+
+        drop(pins);
+        assert!(w.try_write_new(|old, new| *new = *old + 1).is_ok());
+        assert_eq!(*r.read_newest(), 5);
+    }
+
+    #[test]
+    fn test_threads() {
+        let (mut w, mut r) = new::<[u64; 8], 3>([0; 8]);
+        let writer = std::thread::spawn(move || {
+            for _ in 0..100_000 {
+                w.write_new(|old, new| *new = [old[0] + 1; 8]);
+            }
+        });
+        let mut last = 0;
+        while last < 100_000 {
+            let state = r.read_newest();
+            assert!(state.iter().all(|v| *v == state[0]));
+            assert!(state[0] >= last);
+            last = state[0];
+        }
+        writer.join().unwrap();
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+use crate::sync::Mutex;
+use crate::{Reader, TripleBufferBuilder, Writer};
+
+type MakePair<K, T> = Box<dyn FnMut(&K) -> TripleBufferBuilder<T> + Send>;
+
+/// A set of buffer pairs, identified by keys.
+///
+/// Pairs get created on first use by either side, so a consumer can
+/// subscribe to a key before the producer first writes to it, and
+/// the other way around. Cloning the map creates another handle to
+/// the same set of pairs, typically one for the producer and one
+/// for the consumer.
+///
+/// Only available with the `std` feature.
+///
+/// # Example
+/// ```
+/// use simple_triple_buffer::TripleBufferMap;
+///
+/// let producer = TripleBufferMap::new_clone(0);
+/// let consumer = producer.clone();
+///
+/// let mut cpu = consumer.subscribe("cpu").unwrap();
+/// producer.write("cpu", |_, new| *new = 42);
+/// producer.write("mem", |_, new| *new = 7);
+///
+/// assert_eq!(*cpu.read_newest(), 42);
+/// assert_eq!(consumer.keys().len(), 2);
+///
+/// producer.remove(&"cpu");
+/// assert!(cpu.is_disconnected());
+/// ````
+pub struct TripleBufferMap<K, T> {
+    inner: Arc<Mutex<Inner<K, T>>>,
+}
+
+struct Inner<K, T> {
+    pairs: HashMap<K, Entry<T>>,
+    make_pair: MakePair<K, T>,
+}
+
+struct Entry<T> {
+    writer: Writer<T>,
+    /// The reader created along with the pair, until someone subscribes.
+    reader: Option<Reader<T>>,
+}
+
+impl<K: Hash + Eq, T> Inner<K, T> {
+    fn entry(&mut self, key: K) -> &mut Entry<T> {
+        let make_pair = &mut self.make_pair;
+        self.pairs.entry(key).or_insert_with_key(|key| {
+            let (writer, reader) = make_pair(key)
+                .build()
+                .expect("TripleBufferMap got an invalid builder");
+            Entry {
+                writer,
+                reader: Some(reader),
+            }
+        })
+    }
+}
+
+impl<K: Hash + Eq, T> TripleBufferMap<K, T> {
+    /// Create a map that creates the pair for a new key
+    /// from the builder returned by `make_pair`.
+    ///
+    /// # Panics
+    /// Using a new key panics if its builder fails to build.
+    pub fn with_builder(
+        make_pair: impl FnMut(&K) -> TripleBufferBuilder<T> + Send + 'static,
+    ) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Inner {
+                pairs: HashMap::new(),
+                make_pair: Box::new(make_pair),
+            })),
+        }
+    }
+
+    /// Create a map whose pairs start with a clone of `init`,
+    /// and create additional buffer instances by cloning.
+    pub fn new_clone(init: T) -> Self
+    where
+        T: Clone + Send + 'static,
+    {
+        Self::with_builder(move |_| TripleBufferBuilder::new(init.clone()).clone_with(T::clone))
+    }
+
+    /// Write a new state for `key`, see `Writer::write_new`.
+    ///
+    /// The map is locked while `write_op` runs.
+    pub fn write(&self, key: K, write_op: impl FnOnce(&T, &mut T)) {
+        self.inner.lock().entry(key).writer.write_new(write_op);
+    }
+
+    /// Get the reader for `key`.
+    ///
+    /// Returns `None` if another reader for the key is still alive.
+    /// Once it gets dropped, the key can be subscribed to again.
+    pub fn subscribe(&self, key: K) -> Option<Reader<T>> {
+        let mut inner = self.inner.lock();
+        let entry = inner.entry(key);
+        if let Some(reader) = entry.reader.take() {
+            return Some(reader);
+        }
+        if !entry.writer.is_closed() {
+            return None;
+        }
+        let shared = &entry.writer.read_update.shared;
+        shared.readers.fetch_add(1, Ordering::AcqRel);
+        Some(entry.writer.new_reader())
+    }
+
+    /// Remove the pair for `key`, disconnecting its reader.
+    ///
+    /// Returns whether the key existed.
+    pub fn remove(&self, key: &K) -> bool {
+        // Drop the pair outside of the lock.
+        let entry = self.inner.lock().pairs.remove(key);
+        entry.is_some()
+    }
+
+    /// Get all keys that currently have a pair.
+    pub fn keys(&self) -> Vec<K>
+    where
+        K: Clone,
+    {
+        self.inner.lock().pairs.keys().cloned().collect()
+    }
+}
+
+impl<K, T> Clone for TripleBufferMap<K, T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    use super::TripleBufferMap;
+
+    #[test]
+    fn test_subscribe_before_and_after_write() {
+        let map = TripleBufferMap::new_clone(0);
+        let mut a = map.subscribe("a").unwrap();
+        map.write("a", |_, new| *new = 1);
+        map.write("b", |_, new| *new = 2);
+        let mut b = map.subscribe("b").unwrap();
+        assert_eq!(*a.read_newest(), 1);
+        assert_eq!(*b.read_newest(), 2);
+
+        let mut keys = map.keys();
+        keys.sort_unstable();
+        assert_eq!(keys, ["a", "b"]);
+    }
+
+    #[test]
+    fn test_resubscribe() {
+        let map = TripleBufferMap::new_clone(0);
+        let r = map.subscribe(1).unwrap();
+        assert!(map.subscribe(1).is_none());
+        drop(r);
+
+        map.write(1, |_, new| *new = 5);
+        let mut r = map.subscribe(1).unwrap();
+        assert_eq!(*r.read_newest(), 5);
+        map.write(1, |_, new| *new = 6);
+        assert_eq!(*r.read_newest(), 6);
+    }
+
+    #[test]
+    fn test_panicking_write_does_not_poison() {
+        let map = TripleBufferMap::new_clone(0);
+        let mut r = map.subscribe(1).unwrap();
+        let panicked = catch_unwind(AssertUnwindSafe(|| {
+            map.write(1, |_, _| panic!("failed to compute the state"));
+        }));
+        assert!(panicked.is_err());
+        assert_eq!(*r.read_newest(), 0);
+        map.write(1, |_, new| *new = 5);
+        assert_eq!(*r.read_newest(), 5);
+        assert_eq!(map.keys(), [1]);
+    }
+
+    #[test]
+    fn test_remove_disconnects() {
+        let map = TripleBufferMap::new_clone(0);
+        let mut r = map.subscribe(1).unwrap();
+        map.write(1, |_, new| *new = 5);
+        assert!(map.remove(&1));
+        assert!(!map.remove(&1));
+        assert!(r.is_disconnected());
+        assert_eq!(*r.read_newest(), 5);
+    }
+
+    #[test]
+    fn test_concurrent_creation() {
+        for _ in 0..100 {
+            let map = TripleBufferMap::new_clone(0);
+            let producer = map.clone();
+            let t = std::thread::spawn(move || producer.write(1, |_, new| *new = 1));
+            let mut r = map.subscribe(1).unwrap();
+            t.join().unwrap();
+            assert_eq!(*r.read_newest(), 1);
+            assert_eq!(map.keys(), [1]);
+        }
+    }
+}
@@ -0,0 +1,156 @@
+//! Reporting the events of labeled pairs to the `metrics` crate.
+
+use alloc::borrow::Cow;
+
+use ::metrics::{counter, gauge, Counter, Gauge};
+
+use crate::{Reader, Writer};
+
+/// The handles the writer reports to, registered once when
+/// building the pair, so reporting is an atomic add or store.
+pub(crate) struct WriterMetrics {
+    publishes: Counter,
+    allocated: Counter,
+    pool_size: Gauge,
+}
+
+impl WriterMetrics {
+    pub(crate) fn new(label: Cow<'static, str>) -> Self {
+        Self {
+            publishes: counter!("stb_publishes_total", "pair" => label.clone()),
+            allocated: counter!("stb_buffers_allocated_total", "pair" => label.clone()),
+            pool_size: gauge!("stb_pool_size", "pair" => label),
+        }
+    }
+}
+
+/// The counter readers report to, shared by all of them.
+pub(crate) fn observed_counter(label: Cow<'static, str>) -> Counter {
+    counter!("stb_updates_observed_total", "pair" => label)
+}
+
+impl<T> Writer<T> {
+    pub(crate) fn metrics_published(&self) {
+        if let Some(metrics) = &self.metrics {
+            metrics.publishes.increment(1);
+            // Buffers are dropped in more places than they are created,
+            // so the size gets caught up with every publish.
+            metrics.pool_size.set(self.created as f64);
+        }
+    }
+
+    pub(crate) fn metrics_allocated(&self, buffers: u64) {
+        if let Some(metrics) = &self.metrics {
+            metrics.allocated.increment(buffers);
+            metrics.pool_size.set(self.created as f64);
+        }
+    }
+}
+
+impl<T> Reader<T> {
+    pub(crate) fn metrics_observed(&self) {
+        if let Some(observed) = &self.read_update.shared.observed_metric {
+            observed.increment(1);
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use ::metrics::{
+        Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+
+    use crate::TripleBufferBuilder;
+
+    /// Keeps the values of all metrics, by name and `pair` label.
+    #[derive(Default)]
+    struct TestRecorder {
+        values: Mutex<HashMap<(String, String), Arc<AtomicU64>>>,
+    }
+
+    impl TestRecorder {
+        fn value(&self, name: &str, pair: &str) -> Option<u64> {
+            let values = self.values.lock().unwrap();
+            let value = values.get(&(name.into(), pair.into()))?;
+            Some(value.load(Ordering::Relaxed))
+        }
+
+        fn register(&self, key: &Key) -> Arc<AtomicU64> {
+            let pair = key.labels().find(|label| label.key() == "pair").unwrap();
+            let mut values = self.values.lock().unwrap();
+            let value = values.entry((key.name().into(), pair.value().into()));
+            value.or_default().clone()
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            Counter::from_arc(self.register(key))
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            Gauge::from_arc(self.register(key))
+        }
+
+        fn register_histogram(&self, _: &Key, _: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn test_labeled_pairs_report() {
+        let recorder = TestRecorder::default();
+        let (mut w, mut r) = ::metrics::with_local_recorder(&recorder, || {
+            TripleBufferBuilder::new(0)
+                .copy_buffers()
+                .preallocate(1)
+                .label("physics")
+                .build()
+                .unwrap()
+        });
+        for i in 1..=5 {
+            w.write_new(|_, new| *new = i);
+            if i % 2 == 0 {
+                r.read_newest();
+            }
+        }
+        assert_eq!(recorder.value("stb_publishes_total", "physics"), Some(5));
+        assert_eq!(
+            recorder.value("stb_updates_observed_total", "physics"),
+            Some(2)
+        );
+        assert_eq!(
+            recorder.value("stb_buffers_allocated_total", "physics"),
+            Some(2)
+        );
+        let pool_size = recorder.value("stb_pool_size", "physics").unwrap();
+        assert_eq!(f64::from_bits(pool_size), 3.0);
+    }
+
+    #[test]
+    fn test_unlabeled_pairs() {
+        let recorder = TestRecorder::default();
+        let (mut w, _) = ::metrics::with_local_recorder(&recorder, || crate::new_clone(0));
+        w.write_new(|_, new| *new = 1);
+        assert!(recorder.values.lock().unwrap().is_empty());
+
+        let (mut w, _) = ::metrics::with_local_recorder(&recorder, || {
+            TripleBufferBuilder::new(0)
+                .copy_buffers()
+                .metrics_default_label("unlabeled")
+                .build()
+                .unwrap()
+        });
+        w.write_new(|_, new| *new = 1);
+        assert_eq!(recorder.value("stb_publishes_total", "unlabeled"), Some(1));
+    }
+}
@@ -0,0 +1,506 @@
+//! Buffer pairs whose states live in a memory-mapped file,
+//! so the last published state survives a crash of the process.
+//!
+//! The file holds a small header and three slots. Every publish first
+//! writes the new state into a slot nobody else uses, together with
+//! a sequence number and a checksum, and only then records that slot
+//! as the latest committed one in the header. A crash in the middle
+//! of a publish therefore leaves the previous state as the latest one,
+//! and `recover` reads it back after a restart.
+//!
+//! Surviving a crash of the whole machine additionally requires the
+//! data to reach the disk, see `Flush`. Without flushing, the checksums
+//! let `recover` detect a slot that only partially made it to disk,
+//! and fall back to the newest intact one.
+//!
+//! Only available with the `mmap` feature.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::mmap;
+//!
+//! let path = std::env::temp_dir().join("simple_triple_buffer_mmap_doc");
+//!
+//! let (mut writer, mut reader) = mmap::new_mmap(&path, [0u32; 4]).unwrap();
+//! writer.write_new(|old, new| *new = [old[0] + 1; 4]).unwrap();
+//! assert_eq!(*reader.read_newest(), [1; 4]);
+//! drop((writer, reader));
+//!
+//! // After a restart:
+//! let state: [u32; 4] = mmap::recover(&path).unwrap();
+//! assert_eq!(state, [1; 4]);
+//! # std::fs::remove_file(&path).unwrap();
+//! ````
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::marker::PhantomData;
+use std::mem;
+use std::path::Path;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use bytemuck::Pod;
+use memmap2::MmapMut;
+
+/// Set in the shared index if its slot contains a state
+/// the reader has not seen yet.
+pub(crate) const DIRTY: u8 = 0b100;
+pub(crate) const INDEX: u8 = 0b011;
+
+const MAGIC: [u8; 8] = *b"STBMMAP1";
+/// Size of the file header, and of the header of each slot.
+///
+/// States start at a multiple of it, which is enough
+/// alignment for any reasonable `Pod` type.
+const HEADER_LEN: usize = 64;
+const MAX_ALIGN: usize = 64;
+
+const MAGIC_OFFSET: usize = 0;
+const SIZE_OFFSET: usize = 8;
+/// `seq << 2 | slot` of the latest committed publish.
+const LATEST_OFFSET: usize = 16;
+/// Index of the slot neither half uses, plus `DIRTY`.
+const BACK_OFFSET: usize = 24;
+/// Index of the slot owned by the reader.
+const FRONT_OFFSET: usize = 25;
+/// Which halves are attached, used by the `ipc` module.
+#[cfg(feature = "ipc")]
+const ATTACHED_OFFSET: usize = 26;
+
+const SEQ_OFFSET: usize = 0;
+const CHECKSUM_OFFSET: usize = 8;
+
+/// When the mapped file gets flushed to disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flush {
+    /// Flush the written slot and then the header on every publish.
+    ///
+    /// This makes every publish durable, but is as slow as the disk.
+    EveryPublish,
+    /// Flush on a publish if the last flush is at least this long ago.
+    Periodic(Duration),
+    /// Only flush on `MmapWriter::flush`.
+    ///
+    /// The operating system still writes the file back eventually,
+    /// so this is enough to survive crashes of the process itself.
+    Manual,
+}
+
+/// Distance between slots for states of type `T`.
+fn slot_stride<T>() -> usize {
+    let len = HEADER_LEN + mem::size_of::<T>();
+    len.div_ceil(HEADER_LEN) * HEADER_LEN
+}
+
+fn file_len<T>() -> usize {
+    HEADER_LEN + 3 * slot_stride::<T>()
+}
+
+fn slot_offset<T>(slot: u8) -> usize {
+    HEADER_LEN + slot as usize * slot_stride::<T>()
+}
+
+/// FNV-1a over the sequence number and the state.
+fn checksum(seq: u64, bytes: &[u8]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    for byte in seq.to_ne_bytes().iter().chain(bytes) {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// A file mapped into memory, laid out for states of type `T`.
+pub(crate) struct Mapping<T> {
+    map: MmapMut,
+    ptr: *mut u8,
+    _state: PhantomData<T>,
+}
+
+// SAFETY: Access to the mapping is coordinated through its atomics,
+// like for `StaticTripleBuffer`.
+unsafe impl<T: Send + Sync> Send for Mapping<T> {}
+// SAFETY: See above.
+unsafe impl<T: Send + Sync> Sync for Mapping<T> {}
+
+impl<T: Pod> Mapping<T> {
+    /// Create the file at `path`, with all slots set to `init`
+    /// and the first one committed as the latest.
+    pub(crate) fn create(path: &Path, init: T) -> io::Result<Self> {
+        assert!(
+            mem::align_of::<T>() <= MAX_ALIGN,
+            "states with an alignment over {} are not supported",
+            MAX_ALIGN
+        );
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(file_len::<T>() as u64)?;
+        let mapping = Self::map(&file)?;
+        for slot in 0..3 {
+            // SAFETY: Nobody else has access to the mapping yet.
+            unsafe { mapping.state(slot).write(init) };
+        }
+        mapping.commit(0, 1, false)?;
+        mapping.back().store(1, Ordering::Relaxed);
+        // SAFETY: See above.
+        unsafe {
+            ptr::copy_nonoverlapping(MAGIC.as_ptr(), mapping.ptr.add(MAGIC_OFFSET), 8);
+            (mapping.ptr.add(SIZE_OFFSET) as *mut u64).write(mem::size_of::<T>() as u64);
+        }
+        mapping.map.flush()?;
+        Ok(mapping)
+    }
+
+    /// Map an existing file, checking that it was created for `T`.
+    #[cfg(feature = "ipc")]
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        validate::<T>(&std::fs::read(path)?)?;
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Self::map(&file)
+    }
+
+    fn map(file: &File) -> io::Result<Self> {
+        // SAFETY: The file is only accessed through mappings created by
+        // this module, which coordinate through the atomics in it.
+        let mut map = unsafe { MmapMut::map_mut(file)? };
+        let ptr = map.as_mut_ptr();
+        Ok(Self {
+            map,
+            ptr,
+            _state: PhantomData,
+        })
+    }
+
+    fn atomic_u64(&self, offset: usize) -> &AtomicU64 {
+        // SAFETY: The offset is within the header and 8 byte aligned,
+        // and the mapping itself is page aligned.
+        unsafe { &*(self.ptr.add(offset) as *const AtomicU64) }
+    }
+
+    pub(crate) fn latest(&self) -> &AtomicU64 {
+        self.atomic_u64(LATEST_OFFSET)
+    }
+
+    fn atomic_u8(&self, offset: usize) -> &AtomicU8 {
+        // SAFETY: The offset is within the header.
+        unsafe { &*(self.ptr.add(offset) as *const AtomicU8) }
+    }
+
+    pub(crate) fn back(&self) -> &AtomicU8 {
+        self.atomic_u8(BACK_OFFSET)
+    }
+
+    /// Only accessed by the reader, but kept in the file
+    /// so that a reader attaching later can take over.
+    pub(crate) fn front(&self) -> &AtomicU8 {
+        self.atomic_u8(FRONT_OFFSET)
+    }
+
+    #[cfg(feature = "ipc")]
+    pub(crate) fn attached(&self) -> &AtomicU8 {
+        self.atomic_u8(ATTACHED_OFFSET)
+    }
+
+    /// Get a pointer to the state in `slot`.
+    pub(crate) fn state(&self, slot: u8) -> *mut T {
+        // SAFETY: All slots are within the mapping.
+        unsafe { self.ptr.add(slot_offset::<T>(slot) + HEADER_LEN) as *mut T }
+    }
+
+    /// Record the state in `slot` as the latest committed publish,
+    /// optionally flushing it to disk before and after.
+    ///
+    /// The state must not change anymore until
+    /// another slot has been committed.
+    pub(crate) fn commit(&self, slot: u8, seq: u64, flush: bool) -> io::Result<()> {
+        let header = slot_offset::<T>(slot);
+        // SAFETY: The slot is not committed yet, so only
+        // the caller accesses its header.
+        unsafe {
+            let bytes = bytemuck::bytes_of(&*self.state(slot));
+            let sum = checksum(seq, bytes);
+            (self.ptr.add(header + SEQ_OFFSET) as *mut u64).write(seq);
+            (self.ptr.add(header + CHECKSUM_OFFSET) as *mut u64).write(sum);
+        }
+        // The slot has to be on disk before the header refers to it.
+        let flushed = match flush {
+            true => self.map.flush_range(header, slot_stride::<T>()),
+            false => Ok(()),
+        };
+        self.latest()
+            .store(seq << 2 | u64::from(slot), Ordering::Release);
+        match flush {
+            true => flushed.and_then(|()| self.map.flush_range(0, HEADER_LEN)),
+            false => flushed,
+        }
+    }
+
+    fn flush(&self) -> io::Result<()> {
+        self.map.flush()
+    }
+}
+
+/// Check that the file contents were created for states of type `T`.
+fn validate<T>(bytes: &[u8]) -> io::Result<()> {
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+    if bytes.len() != file_len::<T>() {
+        return Err(invalid("file size does not match the state type"));
+    }
+    if bytes[MAGIC_OFFSET..MAGIC_OFFSET + 8] != MAGIC {
+        return Err(invalid("not a triple buffer file"));
+    }
+    let mut size = [0; 8];
+    size.copy_from_slice(&bytes[SIZE_OFFSET..SIZE_OFFSET + 8]);
+    let size = u64::from_ne_bytes(size);
+    if size != mem::size_of::<T>() as u64 {
+        return Err(invalid("file size does not match the state type"));
+    }
+    Ok(())
+}
+
+/// Create a new buffer pair whose states live in the file at `path`,
+/// which gets created or overwritten, starting with `init`.
+///
+/// Every publish gets flushed to disk, see `new_mmap_with`.
+pub fn new_mmap<T: Pod + Send + Sync>(
+    path: impl AsRef<Path>,
+    init: T,
+) -> io::Result<(MmapWriter<T>, MmapReader<T>)> {
+    new_mmap_with(path, init, Flush::EveryPublish)
+}
+
+/// Create a new buffer pair whose states live in the file at `path`,
+/// flushing it to disk according to `flush`.
+pub fn new_mmap_with<T: Pod + Send + Sync>(
+    path: impl AsRef<Path>,
+    init: T,
+    flush: Flush,
+) -> io::Result<(MmapWriter<T>, MmapReader<T>)> {
+    let mapping = Arc::new(Mapping::create(path.as_ref(), init)?);
+    let writer = MmapWriter::new(mapping.clone(), flush);
+    Ok((writer, MmapReader::attach(mapping)))
+}
+
+/// Read the latest committed state from a file
+/// written by a pair created with `new_mmap`.
+///
+/// If that state did not fully reach the disk before a crash of the
+/// machine, this falls back to the newest state that did.
+/// Fails with `InvalidData` if the file was not created for
+/// states of type `T`, or no intact state is left.
+pub fn recover<T: Pod>(path: impl AsRef<Path>) -> io::Result<T> {
+    let bytes = std::fs::read(path)?;
+    validate::<T>(&bytes)?;
+    let read_u64 = |offset: usize| {
+        let mut value = [0; 8];
+        value.copy_from_slice(&bytes[offset..offset + 8]);
+        u64::from_ne_bytes(value)
+    };
+
+    let latest_seq = read_u64(LATEST_OFFSET) >> 2;
+    let newest = (0..3)
+        .filter_map(|slot| {
+            let header = slot_offset::<T>(slot);
+            let seq = read_u64(header + SEQ_OFFSET);
+            let start = header + HEADER_LEN;
+            let data = &bytes[start..start + mem::size_of::<T>()];
+            // Slots newer than the latest commit were torn by a crash.
+            let intact = seq != 0
+                && seq <= latest_seq
+                && read_u64(header + CHECKSUM_OFFSET) == checksum(seq, data);
+            intact.then_some((seq, data))
+        })
+        .max_by_key(|(seq, _)| *seq);
+    match newest {
+        Some((_, data)) => Ok(bytemuck::pod_read_unaligned(data)),
+        None => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "no intact state left",
+        )),
+    }
+}
+
+/// Write side of a pair created with `new_mmap`.
+pub struct MmapWriter<T> {
+    mapping: Arc<Mapping<T>>,
+    /// The slot owned by the writer.
+    write: u8,
+    /// The slot containing the newest published state.
+    last: u8,
+    /// Sequence number of the newest published state.
+    seq: u64,
+    flush: Flush,
+    last_flush: Instant,
+}
+
+impl<T: Pod> MmapWriter<T> {
+    /// Create the writer of a freshly created mapping.
+    pub(crate) fn new(mapping: Arc<Mapping<T>>, flush: Flush) -> Self {
+        Self {
+            mapping,
+            write: 2,
+            last: 0,
+            seq: 1,
+            flush,
+            last_flush: Instant::now(),
+        }
+    }
+
+    /// Write a new state and publish it.
+    ///
+    /// The closure gets a reference to the previously published state
+    /// and to an older state it has to overwrite.
+    ///
+    /// Only fails if flushing to disk fails, in which case
+    /// the state has still been published to the reader.
+    pub fn write_new(&mut self, f: impl FnOnce(&T, &mut T)) -> io::Result<()> {
+        let mapping = &self.mapping;
+        // SAFETY: Nobody writes to the last published slot until the
+        // writer takes it back, and the write slot is exclusively ours.
+        let (old, new) = unsafe { (&*mapping.state(self.last), &mut *mapping.state(self.write)) };
+        f(old, new);
+
+        self.seq += 1;
+        let slot = self.write;
+        let committed = mapping.commit(slot, self.seq, self.flush == Flush::EveryPublish);
+        self.last = slot;
+        let back = mapping.back().swap(slot | DIRTY, Ordering::AcqRel);
+        self.write = back & INDEX;
+
+        committed?;
+        match self.flush {
+            Flush::Periodic(period) if self.last_flush.elapsed() >= period => self.flush(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Publish `value` as the new state, see `write_new`.
+    pub fn write(&mut self, value: T) -> io::Result<()> {
+        self.write_new(|_, new| *new = value)
+    }
+
+    /// Flush the whole file to disk.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.last_flush = Instant::now();
+        self.mapping.flush()
+    }
+}
+
+/// Read side of a pair created with `new_mmap`.
+pub struct MmapReader<T> {
+    mapping: Arc<Mapping<T>>,
+    /// The slot owned by the reader.
+    front: u8,
+}
+
+impl<T: Pod> MmapReader<T> {
+    /// Create a reader taking over the slot recorded in the mapping.
+    pub(crate) fn attach(mapping: Arc<Mapping<T>>) -> Self {
+        let front = mapping.front().load(Ordering::Relaxed);
+        Self { mapping, front }
+    }
+
+    /// Get the newest published state.
+    pub fn read_newest(&mut self) -> &T {
+        let back = self.mapping.back();
+        if back.load(Ordering::Relaxed) & DIRTY != 0 {
+            self.front = back.swap(self.front, Ordering::AcqRel) & INDEX;
+            self.mapping.front().store(self.front, Ordering::Relaxed);
+        }
+        // SAFETY: The writer never writes to the slot owned by the reader.
+        unsafe { &*self.mapping.state(self.front) }
+    }
+
+    /// Check whether a state has been published
+    /// that `read_newest` has not returned yet.
+    pub fn has_update(&self) -> bool {
+        self.mapping.back().load(Ordering::Relaxed) & DIRTY != 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom, Write};
+    use std::path::PathBuf;
+
+    use super::{new_mmap, new_mmap_with, recover, slot_offset, Flush, HEADER_LEN};
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("stb-mmap-{}-{}", std::process::id(), name))
+    }
+
+    fn corrupt_slot(path: &PathBuf, slot: u8) {
+        let mut file = OpenOptions::new().write(true).open(path).unwrap();
+        let offset = slot_offset::<[u64; 4]>(slot) + HEADER_LEN;
+        file.seek(SeekFrom::Start(offset as u64)).unwrap();
+        file.write_all(&[0xff; 8]).unwrap();
+    }
+
+    #[test]
+    fn test_recover_last_publish() {
+        let path = temp_path("last");
+        let (mut w, mut r) = new_mmap(&path, [0u64; 4]).unwrap();
+        for i in 1..=10 {
+            w.write_new(|old, new| *new = [old[0] + 1; 4]).unwrap();
+            assert_eq!(*r.read_newest(), [i; 4]);
+        }
+        drop((w, r));
+        assert_eq!(recover::<[u64; 4]>(&path).unwrap(), [10; 4]);
+        assert!(recover::<[u64; 3]>(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_torn_slots_are_skipped() {
+        let path = temp_path("torn");
+        let (mut w, _r) = new_mmap_with(&path, [0u64; 4], Flush::Manual).unwrap();
+        for i in 1..=3 {
+            w.write([i; 4]).unwrap();
+        }
+        // The latest slot, as if it never made it to disk.
+        corrupt_slot(&path, w.last);
+        assert_eq!(recover::<[u64; 4]>(&path).unwrap(), [2; 4]);
+
+        // Without reads, the writer's next slot holds the previous state,
+        // and the reader's slot the initial one.
+        corrupt_slot(&path, w.write);
+        assert_eq!(recover::<[u64; 4]>(&path).unwrap(), [0; 4]);
+        corrupt_slot(&path, 3 - w.write - w.last);
+        assert!(recover::<[u64; 4]>(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_threads() {
+        let path = temp_path("threads");
+        let (mut w, mut r) = new_mmap_with(
+            &path,
+            [0u64; 8],
+            Flush::Periodic(std::time::Duration::from_millis(1)),
+        )
+        .unwrap();
+        let writer = std::thread::spawn(move || {
+            for _ in 0..10_000 {
+                w.write_new(|old, new| *new = [old[0] + 1; 8]).unwrap();
+            }
+        });
+        let mut last = 0;
+        while last < 10_000 {
+            let state = r.read_newest();
+            assert!(state.iter().all(|v| *v == state[0]));
+            assert!(state[0] >= last);
+            last = state[0];
+        }
+        writer.join().unwrap();
+        assert_eq!(recover::<[u64; 8]>(&path).unwrap(), [10_000; 8]);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,314 @@
+//! Checking pairs against a trivially correct model of them.
+//!
+//! `Model` is a single state with its version behind a mutex, which
+//! also remembers every state it ever published. Anything a real reader
+//! observes has to be a state the model published, with the same
+//! version, and versions must never go backwards.
+//!
+//! `check` runs a sequence of `Op`s against a real pair and a model from
+//! two threads, the writer and the reader, and reports the first state
+//! the reader observed that the model can not account for. `random_ops`
+//! generates sequences from a seed, and `shrink` removes operations
+//! from a failing one for as long as it keeps failing, which makes for
+//! short operation logs to put into bug reports.
+//!
+//! Only available with the `std` feature.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::model;
+//!
+//! for seed in 0..10 {
+//!     let ops = model::random_ops(seed, 200);
+//!     if let Err(failure) = model::check(&ops) {
+//!         panic!("{}", model::shrink(failure));
+//!     }
+//! }
+//! ````
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use std::thread;
+
+use crate::new_clone;
+use crate::sync::Mutex;
+
+/// The reference model of a pair.
+pub struct Model<T> {
+    inner: Mutex<ModelInner<T>>,
+}
+
+struct ModelInner<T> {
+    version: u64,
+    state: T,
+    /// Every state published, by version, including the initial one.
+    published: BTreeMap<u64, T>,
+}
+
+impl<T: Clone + PartialEq + fmt::Debug> Model<T> {
+    /// Create a model starting out with `init` as version 0.
+    pub fn new(init: T) -> Self {
+        let mut published = BTreeMap::new();
+        published.insert(0, init.clone());
+        Self {
+            inner: Mutex::new(ModelInner {
+                version: 0,
+                state: init,
+                published,
+            }),
+        }
+    }
+
+    /// Publish `state` as the next version, and return that version.
+    ///
+    /// Publish to the model before the real pair, so it
+    /// never lags behind what a real reader can observe.
+    pub fn publish(&self, state: T) -> u64 {
+        let mut inner = self.inner.lock();
+        inner.version += 1;
+        let version = inner.version;
+        inner.published.insert(version, state.clone());
+        inner.state = state;
+        version
+    }
+
+    /// Get the version and state published last.
+    pub fn latest(&self) -> (u64, T) {
+        let inner = self.inner.lock();
+        (inner.version, inner.state.clone())
+    }
+
+    /// Check that a reader observing `state` as `version`, after it
+    /// observed `last` before, is something the model allows.
+    pub fn check_observed(&self, last: u64, version: u64, state: &T) -> Result<(), String> {
+        if version < last {
+            return Err(format!("version went back from {} to {}", last, version));
+        }
+        match self.inner.lock().published.get(&version) {
+            Some(published) if published == state => Ok(()),
+            Some(published) => Err(format!(
+                "observed {:?} as version {}, which was {:?}",
+                state, version, published
+            )),
+            None => Err(format!(
+                "observed {:?} as version {}, which was never published",
+                state, version
+            )),
+        }
+    }
+}
+
+/// An operation of `check`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Op {
+    /// Publish a state with `Writer::write_new`.
+    Write(u64),
+    /// Publish the previous state plus the value with `Writer::write_update`.
+    Update(u64),
+    /// Publish a state with `Writer::swap`.
+    Swap(u64),
+    /// Pick up the newest state with `Reader::read_newest`,
+    /// and check it against the model.
+    Read,
+    /// Let the other thread run, on the thread of the previous operation.
+    Yield,
+}
+
+/// A sequence of operations `check` found the pair to disagree with
+/// the model on, see `shrink`.
+#[derive(Debug, Clone)]
+pub struct Failure {
+    /// The operations that got run.
+    pub ops: Vec<Op>,
+    /// The position of the operation that failed.
+    pub index: usize,
+    /// What the model did not allow.
+    pub message: String,
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "operation {} ({:?}) failed: {}",
+            self.index, self.ops[self.index], self.message
+        )?;
+        write!(f, "operations: {:?}", self.ops)
+    }
+}
+
+impl std::error::Error for Failure {}
+
+/// Generate `len` operations from `seed`, the same ones for the same seed.
+pub fn random_ops(seed: u64, len: usize) -> Vec<Op> {
+    // xorshift64*, which needs a state that is not 0.
+    let mut state = seed ^ 0x9e37_79b9_7f4a_7c15;
+    let mut next = move || {
+        state ^= state >> 12;
+        state ^= state << 25;
+        state ^= state >> 27;
+        state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    };
+    (0..len)
+        .map(|_| {
+            let value = next();
+            match value % 8 {
+                0 | 1 => Op::Write(value >> 32),
+                2 => Op::Update(value >> 48),
+                3 => Op::Swap(value >> 32),
+                4 => Op::Yield,
+                _ => Op::Read,
+            }
+        })
+        .collect()
+}
+
+/// Run `ops` against a real pair and a `Model`, the reads on one
+/// thread and everything else on another, and check every state the
+/// reader observes, as well as the state a final read observes once
+/// the writer is done.
+pub fn check(ops: &[Op]) -> Result<(), Failure> {
+    let model = Model::new(0u64);
+    let (mut writer, mut reader) = new_clone(0u64);
+    let fail = |index, message| Failure {
+        ops: ops.to_vec(),
+        index,
+        message,
+    };
+    let is_read = |index: usize| {
+        // A yield belongs to the thread of the operation before it.
+        ops[..=index]
+            .iter()
+            .rev()
+            .find(|op| **op != Op::Yield)
+            .is_some_and(|op| *op == Op::Read)
+    };
+
+    let last = thread::scope(|s| {
+        s.spawn(|| {
+            for (index, op) in ops.iter().enumerate() {
+                match *op {
+                    Op::Write(value) => {
+                        model.publish(value);
+                        writer.write_new(|_, new| *new = value);
+                    }
+                    Op::Update(value) => {
+                        let (_, prev) = model.latest();
+                        model.publish(prev.wrapping_add(value));
+                        writer.write_update(|state| *state = state.wrapping_add(value));
+                    }
+                    Op::Swap(value) => {
+                        model.publish(value);
+                        writer.swap(value);
+                    }
+                    Op::Yield if !is_read(index) => thread::yield_now(),
+                    Op::Read | Op::Yield => {}
+                }
+            }
+        });
+        let mut last = 0;
+        for (index, op) in ops.iter().enumerate() {
+            match op {
+                Op::Read => {
+                    let state = *reader.read_newest();
+                    let version = reader.version();
+                    model
+                        .check_observed(last, version, &state)
+                        .map_err(|message| fail(index, message))?;
+                    last = version;
+                }
+                Op::Yield if is_read(index) => thread::yield_now(),
+                _ => {}
+            }
+        }
+        Ok(last)
+    })?;
+
+    let state = *reader.read_newest();
+    let version = reader.version();
+    let index = ops.len().saturating_sub(1);
+    if ops.is_empty() {
+        return Ok(());
+    }
+    model
+        .check_observed(last, version, &state)
+        .map_err(|message| fail(index, message))?;
+    let (latest, _) = model.latest();
+    if version != latest {
+        return Err(fail(
+            index,
+            format!("final read observed version {} of {}", version, latest),
+        ));
+    }
+    Ok(())
+}
+
+/// Remove operations from a failing sequence for as long as it keeps
+/// failing, and return the failure of the shortest one found.
+///
+/// Failures that depend on the timing of the two threads may not show
+/// up on every run, so every candidate gets a few runs.
+pub fn shrink(failure: Failure) -> Failure {
+    const RUNS: usize = 10;
+    let fails = |ops: &[Op]| (0..RUNS).find_map(|_| check(ops).err());
+    let mut best = failure;
+    let mut chunk = best.ops.len() / 2;
+    while chunk > 0 {
+        let mut start = 0;
+        while start < best.ops.len() {
+            let mut ops = best.ops.clone();
+            ops.drain(start..(start + chunk).min(ops.len()));
+            match fails(&ops) {
+                Some(failure) => best = failure,
+                None => start += chunk,
+            }
+        }
+        chunk /= 2;
+    }
+    best
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::{check, random_ops, shrink, Model, Op};
+
+    #[test]
+    fn test_random_sequences() {
+        for seed in 0..200 {
+            let ops = random_ops(seed, 500);
+            if let Err(failure) = check(&ops) {
+                panic!("seed {}: {}", seed, shrink(failure));
+            }
+        }
+    }
+
+    #[test]
+    fn test_model_catches_wrong_states() {
+        let model = Model::new(0u32);
+        assert_eq!(model.publish(5), 1);
+        assert_eq!(model.publish(7), 2);
+        assert!(model.check_observed(1, 2, &7).is_ok());
+        assert!(model.check_observed(2, 1, &5).is_err());
+        assert!(model.check_observed(0, 1, &7).is_err());
+        assert!(model.check_observed(0, 3, &7).is_err());
+    }
+
+    #[test]
+    fn test_failures_list_their_ops() {
+        let failure = super::Failure {
+            ops: vec![Op::Write(1), Op::Read],
+            index: 1,
+            message: "version went back from 1 to 0".into(),
+        };
+        assert_eq!(
+            failure.to_string(),
+            "operation 1 (Read) failed: version went back from 1 to 0\n\
+             operations: [Write(1), Read]"
+        );
+        // A sequence that passes has nothing left to remove.
+        assert_eq!(shrink(failure).ops.len(), 2);
+    }
+}
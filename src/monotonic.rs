@@ -0,0 +1,99 @@
+//! Publishing only states that are newer than the published one.
+
+use crate::{PoolExhausted, WriteError, Writer};
+
+impl<T> Writer<T> {
+    /// Write a candidate state like `write_new`, and only publish it if
+    /// its `key` is greater than the one of the state published last,
+    /// returning whether it did.
+    ///
+    /// This keeps the published state from ever going backwards when
+    /// several sources race to publish, like threads sharing a
+    /// `SharedWriter`, each with a sequence number inside its states.
+    /// A rejected candidate goes straight back to the unused buffers,
+    /// and with the `stats` feature, it counts towards
+    /// `stats::PairStats::rejected`.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone((0, "init"));
+    /// let seq = |state: &(u32, &str)| state.0;
+    ///
+    /// assert!(writer.publish_if_newer(seq, |_, new| *new = (2, "second")));
+    /// assert!(!writer.publish_if_newer(seq, |_, new| *new = (1, "first")));
+    /// assert_eq!(*reader.read_newest(), (2, "second"));
+    /// ````
+    ///
+    /// # Panics
+    /// Panics if the pair was created without a way to create
+    /// new buffers, and all existing ones are in use.
+    pub fn publish_if_newer<K: Ord>(
+        &mut self,
+        key: impl Fn(&T) -> K,
+        write_op: impl FnOnce(&T, &mut T),
+    ) -> bool {
+        let result = self.try_write_with(|old, new| {
+            write_op(old, new);
+            if key(new) > key(old) {
+                Ok(())
+            } else {
+                Err(())
+            }
+        });
+        match result {
+            Ok(()) => true,
+            Err(WriteError::User(())) => {
+                #[cfg(feature = "stats")]
+                {
+                    self.rejected += 1;
+                }
+                false
+            }
+            // The only other way `try_write_with` fails.
+            Err(_) => self.fail(PoolExhausted),
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::new_clone;
+
+    #[test]
+    fn test_racing_sources_never_go_back() {
+        let (w, mut r) = new_clone(0u64);
+        let w = w.into_shared();
+        let sources: Vec<_> = (0..4)
+            .map(|source| {
+                let w = w.clone();
+                std::thread::spawn(move || {
+                    for i in 0..1000 {
+                        w.publish_if_newer(|state| *state, |_, new| *new = i * 4 + source);
+                    }
+                })
+            })
+            .collect();
+        let mut last = 0;
+        while sources.iter().any(|source| !source.is_finished()) {
+            let state = *r.read_newest();
+            assert!(state >= last);
+            last = state;
+        }
+        for source in sources {
+            source.join().unwrap();
+        }
+        assert!(*r.read_newest() >= 3996);
+    }
+
+    #[cfg(feature = "stats")]
+    #[test]
+    fn test_rejects_get_counted() {
+        let (mut w, r) = new_clone(5u32);
+        for i in 0..10 {
+            w.publish_if_newer(|state| *state, |_, new| *new = i);
+        }
+        let stats = crate::stats::collect(&w, &r);
+        assert_eq!(stats.rejected, 6);
+        assert_eq!(stats.published, 4);
+    }
+}
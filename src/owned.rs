@@ -0,0 +1,210 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::mem;
+use core::sync::atomic::{AtomicU8, Ordering};
+
+/// Set in the shared index if its slot contains a state
+/// the reader has not seen yet.
+const DIRTY: u8 = 0b100;
+const INDEX: u8 = 0b011;
+
+/// Create a new buffer pair that hands its buffers over between the
+/// halves, instead of sharing them, so that it only requires `T: Send`.
+///
+/// `Writer` and `Reader` share every published state, which is why they
+/// can only be sent to other threads if `T` is `Sync` as well. Here the
+/// three buffers are boxes, and each of them only ever belongs to one
+/// half at a time, so states with `Cell` or `RefCell` inside work too.
+/// The price is that the writer never gets to see the published state
+/// again: `OwnedWriter::write_with` hands it an older buffer to overwrite.
+/// In turn, the reader gets exclusive access to its state.
+///
+/// # Example
+/// ```
+/// use std::cell::RefCell;
+///
+/// let (mut writer, mut reader) = simple_triple_buffer::new_owned(RefCell::new(vec![0]));
+///
+/// std::thread::spawn(move || {
+///     writer.write_with(|state| *state.get_mut() = vec![1, 2]);
+/// })
+/// .join()
+/// .unwrap();
+/// assert_eq!(*reader.read_newest().borrow(), [1, 2]);
+/// ````
+///
+/// The regular pairs can not be sent with such states:
+/// ```compile_fail
+/// use std::cell::RefCell;
+///
+/// let (mut writer, _reader) = simple_triple_buffer::new_clone(RefCell::new(0));
+/// std::thread::spawn(move || writer.write_update(|state| *state.get_mut() = 1));
+/// ````
+pub fn new_owned<T: Clone>(init: T) -> (OwnedWriter<T>, OwnedReader<T>) {
+    let shared = Arc::new(Shared {
+        slots: [
+            UnsafeCell::new(Box::new(init.clone())),
+            UnsafeCell::new(Box::new(init.clone())),
+            UnsafeCell::new(Box::new(init)),
+        ],
+        back: AtomicU8::new(1),
+    });
+    let writer = OwnedWriter {
+        shared: shared.clone(),
+        write: 2,
+    };
+    (writer, OwnedReader { shared, front: 0 })
+}
+
+struct Shared<T> {
+    slots: [UnsafeCell<Box<T>>; 3],
+    /// Index of the slot neither half currently uses, plus `DIRTY`.
+    back: AtomicU8,
+}
+
+// SAFETY: Every slot is only accessed by the half that currently owns it,
+// and the swaps of `back` hand slots over with acquire/release ordering,
+// just like sending a `Box<T>` through a channel.
+unsafe impl<T: Send> Send for Shared<T> {}
+// SAFETY: See above.
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// Write side of a pair created with `new_owned`.
+pub struct OwnedWriter<T> {
+    shared: Arc<Shared<T>>,
+    /// The slot owned by the writer.
+    write: u8,
+}
+
+impl<T> OwnedWriter<T> {
+    /// Write the next state into an unused buffer, and publish it.
+    ///
+    /// The buffer contains some older state, not necessarily the last
+    /// published one, so the closure has to overwrite all of it.
+    pub fn write_with(&mut self, write_op: impl FnOnce(&mut T)) {
+        write_op(self.buffer());
+        self.publish();
+    }
+
+    /// Publish `value` as the new state.
+    pub fn write(&mut self, value: T) {
+        self.write_with(|state| *state = value);
+    }
+
+    /// Publish the state in `state`, returning the unused buffer it replaced.
+    ///
+    /// This moves the box itself into the pair, without copying the state.
+    pub fn write_boxed(&mut self, state: Box<T>) -> Box<T> {
+        let unused = mem::replace(self.buffer_box(), state);
+        self.publish();
+        unused
+    }
+
+    /// Check whether the `OwnedReader` has been dropped.
+    pub fn is_closed(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+
+    fn buffer_box(&mut self) -> &mut Box<T> {
+        // SAFETY: The write slot is exclusively ours until it gets published.
+        unsafe { &mut *self.shared.slots[self.write as usize].get() }
+    }
+
+    fn buffer(&mut self) -> &mut T {
+        self.buffer_box()
+    }
+
+    fn publish(&mut self) {
+        let back = self.shared.back.swap(self.write | DIRTY, Ordering::AcqRel);
+        self.write = back & INDEX;
+    }
+}
+
+/// Read side of a pair created with `new_owned`.
+pub struct OwnedReader<T> {
+    shared: Arc<Shared<T>>,
+    /// The slot owned by the reader.
+    front: u8,
+}
+
+impl<T> OwnedReader<T> {
+    /// Get the newest published state.
+    pub fn read_newest(&mut self) -> &T {
+        self.read_newest_mut()
+    }
+
+    /// Get the newest published state for modification.
+    ///
+    /// Nothing else has access to it, and the changes are
+    /// overwritten once the writer reuses the buffer.
+    pub fn read_newest_mut(&mut self) -> &mut T {
+        if self.has_update() {
+            let back = self.shared.back.swap(self.front, Ordering::AcqRel);
+            self.front = back & INDEX;
+        }
+        // SAFETY: The front slot is exclusively ours until it gets swapped back.
+        unsafe { &mut *self.shared.slots[self.front as usize].get() }
+    }
+
+    /// Check whether a state has been published
+    /// that `read_newest` has not returned yet.
+    pub fn has_update(&self) -> bool {
+        self.shared.back.load(Ordering::Relaxed) & DIRTY != 0
+    }
+
+    /// Check whether the `OwnedWriter` has been dropped.
+    pub fn is_disconnected(&self) -> bool {
+        Arc::strong_count(&self.shared) == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::{Cell, RefCell};
+    use std::rc::Rc;
+
+    use super::{new_owned, OwnedReader, OwnedWriter};
+
+    fn assert_send<T: Send>() {}
+
+    #[test]
+    fn test_only_send_required() {
+        assert_send::<OwnedWriter<Cell<u32>>>();
+        assert_send::<OwnedReader<Cell<u32>>>();
+        assert_send::<OwnedWriter<RefCell<Vec<u32>>>>();
+        assert_send::<OwnedReader<RefCell<Vec<u32>>>>();
+    }
+
+    #[test]
+    fn test_boxes_are_exchanged() {
+        let (mut w, mut r) = new_owned(Rc::new(0));
+        // Non-`Send` states still work within a thread.
+        let old = w.write_boxed(Box::new(Rc::new(1)));
+        assert_eq!(*old, Rc::new(0));
+        assert!(r.has_update());
+        assert_eq!(**r.read_newest(), 1);
+        *r.read_newest_mut() = Rc::new(5);
+        assert_eq!(**r.read_newest(), 5);
+        drop(w);
+        assert!(r.is_disconnected());
+    }
+
+    #[test]
+    fn test_threads() {
+        let (mut w, mut r) = new_owned(Cell::new([0u64; 8]));
+        let writer = std::thread::spawn(move || {
+            for i in 1..=100_000 {
+                w.write_with(|state| state.set([i; 8]));
+            }
+        });
+        let mut last = 0;
+        while last < 100_000 {
+            let state = r.read_newest().get();
+            assert!(state.iter().all(|v| *v == state[0]));
+            assert!(state[0] >= last);
+            last = state[0];
+        }
+        writer.join().unwrap();
+    }
+}
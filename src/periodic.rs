@@ -0,0 +1,228 @@
+use std::any::Any;
+use std::panic;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::sync::{Arc, Condvar, Mutex};
+use crate::Writer;
+
+struct Control {
+    period: Duration,
+    stop: bool,
+}
+
+struct Shared {
+    control: Mutex<Control>,
+    changed: Condvar,
+}
+
+/// Spawn a thread that writes a new state with `f` every `period`.
+///
+/// Writes are scheduled relative to the previous scheduled write, not to
+/// when it finished, so the time `f` takes does not add up to a drift.
+/// If a write takes longer than a whole period, the ones it overran are
+/// skipped rather than made up in a burst. The first write happens one
+/// period after the thread starts.
+///
+/// The thread ends once the `Reader` is dropped, or the returned handle
+/// stops it. A panic in `f` ends it too, and gets reported by
+/// `PeriodicHandle::join`.
+///
+/// # Example
+/// ```
+/// use std::time::Duration;
+///
+/// let (writer, mut reader) = simple_triple_buffer::new_clone(0u32);
+/// let ticker = simple_triple_buffer::spawn_periodic_writer(
+///     writer,
+///     Duration::from_millis(1),
+///     |old, new| *new = *old + 1,
+/// );
+/// while *reader.read_newest() < 3 {
+///     std::thread::yield_now();
+/// }
+/// ticker.stop().unwrap();
+/// ````
+pub fn spawn_periodic_writer<T, F>(
+    mut writer: Writer<T>,
+    period: Duration,
+    mut f: F,
+) -> PeriodicHandle
+where
+    T: Send + Sync + 'static,
+    F: FnMut(&T, &mut T) + Send + 'static,
+{
+    let shared = Arc::new(Shared {
+        control: Mutex::new(Control {
+            period,
+            stop: false,
+        }),
+        changed: Condvar::new(),
+    });
+    let thread = {
+        let shared = shared.clone();
+        thread::spawn(move || {
+            let mut last = Instant::now();
+            loop {
+                let mut control = shared.control.lock();
+                let due = loop {
+                    if control.stop {
+                        return;
+                    }
+                    // Read again after every wakeup, as `set_period` may change it.
+                    let due = last + control.period;
+                    let now = Instant::now();
+                    if now >= due {
+                        break due;
+                    }
+                    control = shared.changed.wait_timeout(control, due - now);
+                };
+                let period = control.period;
+                drop(control);
+                if writer.is_closed() {
+                    return;
+                }
+                writer.write_new(&mut f);
+                last = if due.elapsed() < period {
+                    due
+                } else {
+                    Instant::now()
+                };
+            }
+        })
+    };
+    PeriodicHandle {
+        shared,
+        thread: Some(thread),
+    }
+}
+
+/// Handle to the thread of `spawn_periodic_writer`,
+/// which stops it when dropped.
+pub struct PeriodicHandle {
+    shared: Arc<Shared>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PeriodicHandle {
+    /// Change the period, starting with the next write.
+    ///
+    /// The next write is due one new period after the previous one.
+    pub fn set_period(&self, period: Duration) {
+        self.shared.control.lock().period = period;
+        self.shared.changed.notify_one();
+    }
+
+    /// Check whether the thread has ended,
+    /// because the reader was dropped or `f` panicked.
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Stop the thread, interrupting its wait for the next write,
+    /// and wait for it to end.
+    ///
+    /// Returns the payload of the panic if `f` panicked.
+    pub fn stop(mut self) -> Result<(), Box<dyn Any + Send>> {
+        self.shared.control.lock().stop = true;
+        self.shared.changed.notify_one();
+        self.join_thread()
+    }
+
+    /// Wait for the thread to end by itself, because the reader
+    /// was dropped or `f` panicked.
+    ///
+    /// Returns the payload of the panic if `f` panicked.
+    pub fn join(mut self) -> Result<(), Box<dyn Any + Send>> {
+        self.join_thread()
+    }
+
+    fn join_thread(&mut self) -> Result<(), Box<dyn Any + Send>> {
+        self.thread.take().map_or(Ok(()), JoinHandle::join)
+    }
+}
+
+impl Drop for PeriodicHandle {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.shared.control.lock().stop = true;
+            self.shared.changed.notify_one();
+            if let Err(panic) = self.join_thread() {
+                if !thread::panicking() {
+                    panic::resume_unwind(panic);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::spawn_periodic_writer;
+    use crate::new_clone;
+
+    #[test]
+    fn test_schedule_does_not_drift() {
+        let (w, mut r) = new_clone(0u32);
+        let start = Instant::now();
+        let handle = spawn_periodic_writer(w, Duration::from_millis(10), |old, new| {
+            // Takes half of the period every time.
+            std::thread::sleep(Duration::from_millis(5));
+            *new = *old + 1;
+        });
+        while *r.read_newest() < 20 {
+            std::thread::yield_now();
+        }
+        // Sleeping a period after every write would take 300ms.
+        assert!(
+            start.elapsed() < Duration::from_millis(260),
+            "{:?}",
+            start.elapsed()
+        );
+        assert!(start.elapsed() >= Duration::from_millis(200));
+        handle.stop().unwrap();
+    }
+
+    #[test]
+    fn test_stop_interrupts_wait() {
+        let (w, r) = new_clone(0u32);
+        let handle = spawn_periodic_writer(w, Duration::from_secs(3600), |_, _| {});
+        let start = Instant::now();
+        handle.stop().unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(r.is_disconnected());
+    }
+
+    #[test]
+    fn test_set_period() {
+        let (w, mut r) = new_clone(0u32);
+        let handle =
+            spawn_periodic_writer(w, Duration::from_secs(3600), |old, new| *new = *old + 1);
+        handle.set_period(Duration::from_millis(1));
+        while *r.read_newest() < 5 {
+            std::thread::yield_now();
+        }
+        assert!(!handle.is_finished());
+        drop(handle);
+        assert!(r.is_disconnected());
+    }
+
+    #[test]
+    fn test_reader_gone_and_panics() {
+        let (w, r) = new_clone(0u32);
+        let handle = spawn_periodic_writer(w, Duration::from_millis(1), |_, _| {});
+        drop(r);
+        assert!(handle.join().is_ok());
+
+        let (w, _r) = new_clone(0u32);
+        let handle = spawn_periodic_writer(w, Duration::from_millis(1), |old, _| {
+            if *old == 0 {
+                panic!("tick failed");
+            }
+        });
+        let panic = handle.join().unwrap_err();
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"tick failed"));
+    }
+}
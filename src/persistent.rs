@@ -0,0 +1,116 @@
+//! Buffer pairs of persistent collections from the `im` crate.
+//!
+//! Cloning an `im` collection is O(1): the clone shares all of its
+//! nodes with the original, and only copies the nodes along the path
+//! to an entry once either of them changes it. So pairs created with
+//! `new` create their buffers in constant time, however large the
+//! state is, and `Writer::update_persistent` starts every state from
+//! a clone of the previous one instead of copying it over.
+//!
+//! That is not free, though. Every change to a shared collection
+//! copies the nodes on its path, and lookups walk a tree instead of
+//! a flat table, so each operation costs several times what it does
+//! on a `std` collection. This pays off for large states that change
+//! a little with every publish, where copying the whole state into
+//! the buffer dominates, but not for small ones, or ones that get
+//! rewritten entirely anyway. `cargo bench --features im --bench
+//! persistent` compares both on a map of 100k entries: changing a
+//! single entry per publish is around 50 times faster than with a
+//! `std::collections::HashMap`, around 100 changes break even, and
+//! 1000 changes take about ten times as long.
+//!
+//! Only available with the `im` feature.
+//!
+//! # Example
+//! ```
+//! let (mut writer, mut reader) =
+//!     simple_triple_buffer::persistent::new(im::HashMap::<u32, &str>::new());
+//! writer.update_persistent(|map| {
+//!     map.insert(1, "one");
+//! });
+//! let old = reader.read_newest().clone();
+//!
+//! writer.update_persistent(|map| {
+//!     map.insert(2, "two");
+//! });
+//! assert_eq!(reader.read_newest().len(), 2);
+//! // Still sharing most of its nodes with the new state.
+//! assert_eq!(old.len(), 1);
+//! ````
+
+use core::hash::{BuildHasher, Hash};
+
+use crate::{new_with, PublishReceipt, Reader, Writer};
+
+/// Collections that clone in O(1) by sharing their structure,
+/// see the module documentation.
+pub trait Persistent: Clone {}
+
+impl<K: Hash + Eq + Clone, V: Clone, S: BuildHasher> Persistent for im::HashMap<K, V, S> {}
+impl<A: Hash + Eq + Clone, S: BuildHasher> Persistent for im::HashSet<A, S> {}
+impl<K: Ord + Clone, V: Clone> Persistent for im::OrdMap<K, V> {}
+impl<A: Ord + Clone> Persistent for im::OrdSet<A> {}
+impl<A: Clone> Persistent for im::Vector<A> {}
+
+/// Create a new buffer pair whose buffers are clones of the
+/// persistent collection `init`, sharing its structure.
+pub fn new<T: Persistent + 'static>(init: T) -> (Writer<T>, Reader<T>) {
+    new_with(init, T::clone)
+}
+
+impl<T: Persistent> Writer<T> {
+    /// Update a clone of the previous state and publish it.
+    ///
+    /// Unlike `write_update`, this never copies the previous state into
+    /// the buffer, which for a persistent collection means dropping the
+    /// nodes it held first. The clone starts out sharing everything with
+    /// the previous state, `update_op` only copies the nodes it changes,
+    /// and the stale contents of the buffer get dropped after publishing.
+    ///
+    /// # Panics
+    /// Panics if the pair was created without a way to create
+    /// new buffers, and all existing ones are in use.
+    pub fn update_persistent(&mut self, update_op: impl FnOnce(&mut T)) -> PublishReceipt {
+        let created = self.created;
+        let mut next = T::clone(&self.prev_buf);
+        update_op(&mut next);
+        let stale = self.swap(next);
+        let receipt = self.receipt(created);
+        drop(stale);
+        receipt
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::new;
+
+    #[test]
+    fn test_states_stay_apart() {
+        let (mut w, mut r) = new(im::HashMap::new());
+        let mut states = Vec::new();
+        for i in 1..100u32 {
+            w.update_persistent(|map| {
+                map.insert(i, i);
+                map.remove(&(i / 2));
+            });
+            states.push((i, r.read_newest().clone()));
+        }
+        // Every state read is still the one that got published.
+        for (i, state) in &states {
+            assert!(state.contains_key(i));
+            assert!(!state.contains_key(&(i / 2)));
+            assert_eq!(state.len() as u32, i - i / 2);
+        }
+        // Buffers got reused, instead of every publish creating one.
+        assert!(w.debug_state().created <= 4);
+    }
+
+    #[test]
+    fn test_vectors() {
+        let (mut w, mut r) = new(im::vector![1, 2, 3]);
+        w.update_persistent(|v| v.push_back(4));
+        w.write_update(|v| v[0] = 0);
+        assert_eq!(*r.read_newest(), im::vector![0, 2, 3, 4]);
+    }
+}
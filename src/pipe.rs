@@ -0,0 +1,226 @@
+use std::any::Any;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::{ReadError, Reader, Writer};
+
+/// How long a pipe waits for a new state before checking whether it
+/// should stop, which bounds how long `PipeHandle::stop` takes.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Throughput counters of a pipe.
+#[derive(Default)]
+struct Counters {
+    received: AtomicU64,
+    published: AtomicU64,
+    stop: AtomicBool,
+}
+
+/// Spawn a thread that transforms every new state of `src`
+/// with `f`, and publishes the result with `dst`.
+///
+/// The thread waits for new states with `Reader::read_blocking`, and
+/// `f` writes into an unused buffer of `dst`, like with `write_new`.
+/// If `src` publishes faster than the pipe keeps up, it skips states
+/// like any other reader would. The initial state of `src` is not
+/// transformed, so `dst` starts with its own.
+///
+/// Disconnection propagates in both directions: once the writer of
+/// `src` is gone, the pipe publishes its last state and drops `dst`,
+/// so its readers see the writer go too. Once the readers of `dst`
+/// are gone, the pipe stops reading and drops `src`.
+///
+/// # Example
+/// ```
+/// let (mut capture, frames) = simple_triple_buffer::new_clone(vec![0u8; 4]);
+/// let (filtered, mut display) = simple_triple_buffer::new_clone(0u32);
+///
+/// let pipe = simple_triple_buffer::pipe(frames, filtered, |frame, sum| {
+///     *sum = frame.iter().map(|&v| u32::from(v)).sum();
+/// });
+/// capture.write_new(|_, frame| *frame = vec![1, 2, 3, 4]);
+/// drop(capture);
+///
+/// assert_eq!(display.read_blocking(), Ok(&10));
+/// pipe.join().unwrap();
+/// ````
+pub fn pipe<A, B, F>(src: Reader<A>, dst: Writer<B>, f: F) -> PipeHandle
+where
+    A: Send + Sync + 'static,
+    B: Send + Sync + 'static,
+    F: FnMut(&A, &mut B) + Send + 'static,
+{
+    let counters = Arc::new(Counters::default());
+    let thread = {
+        let counters = counters.clone();
+        thread::spawn(move || run(src, dst, f, &counters))
+    };
+    PipeHandle {
+        counters,
+        thread: Some(thread),
+    }
+}
+
+/// Run a pipe on the current thread, see `pipe`.
+///
+/// Returns the number of states received and published
+/// once one of the pairs has been disconnected.
+pub fn pipe_inline<A, B>(src: Reader<A>, dst: Writer<B>, f: impl FnMut(&A, &mut B)) -> (u64, u64) {
+    let counters = Counters::default();
+    run(src, dst, f, &counters);
+    (
+        counters.received.into_inner(),
+        counters.published.into_inner(),
+    )
+}
+
+fn run<A, B>(
+    mut src: Reader<A>,
+    mut dst: Writer<B>,
+    mut f: impl FnMut(&A, &mut B),
+    counters: &Counters,
+) {
+    while !counters.stop.load(Ordering::Relaxed) && !dst.is_closed() {
+        let state = match src.read_timeout(POLL_INTERVAL) {
+            Ok(state) => state,
+            Err(ReadError::Timeout) => continue,
+            Err(_) => return,
+        };
+        counters.received.fetch_add(1, Ordering::Relaxed);
+        // Without a buffer to write into, the state is skipped,
+        // like the ones that were published in the meantime.
+        if dst.try_write_new(|_, new| f(state, new)).is_ok() {
+            counters.published.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Handle to the thread of `pipe`, which stops it when dropped.
+pub struct PipeHandle {
+    counters: Arc<Counters>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PipeHandle {
+    /// Get the number of new states picked up from the source so far.
+    pub fn received(&self) -> u64 {
+        self.counters.received.load(Ordering::Relaxed)
+    }
+
+    /// Get the number of transformed states published so far.
+    ///
+    /// This only falls behind `received` if the destination
+    /// ran out of buffers to write into.
+    pub fn published(&self) -> u64 {
+        self.counters.published.load(Ordering::Relaxed)
+    }
+
+    /// Check whether the thread has ended, because one
+    /// of the pairs was disconnected or `f` panicked.
+    pub fn is_finished(&self) -> bool {
+        self.thread.as_ref().is_none_or(JoinHandle::is_finished)
+    }
+
+    /// Stop the thread, and wait for it to end.
+    ///
+    /// Returns the payload of the panic if `f` panicked.
+    pub fn stop(mut self) -> Result<(), Box<dyn Any + Send>> {
+        self.counters.stop.store(true, Ordering::Relaxed);
+        self.join_thread()
+    }
+
+    /// Wait for the thread to end by itself,
+    /// because one of the pairs was disconnected.
+    ///
+    /// Returns the payload of the panic if `f` panicked.
+    pub fn join(mut self) -> Result<(), Box<dyn Any + Send>> {
+        self.join_thread()
+    }
+
+    fn join_thread(&mut self) -> Result<(), Box<dyn Any + Send>> {
+        self.thread.take().map_or(Ok(()), JoinHandle::join)
+    }
+}
+
+impl Drop for PipeHandle {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.counters.stop.store(true, Ordering::Relaxed);
+            if let Err(panic) = self.join_thread() {
+                if !thread::panicking() {
+                    std::panic::resume_unwind(panic);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::{pipe, pipe_inline};
+    use crate::new_clone;
+
+    #[test]
+    fn test_chain_propagates_disconnection() {
+        let (mut capture, raw) = new_clone(0u32);
+        let (filter_w, filter_r) = new_clone(0u64);
+        let (display_w, mut display) = new_clone(String::new());
+        let first = pipe(raw, filter_w, |raw, doubled| *doubled = u64::from(*raw) * 2);
+        let second = pipe(filter_r, display_w, |doubled, text| {
+            text.clear();
+            text.push_str(&doubled.to_string());
+        });
+        for i in 1..=1000 {
+            capture.write_new(|_, new| *new = i);
+        }
+        drop(capture);
+
+        let mut last = String::new();
+        while let Ok(text) = display.read_blocking() {
+            last.clone_from(text);
+        }
+        // The last state always makes it through.
+        assert_eq!(last, "2000");
+        assert!(first.received() >= 1 && first.received() <= 1000);
+        assert_eq!(first.received(), first.published());
+        first.join().unwrap();
+        second.join().unwrap();
+    }
+
+    #[test]
+    fn test_stops_when_destination_closes() {
+        let (_src_w, src_r) = new_clone(0u32);
+        let (dst_w, dst_r) = new_clone(0u32);
+        let handle = pipe(src_r, dst_w, |a, b| *b = *a);
+        drop(dst_r);
+        handle.join().unwrap();
+
+        let (mut src_w, src_r) = new_clone(0u32);
+        let (dst_w, dst_r) = new_clone(0u32);
+        let handle = pipe(src_r, dst_w, |a, b| *b = *a);
+        handle.stop().unwrap();
+        assert!(src_w.is_closed());
+        assert!(dst_r.is_disconnected());
+        src_w.write_new(|_, new| *new = 1);
+    }
+
+    #[test]
+    fn test_inline_and_panics() {
+        let (mut src_w, src_r) = new_clone(0u32);
+        let (dst_w, mut dst_r) = new_clone(0u32);
+        src_w.write_new(|_, new| *new = 7);
+        drop(src_w);
+        assert_eq!(pipe_inline(src_r, dst_w, |a, b| *b = *a + 1), (1, 1));
+        assert_eq!(*dst_r.read_newest(), 8);
+
+        let (mut src_w, src_r) = new_clone(0u32);
+        let (dst_w, _dst_r) = new_clone(0u32);
+        let handle = pipe(src_r, dst_w, |_, _| panic!("bad frame"));
+        src_w.write_new(|_, new| *new = 1);
+        let panic = handle.join().unwrap_err();
+        assert_eq!(panic.downcast_ref::<&str>(), Some(&"bad frame"));
+        assert!(src_w.is_closed());
+    }
+}
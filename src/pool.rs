@@ -0,0 +1,176 @@
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+
+use crate::sync::Mutex;
+use crate::Buf;
+
+/// A pool of unused buffers shared between multiple buffer pairs.
+///
+/// Buffers that become unused in any pair using the pool are put
+/// into it, and any writer using the pool may take them to write
+/// its next state into. Cloning the pool creates another handle
+/// to the same pool.
+///
+/// See `new_with_pool`.
+pub struct BufferPool<T> {
+    inner: Arc<Mutex<PoolInner<T>>>,
+}
+
+struct PoolInner<T> {
+    bufs: Vec<Buf<T>>,
+    capacity: usize,
+    stats: PoolStats,
+}
+
+/// Statistics about a `BufferPool`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Number of buffers currently in the pool.
+    pub pooled: usize,
+    /// Number of buffers that were put into the pool.
+    pub returned: u64,
+    /// Number of buffers that were taken out of the pool for reuse.
+    pub reused: u64,
+    /// Number of buffers that were dropped because the pool was full.
+    pub discarded: u64,
+}
+
+impl<T> BufferPool<T> {
+    /// Create a pool that holds on to at most `capacity` unused buffers.
+    ///
+    /// Buffers returned while the pool is full are dropped.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(PoolInner {
+                bufs: Vec::with_capacity(capacity),
+                capacity,
+                stats: PoolStats::default(),
+            })),
+        }
+    }
+
+    /// Get the maximum number of unused buffers the pool holds on to.
+    pub fn capacity(&self) -> usize {
+        self.inner.lock().capacity
+    }
+
+    /// Get statistics about the pool.
+    pub fn stats(&self) -> PoolStats {
+        let inner = self.inner.lock();
+        PoolStats {
+            pooled: inner.bufs.len(),
+            ..inner.stats
+        }
+    }
+
+    pub(crate) fn put(&self, buf: Buf<T>) {
+        let mut inner = self.inner.lock();
+        inner.stats.returned += 1;
+        if inner.bufs.len() < inner.capacity {
+            inner.bufs.push(buf);
+        } else {
+            inner.stats.discarded += 1;
+            // Drop the buffer outside of the lock.
+            drop(inner);
+            drop(buf);
+        }
+    }
+
+    /// Call `f` with every buffer currently in the pool.
+    pub(crate) fn for_each(&self, f: impl FnMut(&Buf<T>)) {
+        self.inner.lock().bufs.iter().for_each(f);
+    }
+
+    pub(crate) fn take(&self) -> Option<Buf<T>> {
+        let mut inner = self.inner.lock();
+        let buf = inner.bufs.pop()?;
+        inner.stats.reused += 1;
+        Some(buf)
+    }
+}
+
+impl<T> Clone for BufferPool<T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::BufferPool;
+    use crate::{new_with_pool, TripleBufferBuilder};
+
+    #[test]
+    fn test_pairs_share_buffers() {
+        let count = Arc::new(Mutex::new(0));
+        let pool = BufferPool::new(8);
+        let mut pairs: Vec<_> = (0..10)
+            .map(|_| {
+                let c = count.clone();
+                TripleBufferBuilder::new(0u32)
+                    .clone_with(move |v| {
+                        *c.lock().unwrap() += 1;
+                        *v
+                    })
+                    .pool(pool.clone())
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+
+        // Only one pair is active at a time, so all of them
+        // can keep drawing from the same few buffers.
+        for round in 1..20 {
+            for (i, (w, r)) in pairs.iter_mut().enumerate() {
+                let value = round * 100 + i as u32;
+                w.write_new(|_, new| *new = value);
+                assert_eq!(*r.read_newest(), value);
+            }
+        }
+        assert!(*count.lock().unwrap() <= 2);
+        assert!(pool.stats().reused > 0);
+    }
+
+    #[test]
+    fn test_capacity_limit() {
+        let pool = BufferPool::new(1);
+        let (mut w1, mut r1) = new_with_pool(1, pool.clone());
+        let (mut w2, mut r2) = new_with_pool(2, pool.clone());
+        w1.write_new(|_, new| *new = 10);
+        w2.write_new(|_, new| *new = 20);
+        r1.read_newest();
+        r2.read_newest();
+
+        let stats = pool.stats();
+        assert_eq!(stats.pooled, 1);
+        assert_eq!(stats.returned, 2);
+        assert_eq!(stats.discarded, 1);
+    }
+
+    #[test]
+    fn test_pool_across_threads() {
+        let pool = BufferPool::new(16);
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let (mut w, mut r) = new_with_pool(vec![t; 64], pool.clone());
+                std::thread::spawn(move || {
+                    for i in 0..500 {
+                        w.write_new(|_, new| {
+                            new.clear();
+                            new.resize(64, t * 1000 + i);
+                        });
+                        let state = r.read_newest();
+                        assert!(state.iter().all(|v| *v == t * 1000 + i));
+                    }
+                })
+            })
+            .collect();
+        for t in threads {
+            t.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,143 @@
+//! The times of the last few publishes, kept by the writer for itself.
+
+use alloc::boxed::Box;
+use std::time::{Duration, Instant};
+
+use crate::Writer;
+
+/// The number of publish times kept.
+const TIMES: usize = 64;
+
+/// A ring of the times of the last `TIMES` publishes.
+///
+/// Only the writer touches it, so recording a publish is a plain store.
+pub(crate) struct PublishTimes {
+    times: [Option<Instant>; TIMES],
+    /// The index the next publish time gets stored at.
+    next: usize,
+}
+
+impl PublishTimes {
+    pub(crate) fn new() -> Box<Self> {
+        Box::new(Self {
+            times: [None; TIMES],
+            next: 0,
+        })
+    }
+
+    pub(crate) fn record(&mut self, time: Instant) {
+        self.times[self.next] = Some(time);
+        self.next = (self.next + 1) % TIMES;
+    }
+
+    fn last(&self) -> Option<Instant> {
+        self.times[(self.next + TIMES - 1) % TIMES]
+    }
+
+    /// Iterate over the recorded times, newest first.
+    fn newest_first(&self) -> impl Iterator<Item = Instant> + '_ {
+        (1..=TIMES).map_while(move |back| self.times[(self.next + TIMES - back) % TIMES])
+    }
+}
+
+impl<T> Writer<T> {
+    /// Get the time of the last publish, if there was one.
+    ///
+    /// Only available with the `stats` feature.
+    pub fn last_publish_instant(&self) -> Option<Instant> {
+        self.publish_times.last()
+    }
+
+    /// Get the number of publishes per second over the last `window`.
+    ///
+    /// The writer keeps the times of its last 64 publishes. If all of them
+    /// fall into `window`, the rate is estimated from the time they span
+    /// instead, so it stays meaningful for windows longer than that.
+    ///
+    /// Only available with the `stats` feature.
+    ///
+    /// # Example
+    /// ```
+    /// use std::time::Duration;
+    ///
+    /// let (mut writer, _reader) = simple_triple_buffer::new_clone(0);
+    /// assert_eq!(writer.publish_rate(Duration::from_secs(1)), 0.0);
+    ///
+    /// for i in 1..=10 {
+    ///     writer.write_new(|_, new| *new = i);
+    /// }
+    /// assert_eq!(writer.publish_rate(Duration::from_secs(1)), 10.0);
+    /// ````
+    pub fn publish_rate(&self, window: Duration) -> f32 {
+        let now = Instant::now();
+        let mut times = self.publish_times.newest_first();
+        let Some(newest) = times.next() else {
+            return 0.0;
+        };
+        if now.duration_since(newest) > window {
+            return 0.0;
+        }
+        let mut count = 1;
+        let mut oldest = newest;
+        for time in times {
+            if now.duration_since(time) > window {
+                return count as f32 / window.as_secs_f32();
+            }
+            count += 1;
+            oldest = time;
+        }
+        let span = newest.duration_since(oldest);
+        if count < TIMES || span.is_zero() {
+            // Not even a full ring of publishes in the window.
+            return count as f32 / window.as_secs_f32();
+        }
+        (count - 1) as f32 / span.as_secs_f32()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::{PublishTimes, TIMES};
+    use crate::new_clone;
+
+    #[test]
+    fn test_ring_keeps_newest() {
+        let mut times = PublishTimes::new();
+        let start = Instant::now();
+        for i in 0..100 {
+            times.record(start + Duration::from_millis(i));
+        }
+        assert_eq!(times.last(), Some(start + Duration::from_millis(99)));
+        let kept: Vec<_> = times.newest_first().collect();
+        assert_eq!(kept.len(), TIMES);
+        assert_eq!(
+            kept[TIMES - 1],
+            start + Duration::from_millis(100 - TIMES as u64)
+        );
+    }
+
+    #[test]
+    fn test_rate() {
+        let (mut w, r) = new_clone(0u32);
+        drop(r);
+        assert_eq!(w.last_publish_instant(), None);
+        let before = Instant::now();
+        for i in 0..5 {
+            w.write_new(|_, new| *new = i);
+        }
+        assert!(w.last_publish_instant().unwrap() >= before);
+        assert_eq!(w.publish_rate(Duration::from_millis(500)), 10.0);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(w.publish_rate(Duration::from_millis(10)), 0.0);
+
+        // More publishes than the ring holds get estimated from their span.
+        for i in 0..1000 {
+            w.write_new(|_, new| *new = i);
+            std::thread::sleep(Duration::from_micros(100));
+        }
+        let rate = w.publish_rate(Duration::from_secs(60));
+        assert!(rate > 100.0 && rate < 10_000.0, "{}", rate);
+    }
+}
@@ -0,0 +1,233 @@
+//! A file descriptor that is readable while a reader has a state to pick
+//! up, for event loops like `mio` or plain `epoll`.
+//!
+//! On Linux and Android it is an `eventfd`, elsewhere on Unix the read end
+//! of a socket pair the writer writes a byte into. Either way, the writer
+//! only writes to it when it is not readable already, and `read_newest`
+//! drains it, so a fast writer costs at most one system call per read.
+
+use std::io::{self, Read, Write};
+use std::os::unix::io::{AsFd, BorrowedFd};
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::{Reader, Writer};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+struct Signal(std::fs::File);
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+impl Signal {
+    fn new() -> io::Result<Self> {
+        use std::os::unix::io::FromRawFd;
+
+        // SAFETY: Plain system call, the result gets checked below.
+        let fd = unsafe { libc::eventfd(0, libc::EFD_CLOEXEC | libc::EFD_NONBLOCK) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: The descriptor was just created, and nothing else owns it.
+        Ok(Self(unsafe { std::fs::File::from_raw_fd(fd) }))
+    }
+
+    fn fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+
+    fn raise(&self) {
+        // Can only fail if the counter would overflow, which
+        // nothing but the writer ever adding 1 prevents.
+        let _ = (&self.0).write(&1u64.to_ne_bytes());
+    }
+
+    fn drain(&self) {
+        let _ = (&self.0).read(&mut [0; 8]);
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+struct Signal {
+    read: UnixStream,
+    write: UnixStream,
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+impl Signal {
+    fn new() -> io::Result<Self> {
+        let (read, write) = UnixStream::pair()?;
+        read.set_nonblocking(true)?;
+        write.set_nonblocking(true)?;
+        Ok(Self { read, write })
+    }
+
+    fn fd(&self) -> BorrowedFd<'_> {
+        self.read.as_fd()
+    }
+
+    fn raise(&self) {
+        let _ = (&self.write).write(&[1]);
+    }
+
+    fn drain(&self) {
+        let mut buf = [0; 16];
+        while matches!((&self.read).read(&mut buf), Ok(n) if n > 0) {}
+    }
+}
+
+/// The readiness descriptor of a pair, created on first use.
+pub(crate) struct Readiness {
+    /// Whether the descriptor is readable, or about to be.
+    raised: AtomicBool,
+    signal: Signal,
+}
+
+impl Readiness {
+    /// Make the descriptor readable, after publishing a state.
+    pub(crate) fn raise(&self) {
+        if !self.raised.swap(true, Ordering::SeqCst) {
+            self.signal.raise();
+        }
+    }
+
+    /// Make the descriptor no longer readable,
+    /// before picking up the newest state.
+    ///
+    /// Draining first means that a raise in between leaves the descriptor
+    /// readable for nothing, rather than `raised` set for nothing, which
+    /// would keep the next publish from raising it. Reading `raised`
+    /// back in the swap makes the state published before raising it
+    /// visible to the reader.
+    pub(crate) fn clear(&self) {
+        if self.raised.load(Ordering::Relaxed) {
+            self.signal.drain();
+            self.raised.swap(false, Ordering::SeqCst);
+        }
+    }
+}
+
+impl<T> Writer<T> {
+    /// Make the readiness descriptor of the pair readable, if it has one.
+    pub(crate) fn raise_readiness(&self) {
+        if let Some(readiness) = self.read_update.shared.readiness.get() {
+            readiness.raise();
+        }
+    }
+}
+
+impl<T> Reader<T> {
+    /// Get a file descriptor that is readable while the writer
+    /// published a state that `read_newest` has not returned yet.
+    ///
+    /// Register it with an event loop to treat new states like any other
+    /// readable event. It is level-triggered: it stays readable until
+    /// the next `read_newest`, which clears it. It occasionally is readable
+    /// without a new state, if the writer published just as the state got
+    /// picked up, so handlers have to cope with `has_update` being false.
+    /// It also becomes readable once the writer gets dropped, see
+    /// `is_disconnected`.
+    ///
+    /// The descriptor belongs to the pair, and gets created on the first
+    /// call. With several readers, it gets cleared by the first one
+    /// picking up a state, so it only suits pairs with a single reader.
+    ///
+    /// Only available on Unix, with the `readiness` feature. Other
+    /// platforms can wait for new states with a thread of their own.
+    ///
+    /// # Example
+    /// ```
+    /// use std::os::unix::io::AsRawFd;
+    ///
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// let fd = reader.readiness_fd().unwrap().as_raw_fd();
+    /// // Register `fd` for readability with epoll or mio here.
+    ///
+    /// writer.write_new(|_, new| *new = 1);
+    /// // ... the event loop reports `fd` as readable ...
+    /// assert_eq!(*reader.read_newest(), 1);
+    /// # let _ = fd;
+    /// ````
+    pub fn readiness_fd(&self) -> io::Result<BorrowedFd<'_>> {
+        let shared = &self.read_update.shared;
+        if shared.readiness.get().is_none() {
+            let readiness = Readiness {
+                raised: AtomicBool::new(false),
+                signal: Signal::new()?,
+            };
+            // Another reader may have been first.
+            let _ = shared.readiness.set(readiness);
+            let readiness = shared.readiness.get().unwrap();
+            // States published before it existed count as well.
+            if self.has_update() || self.is_disconnected() {
+                readiness.raise();
+            }
+        }
+        Ok(shared.readiness.get().unwrap().signal.fd())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::io::{AsRawFd, RawFd};
+
+    use crate::new_clone;
+
+    fn readable(fd: RawFd) -> bool {
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        // SAFETY: `pollfd` is valid for the duration of the call.
+        let n = unsafe { libc::poll(&mut pollfd, 1, 0) };
+        assert!(n >= 0);
+        n == 1
+    }
+
+    #[test]
+    fn test_readable_while_update_pending() {
+        let (mut w, mut r) = new_clone(0);
+        w.write_new(|_, new| *new = 1);
+        let fd = r.readiness_fd().unwrap().as_raw_fd();
+        assert!(readable(fd));
+        r.read_newest();
+        assert!(!readable(fd));
+
+        for i in 2..5 {
+            w.write_new(|_, new| *new = i);
+            assert!(readable(fd));
+        }
+        // Still readable, no matter how often it got polled.
+        assert!(readable(fd));
+        assert_eq!(*r.read_newest(), 4);
+        assert!(!readable(fd));
+        r.read_newest();
+        assert!(!readable(fd));
+
+        drop(w);
+        assert!(readable(fd));
+    }
+
+    #[test]
+    fn test_wakes_up_poller_thread() {
+        let (mut w, mut r) = new_clone(0u32);
+        let fd = r.readiness_fd().unwrap().as_raw_fd();
+        let reader = std::thread::spawn(move || {
+            let mut seen = 0;
+            while seen < 1000 {
+                let mut pollfd = libc::pollfd {
+                    fd,
+                    events: libc::POLLIN,
+                    revents: 0,
+                };
+                // SAFETY: `pollfd` is valid for the duration of the call.
+                assert_eq!(unsafe { libc::poll(&mut pollfd, 1, 5000) }, 1);
+                seen = *r.read_newest();
+            }
+        });
+        for i in 1..=1000 {
+            w.write_new(|_, new| *new = i);
+        }
+        reader.join().unwrap();
+    }
+}
@@ -0,0 +1,153 @@
+//! Checking that a writer stops allocating once it reached its steady state.
+
+use alloc::boxed::Box;
+use core::fmt;
+
+use crate::Writer;
+
+/// Where a writer allocated after `Writer::warm_up`.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Allocation {
+    /// A new buffer got created, because all existing ones were in use.
+    NewBuffer,
+    /// The history kept with `Writer::keep_history` had to grow.
+    History,
+}
+
+impl fmt::Display for Allocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Allocation::NewBuffer => "created a new buffer",
+            Allocation::History => "grew the history",
+        })
+    }
+}
+
+/// Called with every allocation after warm-up, see `Writer::on_violation`.
+pub(crate) type Violation = Box<dyn FnMut(Allocation) + Send>;
+
+impl<T> Writer<T> {
+    /// Mark the end of the phase in which the writer may allocate.
+    ///
+    /// Once a pair has seen its usual traffic, it has all the buffers it
+    /// needs, and neither writing nor reading allocates anymore, which
+    /// real-time threads like audio callbacks rely on. From now on, every
+    /// allocation of the writer is a violation of that: builds with debug
+    /// assertions panic, naming the `Allocation`, unless a hook was
+    /// installed with `on_violation`, which gets called instead.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// for i in 0..10 {
+    ///     writer.write_new(|_, new| *new = i);
+    ///     reader.read_newest();
+    /// }
+    /// writer.warm_up();
+    ///
+    /// // Keeps working without allocating.
+    /// for i in 0..10 {
+    ///     writer.write_new(|_, new| *new = i);
+    ///     reader.read_newest();
+    /// }
+    /// ````
+    pub fn warm_up(&mut self) {
+        self.warmed_up = true;
+    }
+
+    /// Call `hook` for every allocation after `warm_up`,
+    /// instead of panicking in debug builds.
+    ///
+    /// This also works in release builds, for example to count
+    /// violations in production.
+    pub fn on_violation(&mut self, hook: impl FnMut(Allocation) + Send + 'static) {
+        self.on_violation = Some(Box::new(hook));
+    }
+
+    /// Note that the writer allocated at `site`.
+    pub(crate) fn allocated(&mut self, site: Allocation) {
+        if !self.warmed_up {
+            return;
+        }
+        match &mut self.on_violation {
+            Some(hook) => hook(site),
+            None if cfg!(debug_assertions) => self.fail(format_args!("{} after warm-up", site)),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::Allocation;
+    use crate::{new_clone, TripleBufferBuilder};
+
+    fn warmed_up() -> (crate::Writer<u32>, crate::Reader<u32>) {
+        let (mut w, mut r) = new_clone(0);
+        for i in 0..10 {
+            w.write_new(|_, new| *new = i);
+            r.read_newest();
+        }
+        w.warm_up();
+        (w, r)
+    }
+
+    #[test]
+    #[cfg_attr(
+        debug_assertions,
+        should_panic(expected = "created a new buffer after warm-up")
+    )]
+    fn test_pinned_snapshots_violate() {
+        let (mut w, r) = warmed_up();
+        // Every clone pins the state it starts at.
+        let mut pinned = Vec::new();
+        for i in 0..4 {
+            pinned.push(r.clone());
+            w.write_new(|_, new| *new = i);
+        }
+    }
+
+    #[test]
+    fn test_violations_reach_hook() {
+        let violations = Arc::new(Mutex::new(Vec::new()));
+        let (mut w, mut r) = warmed_up();
+        let log = violations.clone();
+        w.on_violation(move |site| log.lock().unwrap().push(site));
+
+        for i in 0..100 {
+            w.write_new(|_, new| *new = i);
+            w.write_update(|state| *state += 1);
+            r.read_newest();
+        }
+        assert!(violations.lock().unwrap().is_empty());
+
+        let _pinned = r.clone();
+        w.write_new(|_, new| *new = 1);
+        w.write_new(|_, new| *new = 2);
+        w.keep_history(4);
+        for i in 0..4 {
+            w.write_new(|_, new| *new = i);
+        }
+        let violations = violations.lock().unwrap();
+        assert!(violations.contains(&Allocation::NewBuffer));
+        assert!(violations.contains(&Allocation::History));
+    }
+
+    #[test]
+    fn test_no_violations_before_warm_up() {
+        let (mut w, r) = TripleBufferBuilder::new(0)
+            .copy_buffers()
+            .label("audio")
+            .build()
+            .unwrap();
+        let _pinned: Vec<_> = (0..4)
+            .map(|i| {
+                w.write_new(|_, new| *new = i);
+                r.clone()
+            })
+            .collect();
+    }
+}
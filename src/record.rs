@@ -0,0 +1,238 @@
+//! Capturing the states a writer publishes, to replay them later.
+//!
+//! `Writer::record_to` clones every published state into a bounded queue,
+//! which a background thread drains into a `RecordSink`. Publishing never
+//! waits for the sink: if the queue is full, the state is dropped and
+//! counted in `Recorder::dropped`. A `Recording` collected this way can be
+//! fed to a fresh reader with `replay::spawn`.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::record::Recording;
+//!
+//! let (mut writer, _reader) = simple_triple_buffer::new_clone(0);
+//! let recorder = writer.record_to(Recording::new(), 64);
+//! for i in 1..=3 {
+//!     writer.write_new(|_, new| *new = i);
+//! }
+//! writer.stop_recording();
+//!
+//! let recording = recorder.finish();
+//! let states: Vec<_> = recording.iter().map(|r| (r.version, r.state)).collect();
+//! assert_eq!(states, [(0, 0), (1, 1), (2, 2), (3, 3)]);
+//! ````
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, TrySendError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::Writer;
+
+/// A published state, as captured by `Writer::record_to`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recorded<T> {
+    /// The number of states published since recording started.
+    /// The state current at that point is version 0.
+    pub version: u64,
+    /// When the state was published, relative to the start of the recording.
+    pub elapsed: Duration,
+    /// The published state.
+    pub state: T,
+}
+
+/// Where a recorder delivers the captured states.
+///
+/// It runs on a thread of its own, so it may block, for example
+/// to write the states to a file.
+pub trait RecordSink<T>: Send + 'static {
+    /// Store the next captured state.
+    fn record(&mut self, recorded: Recorded<T>);
+}
+
+impl<T: Send + 'static> RecordSink<T> for Vec<Recorded<T>> {
+    fn record(&mut self, recorded: Recorded<T>) {
+        self.push(recorded);
+    }
+}
+
+impl<T: Send + 'static> RecordSink<T> for mpsc::Sender<Recorded<T>> {
+    fn record(&mut self, recorded: Recorded<T>) {
+        // A receiver that went away is no longer interested.
+        drop(self.send(recorded));
+    }
+}
+
+/// A sequence of captured states, oldest first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recording<T> {
+    states: Vec<Recorded<T>>,
+}
+
+impl<T> Recording<T> {
+    /// Create an empty recording.
+    pub fn new() -> Self {
+        Self { states: Vec::new() }
+    }
+
+    /// Get the number of captured states.
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    /// Check whether no state has been captured.
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Iterate over the captured states, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &Recorded<T>> + '_ {
+        self.states.iter()
+    }
+
+    /// Add a state at the end, for recordings stored elsewhere.
+    ///
+    /// # Panics
+    /// Panics if it was published before the last state.
+    pub fn push(&mut self, recorded: Recorded<T>) {
+        if let Some(last) = self.states.last() {
+            assert!(
+                recorded.elapsed >= last.elapsed,
+                "recorded states out of order"
+            );
+        }
+        self.states.push(recorded);
+    }
+
+    pub(crate) fn into_states(self) -> Vec<Recorded<T>> {
+        self.states
+    }
+}
+
+impl<T> Default for Recording<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Send + 'static> RecordSink<T> for Recording<T> {
+    fn record(&mut self, recorded: Recorded<T>) {
+        self.push(recorded);
+    }
+}
+
+/// Called by the writer with every published state.
+pub(crate) type Hook<T> = Box<dyn FnMut(&T) + Send>;
+
+/// Handle to a running recording, see `Writer::record_to`.
+pub struct Recorder<S> {
+    dropped: Arc<AtomicU64>,
+    thread: JoinHandle<S>,
+}
+
+impl<S> Recorder<S> {
+    /// Get the number of states dropped because the queue was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Wait for all captured states to reach the sink, and return it.
+    ///
+    /// This blocks until the recording stops, with
+    /// `Writer::stop_recording` or by dropping the writer.
+    pub fn finish(self) -> S {
+        match self.thread.join() {
+            Ok(sink) => sink,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+impl<T: Clone + Send + 'static> Writer<T> {
+    /// Capture every state published from now on into `sink`,
+    /// starting with the last published one.
+    ///
+    /// Up to `capacity` states get queued for the sink, which runs on a
+    /// thread of its own. The writer never waits for it: states that do
+    /// not fit into the queue are dropped, and counted by the `Recorder`.
+    /// Starting another recording stops the previous one.
+    ///
+    /// Only available with the `record` feature.
+    pub fn record_to<S: RecordSink<T>>(&mut self, mut sink: S, capacity: usize) -> Recorder<S> {
+        let (tx, rx) = mpsc::sync_channel::<Recorded<T>>(capacity);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let start = Instant::now();
+        sink.record(Recorded {
+            version: 0,
+            elapsed: Duration::ZERO,
+            state: (**self.prev_buf).clone(),
+        });
+        let mut version = 0;
+        let counter = dropped.clone();
+        self.recorder = Some(Box::new(move |state: &T| {
+            version += 1;
+            let recorded = Recorded {
+                version,
+                elapsed: start.elapsed(),
+                state: state.clone(),
+            };
+            if let Err(TrySendError::Full(_)) = tx.try_send(recorded) {
+                counter.fetch_add(1, Ordering::Relaxed);
+            }
+        }));
+        let thread = thread::spawn(move || {
+            for recorded in rx {
+                sink.record(recorded);
+            }
+            sink
+        });
+        Recorder { dropped, thread }
+    }
+}
+
+impl<T> Writer<T> {
+    /// Stop capturing published states, see `record_to`.
+    ///
+    /// Only available with the `record` feature.
+    pub fn stop_recording(&mut self) {
+        self.recorder = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Recording;
+    use crate::new_clone;
+
+    #[test]
+    fn test_full_queue_drops_states() {
+        let (mut w, _r) = new_clone(0u64);
+        // A sink that blocks until the test is done publishing.
+        let (gate_tx, gate_rx) = std::sync::mpsc::channel::<()>();
+        struct Gated(std::sync::mpsc::Receiver<()>, Recording<u64>);
+        impl super::RecordSink<u64> for Gated {
+            fn record(&mut self, recorded: super::Recorded<u64>) {
+                if recorded.version == 1 {
+                    self.0.recv().unwrap_err();
+                }
+                self.1.push(recorded);
+            }
+        }
+        let recorder = w.record_to(Gated(gate_rx, Recording::new()), 4);
+        for i in 1..=100 {
+            w.write_new(|_, new| *new = i);
+        }
+        drop(w);
+        drop(gate_tx);
+        let dropped = recorder.dropped();
+        let recording = recorder.finish().1;
+        assert!(dropped >= 100 - 5, "{}", dropped);
+        assert_eq!(recording.len() as u64, 101 - dropped);
+        assert!(recording
+            .iter()
+            .zip(recording.iter().skip(1))
+            .all(|(a, b)| a.version < b.version));
+        assert!(recording.iter().all(|r| r.state == r.version));
+    }
+}
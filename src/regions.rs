@@ -0,0 +1,336 @@
+use alloc::boxed::Box;
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as HashMap;
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{Buf, Writer};
+
+/// Number of past publishes whose changed regions are remembered.
+///
+/// Buffers that fell further behind than this get fully resynced.
+const TRACKED_PUBLISHES: usize = 16;
+
+enum Dirty {
+    All,
+    Ranges(Vec<Range<usize>>),
+}
+
+/// Bookkeeping for `Writer::write_regions`.
+///
+/// Buffers are identified by address, which is stable for as long as
+/// the buffer is alive. Every buffer that leaves the writer without
+/// being published, or that gets freshly created, is forgotten,
+/// so a recorded generation always describes the actual contents.
+pub(crate) struct RegionLog {
+    generation: u64,
+    synced: HashMap<usize, u64>,
+    dirty: VecDeque<(u64, Dirty)>,
+    catch_up: Vec<Range<usize>>,
+}
+
+fn key<T>(buf: &Buf<T>) -> usize {
+    Buf::as_ptr(buf) as usize
+}
+
+impl RegionLog {
+    fn new<T>(prev_buf: &Buf<T>) -> Self {
+        let mut synced = HashMap::new();
+        synced.insert(key(prev_buf), 0);
+        Self {
+            generation: 0,
+            synced,
+            dirty: VecDeque::with_capacity(TRACKED_PUBLISHES),
+            catch_up: Vec::new(),
+        }
+    }
+
+    /// Record a publish of `buf`, which changed either
+    /// everything or just the given regions.
+    pub(crate) fn record<T>(&mut self, buf: &Buf<T>, ranges: Option<&[Range<usize>]>) {
+        let reused = if self.dirty.len() == TRACKED_PUBLISHES {
+            self.dirty.pop_front().map(|(_, dirty)| dirty)
+        } else {
+            None
+        };
+        let dirty = match ranges {
+            Some(ranges) => {
+                let mut v = match reused {
+                    Some(Dirty::Ranges(v)) => v,
+                    _ => Vec::new(),
+                };
+                v.clear();
+                v.extend_from_slice(ranges);
+                Dirty::Ranges(v)
+            }
+            None => Dirty::All,
+        };
+
+        self.generation += 1;
+        self.dirty.push_back((self.generation, dirty));
+        self.synced.insert(key(buf), self.generation);
+
+        // Buffers older than the remembered publishes can only
+        // be fully resynced, so their entries are useless.
+        let oldest = self.generation - self.dirty.len() as u64;
+        self.synced.retain(|_, gen| *gen >= oldest);
+    }
+
+    pub(crate) fn forget<T>(&mut self, buf: &Buf<T>) {
+        self.synced.remove(&key(buf));
+    }
+
+    /// Compute the merged regions `buf` is missing compared to the
+    /// newest publish, or `None` if it needs a full resync.
+    fn catch_up<T>(&mut self, buf: &Buf<T>) -> Option<&[Range<usize>]> {
+        let synced = self.synced.remove(&key(buf))?;
+        let oldest = self.generation - self.dirty.len() as u64;
+        if synced < oldest {
+            return None;
+        }
+
+        self.catch_up.clear();
+        for (_, dirty) in self.dirty.iter().filter(|(gen, _)| *gen > synced) {
+            match dirty {
+                Dirty::All => return None,
+                Dirty::Ranges(ranges) => self.catch_up.extend(ranges.iter().cloned()),
+            }
+        }
+
+        self.catch_up.sort_unstable_by_key(|r| r.start);
+        let mut merged = 0;
+        for i in 0..self.catch_up.len() {
+            let r = self.catch_up[i].clone();
+            if r.start >= r.end {
+                continue;
+            }
+            if merged > 0 && r.start <= self.catch_up[merged - 1].end {
+                let last = &mut self.catch_up[merged - 1];
+                last.end = last.end.max(r.end);
+            } else {
+                self.catch_up[merged] = r;
+                merged += 1;
+            }
+        }
+        self.catch_up.truncate(merged);
+        Some(&self.catch_up)
+    }
+}
+
+impl<T> Writer<T> {
+    /// Write the next state by changing only some regions of a slice state.
+    ///
+    /// `dirty` lists the regions the closure is going to modify.
+    /// Before the closure runs, the buffer it writes into is brought
+    /// up to date with the previous state by copying only the regions
+    /// that changed since that buffer was last published, falling
+    /// back to a full `Clone::clone_from` if it is too far behind.
+    ///
+    /// The closure gets the previous state and the buffer for the new state.
+    /// Changes outside of the `dirty` regions are not tracked,
+    /// and would leave the buffers inconsistent.
+    ///
+    /// # Panics
+    /// Panics if a dirty region lies outside of the state.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(vec![0u32; 1024]);
+    ///
+    /// writer.write_regions(&[10..12], |_old, new| {
+    ///     new[10] = 1;
+    ///     new[11] = 2;
+    /// });
+    ///
+    /// let state = reader.read_newest();
+    /// assert_eq!(state[9..13], [0, 1, 2, 0]);
+    /// ````
+    pub fn write_regions<U>(
+        &mut self,
+        dirty: &[Range<usize>],
+        write_op: impl FnOnce(&[U], &mut [U]),
+    ) where
+        T: Clone + AsRef<[U]> + AsMut<[U]>,
+        U: Clone,
+    {
+        let len = (**self.prev_buf).as_ref().len();
+        for r in dirty {
+            assert!(
+                r.start <= r.end && r.end <= len,
+                "dirty region {:?} is out of bounds for a state of length {}",
+                r,
+                len
+            );
+        }
+
+        if self.regions.is_none() {
+            self.regions = Some(Box::new(RegionLog::new(&self.prev_buf)));
+        }
+        let mut new_state = self.next_unused_buffer();
+        let regions = self.regions.as_mut().unwrap();
+
+        let catch_up = regions.catch_up(&new_state);
+
+        // This Arc will have no other clones at this point,
+        // so we can get a mutable reference into it.
+        let new = Buf::get_mut(&mut new_state).unwrap();
+        let old = &**self.prev_buf;
+        match catch_up {
+            Some(ranges) if (*new).as_ref().len() == len => {
+                let (old, new) = (old.as_ref(), new.as_mut());
+                for r in ranges {
+                    new[r.clone()].clone_from_slice(&old[r.clone()]);
+                }
+            }
+            _ => new.clone_from(old),
+        }
+        write_op(old.as_ref(), new.as_mut());
+
+        regions.record(&new_state, Some(dirty));
+        self.publish_untracked(new_state);
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use std::cell::Cell;
+    use std::ops::Range;
+
+    use crate::new_clone;
+
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self, bound: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % bound as u64) as usize
+        }
+
+        fn range(&mut self, len: usize) -> Range<usize> {
+            let a = self.next(len + 1);
+            let b = self.next(len + 1);
+            a.min(b)..a.max(b)
+        }
+    }
+
+    #[test]
+    fn test_overlapping_out_of_order_regions() {
+        let (mut w, mut r) = new_clone(vec![0u32; 64]);
+        let mut model = vec![0u32; 64];
+
+        let writes: &[&[Range<usize>]] = &[
+            &[40..50, 10..20],
+            &[15..45],
+            &[0..5, 3..8, 60..64],
+            &[20..22, 20..22],
+            &[50..60, 5..6],
+        ];
+        for (i, dirty) in writes.iter().enumerate() {
+            let value = i as u32 + 1;
+            w.write_regions(dirty, |_, new| {
+                for r in dirty.iter() {
+                    new[r.clone()].iter_mut().for_each(|v| *v = value);
+                }
+            });
+            for r in dirty.iter() {
+                model[r.clone()].iter_mut().for_each(|v| *v = value);
+            }
+            if i % 2 == 0 {
+                assert_eq!(*r.read_newest(), model);
+            }
+        }
+        assert_eq!(*r.read_newest(), model);
+    }
+
+    #[test]
+    fn test_regions_match_model() {
+        let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+        let (mut w, mut r) = new_clone(vec![0u32; 100]);
+        let mut model = vec![0u32; 100];
+
+        for step in 1..2000u32 {
+            match rng.next(10) {
+                0 => {
+                    // Interleave whole-state writes.
+                    w.write_new(|_, new| {
+                        new.clear();
+                        new.extend((0..100).map(|i| i * step));
+                    });
+                    model
+                        .iter_mut()
+                        .enumerate()
+                        .for_each(|(i, v)| *v = i as u32 * step);
+                }
+                _ => {
+                    let dirty: Vec<_> = (0..rng.next(4)).map(|_| rng.range(100)).collect();
+                    w.write_regions(&dirty, |old, new| {
+                        assert_eq!(old, &model[..]);
+                        for r in &dirty {
+                            new[r.clone()].iter_mut().for_each(|v| *v = step);
+                        }
+                    });
+                    for r in &dirty {
+                        model[r.clone()].iter_mut().for_each(|v| *v = step);
+                    }
+                }
+            }
+            if rng.next(3) == 0 {
+                assert_eq!(*r.read_newest(), model);
+            }
+        }
+    }
+
+    #[test]
+    fn test_lagging_buffer_is_fully_resynced() {
+        let (mut w, mut r) = new_clone(vec![0u8; 8]);
+        let mut model = vec![0u8; 8];
+
+        // The reader pins one buffer across more publishes than are tracked.
+        r.read_newest();
+        for i in 0..40u8 {
+            let idx = i as usize % 8;
+            w.write_regions(&[idx..idx + 1], |_, new| new[idx] = i);
+            model[idx] = i;
+        }
+        assert_eq!(*r.read_newest(), model);
+        w.write_regions(&[0..1], |_, new| new[0] = 100);
+        model[0] = 100;
+        assert_eq!(*r.read_newest(), model);
+    }
+
+    #[test]
+    fn test_catch_up_copies_only_changed_regions() {
+        thread_local!(static CLONES: Cell<usize> = const { Cell::new(0) });
+
+        #[derive(Debug, PartialEq)]
+        struct Elem(u8);
+
+        impl Clone for Elem {
+            fn clone(&self) -> Self {
+                CLONES.with(|c| c.set(c.get() + 1));
+                Elem(self.0)
+            }
+        }
+
+        let (mut w, mut r) = new_clone((0..1024).map(|_| Elem(0)).collect::<Vec<_>>());
+        for i in 0..4u8 {
+            w.write_regions(&[0..1], |_, new| new[0] = Elem(i));
+            r.read_newest();
+        }
+
+        CLONES.with(|c| c.set(0));
+        for i in 0..100u8 {
+            let idx = i as usize * 7;
+            w.write_regions(&[idx..idx + 2], |_, new| new[idx] = Elem(i));
+            assert_eq!(r.read_newest()[idx], Elem(i));
+        }
+        // Each write catches up on at most the two previous publishes.
+        assert!(CLONES.with(|c| c.get()) <= 100 * 4);
+    }
+}
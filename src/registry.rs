@@ -0,0 +1,148 @@
+//! A process-wide list of the live buffer pairs, for diagnostics.
+//!
+//! With the `registry` feature, every pair of a `Writer` and `Reader`
+//! registers itself on creation, and unregisters once all its halves are
+//! gone. The other kinds of pairs, like the ones of the `double` module,
+//! are not listed. The
+//! registry only holds weak handles, so it never keeps a pair alive.
+//! `snapshot` lists what the pairs reported last.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::{registry, TripleBufferBuilder};
+//!
+//! let (mut writer, _reader) = TripleBufferBuilder::new(0)
+//!     .copy_buffers()
+//!     .label("physics")
+//!     .build()
+//!     .unwrap();
+//! writer.write_new(|_, new| *new = 1);
+//!
+//! let physics = registry::snapshot()
+//!     .into_iter()
+//!     .find(|pair| pair.label.as_deref() == Some("physics"))
+//!     .unwrap();
+//! assert_eq!(physics.version, 1);
+//! assert!(physics.last_publish.is_some());
+//! ````
+
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+static PAIRS: Mutex<Vec<Weak<Entry>>> = Mutex::new(Vec::new());
+
+/// What a pair reports to the registry.
+struct Entry {
+    label: Option<Cow<'static, str>>,
+    version: AtomicU64,
+    /// Nanoseconds since the Unix epoch, or 0 before the first publish.
+    last_publish: AtomicU64,
+    buffers: AtomicUsize,
+}
+
+/// Information about a live pair, see `snapshot`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairInfo {
+    /// The label of the pair, if any.
+    pub label: Option<String>,
+    /// The number of states published so far.
+    pub version: u64,
+    /// When the last state was published, if any was.
+    pub last_publish: Option<SystemTime>,
+    /// The number of buffers the writer created or was given.
+    pub buffers: usize,
+}
+
+/// List the pairs that are currently alive.
+pub fn snapshot() -> Vec<PairInfo> {
+    let pairs = PAIRS.lock().unwrap_or_else(PoisonError::into_inner);
+    pairs
+        .iter()
+        .filter_map(Weak::upgrade)
+        .map(|entry| {
+            let nanos = entry.last_publish.load(Ordering::Relaxed);
+            PairInfo {
+                label: entry.label.as_deref().map(String::from),
+                version: entry.version.load(Ordering::Relaxed),
+                last_publish: (nanos != 0).then(|| UNIX_EPOCH + Duration::from_nanos(nanos)),
+                buffers: entry.buffers.load(Ordering::Relaxed),
+            }
+        })
+        .collect()
+}
+
+/// The registration of a pair, owned by its shared state.
+pub(crate) struct Registration(Arc<Entry>);
+
+impl Registration {
+    pub(crate) fn new(label: Option<Cow<'static, str>>) -> Self {
+        let entry = Arc::new(Entry {
+            label,
+            version: AtomicU64::new(0),
+            last_publish: AtomicU64::new(0),
+            buffers: AtomicUsize::new(1),
+        });
+        let mut pairs = PAIRS.lock().unwrap_or_else(PoisonError::into_inner);
+        pairs.push(Arc::downgrade(&entry));
+        Self(entry)
+    }
+
+    /// Record a publish, with `buffers` buffers existing.
+    pub(crate) fn published(&self, buffers: usize) {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        self.0.version.fetch_add(1, Ordering::Relaxed);
+        self.0
+            .last_publish
+            .store(since_epoch.as_nanos() as u64, Ordering::Relaxed);
+        self.0.buffers.store(buffers, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut pairs = PAIRS.lock().unwrap_or_else(PoisonError::into_inner);
+        let entry = Arc::downgrade(&self.0);
+        pairs.retain(|pair| !pair.ptr_eq(&entry));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::snapshot;
+    use crate::TripleBufferBuilder;
+
+    fn find(label: &str) -> Option<super::PairInfo> {
+        snapshot()
+            .into_iter()
+            .find(|pair| pair.label.as_deref() == Some(label))
+    }
+
+    #[test]
+    fn test_pairs_unregister_when_dropped() {
+        let (mut w, r) = TripleBufferBuilder::new(0)
+            .copy_buffers()
+            .label("registry test")
+            .build()
+            .unwrap();
+        let info = find("registry test").unwrap();
+        assert_eq!((info.version, info.last_publish), (0, None));
+
+        for i in 1..=3 {
+            w.write_new(|_, new| *new = i);
+        }
+        let info = find("registry test").unwrap();
+        assert_eq!((info.version, info.buffers), (3, 3));
+        assert!(info.last_publish.is_some());
+
+        // The reader keeps the pair alive.
+        drop(w);
+        assert!(find("registry test").is_some());
+        drop(r);
+        assert!(find("registry test").is_none());
+    }
+}
@@ -0,0 +1,126 @@
+//! Feeding a captured `Recording` to a reader, see the `record` module.
+//!
+//! # Example
+//! ```
+//! use std::time::Duration;
+//! use simple_triple_buffer::record::{Recorded, Recording};
+//! use simple_triple_buffer::replay;
+//!
+//! let mut recording = Recording::new();
+//! for i in 0..3 {
+//!     let elapsed = Duration::from_millis(10 * i);
+//!     recording.push(Recorded { version: i, elapsed, state: i });
+//! }
+//!
+//! let mut reader = replay::spawn(recording, 1.0);
+//! assert_eq!(*reader.read_newest(), 0);
+//! while !reader.is_disconnected() {
+//!     std::thread::yield_now();
+//! }
+//! assert_eq!(*reader.read_newest(), 2);
+//! ````
+
+use std::thread;
+use std::time::Instant;
+
+use crate::record::Recording;
+use crate::{new_clone, Reader};
+
+/// Create a reader fed with the states of `recording`.
+///
+/// The reader starts at the first state, and a thread publishes the
+/// others through a regular pair, as far apart in time as they were
+/// originally, divided by `speed`. A reader that does not keep up
+/// skips states, just like it would have when they were captured.
+/// The writer gets dropped after the last state, or as soon as the
+/// reader is, so `Reader::is_disconnected` tells when the replay ended.
+///
+/// # Panics
+/// Panics if `recording` is empty, or `speed` is not positive.
+pub fn spawn<T>(recording: Recording<T>, speed: f64) -> Reader<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    assert!(speed > 0.0, "replay speed must be positive");
+    let mut states = recording.into_states().into_iter();
+    let first = states.next().expect("replaying an empty recording");
+    let (mut writer, reader) = new_clone(first.state);
+    let offset = first.elapsed;
+    thread::spawn(move || {
+        let start = Instant::now();
+        for recorded in states {
+            let due = (recorded.elapsed - offset).div_f64(speed);
+            let now = start.elapsed();
+            if due > now {
+                thread::sleep(due - now);
+            }
+            if writer.is_closed() {
+                return;
+            }
+            let state = recorded.state;
+            writer.write_new(|_, new| *new = state);
+        }
+    });
+    reader
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::spawn;
+    use crate::new_clone;
+    use crate::record::Recording;
+
+    #[test]
+    fn test_round_trip_keeps_timing() {
+        let (mut w, _r) = new_clone(0u32);
+        let recorder = w.record_to(Recording::new(), 16);
+        for i in 1..=4 {
+            std::thread::sleep(Duration::from_millis(20));
+            w.write_new(|_, new| *new = i);
+        }
+        drop(w);
+        let recording = recorder.finish();
+        assert_eq!(recording.len(), 5);
+
+        let start = Instant::now();
+        let mut reader = spawn(recording, 2.0);
+        let mut seen = vec![*reader.read_newest()];
+        while !reader.is_disconnected() {
+            if reader.has_update() {
+                seen.push(*reader.read_newest());
+            }
+            std::thread::yield_now();
+        }
+        // At double speed, the 80ms of the recording take 40ms.
+        assert!(start.elapsed() >= Duration::from_millis(40));
+        seen.extend(reader.has_update().then(|| *reader.read_newest()));
+        // A busy machine may schedule the reader too late for some states.
+        assert!(seen.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(seen.last(), Some(&4));
+    }
+
+    #[test]
+    fn test_slow_reader_skips_states() {
+        let mut recording = Recording::new();
+        let (mut w, _r) = new_clone(0u32);
+        let recorder = w.record_to(Vec::new(), 16);
+        for i in 1..=9 {
+            w.write_new(|_, new| *new = i);
+        }
+        drop(w);
+        for recorded in recorder.finish() {
+            recording.push(recorded);
+        }
+
+        let mut reader = spawn(recording, 1.0);
+        while !reader.is_disconnected() {
+            std::thread::yield_now();
+        }
+        // Published back to back, so a reader arriving late only sees the last.
+        assert!(reader.has_update());
+        assert_eq!(*reader.read_newest(), 9);
+        assert!(!reader.has_update());
+    }
+}
@@ -0,0 +1,81 @@
+//! Publishing the default state, to reset what readers see.
+
+use crate::{PublishReceipt, Writer};
+
+impl<T: Default> Writer<T> {
+    /// Publish `T::default()` as the new state.
+    ///
+    /// This is a publish like any other, with a new version, and
+    /// readers get notified the same way. The unused buffer gets
+    /// overwritten, dropping whatever it held, so for states holding
+    /// on to allocations, `publish_default_in_place` might be cheaper.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// writer.write_new(|_, new| *new = 5);
+    /// writer.publish_default();
+    /// assert_eq!(*reader.read_newest(), 0);
+    /// assert_eq!(writer.version(), 2);
+    /// ````
+    ///
+    /// # Panics
+    /// Panics if the pair was created without a way to create
+    /// new buffers, and all existing ones are in use.
+    pub fn publish_default(&mut self) -> PublishReceipt {
+        self.write_new(|_, new| *new = T::default())
+    }
+}
+
+impl<T: Default + Clone> Writer<T> {
+    /// Publish the default state like `publish_default`, but by
+    /// overwriting the unused buffer with `clone_from`.
+    ///
+    /// For types like `Vec`, `clone_from` keeps the allocation of the
+    /// buffer, so once the buffers are warm, resetting the state does not
+    /// allocate or free anything. The default state it copies from gets
+    /// created once, on the first call, and kept by the writer.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(Vec::new());
+    /// writer.write_new(|_, new| new.extend(0..1000));
+    /// writer.publish_default_in_place();
+    /// assert!(reader.read_newest().is_empty());
+    /// ````
+    ///
+    /// # Panics
+    /// Panics if the pair was created without a way to create
+    /// new buffers, and all existing ones are in use.
+    pub fn publish_default_in_place(&mut self) -> PublishReceipt {
+        let default = self.default_state.take().unwrap_or_default();
+        let receipt = self.write_new(|_, new| new.clone_from(&default));
+        self.default_state = Some(default);
+        receipt
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::new_clone;
+
+    #[test]
+    fn test_in_place_keeps_allocations() {
+        let (mut w, mut r) = new_clone(Vec::new());
+        for i in 0..30 {
+            if i % 3 < 2 {
+                w.write_new(|_, new| new.extend(0..1000));
+                assert!(r.read_newest().len() >= 1000);
+                continue;
+            }
+            w.publish_default_in_place();
+            let state = r.read_newest();
+            assert!(state.is_empty());
+            if i > 5 {
+                // Every buffer held a long state by now.
+                assert!(state.capacity() >= 1000);
+            }
+        }
+        assert_eq!(w.version(), 30);
+    }
+}
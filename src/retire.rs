@@ -0,0 +1,81 @@
+//! Dropping some of the unused buffers of a writer.
+
+use crate::{has_refs, Buf, Writer};
+
+impl<T> Writer<T> {
+    /// Drop the unused buffers for which `retire` returns `true`,
+    /// returning how many there were.
+    ///
+    /// This is for states that hold on to resources which can become
+    /// unsuitable, like frames of a resolution that is no longer used:
+    /// the buffers that are still fine stay around, while the others no
+    /// longer get handed to writes, which create new buffers instead.
+    ///
+    /// Only the buffers waiting to be written into get examined, each of
+    /// them uniquely owned by the writer. The published state, the states
+    /// readers hold and buffers still on their way back from readers are
+    /// never touched, and buffers in a `BufferPool` shared with other
+    /// pairs are left to that pool. Retired buffers no longer count
+    /// towards `TripleBufferBuilder::max_buffers`.
+    ///
+    /// # Example
+    /// ```
+    /// use simple_triple_buffer::TripleBufferBuilder;
+    ///
+    /// let (mut writer, _reader) = TripleBufferBuilder::new(vec![0u8; 16])
+    ///     .spare_buffers(vec![vec![0; 16], vec![0; 4], vec![0; 4]])
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(writer.retire_buffers(|buf| buf.len() != 16), 2);
+    /// assert_eq!(writer.retire_buffers(|buf| buf.len() != 16), 0);
+    /// ````
+    pub fn retire_buffers(&mut self, mut retire: impl FnMut(&T) -> bool) -> usize {
+        let regions = &mut self.regions;
+        let mut unused = |buf: &Buf<T>| {
+            let unused = has_refs(buf, 1) && retire(buf);
+            if let (true, Some(regions)) = (unused, regions.as_mut()) {
+                regions.forget(buf);
+            }
+            unused
+        };
+        let mut retired = self.unused_bufs_rx.retire(&mut unused);
+        if self.spare.as_ref().is_some_and(unused) {
+            self.spare = None;
+            retired += 1;
+        }
+        self.created -= retired;
+        retired
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::TripleBufferBuilder;
+
+    #[test]
+    fn test_retired_buffers_get_replaced() {
+        let (mut w, mut r) = TripleBufferBuilder::new(vec![0u32; 8])
+            .clone_with(|state| vec![0; state.len()])
+            .max_buffers(4)
+            .build()
+            .unwrap();
+        for i in 0..10 {
+            w.write_new(|_, new| new.fill(i));
+            r.read_newest();
+        }
+        // Switch to a new size, which the old buffers do not fit.
+        w.write_new(|_, new| *new = vec![0; 16]);
+        r.read_newest();
+        let retired = w.retire_buffers(|buf| buf.len() != 16);
+        assert!(retired > 0);
+        for i in 0..10 {
+            w.try_write_new(|old, new| {
+                assert_eq!(new.len(), 16);
+                new.copy_from_slice(old);
+                new[0] = i;
+            })
+            .unwrap();
+            assert_eq!(r.read_newest().len(), 16);
+        }
+    }
+}
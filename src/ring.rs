@@ -0,0 +1,251 @@
+//! The fixed set of slots unused buffers get returned to the writer through.
+//!
+//! Buffers come back from the writer itself and from any number of
+//! readers, so every slot is a single atomic word holding the address of
+//! a buffer, as returned by `Buf::into_raw`, or 0. Senders claim an empty
+//! slot with a compare-exchange, and the writer empties occupied ones with
+//! a swap. The order buffers come back in does not matter, so there are
+//! no head and tail indices to keep consistent between the senders.
+//!
+//! Each slot has a cache line of its own, so the writer taking a buffer
+//! out of one slot does not get in the way of a reader returning one
+//! into the next.
+//!
+//! Nothing allocates after the slots are created. The capacity covers
+//! every buffer a pair with `TripleBufferBuilder::max_buffers` can have,
+//! so for those the slots never overflow. Other pairs get a fixed default,
+//! and drop buffers that find all slots occupied, to be replaced with new
+//! ones by the writer if it needs them.
+
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+use core::mem::ManuallyDrop;
+use core::sync::atomic::Ordering;
+
+use crate::sync::atomic::{AtomicBool, AtomicUsize};
+use crate::sync::{Arc, CachePadded};
+use crate::Buf;
+
+/// The capacity of pairs that can have any number of buffers.
+pub(crate) const DEFAULT_CAPACITY: usize = 16;
+
+struct Slots<T> {
+    slots: Box<[CachePadded<AtomicUsize>]>,
+    receiver_alive: AtomicBool,
+    /// The number of buffers dropped because all slots were occupied.
+    overflowed: AtomicUsize,
+    /// Occupied slots own one reference to their buffer.
+    _buf: PhantomData<Buf<T>>,
+}
+
+impl<T> Slots<T> {
+    fn take(&self) -> Option<Buf<T>> {
+        self.slots.iter().find_map(|slot| {
+            if slot.load(Ordering::Relaxed) == 0 {
+                return None;
+            }
+            let ptr = slot.swap(0, Ordering::Acquire) as *const T;
+            // SAFETY: The reference the slot owned is ours now.
+            (!ptr.is_null()).then(|| unsafe { Buf::from_raw(ptr) })
+        })
+    }
+}
+
+impl<T> Drop for Slots<T> {
+    fn drop(&mut self) {
+        while self.take().is_some() {}
+    }
+}
+
+/// Returns unused buffers to a `Receiver`.
+pub(crate) struct Sender<T>(Arc<Slots<T>>);
+
+/// Takes unused buffers out of the slots, only used by the writer.
+pub(crate) struct Receiver<T>(Arc<Slots<T>>);
+
+/// Create the slots for a pair, with room for `capacity` buffers.
+pub(crate) fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let slots = Arc::new(Slots {
+        slots: (0..capacity)
+            .map(|_| CachePadded(AtomicUsize::new(0)))
+            .collect::<Vec<_>>()
+            .into(),
+        receiver_alive: AtomicBool::new(true),
+        overflowed: AtomicUsize::new(0),
+        _buf: PhantomData,
+    });
+    (Sender(slots.clone()), Receiver(slots))
+}
+
+impl<T> Sender<T> {
+    /// Return a buffer, or hand it back if the receiver is gone.
+    ///
+    /// If all slots are occupied, the buffer gets dropped.
+    pub(crate) fn send(&self, buf: Buf<T>) -> Result<(), Buf<T>> {
+        if !self.0.receiver_alive.load(Ordering::Relaxed) {
+            return Err(buf);
+        }
+        let ptr = Buf::into_raw(buf) as usize;
+        for slot in self.0.slots.iter() {
+            if slot.load(Ordering::Relaxed) == 0
+                && slot
+                    .compare_exchange(0, ptr, Ordering::Release, Ordering::Relaxed)
+                    .is_ok()
+            {
+                return Ok(());
+            }
+        }
+        self.0.overflowed.fetch_add(1, Ordering::Relaxed);
+        // SAFETY: `ptr` came from `into_raw` above and was not stored anywhere.
+        drop(unsafe { Buf::from_raw(ptr as *const T) });
+        Ok(())
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Receiver<T> {
+    pub(crate) fn try_recv(&self) -> Option<Buf<T>> {
+        self.0.take()
+    }
+
+    /// Call `f` with every buffer currently in the slots.
+    ///
+    /// Only the receiver empties slots, so they stay alive meanwhile.
+    pub(crate) fn for_each(&self, mut f: impl FnMut(&Buf<T>)) {
+        for slot in self.0.slots.iter() {
+            let ptr = slot.load(Ordering::Acquire) as *const T;
+            if !ptr.is_null() {
+                // SAFETY: The slot keeps owning the reference, see above.
+                f(&ManuallyDrop::new(unsafe { Buf::from_raw(ptr) }));
+            }
+        }
+    }
+
+    /// Drop the buffers in the slots for which `f` returns `true`,
+    /// returning how many there were.
+    pub(crate) fn retire(&self, mut f: impl FnMut(&Buf<T>) -> bool) -> usize {
+        let mut retired = 0;
+        for slot in self.0.slots.iter() {
+            let ptr = slot.load(Ordering::Acquire) as *const T;
+            if ptr.is_null() {
+                continue;
+            }
+            // SAFETY: The slot keeps owning the reference, like for `for_each`.
+            if f(&ManuallyDrop::new(unsafe { Buf::from_raw(ptr) })) {
+                // Only the receiver empties slots, so this is still `ptr`.
+                slot.store(0, Ordering::Relaxed);
+                // SAFETY: The reference the slot owned is ours now.
+                drop(unsafe { Buf::from_raw(ptr) });
+                retired += 1;
+            }
+        }
+        retired
+    }
+
+    /// Get the number of slots, which is how many buffers
+    /// can be returned at a time without dropping any.
+    pub(crate) fn capacity(&self) -> usize {
+        self.0.slots.len()
+    }
+
+    /// Get the number of buffers dropped because all slots were occupied.
+    #[cfg(test)]
+    pub(crate) fn overflowed(&self) -> usize {
+        self.0.overflowed.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.0.receiver_alive.store(false, Ordering::Relaxed);
+        // Buffers sent concurrently with this get dropped
+        // along with the slots, by the last sender.
+        while self.0.take().is_some() {}
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::channel;
+    use crate::Buf;
+
+    #[test]
+    fn test_overflow_drops_buffers() {
+        let (tx, rx) = channel(2);
+        for i in 0..3 {
+            tx.send(Buf::new(i)).unwrap();
+        }
+        assert_eq!(rx.overflowed(), 1);
+        let mut received = [*rx.try_recv().unwrap(), *rx.try_recv().unwrap()];
+        received.sort();
+        assert_eq!(received, [0, 1]);
+        assert!(rx.try_recv().is_none());
+
+        drop(rx);
+        assert!(tx.send(Buf::new(3)).is_err());
+    }
+
+    #[test]
+    fn test_many_senders() {
+        let (tx, rx) = channel(64);
+        let senders: Vec<_> = (0..4)
+            .map(|t| {
+                let tx = tx.clone();
+                std::thread::spawn(move || {
+                    for i in 0..16 {
+                        tx.send(Buf::new(t * 16 + i)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        let mut received = Vec::new();
+        while received.len() < 64 {
+            if let Some(buf) = rx.try_recv() {
+                received.push(*buf);
+            }
+        }
+        for sender in senders {
+            sender.join().unwrap();
+        }
+        received.sort();
+        assert!(received.into_iter().eq(0..64));
+        assert_eq!(rx.overflowed(), 0);
+    }
+}
+
+#[cfg(all(test, loom))]
+mod loom_tests {
+    use loom::thread;
+
+    use super::channel;
+    use crate::Buf;
+
+    #[test]
+    fn test_concurrent_returns() {
+        loom::model(|| {
+            let (tx, rx) = channel(2);
+            let senders: Vec<_> = (0..2)
+                .map(|i| {
+                    let tx = tx.clone();
+                    thread::spawn(move || tx.send(Buf::new(i)).unwrap())
+                })
+                .collect();
+            let first = rx.try_recv();
+            for sender in senders {
+                sender.join().unwrap();
+            }
+            let mut received: Vec<_> = first.into_iter().chain(rx.try_recv()).collect();
+            received.extend(rx.try_recv());
+            assert_eq!(received.len(), 2);
+            for buf in &received {
+                assert!(crate::has_refs(buf, 1));
+            }
+        });
+    }
+}
@@ -0,0 +1,113 @@
+use crate::{Allocation, Buf, PoolExhausted, Reader, TripleBufferBuilder, Writer};
+
+/// Create a new buffer pair that creates additional buffer
+/// instances with a clone function that may borrow local data.
+///
+/// `new_with` boxes its clone function inside the `Writer`, which has
+/// no lifetime to tie it to, so the function has to be `'static`. Here
+/// it is a type parameter of the `ScopedWriter` instead, so it can
+/// borrow anything that outlives the writer, like the state itself can.
+/// This fits pairs that live inside `std::thread::scope`.
+///
+/// # Example
+/// ```
+/// #[derive(Clone)]
+/// struct Frame<'a> {
+///     palette: &'a [u32],
+///     pixels: Vec<u8>,
+/// }
+///
+/// let palette = vec![0x000000, 0xffffff];
+/// let blank = vec![0u8; 16];
+///
+/// std::thread::scope(|s| {
+///     let init = Frame { palette: &palette, pixels: blank.clone() };
+///     let (mut writer, mut reader) = simple_triple_buffer::new_scoped(init, |prev: &Frame<'_>| {
+///         Frame { palette: prev.palette, pixels: blank.clone() }
+///     });
+///     s.spawn(move || writer.write_new(|_, new| new.pixels.fill(1)));
+///     s.spawn(move || reader.read_newest().palette.len());
+/// });
+/// ````
+pub fn new_scoped<T, F: FnMut(&T) -> T>(init: T, make_buf: F) -> (ScopedWriter<T, F>, Reader<T>) {
+    // The pair itself never creates buffers, see `ScopedWriter::next_buffer`.
+    let (writer, reader) = TripleBufferBuilder::new(init).finish();
+    (ScopedWriter { writer, make_buf }, reader)
+}
+
+/// Write side of a pair created with `new_scoped`.
+pub struct ScopedWriter<T, F> {
+    writer: Writer<T>,
+    make_buf: F,
+}
+
+impl<T, F: FnMut(&T) -> T> ScopedWriter<T, F> {
+    /// Write the next state into the buffer, see `Writer::write_new`.
+    pub fn write_new(&mut self, write_op: impl FnOnce(&T, &mut T)) {
+        let mut new_state = self.next_buffer();
+        write_op(&self.writer.prev_buf, Buf::get_mut(&mut new_state).unwrap());
+        self.writer.publish(new_state);
+    }
+
+    /// Write the next state by updating a copy of the previous one,
+    /// synced via `Clone::clone_from`.
+    ///
+    /// Like `Writer::write_update`, this updates the previous state
+    /// in place if no reader has picked it up yet.
+    pub fn write_update(&mut self, update_op: impl FnOnce(&mut T))
+    where
+        T: Clone,
+    {
+        let update_op = match self.writer.try_update_pending(update_op) {
+            Ok(()) => return,
+            Err(update_op) => update_op,
+        };
+        self.write_new(|old, new| {
+            new.clone_from(old);
+            update_op(new);
+        });
+    }
+
+    /// Check whether the `Reader` has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.writer.is_closed()
+    }
+
+    fn next_buffer(&mut self) -> Buf<T> {
+        match self.writer.try_next_unused_buffer() {
+            Ok(buf) => buf,
+            Err(PoolExhausted) => {
+                self.writer.created += 1;
+                let buf = Buf::new((self.make_buf)(&self.writer.prev_buf));
+                self.writer.allocated(Allocation::NewBuffer);
+                #[cfg(feature = "std")]
+                self.writer.hook_created(&buf);
+                buf
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::new_scoped;
+
+    #[test]
+    fn test_buffers_are_reused() {
+        let mut created = 0;
+        let (mut w, mut r) = new_scoped(0u32, |v| {
+            created += 1;
+            *v
+        });
+        for i in 1..=100 {
+            w.write_update(|v| *v += 1);
+            if i % 3 == 0 {
+                assert_eq!(*r.read_newest(), i);
+            }
+        }
+        // Unread states get updated in place, so one more buffer suffices.
+        assert_eq!(w.writer.created, 2);
+        drop((w, r));
+        assert_eq!(created, 1);
+    }
+}
@@ -0,0 +1,205 @@
+use alloc::vec::Vec;
+use zeroize::Zeroize;
+
+use crate::{recycle, Buf, Reader, TripleBufferBuilder, Writer};
+
+/// Create a new buffer pair that creates additional buffer instances
+/// by cloning, and zeroizes buffers once they no longer hold
+/// a needed state, see `TripleBufferBuilder::zeroize`.
+///
+/// Only available with the `zeroize` feature.
+///
+/// # Example
+/// ```
+/// let (mut writer, mut reader) = simple_triple_buffer::new_zeroizing([0u8; 32]);
+/// writer.write_new(|_, key| *key = [7; 32]);
+/// assert_eq!(*reader.read_newest(), [7; 32]);
+/// ````
+pub fn new_zeroizing<T: Clone + Zeroize>(init: T) -> (Writer<T>, Reader<T>) {
+    TripleBufferBuilder::new(init)
+        .clone_with(|v| v.clone())
+        .zeroize()
+        .finish()
+}
+
+impl<T: Zeroize> Writer<T> {
+    /// Zeroize everything the writer holds on to besides
+    /// the published state.
+    ///
+    /// This covers its unused buffers, which may contain older states
+    /// if the pair was created without `TripleBufferBuilder::zeroize`,
+    /// and the states kept by `keep_history`, which get released.
+    /// The history then starts over empty.
+    ///
+    /// States still held by a `Reader`, or by another pair in a shared
+    /// `BufferPool`, are out of reach, and only get zeroized when they
+    /// are recycled by a pair created with `TripleBufferBuilder::zeroize`.
+    ///
+    /// Only available with the `zeroize` feature.
+    pub fn scrub(&mut self) {
+        let mut unused = Vec::new();
+        while let Some(buf) = self.unused_bufs_rx.try_recv() {
+            unused.push(buf);
+        }
+        let history: Vec<_> = self.read_update.shared.history.lock().drain(..).collect();
+        // Only history states nothing else references can be reused.
+        unused.extend(
+            history
+                .into_iter()
+                .filter_map(|mut buf| Buf::get_mut(&mut buf).is_some().then_some(buf)),
+        );
+
+        for mut buf in unused {
+            Buf::get_mut(&mut buf).unwrap().zeroize();
+            if let Some(regions) = &mut self.regions {
+                regions.forget(&buf);
+            }
+            recycle(&self.unused_bufs_tx, None, buf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use zeroize::Zeroize;
+
+    use super::new_zeroizing;
+    use crate::{new_clone, BufferPool, TripleBufferBuilder};
+
+    /// Records the value each instance had when it got dropped,
+    /// and how often instances got zeroized.
+    #[derive(Clone)]
+    struct Secret {
+        value: u64,
+        log: Arc<Mutex<Log>>,
+    }
+
+    #[derive(Default)]
+    struct Log {
+        zeroized: usize,
+        dropped: Vec<u64>,
+    }
+
+    impl Zeroize for Secret {
+        fn zeroize(&mut self) {
+            self.value.zeroize();
+            self.log.lock().unwrap().zeroized += 1;
+        }
+    }
+
+    impl Drop for Secret {
+        fn drop(&mut self) {
+            self.log.lock().unwrap().dropped.push(self.value);
+        }
+    }
+
+    fn secret() -> (Secret, Arc<Mutex<Log>>) {
+        let log = Arc::new(Mutex::new(Log::default()));
+        let secret = Secret {
+            value: 0,
+            log: log.clone(),
+        };
+        (secret, log)
+    }
+
+    #[test]
+    fn test_recycled_buffers_are_zeroized() {
+        let (init, log) = secret();
+        let (mut w, mut r) = new_zeroizing(init);
+        for i in 1..=10 {
+            w.write_new(|_, new| new.value = i);
+            assert_eq!(r.read_newest().value, i);
+        }
+        assert!(log.lock().unwrap().zeroized > 0);
+        let mut unused = Vec::new();
+        while let Some(buf) = w.unused_bufs_rx.try_recv() {
+            assert_eq!(buf.value, 0);
+            unused.push(buf);
+        }
+        assert!(!unused.is_empty());
+        unused.into_iter().for_each(|buf| w.recycle(buf));
+    }
+
+    #[test]
+    fn test_dropped_pair_leaves_nothing_behind() {
+        for drop_writer_first in [false, true] {
+            let (init, log) = secret();
+            let (mut w, mut r) = new_zeroizing(init);
+            w.keep_history(2);
+            for i in 1..=10 {
+                w.write_new(|_, new| new.value = i);
+                if i % 3 == 0 {
+                    r.read_newest();
+                }
+            }
+            r.rewind(1);
+            if drop_writer_first {
+                drop(w);
+                drop(r);
+            } else {
+                drop(r);
+                drop(w);
+            }
+            let log = log.lock().unwrap();
+            assert!(!log.dropped.is_empty());
+            assert!(log.dropped.iter().all(|v| *v == 0), "{:?}", log.dropped);
+        }
+    }
+
+    #[test]
+    fn test_pool_discards_are_zeroized() {
+        let (init, log) = secret();
+        let (mut w, mut r) = TripleBufferBuilder::new(init)
+            .clone_with(Secret::clone)
+            .pool(BufferPool::new(0))
+            .zeroize()
+            .build()
+            .unwrap();
+        for i in 1..=10 {
+            w.write_new(|_, new| new.value = i);
+            r.read_newest();
+        }
+        let dropped = log.lock().unwrap().dropped.clone();
+        assert!(!dropped.is_empty());
+        assert!(dropped.iter().all(|v| *v == 0));
+    }
+
+    #[test]
+    fn test_scrub() {
+        let (init, log) = secret();
+        let (mut w, mut r) = new_clone(init);
+        w.keep_history(3);
+        for i in 1..=5 {
+            w.write_new(|_, new| new.value = i);
+            r.read_newest();
+        }
+        assert_eq!(log.lock().unwrap().zeroized, 0);
+
+        w.scrub();
+        assert!(log.lock().unwrap().zeroized >= 2);
+        assert!(r.rewind(1).is_none());
+        assert_eq!(r.read_newest().value, 5);
+        let mut unused = Vec::new();
+        while let Some(buf) = w.unused_bufs_rx.try_recv() {
+            assert_eq!(buf.value, 0);
+            unused.push(buf);
+        }
+        assert!(!unused.is_empty());
+        unused.into_iter().for_each(|buf| w.recycle(buf));
+        w.write_new(|old, new| new.value = old.value + 1);
+        assert_eq!(r.read_newest().value, 6);
+    }
+
+    #[test]
+    fn test_refresh_is_rejected() {
+        let (init, _) = secret();
+        let result = TripleBufferBuilder::new(init)
+            .clone_with(Secret::clone)
+            .refresh_with(|old, new| new.value = old.value)
+            .zeroize()
+            .build();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,236 @@
+//! Cold parts of a state that all buffers share instead of copying.
+
+use alloc::sync::Arc;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::ops::Deref;
+
+/// A part of a state that clones by bumping a reference count,
+/// for the large, rarely changing parts of a state, like meshes or
+/// lookup tables next to a few hot fields.
+///
+/// Every buffer of a pair holds its own copy of the state, so cloning
+/// it into a new buffer, or with `clone_from` in `write_update`, copies
+/// everything the state owns. Wrapped in `Shared`, a part gets shared
+/// between all buffers holding the same version of it instead, and
+/// only `make_mut` copies it, if another buffer still references it.
+/// So creating and refreshing buffers costs the same, however large
+/// the shared parts are, and a reader never sees a change to one
+/// happen, just like with the rest of the state.
+///
+/// With the `serde` feature, it serializes as the value it wraps.
+///
+/// # Example
+/// ```
+/// use simple_triple_buffer::Shared;
+///
+/// #[derive(Clone)]
+/// struct Level {
+///     meshes: Shared<Vec<[f32; 3]>>,
+///     player: [f32; 3],
+/// }
+///
+/// let (mut writer, mut reader) = simple_triple_buffer::new_clone(Level {
+///     meshes: Shared::new(vec![[0.0; 3]; 100_000]),
+///     player: [0.0; 3],
+/// });
+/// // Only copies `player`.
+/// writer.write_update(|level| level.player[0] += 1.0);
+/// let old = reader.read_newest().clone();
+///
+/// // Copies the meshes once, as `old` still shares them.
+/// writer.write_update(|level| Shared::make_mut(&mut level.meshes)[0] = [1.0; 3]);
+/// assert_eq!(old.meshes[0], [0.0; 3]);
+/// assert_eq!(reader.read_newest().meshes[0], [1.0; 3]);
+/// ````
+pub struct Shared<U>(Arc<U>);
+
+impl<U> Shared<U> {
+    /// Wrap `value` to be shared between clones.
+    pub fn new(value: U) -> Self {
+        Self(Arc::new(value))
+    }
+
+    /// Get mutable access to the value, first copying it if any
+    /// other clone shares it, so none of them sees the change.
+    pub fn make_mut(this: &mut Self) -> &mut U
+    where
+        U: Clone,
+    {
+        Arc::make_mut(&mut this.0)
+    }
+
+    /// Check whether two clones share the same value,
+    /// without comparing the values themselves.
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+
+    /// Get the value, copying it only if other clones share it.
+    pub fn into_inner(this: Self) -> U
+    where
+        U: Clone,
+    {
+        Arc::try_unwrap(this.0).unwrap_or_else(|shared| U::clone(&shared))
+    }
+}
+
+impl<U> Clone for Shared<U> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        // Skips touching the count when the buffer still shares it.
+        if !Shared::ptr_eq(self, source) {
+            self.0 = source.0.clone();
+        }
+    }
+}
+
+impl<U> Deref for Shared<U> {
+    type Target = U;
+
+    fn deref(&self) -> &U {
+        &self.0
+    }
+}
+
+impl<U> AsRef<U> for Shared<U> {
+    fn as_ref(&self) -> &U {
+        &self.0
+    }
+}
+
+impl<U> From<U> for Shared<U> {
+    fn from(value: U) -> Self {
+        Self::new(value)
+    }
+}
+
+impl<U: Default> Default for Shared<U> {
+    fn default() -> Self {
+        Self::new(U::default())
+    }
+}
+
+impl<U: fmt::Debug> fmt::Debug for Shared<U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        U::fmt(self, f)
+    }
+}
+
+impl<U: PartialEq> PartialEq for Shared<U> {
+    fn eq(&self, other: &Self) -> bool {
+        Shared::ptr_eq(self, other) || **self == **other
+    }
+}
+
+impl<U: Eq> Eq for Shared<U> {}
+
+impl<U: Hash> Hash for Shared<U> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        U::hash(self, state)
+    }
+}
+
+/// Only available with the `serde` feature.
+#[cfg(feature = "serde")]
+impl<U: serde::Serialize> serde::Serialize for Shared<U> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        U::serialize(self, serializer)
+    }
+}
+
+/// Only available with the `serde` feature.
+#[cfg(feature = "serde")]
+impl<'de, U: serde::Deserialize<'de>> serde::Deserialize<'de> for Shared<U> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        U::deserialize(deserializer).map(Shared::new)
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::Shared;
+    use crate::new_clone;
+
+    /// Large cold data that counts how often it gets copied.
+    struct Assets {
+        table: Vec<u64>,
+        copies: Arc<AtomicUsize>,
+    }
+
+    impl Clone for Assets {
+        fn clone(&self) -> Self {
+            self.copies.fetch_add(1, Ordering::Relaxed);
+            Self {
+                table: self.table.clone(),
+                copies: self.copies.clone(),
+            }
+        }
+    }
+
+    #[derive(Clone)]
+    struct State {
+        assets: Shared<Assets>,
+        frame: u64,
+    }
+
+    #[test]
+    fn test_cold_fields_are_not_copied() {
+        let copies = Arc::new(AtomicUsize::new(0));
+        let (mut w, mut r) = new_clone(State {
+            assets: Shared::new(Assets {
+                table: vec![0; 100_000],
+                copies: copies.clone(),
+            }),
+            frame: 0,
+        });
+        for _ in 0..100 {
+            w.write_update(|state| state.frame += 1);
+            w.write_new(|old, new| new.clone_from(old));
+            r.read_newest();
+        }
+        assert_eq!(copies.load(Ordering::Relaxed), 0);
+
+        // The buffers and the reader share the assets, so changing
+        // them copies them once, and never behind the reader's back.
+        let before = r.read_newest().clone();
+        w.write_update(|state| Shared::make_mut(&mut state.assets).table[0] = 1);
+        assert_eq!(copies.load(Ordering::Relaxed), 1);
+        assert_eq!(before.assets.table[0], 0);
+        let after = r.read_newest();
+        assert_eq!(after.assets.table[0], 1);
+        assert!(!Shared::ptr_eq(&before.assets, &after.assets));
+
+        // Once the new assets are in every buffer, they get shared again.
+        for _ in 0..10 {
+            w.write_update(|state| state.frame += 1);
+            r.read_newest();
+        }
+        assert_eq!(copies.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_into_inner() {
+        let a = Shared::new(vec![1, 2]);
+        let b = a.clone();
+        assert_eq!(a, b);
+        assert_eq!(Shared::into_inner(a), [1, 2]);
+        assert_eq!(Shared::into_inner(b), [1, 2]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_passthrough() {
+        let shared = Shared::new(vec![1, 2, 3]);
+        let json = serde_json::to_string(&shared).unwrap();
+        assert_eq!(json, "[1,2,3]");
+        let back: Shared<Vec<i32>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, shared);
+    }
+}
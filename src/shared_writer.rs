@@ -0,0 +1,145 @@
+use alloc::sync::Arc;
+
+use crate::sync::Mutex;
+use crate::{PoolExhausted, PublishReceipt, Writer};
+
+/// A `Writer` that can be cloned and used from multiple threads.
+///
+/// Writes through any of the clones are serialized by a lock,
+/// so the closures passed to them never run concurrently, and
+/// a write waits for any write in progress on another clone.
+/// The `Reader` is not affected by this at all.
+///
+/// Created with `Writer::into_shared`.
+///
+/// # Example
+/// ```
+/// let (writer, mut reader) = simple_triple_buffer::new_clone(0);
+/// let writer = writer.into_shared();
+///
+/// let threads: Vec<_> = (0..4)
+///     .map(|_| {
+///         let writer = writer.clone();
+///         std::thread::spawn(move || writer.write_new(|old, new| *new = *old + 1))
+///     })
+///     .collect();
+/// for t in threads {
+///     t.join().unwrap();
+/// }
+/// assert_eq!(*reader.read_newest(), 4);
+/// ````
+pub struct SharedWriter<T> {
+    writer: Arc<Mutex<Writer<T>>>,
+}
+
+impl<T> Writer<T> {
+    /// Turn this writer into one that can be cloned
+    /// and used from multiple threads.
+    pub fn into_shared(self) -> SharedWriter<T> {
+        SharedWriter {
+            writer: Arc::new(Mutex::new(self)),
+        }
+    }
+}
+
+impl<T> SharedWriter<T> {
+    /// Write the next state, see `Writer::write_new`.
+    ///
+    /// The previous state passed to `write_op` is the one published
+    /// last through any of the clones.
+    pub fn write_new(&self, write_op: impl FnOnce(&T, &mut T)) -> PublishReceipt {
+        self.writer.lock().write_new(write_op)
+    }
+
+    /// Write the next state unless no unused buffer
+    /// is available, see `Writer::try_write_new`.
+    pub fn try_write_new(
+        &self,
+        write_op: impl FnOnce(&T, &mut T),
+    ) -> Result<PublishReceipt, PoolExhausted> {
+        self.writer.lock().try_write_new(write_op)
+    }
+
+    /// Publish a candidate state only if its `key` is greater than
+    /// the one of the state published last through any of the
+    /// clones, see `Writer::publish_if_newer`.
+    pub fn publish_if_newer<K: Ord>(
+        &self,
+        key: impl Fn(&T) -> K,
+        write_op: impl FnOnce(&T, &mut T),
+    ) -> bool {
+        self.writer.lock().publish_if_newer(key, write_op)
+    }
+
+    /// Check whether the `Reader` has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.writer.lock().is_closed()
+    }
+}
+
+impl<T: Clone> SharedWriter<T> {
+    /// Write the next state by updating a copy of
+    /// the previous one, see `Writer::write_update`.
+    pub fn write_update(&self, update_op: impl FnOnce(&mut T)) -> PublishReceipt {
+        self.writer.lock().write_update(update_op)
+    }
+}
+
+impl<T> Clone for SharedWriter<T> {
+    fn clone(&self) -> Self {
+        Self {
+            writer: self.writer.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use crate::new_with;
+
+    #[test]
+    fn test_concurrent_writers() {
+        let count = Arc::new(Mutex::new(0));
+        let c = count.clone();
+        let (w, mut r) = new_with(vec![0u64; 16], move |v| {
+            *c.lock().unwrap() += 1;
+            v.clone()
+        });
+        let w = w.into_shared();
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let w = w.clone();
+                std::thread::spawn(move || {
+                    for _ in 0..1000 {
+                        w.write_update(|v| v.iter_mut().for_each(|x| *x += 1));
+                    }
+                })
+            })
+            .collect();
+
+        let mut last = 0;
+        while last < 4000 {
+            let state = r.read_newest();
+            assert!(state.iter().all(|v| *v == state[0]));
+            assert!(state[0] >= last);
+            last = state[0];
+        }
+        for t in threads {
+            t.join().unwrap();
+        }
+        assert!(*count.lock().unwrap() <= 3);
+    }
+
+    #[test]
+    fn test_disconnect_after_last_clone() {
+        let (w, r) = crate::new_clone(0);
+        let w = w.into_shared();
+        let w2 = w.clone();
+        drop(w);
+        assert!(!r.is_disconnected());
+        drop(w2);
+        assert!(r.is_disconnected());
+    }
+}
@@ -0,0 +1,203 @@
+//! A buffer pair for small `Copy` states, built on a sequence lock.
+//!
+//! There is only a single shared copy of the state, stored inline next
+//! to a sequence counter. The writer bumps the counter around every
+//! write, and the reader copies the state out and retries if the
+//! counter shows that a write overlapped with the copy. Both halves
+//! keep a private copy of the state, so neither ever waits for
+//! the other while holding a reference to it. Writes never wait at all,
+//! reads only retry while a write is in progress.
+//!
+//! Apart from creating the pair, nothing allocates, and no locks are
+//! involved. Since the reader copies the whole state whenever it
+//! picks up a new one, this beats the regular pairs as long as that
+//! copy is cheap compared to their bookkeeping. In `benches/small.rs`
+//! this is about 10x faster for 16 bytes, 4x for 64 bytes, and breaks
+//! even around 256 bytes.
+//! For large states, or if the reader has to be wait-free too,
+//! use `new_pod` or `StaticTripleBuffer` instead.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::small;
+//!
+//! let (mut writer, mut reader) = small::new([0u32; 16]);
+//! writer.write_new(|old, new| new[0] = old[0] + 1);
+//! assert_eq!(reader.read_newest()[0], 1);
+//! ````
+
+use alloc::sync::Arc;
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ptr;
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+
+struct Shared<T> {
+    /// Odd while a write is in progress.
+    seq: AtomicUsize,
+    state: UnsafeCell<T>,
+}
+
+// SAFETY: Concurrent accesses to the state are detected
+// with the sequence counter, and discarded.
+unsafe impl<T: Copy + Send> Sync for Shared<T> {}
+
+/// Create a new buffer pair for a small `Copy` state.
+pub fn new<T: Copy>(init: T) -> (Writer<T>, Reader<T>) {
+    let shared = Arc::new(Shared {
+        seq: AtomicUsize::new(0),
+        state: UnsafeCell::new(init),
+    });
+    let writer = Writer {
+        shared: shared.clone(),
+        last: init,
+    };
+    let reader = Reader {
+        shared,
+        last: init,
+        seq: 0,
+    };
+    (writer, reader)
+}
+
+/// Write side of a pair for a small `Copy` state.
+pub struct Writer<T> {
+    shared: Arc<Shared<T>>,
+    /// The last published state.
+    last: T,
+}
+
+impl<T: Copy> Writer<T> {
+    /// Write a new state and publish it.
+    ///
+    /// The closure gets a reference to the previously published
+    /// state and to a copy of it it can modify.
+    pub fn write_new(&mut self, f: impl FnOnce(&T, &mut T)) {
+        let mut new = self.last;
+        f(&self.last, &mut new);
+        self.write(new);
+    }
+
+    /// Write a new state by modifying a copy of the previous one.
+    pub fn write_update(&mut self, f: impl FnOnce(&mut T)) {
+        let mut new = self.last;
+        f(&mut new);
+        self.write(new);
+    }
+
+    /// Publish `value` as the new state.
+    pub fn write(&mut self, value: T) {
+        let shared = &*self.shared;
+        // Only the writer changes the counter, so no RMW is needed.
+        let seq = shared.seq.load(Ordering::Relaxed);
+        shared.seq.store(seq.wrapping_add(1), Ordering::Relaxed);
+        fence(Ordering::Release);
+        // SAFETY: Readers that race with this write notice
+        // the changed counter and discard what they read.
+        unsafe { ptr::write_volatile(shared.state.get(), value) };
+        shared.seq.store(seq.wrapping_add(2), Ordering::Release);
+        self.last = value;
+    }
+}
+
+/// Read side of a pair for a small `Copy` state.
+pub struct Reader<T> {
+    shared: Arc<Shared<T>>,
+    /// The last state read.
+    last: T,
+    /// The counter value `last` was read at.
+    seq: usize,
+}
+
+impl<T: Copy> Reader<T> {
+    /// Get a view to the newest published state.
+    pub fn read_newest(&mut self) -> &T {
+        let shared = &*self.shared;
+        loop {
+            let seq = shared.seq.load(Ordering::Acquire);
+            if seq == self.seq {
+                break;
+            }
+            if seq % 2 == 1 {
+                core::hint::spin_loop();
+                continue;
+            }
+            // SAFETY: The copy might be torn, which is why it stays a
+            // `MaybeUninit`, as a torn `T` may not even be a valid one.
+            let state = unsafe { ptr::read_volatile(shared.state.get() as *const MaybeUninit<T>) };
+            fence(Ordering::Acquire);
+            if shared.seq.load(Ordering::Relaxed) == seq {
+                // SAFETY: No write happened in the meantime,
+                // so this is a copy of the published state.
+                self.last = unsafe { state.assume_init() };
+                self.seq = seq;
+                break;
+            }
+        }
+        &self.last
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::new;
+
+    #[test]
+    fn test_single_thread() {
+        let (mut w, mut r) = new(0u32);
+        assert_eq!(*r.read_newest(), 0);
+        w.write_new(|old, new| *new = *old + 1);
+        w.write_update(|v| *v += 1);
+        assert_eq!(*r.read_newest(), 2);
+        w.write(10);
+        assert_eq!(*r.read_newest(), 10);
+    }
+
+    #[test]
+    fn test_no_torn_reads() {
+        let (mut w, mut r) = new([0u64; 8]);
+        let writer = std::thread::spawn(move || {
+            for i in 1..=1_000_000 {
+                w.write([i; 8]);
+            }
+        });
+        let mut last = 0;
+        while last < 1_000_000 {
+            let state = r.read_newest();
+            assert!(state.iter().all(|v| *v == state[0]), "torn read");
+            assert!(state[0] >= last);
+            last = state[0];
+        }
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_states_with_invalid_bit_patterns() {
+        #[derive(Debug, Clone, Copy, PartialEq)]
+        enum Phase {
+            Idle,
+            Running(u32),
+            Done,
+        }
+
+        let (mut w, mut r) = new((false, Phase::Idle));
+        let writer = std::thread::spawn(move || {
+            for i in 1..=100_000 {
+                let phase = if i % 2 == 0 {
+                    Phase::Running(i)
+                } else {
+                    Phase::Idle
+                };
+                w.write((i % 3 == 0, phase));
+            }
+            w.write((true, Phase::Done));
+        });
+        while *r.read_newest() != (true, Phase::Done) {
+            let (flag, phase) = *r.read_newest();
+            if let Phase::Running(i) = phase {
+                assert_eq!(flag, i % 3 == 0);
+            }
+        }
+        writer.join().unwrap();
+    }
+}
@@ -0,0 +1,324 @@
+//! Saving the newest state to, and loading it back from, any `io::Write`
+//! or `io::Read`, for checkpointing across restarts.
+//!
+//! The serialization itself is left to a `SnapshotFormat`, so any serde
+//! format can be plugged in. Snapshots start with a small header naming
+//! the format and the version of the state type, so loading one saved
+//! with a different format or version fails with a `SnapshotError`
+//! instead of producing garbage.
+//!
+//! # Example
+//! ```
+//! use std::io;
+//! use simple_triple_buffer::snapshot::{Snapshot, SnapshotFormat};
+//!
+//! struct Json;
+//!
+//! impl SnapshotFormat for Json {
+//!     const NAME: &'static str = "json";
+//!     type Error = serde_json::Error;
+//!
+//!     fn serialize<T: serde::Serialize>(&self, state: &T, w: &mut dyn io::Write) -> Result<(), Self::Error> {
+//!         serde_json::to_writer(w, state)
+//!     }
+//!
+//!     fn deserialize<T: serde::de::DeserializeOwned>(&self, r: &mut dyn io::Read) -> Result<T, Self::Error> {
+//!         serde_json::from_reader(r)
+//!     }
+//! }
+//!
+//! let snapshot = Snapshot::new(Json).version(2);
+//!
+//! let (_writer, mut reader) = simple_triple_buffer::new_clone(vec![1, 2, 3]);
+//! let mut saved = Vec::new();
+//! reader.save_newest(&snapshot, &mut saved).unwrap();
+//!
+//! let (mut writer, mut reader) = simple_triple_buffer::new_clone(Vec::<i32>::new());
+//! writer.load_published(&snapshot, &saved[..]).unwrap();
+//! assert_eq!(*reader.read_newest(), [1, 2, 3]);
+//! ````
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::io::{self, Read, Write};
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::{Reader, SnapshotError, Writer};
+
+const MAGIC: &[u8; 4] = b"STBS";
+/// The version of the header layout itself.
+const HEADER_VERSION: u8 = 1;
+
+/// A serde format snapshots get written in.
+pub trait SnapshotFormat {
+    /// A name identifying the format, stored in every snapshot.
+    const NAME: &'static str;
+
+    /// The error of the format.
+    type Error: Error + Send + Sync + 'static;
+
+    /// Serialize `state` into `w`.
+    fn serialize<T: Serialize>(&self, state: &T, w: &mut dyn Write) -> Result<(), Self::Error>;
+
+    /// Deserialize a state from `r`.
+    fn deserialize<T: DeserializeOwned>(&self, r: &mut dyn Read) -> Result<T, Self::Error>;
+}
+
+/// How snapshots get saved and loaded: with which format,
+/// and which version of the state type.
+pub struct Snapshot<F> {
+    format: F,
+    version: u32,
+}
+
+impl<F: SnapshotFormat> Snapshot<F> {
+    /// Save and load snapshots in `format`, with version 0.
+    pub fn new(format: F) -> Self {
+        Self { format, version: 0 }
+    }
+
+    /// Set the version of the state type, to be bumped whenever
+    /// snapshots of the old one can no longer be loaded.
+    pub fn version(mut self, version: u32) -> Self {
+        self.version = version;
+        self
+    }
+
+    fn save<T: Serialize>(&self, state: &T, mut w: impl Write) -> Result<(), SnapshotError> {
+        let name = F::NAME.as_bytes();
+        let name_len =
+            u8::try_from(name.len()).expect("snapshot format names are at most 255 bytes");
+        w.write_all(MAGIC)?;
+        w.write_all(&[HEADER_VERSION, name_len])?;
+        w.write_all(name)?;
+        w.write_all(&self.version.to_le_bytes())?;
+        self.format
+            .serialize(state, &mut w)
+            .map_err(|e| SnapshotError::Format(Box::new(e)))?;
+        w.flush()?;
+        Ok(())
+    }
+
+    fn load<T: DeserializeOwned>(&self, mut r: impl Read) -> Result<T, SnapshotError> {
+        let mut start = [0; 6];
+        r.read_exact(&mut start).map_err(not_a_snapshot)?;
+        if start[..4] != MAGIC[..] || start[4] != HEADER_VERSION {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        let mut name = vec![0; usize::from(start[5])];
+        r.read_exact(&mut name).map_err(not_a_snapshot)?;
+        if name != F::NAME.as_bytes() {
+            return Err(SnapshotError::FormatMismatch {
+                expected: F::NAME,
+                found: String::from_utf8_lossy(&name).into_owned(),
+            });
+        }
+        let mut version = [0; 4];
+        r.read_exact(&mut version).map_err(not_a_snapshot)?;
+        let version = u32::from_le_bytes(version);
+        if version != self.version {
+            return Err(SnapshotError::VersionMismatch {
+                expected: self.version,
+                found: version,
+            });
+        }
+        self.format
+            .deserialize(&mut r)
+            .map_err(|e| SnapshotError::Format(Box::new(e)))
+    }
+}
+
+/// Data too short for a header is not a snapshot.
+fn not_a_snapshot(e: io::Error) -> SnapshotError {
+    match e.kind() {
+        io::ErrorKind::UnexpectedEof => SnapshotError::NotASnapshot,
+        _ => SnapshotError::Io(e),
+    }
+}
+
+impl<T: Serialize> Reader<T> {
+    /// Save the newest state to `w`, picking it up first.
+    ///
+    /// Only available with the `serde` feature.
+    pub fn save_newest<F: SnapshotFormat>(
+        &mut self,
+        snapshot: &Snapshot<F>,
+        w: impl Write,
+    ) -> Result<(), SnapshotError> {
+        snapshot.save(self.read_newest(), w)
+    }
+}
+
+impl<T: Serialize> Writer<T> {
+    /// Save the last published state to `w`.
+    ///
+    /// Only available with the `serde` feature.
+    pub fn save_newest<F: SnapshotFormat>(
+        &self,
+        snapshot: &Snapshot<F>,
+        w: impl Write,
+    ) -> Result<(), SnapshotError> {
+        snapshot.save(&**self.prev_buf, w)
+    }
+}
+
+impl<T: DeserializeOwned> Writer<T> {
+    /// Load a state saved with `save_newest` from `r`, and publish it.
+    ///
+    /// Nothing gets published if loading fails.
+    ///
+    /// Only available with the `serde` feature.
+    pub fn load_published<F: SnapshotFormat>(
+        &mut self,
+        snapshot: &Snapshot<F>,
+        r: impl Read,
+    ) -> Result<(), SnapshotError> {
+        let state = snapshot.load(r)?;
+        self.try_write_new(|_, new| *new = state)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+    use std::io::{Read, Write};
+
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Serialize};
+
+    use super::{Snapshot, SnapshotFormat};
+    use crate::{new_clone, SnapshotError, TripleBufferBuilder};
+
+    struct Json;
+
+    impl SnapshotFormat for Json {
+        const NAME: &'static str = "json";
+        type Error = serde_json::Error;
+
+        fn serialize<T: Serialize>(&self, state: &T, w: &mut dyn Write) -> Result<(), Self::Error> {
+            serde_json::to_writer(w, state)
+        }
+
+        fn deserialize<T: DeserializeOwned>(&self, r: &mut dyn Read) -> Result<T, Self::Error> {
+            serde_json::from_reader(r)
+        }
+    }
+
+    /// Same encoding, but a different name.
+    struct OtherJson;
+
+    impl SnapshotFormat for OtherJson {
+        const NAME: &'static str = "other";
+        type Error = serde_json::Error;
+
+        fn serialize<T: Serialize>(&self, state: &T, w: &mut dyn Write) -> Result<(), Self::Error> {
+            Json.serialize(state, w)
+        }
+
+        fn deserialize<T: DeserializeOwned>(&self, r: &mut dyn Read) -> Result<T, Self::Error> {
+            Json.deserialize(r)
+        }
+    }
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+    struct Scene {
+        name: String,
+        bodies: Vec<Body>,
+        groups: HashMap<String, Vec<usize>>,
+        settings: BTreeMap<u32, Option<f64>>,
+    }
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Body {
+        position: [f32; 3],
+        tags: Vec<String>,
+    }
+
+    fn scene() -> Scene {
+        Scene {
+            name: "level 1".into(),
+            bodies: vec![
+                Body {
+                    position: [1.0, 2.0, 3.0],
+                    tags: vec!["player".into()],
+                },
+                Body {
+                    position: [0.5, 0.0, -1.0],
+                    tags: Vec::new(),
+                },
+            ],
+            groups: vec![("all".to_string(), vec![0, 1])].into_iter().collect(),
+            settings: vec![(1, Some(9.81)), (2, None)].into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let snapshot = Snapshot::new(Json).version(3);
+        let (mut w, mut r) = new_clone(Scene::default());
+        w.write_new(|_, new| *new = scene());
+
+        let (mut from_reader, mut from_writer) = (Vec::new(), Vec::new());
+        r.save_newest(&snapshot, &mut from_reader).unwrap();
+        w.save_newest(&snapshot, &mut from_writer).unwrap();
+        assert_eq!(from_reader, from_writer);
+
+        let (mut w, mut r) = new_clone(Scene::default());
+        w.load_published(&snapshot, &from_reader[..]).unwrap();
+        assert!(r.has_update());
+        assert_eq!(*r.read_newest(), scene());
+    }
+
+    #[test]
+    fn test_mismatches_are_errors() {
+        let mut saved = Vec::new();
+        let (mut w, mut r) = new_clone(scene());
+        r.save_newest(&Snapshot::new(Json).version(1), &mut saved)
+            .unwrap();
+
+        let result = w.load_published(&Snapshot::new(Json).version(2), &saved[..]);
+        assert!(matches!(
+            result,
+            Err(SnapshotError::VersionMismatch {
+                expected: 2,
+                found: 1
+            })
+        ));
+        let result = w.load_published(&Snapshot::new(OtherJson).version(1), &saved[..]);
+        match result {
+            Err(SnapshotError::FormatMismatch { expected, found }) => {
+                assert_eq!((expected, &*found), ("other", "json"))
+            }
+            other => panic!("{:?}", other),
+        }
+        let result = w.load_published(&Snapshot::new(Json).version(1), &b"{}"[..]);
+        assert!(matches!(result, Err(SnapshotError::NotASnapshot)));
+        let result = w.load_published(&Snapshot::new(Json).version(1), &saved[..20]);
+        assert!(matches!(result, Err(SnapshotError::Format(_))));
+        assert!(!r.has_update());
+
+        // Loading a different type fails in the format.
+        let (mut w, _r) = new_clone(0u32);
+        let result = w.load_published(&Snapshot::new(Json).version(1), &saved[..]);
+        assert!(matches!(result, Err(SnapshotError::Format(_))));
+    }
+
+    #[test]
+    fn test_load_without_buffer() {
+        let mut saved = Vec::new();
+        let (w, _r) = new_clone(1u8);
+        w.save_newest(&Snapshot::new(Json), &mut saved).unwrap();
+
+        let (mut w, r) = TripleBufferBuilder::new(0u8)
+            .spare_buffers(vec![0])
+            .build()
+            .unwrap();
+        let _held = r.clone();
+        w.write_new(|_, new| *new = 2);
+        let result = w.load_published(&Snapshot::new(Json), &saved[..]);
+        assert!(matches!(result, Err(SnapshotError::PoolExhausted)));
+    }
+}
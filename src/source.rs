@@ -0,0 +1,323 @@
+use crate::{
+    recycle, scrub_unique, Buf, MakeBuf, Reader, SharedState, TripleBufferBuilder, Writer,
+};
+
+/// Creates the buffers of a pair, and gets notified
+/// as they move in and out of use.
+///
+/// This is for buffers that are not plain values, like handles
+/// to memory that needs to be mapped before it can be written to.
+///
+/// All methods get called for the `Writer` while it is alive, so
+/// they never run concurrently and do not need to be `Sync`.
+/// After the `Writer` has been dropped, the source is kept around
+/// to retire the buffers still in use by readers. That is also why
+/// sources have to be `'static`, as readers have no lifetime
+/// that could bound what a source borrows.
+pub trait BufferSource<T> {
+    /// Create a new buffer, given the previous state.
+    fn create(&mut self, prev: &T) -> T;
+
+    /// Called once for each time a buffer got returned to the pool
+    /// of unused buffers, when the `Writer` picks it up again.
+    fn on_recycle(&mut self, buf: &mut T) {
+        let _ = buf;
+    }
+
+    /// Called when a buffer gets dropped from rotation,
+    /// which happens when the pair gets dropped.
+    fn on_retire(&mut self, buf: T) {
+        drop(buf);
+    }
+}
+
+/// The source used by the constructors taking a clone function.
+pub(crate) struct CloneWith<F>(pub(crate) F);
+
+impl<T, F: FnMut(&T) -> T> BufferSource<T> for CloneWith<F> {
+    fn create(&mut self, prev: &T) -> T {
+        (self.0)(prev)
+    }
+}
+
+/// Create a new buffer pair whose buffers are managed by `source`.
+///
+/// Buffers returned into a `BufferPool` that is full get dropped
+/// without being retired through the source, so pairs using one should
+/// size it to hold all their buffers, see `TripleBufferBuilder::pool`.
+///
+/// # Example
+/// ```
+/// use simple_triple_buffer::BufferSource;
+///
+/// struct Mapped {
+///     mapped: bool,
+///     data: Vec<u8>,
+/// }
+///
+/// struct Mapper;
+///
+/// impl BufferSource<Mapped> for Mapper {
+///     fn create(&mut self, prev: &Mapped) -> Mapped {
+///         Mapped { mapped: true, data: prev.data.clone() }
+///     }
+///     fn on_recycle(&mut self, buf: &mut Mapped) {
+///         buf.mapped = true;
+///     }
+/// }
+///
+/// let init = Mapped { mapped: true, data: vec![0] };
+/// let (mut writer, mut reader) = simple_triple_buffer::new_with_source(init, Mapper);
+/// writer.write_new(|old, new| {
+///     assert!(new.mapped);
+///     new.data.clone_from(&old.data);
+///     new.data.push(1);
+/// });
+/// assert_eq!(reader.read_newest().data, [0, 1]);
+/// ````
+pub fn new_with_source<T>(
+    init: T,
+    source: impl BufferSource<T> + 'static + Send,
+) -> (Writer<T>, Reader<T>) {
+    TripleBufferBuilder::new(init).source(source).finish()
+}
+
+impl<T> SharedState<T> {
+    /// Return a buffer released by a reader of a pair with a custom
+    /// source, or retire it if the writer is gone.
+    pub(crate) fn release(&self, reader: &Reader<T>, buf: Buf<T>) {
+        // Holding the lock keeps the writer from handing over
+        // the source while the buffer is on its way to it.
+        let mut orphaned = self.orphaned_source.lock();
+        match orphaned.as_mut() {
+            None => recycle(&reader.unused_bufs_tx, self.scrub, buf),
+            Some(source) => {
+                let mut buf = buf;
+                scrub_unique(self.scrub, &mut buf);
+                if let Ok(buf) = Buf::try_unwrap(buf) {
+                    source.on_retire(buf);
+                }
+            }
+        }
+    }
+
+    /// Take a buffer that was held by a dropped reader,
+    /// and that nothing else references anymore.
+    pub(crate) fn take_released(&self) -> Option<Buf<T>> {
+        let mut released = self.released.lock();
+        let idx = released
+            .iter_mut()
+            .position(|buf| Buf::get_mut(buf).is_some())?;
+        Some(released.swap_remove(idx))
+    }
+
+    /// Retire all buffers still held by the shared state.
+    pub(crate) fn retire_all(&mut self) {
+        let mut source = self.orphaned_source.lock().take();
+        let latest = self.pending.take();
+        let history = core::mem::take(&mut *self.history.lock());
+        let released = core::mem::take(&mut *self.released.lock());
+        for mut buf in latest.into_iter().chain(history).chain(released) {
+            scrub_unique(self.scrub, &mut buf);
+            if let (Some(source), Ok(buf)) = (&mut source, Buf::try_unwrap(buf)) {
+                source.on_retire(buf);
+            }
+        }
+    }
+}
+
+impl<T> Writer<T> {
+    /// Notify the source and hooks that a buffer got returned to the pool.
+    pub(crate) fn on_recycle(&mut self, buf: &mut Buf<T>) {
+        if let MakeBuf::Source(source) = &mut self.make_buf {
+            source.on_recycle(Buf::get_mut(buf).unwrap());
+        }
+        #[cfg(feature = "std")]
+        self.hook_recycled(buf);
+    }
+
+    /// Hand the source over to the shared state,
+    /// retiring the buffers only the writer can reach.
+    pub(crate) fn orphan_source(&mut self) {
+        let mut source = match core::mem::replace(&mut self.make_buf, MakeBuf::Never) {
+            MakeBuf::Source(source) => source,
+            make_buf => {
+                self.make_buf = make_buf;
+                return;
+            }
+        };
+        let shared = &self.read_update.shared;
+        let mut orphaned = shared.orphaned_source.lock();
+        let spare = self.spare.take();
+        for buf in spare
+            .into_iter()
+            .chain(core::iter::from_fn(|| self.unused_bufs_rx.try_recv()))
+        {
+            let mut buf = Buf::try_unwrap(buf).ok().unwrap();
+            source.on_recycle(&mut buf);
+            source.on_retire(buf);
+        }
+        *orphaned = Some(source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use super::{new_with_source, BufferSource};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    enum Event {
+        Create(usize),
+        Write(usize),
+        Recycle(usize),
+        Retire(usize),
+    }
+
+    struct Buffer {
+        id: usize,
+        value: u32,
+    }
+
+    type Log = Arc<Mutex<Vec<Event>>>;
+
+    struct Source {
+        next_id: usize,
+        log: Log,
+    }
+
+    impl BufferSource<Buffer> for Source {
+        fn create(&mut self, prev: &Buffer) -> Buffer {
+            self.next_id += 1;
+            self.log.lock().unwrap().push(Event::Create(self.next_id));
+            Buffer {
+                id: self.next_id,
+                value: prev.value,
+            }
+        }
+
+        fn on_recycle(&mut self, buf: &mut Buffer) {
+            self.log.lock().unwrap().push(Event::Recycle(buf.id));
+        }
+
+        fn on_retire(&mut self, buf: Buffer) {
+            self.log.lock().unwrap().push(Event::Retire(buf.id));
+        }
+    }
+
+    fn source() -> (Source, Log) {
+        let log = Log::default();
+        let source = Source {
+            next_id: 0,
+            log: log.clone(),
+        };
+        (source, log)
+    }
+
+    /// Check that every buffer follows
+    /// `Create, Write, (Recycle, Write)*, Recycle?, Retire`,
+    /// with the initial state starting out written.
+    fn check_sequences(log: &[Event]) {
+        let mut sequences: HashMap<usize, Vec<Event>> = HashMap::new();
+        sequences.insert(0, vec![Event::Create(0), Event::Write(0)]);
+        for event in log {
+            let (Event::Create(id) | Event::Write(id) | Event::Recycle(id) | Event::Retire(id)) =
+                *event;
+            sequences.entry(id).or_default().push(*event);
+        }
+        for (id, events) in sequences {
+            // Whether the buffer is ready to be written into.
+            let mut unused = false;
+            for (i, event) in events.iter().enumerate() {
+                match event {
+                    Event::Create(_) => {
+                        assert_eq!(i, 0);
+                        unused = true;
+                    }
+                    Event::Write(_) => {
+                        assert!(unused, "{} written without recycling", id);
+                        unused = false;
+                    }
+                    Event::Recycle(_) => {
+                        assert!(!unused, "{} recycled twice", id);
+                        unused = true;
+                    }
+                    Event::Retire(_) => assert_eq!(i, events.len() - 1),
+                }
+            }
+            assert!(
+                matches!(events.last(), Some(Event::Retire(_))),
+                "{} was not retired: {:?}",
+                id,
+                events
+            );
+        }
+    }
+
+    fn run(drop_writer_first: bool, clone_reader: bool) {
+        let (source, log) = source();
+        let (mut w, mut r) = new_with_source(Buffer { id: 0, value: 0 }, source);
+        let mut r2 = clone_reader.then(|| r.clone());
+        for i in 1..=20 {
+            let log = log.clone();
+            w.write_new(move |old, new| {
+                log.lock().unwrap().push(Event::Write(new.id));
+                new.value = old.value + 1;
+            });
+            if i % 3 == 0 {
+                assert_eq!(r.read_newest().value, i);
+            }
+            if let Some(r2) = &mut r2 {
+                if i % 7 == 0 {
+                    r2.read_newest();
+                }
+            }
+            if i == 10 {
+                drop(r2.take());
+            }
+        }
+        if drop_writer_first {
+            drop(w);
+            r.read_newest();
+            drop(r);
+        } else {
+            drop(r);
+            drop(w);
+        }
+        let log = log.lock().unwrap();
+        check_sequences(&log);
+    }
+
+    #[test]
+    fn test_call_sequence_reader_dropped_first() {
+        run(false, false);
+        run(false, true);
+    }
+
+    #[test]
+    fn test_call_sequence_writer_dropped_first() {
+        run(true, false);
+        run(true, true);
+    }
+
+    #[test]
+    fn test_released_buffers_get_reused() {
+        let (source, log) = source();
+        let (mut w, r) = new_with_source(Buffer { id: 0, value: 0 }, source);
+        for _ in 0..10 {
+            let mut r2 = r.clone();
+            w.write_new(|_, new| new.value += 1);
+            r2.read_newest();
+            w.write_new(|_, new| new.value += 1);
+        }
+        let creates = log
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|e| matches!(e, Event::Create(_)))
+            .count();
+        assert!(creates <= 3, "{}", creates);
+    }
+}
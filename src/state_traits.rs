@@ -0,0 +1,58 @@
+use crate::{Reader, Writer};
+
+/// The write side of a pair, as far as code publishing states needs it.
+///
+/// `Writer` implements it, and so does `test_util::RecordingPublisher`,
+/// so code taking a `&mut dyn StatePublisher<T>` can be tested without
+/// a real pair. The trait is object safe, which is why the closure is
+/// passed by reference.
+pub trait StatePublisher<T> {
+    /// Publish a new state written by `write_op`, which gets the previous
+    /// state and a buffer to overwrite, like with `Writer::write_new`.
+    fn publish_with(&mut self, write_op: &mut dyn FnMut(&T, &mut T));
+}
+
+/// The read side of a pair, as far as code consuming states needs it.
+///
+/// `Reader` implements it, and so does `test_util::ScriptedSubscriber`,
+/// which yields a fixed sequence of states.
+pub trait StateSubscriber<T> {
+    /// Get the newest state, see `Reader::read_newest`.
+    fn newest(&mut self) -> &T;
+
+    /// Check whether `newest` would return a different state than last time.
+    fn has_update(&self) -> bool;
+}
+
+impl<T> StatePublisher<T> for Writer<T> {
+    fn publish_with(&mut self, write_op: &mut dyn FnMut(&T, &mut T)) {
+        self.write_new(write_op);
+    }
+}
+
+impl<T> StateSubscriber<T> for Reader<T> {
+    fn newest(&mut self) -> &T {
+        self.read_newest()
+    }
+
+    fn has_update(&self) -> bool {
+        Reader::has_update(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{StatePublisher, StateSubscriber};
+    use crate::new_clone;
+
+    #[test]
+    fn test_pairs_as_trait_objects() {
+        let (w, r) = new_clone(0);
+        let mut w: Box<dyn StatePublisher<i32>> = Box::new(w);
+        let mut r: Box<dyn StateSubscriber<i32>> = Box::new(r);
+        w.publish_with(&mut |old, new| *new = *old + 1);
+        assert!(r.has_update());
+        assert_eq!(*r.newest(), 1);
+        assert!(!r.has_update());
+    }
+}
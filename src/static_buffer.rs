@@ -0,0 +1,186 @@
+//! A triple buffer that lives entirely in caller-provided storage.
+//!
+//! Unlike the heap based pairs of this crate, `StaticTripleBuffer`
+//! owns exactly three instances of its state inline, and can be
+//! created in a `const` context, for example to be placed in a `static`.
+//! The halves just exchange slot indices through a single atomic.
+
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+/// Set in the shared index if its slot contains a state
+/// the reader has not seen yet.
+const DIRTY: u8 = 0b100;
+const INDEX: u8 = 0b011;
+
+/// A triple buffer with inline storage for all three states.
+///
+/// # Example
+/// ```
+/// use simple_triple_buffer::StaticTripleBuffer;
+///
+/// static BUFFER: StaticTripleBuffer<[u32; 4]> = StaticTripleBuffer::new([0; 4]);
+///
+/// let (mut writer, mut reader) = BUFFER.split();
+/// writer.write_new(|old, new| {
+///     *new = *old;
+///     new[0] = 1;
+/// });
+/// assert_eq!(*reader.read_newest(), [1, 0, 0, 0]);
+/// ````
+pub struct StaticTripleBuffer<T> {
+    slots: [UnsafeCell<T>; 3],
+    /// Index of the slot neither half currently uses, plus `DIRTY`.
+    back: AtomicU8,
+    split: AtomicBool,
+}
+
+// The writer only ever writes to the slot it exclusively owns,
+// all other slots are only read, possibly from both halves at once.
+unsafe impl<T: Send + Sync> Sync for StaticTripleBuffer<T> {}
+
+impl<T: Copy> StaticTripleBuffer<T> {
+    /// Create a buffer with all three slots set to `init`.
+    pub const fn new(init: T) -> Self {
+        Self::from_slots(init, init, init)
+    }
+}
+
+impl<T> StaticTripleBuffer<T> {
+    /// Create a buffer from three initial slot values.
+    ///
+    /// The reader starts out reading `front`, and the writer
+    /// starts out writing into `write`, seeing `front` as the
+    /// previously published state.
+    pub const fn from_slots(front: T, back: T, write: T) -> Self {
+        Self {
+            slots: [
+                UnsafeCell::new(front),
+                UnsafeCell::new(back),
+                UnsafeCell::new(write),
+            ],
+            back: AtomicU8::new(1),
+            split: AtomicBool::new(false),
+        }
+    }
+
+    /// Split the buffer into its writer and reader half.
+    ///
+    /// # Panics
+    /// If the buffer has already been split before.
+    pub fn split(&self) -> (Writer<'_, T>, Reader<'_, T>) {
+        self.try_split().expect("buffer has already been split")
+    }
+
+    /// Split the buffer into its writer and reader half,
+    /// or return `None` if it has already been split before.
+    pub fn try_split(&self) -> Option<(Writer<'_, T>, Reader<'_, T>)> {
+        if self.split.swap(true, Ordering::Relaxed) {
+            return None;
+        }
+        let writer = Writer {
+            buffer: self,
+            write: 2,
+            last: 0,
+        };
+        let reader = Reader {
+            buffer: self,
+            front: 0,
+        };
+        Some((writer, reader))
+    }
+}
+
+/// The writer half of a `StaticTripleBuffer`.
+pub struct Writer<'a, T> {
+    buffer: &'a StaticTripleBuffer<T>,
+    /// The slot owned by the writer.
+    write: u8,
+    /// The slot containing the newest published state.
+    last: u8,
+}
+
+impl<T> Writer<'_, T> {
+    /// Write a new state and publish it.
+    ///
+    /// The closure gets a reference to the previously published state
+    /// and to an older state it has to overwrite.
+    pub fn write_new(&mut self, f: impl FnOnce(&T, &mut T)) {
+        let slots = &self.buffer.slots;
+        // SAFETY: Nobody writes to the last published slot until the
+        // writer takes it back, and the write slot is exclusively ours.
+        let (old, new) = unsafe {
+            (
+                &*slots[self.last as usize].get(),
+                &mut *slots[self.write as usize].get(),
+            )
+        };
+        f(old, new);
+        self.last = self.write;
+        let back = self.buffer.back.swap(self.write | DIRTY, Ordering::AcqRel);
+        self.write = back & INDEX;
+    }
+
+    /// Publish `value` as the new state.
+    pub fn write(&mut self, value: T) {
+        self.write_new(|_, new| *new = value);
+    }
+}
+
+/// The reader half of a `StaticTripleBuffer`.
+pub struct Reader<'a, T> {
+    buffer: &'a StaticTripleBuffer<T>,
+    /// The slot owned by the reader.
+    front: u8,
+}
+
+impl<T> Reader<'_, T> {
+    /// Get the newest published state.
+    pub fn read_newest(&mut self) -> &T {
+        if self.buffer.back.load(Ordering::Relaxed) & DIRTY != 0 {
+            let back = self.buffer.back.swap(self.front, Ordering::AcqRel);
+            self.front = back & INDEX;
+        }
+        // SAFETY: The writer never writes to the slot owned by the reader.
+        unsafe { &*self.buffer.slots[self.front as usize].get() }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StaticTripleBuffer;
+
+    #[test]
+    fn test_static() {
+        static BUFFER: StaticTripleBuffer<u64> = StaticTripleBuffer::new(0);
+        let (mut w, mut r) = BUFFER.split();
+        assert!(BUFFER.try_split().is_none());
+
+        assert_eq!(*r.read_newest(), 0);
+        w.write_new(|old, new| *new = *old + 1);
+        w.write_new(|old, new| *new = *old + 1);
+        assert_eq!(*r.read_newest(), 2);
+        assert_eq!(*r.read_newest(), 2);
+        w.write(10);
+        assert_eq!(*r.read_newest(), 10);
+    }
+
+    #[test]
+    fn test_threads() {
+        static BUFFER: StaticTripleBuffer<[u64; 8]> = StaticTripleBuffer::new([0; 8]);
+        let (mut w, mut r) = BUFFER.split();
+        let writer = std::thread::spawn(move || {
+            for _ in 0..100_000 {
+                w.write_new(|old, new| *new = [old[0] + 1; 8]);
+            }
+        });
+        let mut last = 0;
+        while last < 100_000 {
+            let state = r.read_newest();
+            assert!(state.iter().all(|v| *v == state[0]));
+            assert!(state[0] >= last);
+            last = state[0];
+        }
+        writer.join().unwrap();
+    }
+}
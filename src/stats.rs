@@ -0,0 +1,179 @@
+//! Counters of what a pair did, collected into one `PairStats` snapshot.
+//!
+//! The writer counts its publishes and the buffers it creates, and every
+//! state replaced before any reader picked it up. Readers count the states
+//! they pick up. All of that is a relaxed increment of an atomic or a plain
+//! field of the writer, and none of it is compiled in without the `stats`
+//! feature, along with `Writer::drop_rate`, `Writer::publish_rate` and the
+//! `latency` feature, which builds on it.
+//!
+//! # Example
+//! ```
+//! let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+//! writer.write_new(|_, new| *new = 1);
+//! reader.read_newest();
+//! writer.write_new(|_, new| *new = 2);
+//! writer.write_new(|_, new| *new = 3);
+//!
+//! let stats = simple_triple_buffer::stats::collect(&writer, &reader);
+//! assert_eq!(stats.published, 3);
+//! assert_eq!(stats.picked_up, 1);
+//! assert_eq!(stats.dropped, 1);
+//! assert_eq!(stats.reader_lag, 2);
+//! ````
+
+use core::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+#[cfg(feature = "latency")]
+use crate::LatencyHistogram;
+use crate::{sync, Reader, SharedState, Writer};
+
+/// The counters of a pair that readers update as well.
+pub(crate) struct Counters {
+    picked_up: AtomicU64,
+    dropped: AtomicU64,
+}
+
+impl Counters {
+    pub(crate) fn new() -> Self {
+        Self {
+            picked_up: AtomicU64::new(0),
+            dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// Count a state a reader picked up.
+    pub(crate) fn picked_up(&self) {
+        self.picked_up.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl<T> SharedState<T> {
+    /// Count a state that got replaced without any reader picking it up.
+    pub(crate) fn counted_drop(&self) {
+        self.counters.dropped.fetch_add(1, Ordering::Relaxed);
+        if let Some(drops) = &self.drop_window {
+            drops.dropped();
+        }
+    }
+}
+
+/// A snapshot of the counters of a pair, see `collect`.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PairStats {
+    /// The number of states published, which is the version of
+    /// the newest one, see `Writer::version`.
+    pub published: u64,
+    /// The number of states picked up by `read_newest`, over all readers.
+    pub picked_up: u64,
+    /// The number of states replaced before any reader picked them up.
+    pub dropped: u64,
+    /// The number of writes `Writer::publish_if_newer` turned down.
+    pub rejected: u64,
+    /// The number of buffers the writer created, including the initial one.
+    pub buffers_created: usize,
+    /// The number of readers of the pair.
+    pub readers: usize,
+    /// The number of states published since the one
+    /// the given reader last picked up.
+    pub reader_lag: u64,
+    /// The time of the last publish, see `Writer::last_publish_instant`.
+    pub last_publish: Option<Instant>,
+    /// The publish-to-read latencies, if the pair was created with
+    /// `TripleBufferBuilder::latency_buckets`.
+    ///
+    /// Only available with the `latency` feature.
+    #[cfg(feature = "latency")]
+    pub latency: Option<LatencyHistogram>,
+}
+
+/// Only the counters, without `last_publish` and `latency`.
+#[cfg(feature = "defmt")]
+impl defmt::Format for PairStats {
+    fn format(&self, f: defmt::Formatter<'_>) {
+        defmt::write!(
+            f,
+            "PairStats {{ published: {=u64}, picked_up: {=u64}, dropped: {=u64}, rejected: {=u64}, buffers_created: {=usize}, readers: {=usize}, reader_lag: {=u64} }}",
+            self.published,
+            self.picked_up,
+            self.dropped,
+            self.rejected,
+            self.buffers_created,
+            self.readers,
+            self.reader_lag,
+        )
+    }
+}
+
+/// Collect the counters of the pair of `writer` and `reader`.
+///
+/// Neither of them can publish or read while they are borrowed here,
+/// so everything they count themselves is from the same point in
+/// time. Only other readers of the pair may still pick up states
+/// meanwhile, which `picked_up` and `latency` might partially miss.
+///
+/// Only available with the `stats` feature.
+///
+/// # Panics
+/// Panics if `writer` and `reader` do not belong to the same pair.
+pub fn collect<T>(writer: &Writer<T>, reader: &Reader<T>) -> PairStats {
+    let shared = &writer.read_update.shared;
+    assert!(
+        sync::Arc::ptr_eq(shared, &reader.read_update.shared),
+        "writer and reader of different pairs"
+    );
+    PairStats {
+        published: writer.version,
+        picked_up: shared.counters.picked_up.load(Ordering::Relaxed),
+        dropped: shared.counters.dropped.load(Ordering::Relaxed),
+        rejected: writer.rejected,
+        buffers_created: writer.created,
+        readers: shared.readers.load(Ordering::Relaxed),
+        reader_lag: writer.version - reader.prev_version,
+        last_publish: writer.last_publish_instant(),
+        #[cfg(feature = "latency")]
+        latency: writer.latency_histogram(),
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use super::collect;
+    use crate::new_clone;
+
+    #[test]
+    fn test_counts_both_write_paths() {
+        let (mut w, mut r) = new_clone(0u32);
+        let mut r2 = r.clone();
+        for i in 1..=10 {
+            if i % 2 == 0 {
+                w.write_new(|_, new| *new = i);
+            } else {
+                w.write_update(|state| *state = i);
+            }
+            if i % 5 == 0 {
+                r.read_newest();
+            }
+        }
+        r2.read_newest();
+        let stats = collect(&w, &r);
+        assert_eq!(stats.published, 10);
+        assert_eq!(stats.picked_up, 3);
+        // Every state but the two picked up by `r`.
+        assert_eq!(stats.dropped, 8);
+        assert_eq!(stats.readers, 2);
+        assert_eq!(stats.reader_lag, 0);
+        assert!(stats.last_publish.is_some());
+        assert_eq!(collect(&w, &r2).reader_lag, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "different pairs")]
+    fn test_other_pair() {
+        let (w, _r) = new_clone(0u32);
+        let (_w, r) = new_clone(0u32);
+        collect(&w, &r);
+    }
+}
@@ -0,0 +1,357 @@
+//! Streams and futures of published states for async readers.
+//!
+//! Readers waiting for a new state register their waker, and the writer
+//! wakes all of them on its next publish, or when it gets dropped. Like
+//! for the blocking reads, the writer only takes the lock while wakers
+//! are registered.
+
+use alloc::vec::Vec;
+use core::future::Future;
+use core::mem;
+use core::pin::Pin;
+use core::sync::atomic::Ordering;
+use core::task::{Context, Poll, Waker};
+
+use futures_core::Stream;
+
+use crate::sync::atomic::{fence, AtomicUsize};
+use crate::sync::Mutex;
+use crate::{Buf, ReadError, Reader, Writer};
+
+pub(crate) struct PublishWakers {
+    /// The number of registered wakers.
+    waiting: AtomicUsize,
+    wakers: Mutex<Vec<Waker>>,
+}
+
+impl PublishWakers {
+    pub(crate) fn new() -> Self {
+        Self {
+            waiting: AtomicUsize::new(0),
+            wakers: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.wakers.lock();
+        if !wakers.iter().any(|registered| registered.will_wake(waker)) {
+            wakers.push(waker.clone());
+            self.waiting.store(wakers.len(), Ordering::Relaxed);
+        }
+        drop(wakers);
+        fence(Ordering::SeqCst);
+    }
+
+    fn wake_all(&self) {
+        fence(Ordering::SeqCst);
+        if self.waiting.load(Ordering::Relaxed) > 0 {
+            let wakers = mem::take(&mut *self.wakers.lock());
+            self.waiting.store(0, Ordering::Relaxed);
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl<T> Writer<T> {
+    /// Wake up streams waiting for a new state, or for the writer to go.
+    pub(crate) fn wake_streams(&self) {
+        self.read_update.shared.publish_wakers.wake_all();
+    }
+}
+
+impl<T> Reader<T> {
+    /// Turn the reader into a stream of pairs of states: the newest one
+    /// of the previous item, and the newest one published since.
+    ///
+    /// The first item pairs the state the reader had picked up last with
+    /// the first new one. Like with `read_newest`, states published in
+    /// between polls get skipped, so the second state of an item is
+    /// always the newest one. The stream ends once the writer has been
+    /// dropped, and its last state was yielded.
+    ///
+    /// The states are shared with the pair, so they can be held across
+    /// await points. They do not get reused while held, so the writer
+    /// creates new buffers to replace them. With the `triomphe` feature,
+    /// they are `triomphe::Arc`s.
+    ///
+    /// Only available with the `async` feature.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::future::Future;
+    /// # use std::pin::pin;
+    /// # use std::task::{Context, Poll, Waker};
+    /// use futures_core::Stream;
+    ///
+    /// let (mut writer, reader) = simple_triple_buffer::new_clone(0);
+    /// let mut diffs = pin!(reader.into_diff_stream());
+    /// let mut cx = Context::from_waker(Waker::noop());
+    ///
+    /// writer.write_new(|_, new| *new = 1);
+    /// writer.write_new(|_, new| *new = 2);
+    /// match diffs.as_mut().poll_next(&mut cx) {
+    ///     Poll::Ready(Some((prev, newest))) => assert_eq!((*prev, *newest), (0, 2)),
+    ///     _ => unreachable!(),
+    /// }
+    /// assert!(diffs.as_mut().poll_next(&mut cx).is_pending());
+    ///
+    /// drop(writer);
+    /// assert!(matches!(diffs.poll_next(&mut cx), Poll::Ready(None)));
+    /// ````
+    pub fn into_diff_stream(self) -> DiffStream<T> {
+        DiffStream {
+            last: self.prev_buf.clone(),
+            reader: self,
+        }
+    }
+}
+
+impl<T> Reader<T> {
+    /// Wait until the newest state satisfies `pred`, and return it.
+    ///
+    /// The predicate gets checked on the newest state right away, and then
+    /// on every new state the reader picks up. Like with `read_newest`,
+    /// states published in between polls get skipped. Fails with
+    /// `ReadError::Disconnected` once the writer has been dropped, and
+    /// its last state did not satisfy `pred` either.
+    ///
+    /// The future can be dropped at any time, like when wrapped into a
+    /// timeout: states get picked up and checked within a single poll, so
+    /// a state the future did not check is still there for the next call.
+    ///
+    /// Only available with the `async` feature.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::future::Future;
+    /// # use std::pin::pin;
+    /// # use std::task::{Context, Poll, Waker};
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// let mut cx = Context::from_waker(Waker::noop());
+    ///
+    /// let mut ready = pin!(reader.wait_for_async(|state| *state >= 2));
+    /// assert!(ready.as_mut().poll(&mut cx).is_pending());
+    /// writer.write_new(|_, new| *new = 1);
+    /// assert!(ready.as_mut().poll(&mut cx).is_pending());
+    /// writer.write_new(|_, new| *new = 3);
+    /// assert_eq!(ready.poll(&mut cx), Poll::Ready(Ok(&3)));
+    /// ````
+    pub fn wait_for_async<F: FnMut(&T) -> bool>(&mut self, pred: F) -> WaitFor<'_, T, F> {
+        WaitFor {
+            reader: Some(self),
+            pred,
+            checked: false,
+        }
+    }
+}
+
+/// Future returned by `Reader::wait_for_async`.
+#[must_use = "futures do nothing unless polled"]
+pub struct WaitFor<'a, T, F> {
+    reader: Option<&'a mut Reader<T>>,
+    pred: F,
+    /// Whether the predicate was checked on the state the reader holds.
+    checked: bool,
+}
+
+// Nothing in it is structurally pinned.
+impl<T, F> Unpin for WaitFor<'_, T, F> {}
+
+impl<'a, T, F: FnMut(&T) -> bool> Future for WaitFor<'a, T, F> {
+    type Output = Result<&'a T, ReadError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let reader = this.reader.as_mut().expect("polled after completion");
+        let mut registered = false;
+        loop {
+            if !this.checked || reader.has_update() {
+                this.checked = true;
+                if (this.pred)(reader.read_newest()) {
+                    let reader: &'a Reader<T> = this.reader.take().unwrap();
+                    return Poll::Ready(Ok(&reader.prev_buf));
+                }
+            } else if reader.is_disconnected() {
+                // The writer might have published a last state before it went.
+                if !reader.has_update() {
+                    this.reader = None;
+                    return Poll::Ready(Err(ReadError::Disconnected));
+                }
+            } else if registered {
+                return Poll::Pending;
+            } else {
+                // Checked again after registering, in case
+                // the writer published in the meantime.
+                reader
+                    .read_update
+                    .shared
+                    .publish_wakers
+                    .register(cx.waker());
+                registered = true;
+            }
+        }
+    }
+}
+
+/// Stream returned by `Reader::into_diff_stream`.
+#[must_use = "streams do nothing unless polled"]
+pub struct DiffStream<T> {
+    reader: Reader<T>,
+    /// The newest state of the previous item.
+    last: Buf<T>,
+}
+
+// Nothing in it is structurally pinned.
+impl<T> Unpin for DiffStream<T> {}
+
+impl<T> DiffStream<T> {
+    /// Take the next item, if the writer published since the last one.
+    ///
+    /// Returns `Some(None)` if the stream ended.
+    fn next_item(&mut self) -> Option<Option<(Buf<T>, Buf<T>)>> {
+        // Checked first, so that a last state published right before
+        // the writer went does not get lost.
+        let disconnected = self.reader.is_disconnected();
+        if self.reader.has_update() {
+            self.reader.read_newest();
+            let newest = self.reader.prev_buf.clone();
+            let prev = mem::replace(&mut self.last, newest.clone());
+            return Some(Some((prev, newest)));
+        }
+        disconnected.then_some(None)
+    }
+}
+
+impl<T> Stream for DiffStream<T> {
+    type Item = (Buf<T>, Buf<T>);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(item) = this.next_item() {
+            return Poll::Ready(item);
+        }
+        this.reader
+            .read_update
+            .shared
+            .publish_wakers
+            .register(cx.waker());
+        // The writer might have published before the waker was registered.
+        match this.next_item() {
+            Some(item) => Poll::Ready(item),
+            None => Poll::Pending,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake, Waker};
+    use std::thread::{self, Thread};
+
+    use futures_core::Stream;
+
+    use crate::{new_clone, ReadError};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on_next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(item) = Pin::new(&mut *stream).poll_next(&mut cx) {
+                return item;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn test_items_chain_up() {
+        let (mut w, r) = new_clone(0u32);
+        let writer = thread::spawn(move || {
+            for i in 1..=10_000 {
+                w.write_new(|_, new| *new = i);
+            }
+        });
+        let mut diffs = r.into_diff_stream();
+        let mut last = 0;
+        while let Some((prev, newest)) = block_on_next(&mut diffs) {
+            assert_eq!(*prev, last);
+            assert!(*newest > *prev);
+            last = *newest;
+        }
+        // The last state is always yielded before the stream ends.
+        assert_eq!(last, 10_000);
+        writer.join().unwrap();
+    }
+
+    fn block_on<F: Future + Unpin>(fut: &mut F) -> F::Output {
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = Pin::new(&mut *fut).poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn test_wait_for() {
+        let (mut w, mut r) = new_clone(0u32);
+        w.write_new(|_, new| *new = 5);
+        // The current state gets checked right away.
+        assert_eq!(block_on(&mut r.wait_for_async(|v| *v == 5)), Ok(&5));
+
+        let writer = thread::spawn(move || {
+            for i in 6..=1000 {
+                w.write_new(|_, new| *new = i);
+            }
+        });
+        assert!(*block_on(&mut r.wait_for_async(|v| *v >= 500)).unwrap() >= 500);
+        assert_eq!(
+            block_on(&mut r.wait_for_async(|_| false)),
+            Err(ReadError::Disconnected)
+        );
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_dropped_wait_keeps_update() {
+        let (mut w, mut r) = new_clone(0u32);
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut waiting = r.wait_for_async(|v| *v == 1);
+        assert!(Pin::new(&mut waiting).poll(&mut cx).is_pending());
+        w.write_new(|_, new| *new = 1);
+        drop(waiting);
+        assert!(r.has_update());
+        let mut waiting = r.wait_for_async(|v| *v == 1);
+        assert_eq!(Pin::new(&mut waiting).poll(&mut cx), Poll::Ready(Ok(&1)));
+    }
+
+    #[test]
+    fn test_items_can_be_held() {
+        let (mut w, r) = new_clone(vec![0u8]);
+        let mut diffs = r.into_diff_stream();
+        w.write_new(|_, new| *new = vec![1]);
+        let first = block_on_next(&mut diffs).unwrap();
+        w.write_new(|_, new| *new = vec![2]);
+        w.write_new(|_, new| *new = vec![3]);
+        let second = block_on_next(&mut diffs).unwrap();
+        assert_eq!((&*first.0, &*first.1), (&vec![0], &vec![1]));
+        assert_eq!((&*second.0, &*second.1), (&vec![1], &vec![3]));
+        drop(w);
+        assert!(block_on_next(&mut diffs).is_none());
+    }
+}
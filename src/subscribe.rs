@@ -0,0 +1,137 @@
+//! Adding readers to a running pair, up to a limit.
+
+use core::sync::atomic::Ordering;
+
+use crate::{Reader, SharedState, TooManyReaders, Writer};
+
+impl<T> SharedState<T> {
+    /// Count one more reader, unless that exceeds `max_readers`.
+    pub(crate) fn add_reader(&self) -> Result<(), TooManyReaders> {
+        let max = self.max_readers;
+        self.readers
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |readers| {
+                (readers < max).then_some(readers + 1)
+            })
+            .map(drop)
+            .map_err(|_| TooManyReaders)
+    }
+}
+
+impl<T> Writer<T> {
+    /// Create a new reader, which starts out with
+    /// the state published last.
+    ///
+    /// Readers can come and go while the pair is in use, like a debug
+    /// view attaching to a running producer for a while. Dropping the
+    /// reader hands the buffer it held back to the writer.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, reader) = simple_triple_buffer::new_clone(0);
+    /// drop(reader);
+    /// writer.write_new(|_, new| *new = 1);
+    ///
+    /// let mut reader = writer.subscribe();
+    /// assert_eq!(*reader.read_newest(), 1);
+    /// assert_eq!(writer.readers(), 1);
+    /// ````
+    ///
+    /// # Panics
+    /// Panics if the pair already has as many readers as
+    /// `TripleBufferBuilder::max_readers` allows.
+    pub fn subscribe(&mut self) -> Reader<T> {
+        self.try_subscribe().unwrap_or_else(|e| self.fail(e))
+    }
+
+    /// Create a new reader like `subscribe`, or fail with
+    /// `TooManyReaders` if the pair already has as many readers
+    /// as `TripleBufferBuilder::max_readers` allows.
+    pub fn try_subscribe(&mut self) -> Result<Reader<T>, TooManyReaders> {
+        self.read_update.shared.add_reader()?;
+        Ok(self.new_reader())
+    }
+
+    /// Get the number of readers of the pair.
+    pub fn readers(&self) -> usize {
+        self.read_update.shared.readers.load(Ordering::Acquire)
+    }
+}
+
+impl<T> Reader<T> {
+    /// Create another reader for the same pair, or fail with
+    /// `TooManyReaders` if the pair already has as many readers
+    /// as `TripleBufferBuilder::max_readers` allows.
+    ///
+    /// Cloning a reader panics in that case.
+    pub fn try_clone(&self) -> Result<Self, TooManyReaders> {
+        self.read_update.shared.add_reader()?;
+        Ok(self.clone_counted())
+    }
+}
+
+#[cfg(all(test, not(loom)))]
+mod tests {
+    use crate::{new_clone, TooManyReaders, TripleBufferBuilder};
+
+    #[test]
+    fn test_reader_limit() {
+        let (mut w, r) = TripleBufferBuilder::new(0u32)
+            .copy_buffers()
+            .max_readers(3)
+            .build()
+            .unwrap();
+        let r2 = w.subscribe();
+        let r3 = r.try_clone().unwrap();
+        assert_eq!(w.readers(), 3);
+        assert_eq!(w.try_subscribe().err(), Some(TooManyReaders));
+        assert_eq!(r2.try_clone().err(), Some(TooManyReaders));
+        drop((r, r3));
+        assert_eq!(w.readers(), 1);
+        let _r4 = w.subscribe();
+        let _r5 = w.subscribe();
+    }
+
+    #[test]
+    fn test_dropped_readers_free_buffers() {
+        let (mut w, r) = TripleBufferBuilder::new(0u32)
+            .copy_buffers()
+            .max_buffers(3)
+            .build()
+            .unwrap();
+        drop(r);
+        // Each reader holds on to one state until it gets dropped.
+        for i in 1..=100 {
+            let mut r = w.subscribe();
+            w.try_write_new(|_, new| *new = i).unwrap();
+            assert_eq!(*r.read_newest(), i);
+            w.try_write_new(|_, new| *new = i + 1).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_subscribe_while_publishing() {
+        let (mut w, r) = new_clone(0u64);
+        drop(r);
+        let mut readers = Vec::new();
+        for i in 0..100 {
+            w.write_new(|_, new| *new = i);
+            let mut r = w.subscribe();
+            readers.push(std::thread::spawn(move || {
+                let mut last = *r.read_newest();
+                assert!(last >= i);
+                while !r.is_disconnected() {
+                    let state = *r.read_newest();
+                    assert!(state >= last);
+                    last = state;
+                }
+            }));
+        }
+        for i in 100..1000 {
+            w.write_new(|_, new| *new = i);
+        }
+        drop(w);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Synchronization primitives used by the buffer pairs.
+//!
+//! With the `std` feature these are thin wrappers around the `std`
+//! types, or around the `parking_lot` ones for the locks with the
+//! `parking_lot` feature. Without `std` they are small spin lock based
+//! replacements that only need `core` and `alloc`.
+//!
+//! When model checking with `--cfg loom`, they are the types of `loom`
+//! instead, so that it can explore every interleaving of the halves.
+
+#[cfg(not(feature = "std"))]
+pub(crate) use self::spin_impl::*;
+#[cfg(feature = "std")]
+pub(crate) use self::std_impl::*;
+
+/// Aligns a value to its own cache line, so that writes to it
+/// do not evict the values next to it from other cores' caches.
+///
+/// Modern x86_64 and aarch64 cores prefetch cache lines in pairs,
+/// so those get 128 bytes, like in `crossbeam-utils`.
+#[cfg_attr(
+    any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "powerpc64"
+    ),
+    repr(align(128))
+)]
+#[cfg_attr(
+    not(any(
+        target_arch = "x86_64",
+        target_arch = "aarch64",
+        target_arch = "powerpc64"
+    )),
+    repr(align(64))
+)]
+pub(crate) struct CachePadded<T>(pub(crate) T);
+
+impl<T> core::ops::Deref for CachePadded<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+/// The reference count of the state shared between the halves of a pair.
+///
+/// Buffers stay `alloc::sync::Arc`s even with `loom`, as pairs take them
+/// from users with `new_from_arc`. The schedules around their counts get
+/// explored through the atomics of the slots they pass through instead.
+#[cfg(not(loom))]
+pub(crate) use alloc::sync::Arc;
+#[cfg(loom)]
+pub(crate) use loom::sync::Arc;
+
+/// Atomics of lock-free code.
+pub(crate) mod atomic {
+    #[cfg(not(loom))]
+    pub(crate) use core::hint::spin_loop;
+    #[cfg(all(any(feature = "std", feature = "async"), not(loom)))]
+    pub(crate) use core::sync::atomic::fence;
+    #[cfg(all(feature = "std", not(loom)))]
+    pub(crate) use core::sync::atomic::AtomicU64;
+    #[cfg(not(loom))]
+    pub(crate) use core::sync::atomic::{AtomicBool, AtomicUsize};
+    #[cfg(loom)]
+    pub(crate) use loom::hint::spin_loop;
+    #[cfg(loom)]
+    pub(crate) use loom::sync::atomic::{fence, AtomicBool, AtomicU64, AtomicUsize};
+}
+
+#[cfg(feature = "std")]
+mod std_impl {
+    #[cfg(loom)]
+    use loom::sync as imp;
+    #[cfg(not(any(feature = "parking_lot", loom)))]
+    use std::sync as imp;
+    #[cfg(any(not(feature = "parking_lot"), loom))]
+    use std::sync::PoisonError;
+    use std::time::Duration;
+
+    #[cfg(any(not(feature = "parking_lot"), loom))]
+    pub(crate) use imp::MutexGuard;
+    #[cfg(all(feature = "parking_lot", not(loom)))]
+    pub(crate) use parking_lot::MutexGuard;
+
+    /// A mutex that ignores poisoning, like the `parking_lot` one.
+    ///
+    /// Most locks only guard a few pushes and pops. Where user code runs
+    /// under a lock, a panic leaves the state as half written as it would
+    /// without one, so there is nothing gained from failing every later
+    /// operation of the other half.
+    #[cfg(any(not(feature = "parking_lot"), loom))]
+    pub(crate) struct Mutex<T>(imp::Mutex<T>);
+
+    #[cfg(any(not(feature = "parking_lot"), loom))]
+    impl<T> Mutex<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(imp::Mutex::new(value))
+        }
+
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            self.0.lock().unwrap_or_else(PoisonError::into_inner)
+        }
+    }
+
+    /// A condition variable for `Mutex`.
+    #[cfg(any(not(feature = "parking_lot"), loom))]
+    pub(crate) struct Condvar(imp::Condvar);
+
+    #[cfg(any(not(feature = "parking_lot"), loom))]
+    impl Condvar {
+        pub(crate) fn new() -> Self {
+            Self(imp::Condvar::new())
+        }
+
+        pub(crate) fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+            self.0.wait(guard).unwrap_or_else(PoisonError::into_inner)
+        }
+
+        pub(crate) fn wait_timeout<'a, T>(
+            &self,
+            guard: MutexGuard<'a, T>,
+            timeout: Duration,
+        ) -> MutexGuard<'a, T> {
+            match self.0.wait_timeout(guard, timeout) {
+                Ok((guard, _)) => guard,
+                Err(e) => e.into_inner().0,
+            }
+        }
+
+        pub(crate) fn notify_one(&self) {
+            self.0.notify_one();
+        }
+
+        pub(crate) fn notify_all(&self) {
+            self.0.notify_all();
+        }
+    }
+
+    /// A mutex that never gets poisoned.
+    #[cfg(all(feature = "parking_lot", not(loom)))]
+    pub(crate) struct Mutex<T>(parking_lot::Mutex<T>);
+
+    #[cfg(all(feature = "parking_lot", not(loom)))]
+    impl<T> Mutex<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self(parking_lot::Mutex::new(value))
+        }
+
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            self.0.lock()
+        }
+    }
+
+    /// A condition variable for `Mutex`.
+    #[cfg(all(feature = "parking_lot", not(loom)))]
+    pub(crate) struct Condvar(parking_lot::Condvar);
+
+    #[cfg(all(feature = "parking_lot", not(loom)))]
+    impl Condvar {
+        pub(crate) fn new() -> Self {
+            Self(parking_lot::Condvar::new())
+        }
+
+        pub(crate) fn wait<'a, T>(&self, mut guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+            self.0.wait(&mut guard);
+            guard
+        }
+
+        pub(crate) fn wait_timeout<'a, T>(
+            &self,
+            mut guard: MutexGuard<'a, T>,
+            timeout: Duration,
+        ) -> MutexGuard<'a, T> {
+            self.0.wait_for(&mut guard, timeout);
+            guard
+        }
+
+        pub(crate) fn notify_one(&self) {
+            self.0.notify_one();
+        }
+
+        pub(crate) fn notify_all(&self) {
+            self.0.notify_all();
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+mod spin_impl {
+    use core::cell::UnsafeCell;
+    use core::ops::{Deref, DerefMut};
+    use core::sync::atomic::{AtomicBool, Ordering};
+
+    /// A minimal spin lock.
+    ///
+    /// Critical sections in this crate are only a few instructions long,
+    /// but note that locking from an interrupt handler while the
+    /// interrupted code holds the same lock deadlocks.
+    pub(crate) struct Mutex<T> {
+        locked: AtomicBool,
+        value: UnsafeCell<T>,
+    }
+
+    unsafe impl<T: Send> Send for Mutex<T> {}
+    unsafe impl<T: Send> Sync for Mutex<T> {}
+
+    pub(crate) struct MutexGuard<'a, T> {
+        mutex: &'a Mutex<T>,
+    }
+
+    impl<T> Mutex<T> {
+        pub(crate) fn new(value: T) -> Self {
+            Self {
+                locked: AtomicBool::new(false),
+                value: UnsafeCell::new(value),
+            }
+        }
+
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            while self
+                .locked
+                .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .is_err()
+            {
+                while self.locked.load(Ordering::Relaxed) {
+                    core::hint::spin_loop();
+                }
+            }
+            MutexGuard { mutex: self }
+        }
+    }
+
+    impl<T> Deref for MutexGuard<'_, T> {
+        type Target = T;
+
+        fn deref(&self) -> &T {
+            // SAFETY: The guard proves the lock is held.
+            unsafe { &*self.mutex.value.get() }
+        }
+    }
+
+    impl<T> DerefMut for MutexGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            // SAFETY: The guard proves the lock is held.
+            unsafe { &mut *self.mutex.value.get() }
+        }
+    }
+
+    impl<T> Drop for MutexGuard<'_, T> {
+        fn drop(&mut self) {
+            self.mutex.locked.store(false, Ordering::Release);
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Delivering published states into tokio channels.
+
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::mpsc::Sender;
+
+use crate::{Buf, Writer};
+
+/// What `Writer::tee_async` does with a state that does
+/// not fit into the channel.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Drop the new state, and count it.
+    DropNewest,
+    /// Keep the new state, and drop an older one instead.
+    ///
+    /// States in the channel can not be taken back out of it, so the tee
+    /// holds back the newest state that did not fit, dropping and counting
+    /// the one it held back before. It gets sent on a later publish, once
+    /// the channel has room again, before the state published then.
+    DropOldest,
+    /// Wait for the channel to have room, blocking the writer.
+    ///
+    /// This is the only policy under which publishing waits
+    /// for the consumers of the channel.
+    Backpressure,
+}
+
+pub(crate) struct Tee<T> {
+    tx: Sender<Buf<T>>,
+    policy: OverflowPolicy,
+    /// The state held back under `OverflowPolicy::DropOldest`.
+    held: Option<Buf<T>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl<T> Tee<T> {
+    /// Send a newly published state, returning
+    /// `false` once the receiver is gone.
+    pub(crate) fn send(&mut self, state: &Buf<T>) -> bool {
+        match self.policy {
+            OverflowPolicy::Backpressure => self.tx.blocking_send(state.clone()).is_ok(),
+            OverflowPolicy::DropNewest => self.try_send(state.clone()).is_some(),
+            OverflowPolicy::DropOldest => {
+                if let Some(held) = self.held.take() {
+                    // Replaced by the new state if it still does not fit.
+                    if self.try_send(held).is_none() {
+                        return false;
+                    }
+                }
+                match self.tx.try_send(state.clone()) {
+                    Ok(()) => true,
+                    Err(TrySendError::Full(state)) => {
+                        self.held = Some(state);
+                        true
+                    }
+                    Err(TrySendError::Closed(_)) => false,
+                }
+            }
+        }
+    }
+
+    /// Send a state unless the channel is full, counting it as dropped
+    /// if it is. Returns `None` once the receiver is gone.
+    fn try_send(&self, state: Buf<T>) -> Option<()> {
+        match self.tx.try_send(state) {
+            Ok(()) => Some(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                Some(())
+            }
+            Err(TrySendError::Closed(_)) => None,
+        }
+    }
+}
+
+/// Handle to a tee started with `Writer::tee_async`.
+pub struct TeeHandle {
+    dropped: Arc<AtomicU64>,
+}
+
+impl TeeHandle {
+    /// Get the number of states dropped because the channel was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<T> Writer<T> {
+    /// Send every state published from now on into `tx`.
+    ///
+    /// Each publish tries to send the state without waiting, and `policy`
+    /// decides what happens if the channel is full. Only with
+    /// `OverflowPolicy::Backpressure` the writer waits for room, with
+    /// `Sender::blocking_send`. Once the receiver is gone, the tee stops.
+    /// Starting another tee stops the previous one.
+    ///
+    /// The states are shared with the pair, and do not get reused while
+    /// the channel or its consumer hold them, so the writer creates new
+    /// buffers to replace them. With the `triomphe` feature, they are
+    /// `triomphe::Arc`s.
+    ///
+    /// Only available with the `tokio` feature.
+    ///
+    /// # Panics
+    /// Publishing panics under `OverflowPolicy::Backpressure`
+    /// if it happens within an asynchronous execution context,
+    /// see `Sender::blocking_send`.
+    ///
+    /// # Example
+    /// ```
+    /// use simple_triple_buffer::OverflowPolicy;
+    ///
+    /// let (mut writer, _reader) = simple_triple_buffer::new_clone(0);
+    /// let (tx, mut rx) = tokio::sync::mpsc::channel(2);
+    /// let tee = writer.tee_async(tx, OverflowPolicy::DropNewest);
+    ///
+    /// for i in 1..=3 {
+    ///     writer.write_new(|_, new| *new = i);
+    /// }
+    /// assert_eq!(*rx.try_recv().unwrap(), 1);
+    /// assert_eq!(*rx.try_recv().unwrap(), 2);
+    /// assert_eq!(tee.dropped(), 1);
+    /// ````
+    pub fn tee_async(&mut self, tx: Sender<Buf<T>>, policy: OverflowPolicy) -> TeeHandle {
+        let dropped = Arc::new(AtomicU64::new(0));
+        self.tee = Some(Box::new(Tee {
+            tx,
+            policy,
+            held: None,
+            dropped: dropped.clone(),
+        }));
+        TeeHandle { dropped }
+    }
+
+    /// Stop sending published states, see `tee_async`.
+    ///
+    /// Only available with the `tokio` feature.
+    pub fn stop_tee(&mut self) {
+        self.tee = None;
+    }
+
+    pub(crate) fn send_to_tee(&mut self) {
+        if let Some(tee) = &mut self.tee {
+            if !tee.send(&self.prev_buf) {
+                self.tee = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+
+    use super::OverflowPolicy;
+    use crate::new_clone;
+
+    #[test]
+    fn test_drop_oldest_keeps_newest() {
+        let (mut w, _r) = new_clone(0u32);
+        let (tx, mut rx) = mpsc::channel(2);
+        let tee = w.tee_async(tx, OverflowPolicy::DropOldest);
+        for i in 1..=5 {
+            w.write_new(|_, new| *new = i);
+        }
+        // 3 and 4 were held back in turn, and replaced.
+        assert_eq!(tee.dropped(), 2);
+        assert_eq!(*rx.try_recv().unwrap(), 1);
+        assert_eq!(*rx.try_recv().unwrap(), 2);
+        assert!(rx.try_recv().is_err());
+
+        w.write_new(|_, new| *new = 6);
+        assert_eq!(*rx.try_recv().unwrap(), 5);
+        assert_eq!(*rx.try_recv().unwrap(), 6);
+        assert_eq!(tee.dropped(), 2);
+    }
+
+    #[test]
+    fn test_backpressure_delivers_everything() {
+        let (mut w, _r) = new_clone(0u32);
+        let (tx, mut rx) = mpsc::channel(1);
+        let tee = w.tee_async(tx, OverflowPolicy::Backpressure);
+        let writer = std::thread::spawn(move || {
+            for i in 1..=1000 {
+                w.write_new(|_, new| *new = i);
+            }
+        });
+        let mut received = Vec::new();
+        while let Some(state) = rx.blocking_recv() {
+            received.push(*state);
+        }
+        writer.join().unwrap();
+        assert!(received.into_iter().eq(1..=1000));
+        assert_eq!(tee.dropped(), 0);
+    }
+
+    #[test]
+    fn test_stops_when_receiver_is_gone() {
+        let (mut w, _r) = new_clone(0u32);
+        let (tx, rx) = mpsc::channel(1);
+        w.tee_async(tx, OverflowPolicy::DropNewest);
+        drop(rx);
+        w.write_new(|_, new| *new = 1);
+        assert!(w.tee.is_none());
+    }
+}
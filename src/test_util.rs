@@ -0,0 +1,256 @@
+//! Helpers for testing code built on buffer pairs.
+//!
+//! The stand-ins `ScriptedSubscriber` and `RecordingPublisher` replace the
+//! halves of a pair for code that takes a `StatePublisher` or
+//! `StateSubscriber`, and `Script` drives a real pair step by step.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::test_util::{RecordingPublisher, ScriptedSubscriber};
+//! use simple_triple_buffer::{StatePublisher, StateSubscriber};
+//!
+//! fn double_newest(input: &mut dyn StateSubscriber<u32>, output: &mut dyn StatePublisher<u32>) {
+//!     if input.has_update() {
+//!         let value = *input.newest() * 2;
+//!         output.publish_with(&mut |_, new| *new = value);
+//!     }
+//! }
+//!
+//! let mut input = ScriptedSubscriber::new([1, 2]);
+//! let mut output = RecordingPublisher::new(0);
+//! for _ in 0..3 {
+//!     double_newest(&mut input, &mut output);
+//! }
+//! assert_eq!(output.published(), [2, 4]);
+//! ````
+
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::{new_clone, Reader, StatePublisher, StateSubscriber, Writer};
+
+/// A subscriber that yields a predefined sequence of states.
+///
+/// Every call to `newest` moves on to the next state in the sequence,
+/// until the last one, which it keeps returning from then on.
+pub struct ScriptedSubscriber<T> {
+    current: Option<T>,
+    upcoming: VecDeque<T>,
+}
+
+impl<T> ScriptedSubscriber<T> {
+    /// Create a subscriber yielding `states` in order.
+    ///
+    /// # Panics
+    /// Panics if `states` is empty.
+    pub fn new(states: impl IntoIterator<Item = T>) -> Self {
+        let upcoming: VecDeque<T> = states.into_iter().collect();
+        assert!(!upcoming.is_empty(), "a scripted subscriber needs a state");
+        Self {
+            current: None,
+            upcoming,
+        }
+    }
+
+    /// Add a state to the end of the sequence.
+    pub fn push(&mut self, state: T) {
+        self.upcoming.push_back(state);
+    }
+}
+
+impl<T> StateSubscriber<T> for ScriptedSubscriber<T> {
+    fn newest(&mut self) -> &T {
+        if let Some(next) = self.upcoming.pop_front() {
+            self.current = Some(next);
+        }
+        self.current.as_ref().unwrap()
+    }
+
+    fn has_update(&self) -> bool {
+        !self.upcoming.is_empty()
+    }
+}
+
+/// A publisher that records every state published through it.
+pub struct RecordingPublisher<T> {
+    prev: T,
+    published: Vec<T>,
+}
+
+impl<T> RecordingPublisher<T> {
+    /// Create a publisher whose previous state starts out as `init`.
+    pub fn new(init: T) -> Self {
+        Self {
+            prev: init,
+            published: Vec::new(),
+        }
+    }
+
+    /// Get all states published so far, oldest first.
+    pub fn published(&self) -> &[T] {
+        &self.published
+    }
+}
+
+impl<T: Clone> StatePublisher<T> for RecordingPublisher<T> {
+    fn publish_with(&mut self, write_op: &mut dyn FnMut(&T, &mut T)) {
+        // Like with a real pair, the buffer holds some older state.
+        let mut new = self.prev.clone();
+        write_op(&self.prev, &mut new);
+        self.published.push(new.clone());
+        self.prev = new;
+    }
+}
+
+/// A real pair, driven step by step from a single thread.
+///
+/// Publishes and reads go through the regular `Writer` and `Reader`, so
+/// a test can arrange exactly how many states land between two reads
+/// without threads or sleeps. The script numbers the published states:
+/// the initial state is version 0, and every publish increments it.
+///
+/// # Example
+/// ```
+/// use simple_triple_buffer::test_util::Script;
+///
+/// let mut script = Script::new(0);
+/// script.publish(1);
+/// script.publish_n(3, |old, new| *new = *old + 1);
+/// assert_eq!(script.read_and(|state| *state), 4);
+/// assert_eq!(script.read_version(), 4);
+/// assert_eq!(script.last_skipped(), 3);
+/// ````
+pub struct Script<T> {
+    writer: Writer<T>,
+    reader: Reader<T>,
+    version: u64,
+    read_version: u64,
+    last_skipped: u64,
+    skipped: u64,
+}
+
+impl<T: Clone> Script<T> {
+    /// Create a script for a pair created with `new_clone`.
+    pub fn new(init: T) -> Self {
+        let (writer, reader) = new_clone(init);
+        Self::from_pair(writer, reader)
+    }
+}
+
+impl<T> Script<T> {
+    /// Create a script for a pair that has not published anything yet,
+    /// for example one configured with `TripleBufferBuilder`.
+    pub fn from_pair(writer: Writer<T>, reader: Reader<T>) -> Self {
+        Self {
+            writer,
+            reader,
+            version: 0,
+            read_version: 0,
+            last_skipped: 0,
+            skipped: 0,
+        }
+    }
+
+    /// Publish `value` as the next state.
+    pub fn publish(&mut self, value: T) {
+        self.publish_with(|_, new| *new = value);
+    }
+
+    /// Publish the next state written by `write_op`, see `Writer::write_new`.
+    pub fn publish_with(&mut self, write_op: impl FnOnce(&T, &mut T)) {
+        self.writer.write_new(write_op);
+        self.version += 1;
+    }
+
+    /// Publish `n` states in a row, each written by `write_op`.
+    pub fn publish_n(&mut self, n: usize, mut write_op: impl FnMut(&T, &mut T)) {
+        for _ in 0..n {
+            self.publish_with(&mut write_op);
+        }
+    }
+
+    /// Read the newest state and pass it to `read_op`.
+    ///
+    /// States published since the previous read that the reader never
+    /// got to see count as skipped.
+    pub fn read_and<R>(&mut self, read_op: impl FnOnce(&T) -> R) -> R {
+        let result = read_op(self.reader.read_newest());
+        self.last_skipped = (self.version - self.read_version).saturating_sub(1);
+        self.skipped += self.last_skipped;
+        self.read_version = self.version;
+        result
+    }
+
+    /// Get the version of the last published state.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Get the version of the state the last read returned.
+    pub fn read_version(&self) -> u64 {
+        self.read_version
+    }
+
+    /// Get the number of states the last read skipped.
+    pub fn last_skipped(&self) -> u64 {
+        self.last_skipped
+    }
+
+    /// Get the number of states all reads so far skipped.
+    pub fn skipped(&self) -> u64 {
+        self.skipped
+    }
+
+    /// Get the writer, for operations the script does not wrap.
+    ///
+    /// States published through it directly are not counted.
+    pub fn writer(&mut self) -> &mut Writer<T> {
+        &mut self.writer
+    }
+
+    /// Get the reader, for operations the script does not wrap.
+    pub fn reader(&mut self) -> &mut Reader<T> {
+        &mut self.reader
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Script;
+    use crate::TripleBufferBuilder;
+
+    #[test]
+    fn test_script_counts_coalesced_states() {
+        let mut script = Script::new(0u32);
+        assert_eq!(script.read_and(|state| *state), 0);
+        assert_eq!(script.last_skipped(), 0);
+
+        script.publish(1);
+        assert_eq!(script.read_and(|state| *state), 1);
+        assert_eq!(script.last_skipped(), 0);
+
+        script.publish_n(3, |old, new| *new = *old + 1);
+        assert!(script.reader().has_update());
+        assert_eq!(script.read_and(|state| *state), 4);
+        assert_eq!((script.version(), script.read_version()), (4, 4));
+        assert_eq!(script.last_skipped(), 2);
+
+        assert_eq!(script.read_and(|state| *state), 4);
+        assert_eq!(script.last_skipped(), 0);
+        assert_eq!(script.skipped(), 2);
+    }
+
+    #[test]
+    fn test_script_with_builder_pair() {
+        let (w, r) = TripleBufferBuilder::new(vec![0u8])
+            .clone_with(Vec::clone)
+            .max_buffers(3)
+            .finish();
+        let mut script = Script::from_pair(w, r);
+        for i in 1..=10 {
+            script.publish_n(i, |_, new| *new = vec![i as u8; i]);
+            assert_eq!(script.read_and(|state| state.clone()), vec![i as u8; i]);
+        }
+        assert_eq!(script.skipped(), (0..10).sum::<u64>());
+    }
+}
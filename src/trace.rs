@@ -0,0 +1,277 @@
+//! A timeline of what a pair did, in the Chrome trace event format.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use crate::sync::Mutex;
+use crate::{Reader, Writer};
+
+/// What happened, named as it shows up in the timeline.
+#[derive(Clone, Copy)]
+pub(crate) enum Event {
+    /// A `write_new` closure running.
+    Write,
+    /// A new buffer being created.
+    MakeBuf,
+    /// A state getting published.
+    Publish,
+    /// A reader picking up a newly published state.
+    Read,
+}
+
+impl Event {
+    fn name(self) -> &'static str {
+        match self {
+            Event::Write => "write",
+            Event::MakeBuf => "make_buf",
+            Event::Publish => "publish",
+            Event::Read => "read",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Phase {
+    Begin,
+    End,
+    Instant,
+}
+
+#[derive(Clone, Copy)]
+struct Record {
+    event: Event,
+    phase: Phase,
+    time: Instant,
+    thread: u64,
+}
+
+/// Small sequential ids, as the ones of `std::thread::ThreadId`
+/// can not be turned into numbers on stable.
+fn thread_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    std::thread_local! {
+        static ID: u64 = NEXT.fetch_add(1, Ordering::Relaxed);
+    }
+    ID.with(|id| *id)
+}
+
+/// The most recent events of a pair, see
+/// `TripleBufferBuilder::trace_events`.
+///
+/// All of its memory is allocated up front. Once full,
+/// every new event replaces the oldest one.
+pub(crate) struct TraceRing {
+    start: Instant,
+    capacity: usize,
+    records: Mutex<VecDeque<Record>>,
+}
+
+impl TraceRing {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            start: Instant::now(),
+            capacity: capacity.max(1),
+            records: Mutex::new(VecDeque::with_capacity(capacity.max(1))),
+        }
+    }
+
+    fn push(&self, event: Event, phase: Phase) {
+        let record = Record {
+            event,
+            phase,
+            time: Instant::now(),
+            thread: thread_id(),
+        };
+        let mut records = self.records.lock();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    pub(crate) fn begin(&self, event: Event) {
+        self.push(event, Phase::Begin);
+    }
+
+    pub(crate) fn end(&self, event: Event) {
+        self.push(event, Phase::End);
+    }
+
+    pub(crate) fn instant(&self, event: Event) {
+        self.push(event, Phase::Instant);
+    }
+
+    fn write_json(&self, label: Option<&str>, mut out: impl Write) -> io::Result<()> {
+        // Copied out first, so the pair does not wait for the output.
+        let records: Vec<Record> = self.records.lock().iter().copied().collect();
+        let mut category = String::from("\"");
+        for c in label.unwrap_or("triple_buffer").chars() {
+            match c {
+                '"' | '\\' => category.extend(['\\', c]),
+                c if c.is_control() => category += &format!("\\u{:04x}", c as u32),
+                c => category.push(c),
+            }
+        }
+        category.push('"');
+
+        write!(out, "[")?;
+        for (i, record) in records.iter().enumerate() {
+            let phase = match record.phase {
+                Phase::Begin => "B",
+                Phase::End => "E",
+                Phase::Instant => "i",
+            };
+            // Events that lost their begin to the ring wrapping around
+            // are simply ignored by the viewers.
+            let micros = record.time.saturating_duration_since(self.start).as_nanos() as f64 / 1e3;
+            write!(
+                out,
+                "{}\n{{\"name\":\"{}\",\"cat\":{},\"ph\":\"{}\",\"ts\":{:.3},\"pid\":{},\"tid\":{}{}}}",
+                if i == 0 { "" } else { "," },
+                record.event.name(),
+                category,
+                phase,
+                micros,
+                std::process::id(),
+                record.thread,
+                if let Phase::Instant = record.phase {
+                    ",\"s\":\"t\""
+                } else {
+                    ""
+                },
+            )?;
+        }
+        writeln!(out, "\n]")?;
+        out.flush()
+    }
+}
+
+impl<T> Writer<T> {
+    /// Write the recorded events of the pair to `path`, as a JSON array
+    /// of Chrome trace events, which chrome://tracing and Perfetto can
+    /// open. Does nothing if the pair was created without
+    /// `TripleBufferBuilder::trace_events`.
+    ///
+    /// The events are grouped by the thread they happened on, and carry
+    /// the label of the pair as their category.
+    ///
+    /// Only available with the `trace-export` feature.
+    pub fn export_chrome_trace(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_chrome_trace(BufWriter::new(File::create(path)?))
+    }
+
+    /// Write the recorded events of the pair to `out`,
+    /// see `export_chrome_trace`.
+    ///
+    /// Only available with the `trace-export` feature.
+    pub fn write_chrome_trace(&self, out: impl Write) -> io::Result<()> {
+        match &self.read_update.shared.trace {
+            Some(trace) => trace.write_json(self.label(), out),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<T> Reader<T> {
+    /// Write the recorded events of the pair to `path`,
+    /// see `Writer::export_chrome_trace`.
+    ///
+    /// Only available with the `trace-export` feature.
+    pub fn export_chrome_trace(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.write_chrome_trace(BufWriter::new(File::create(path)?))
+    }
+
+    /// Write the recorded events of the pair to `out`,
+    /// see `Writer::export_chrome_trace`.
+    ///
+    /// Only available with the `trace-export` feature.
+    pub fn write_chrome_trace(&self, out: impl Write) -> io::Result<()> {
+        match &self.read_update.shared.trace {
+            Some(trace) => trace.write_json(self.label(), out),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::TripleBufferBuilder;
+
+    fn events(json: &str) -> Vec<(&str, &str)> {
+        json.lines()
+            .filter(|line| line.contains("\"name\""))
+            .map(|line| {
+                let field = |key: &str| {
+                    let key = format!("\"{}\":\"", key);
+                    let start = line.find(&key).unwrap() + key.len();
+                    let len = line[start..].find('"').unwrap();
+                    &line[start..start + len]
+                };
+                (field("name"), field("ph"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_events_are_recorded() {
+        let (mut w, mut r) = TripleBufferBuilder::new(0)
+            .copy_buffers()
+            .label("physics \"main\"")
+            .trace_events(64)
+            .build()
+            .unwrap();
+        w.write_new(|_, new| *new = 1);
+        r.read_newest();
+        r.read_newest();
+
+        let mut json = Vec::new();
+        r.write_chrome_trace(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert!(json.starts_with('[') && json.trim_end().ends_with(']'));
+        assert!(json.contains("\"cat\":\"physics \\\"main\\\"\""));
+        assert_eq!(
+            events(&json),
+            [
+                ("make_buf", "B"),
+                ("make_buf", "E"),
+                ("write", "B"),
+                ("write", "E"),
+                ("publish", "i"),
+                ("read", "i"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ring_keeps_newest_events() {
+        let (mut w, _r) = TripleBufferBuilder::new(0)
+            .copy_buffers()
+            .trace_events(4)
+            .build()
+            .unwrap();
+        for i in 0..10 {
+            w.write_new(|_, new| *new = i);
+        }
+        let mut json = Vec::new();
+        w.write_chrome_trace(&mut json).unwrap();
+        let json = String::from_utf8(json).unwrap();
+        assert_eq!(
+            events(&json),
+            [
+                ("publish", "i"),
+                ("write", "B"),
+                ("write", "E"),
+                ("publish", "i")
+            ]
+        );
+
+        let (w, _r) = TripleBufferBuilder::new(0).copy_buffers().build().unwrap();
+        let mut json = Vec::new();
+        w.write_chrome_trace(&mut json).unwrap();
+        assert!(json.is_empty());
+    }
+}
@@ -0,0 +1,189 @@
+use core::marker::PhantomData;
+use core::mem::MaybeUninit;
+
+use crate::{Buf, PoolExhausted, Reader, TripleBufferBuilder, Writer};
+
+/// Create a new buffer pair that creates additional buffer
+/// instances uninitialized, instead of copying a previous state.
+///
+/// Each new buffer gets initialized by the first write into it,
+/// see `InitWriter::write`. This avoids building states that are
+/// overwritten right away, which matters for very large ones.
+///
+/// # Example
+/// ```
+/// let (mut writer, mut reader) = simple_triple_buffer::new_write_init(vec![0u8; 4]);
+///
+/// for i in 1..=3 {
+///     writer.write(
+///         |_, new| new.fill(i),
+///         |_, uninit| uninit.write(vec![i; 4]),
+///     );
+/// }
+/// assert_eq!(*reader.read_newest(), [3; 4]);
+/// ````
+pub fn new_write_init<T>(init: T) -> (InitWriter<T>, Reader<T>) {
+    // The pair itself never creates buffers, see `InitWriter::next_buffer`.
+    let (writer, reader) = TripleBufferBuilder::new(init).finish();
+    (InitWriter { writer }, reader)
+}
+
+/// Write side of a pair created with `new_write_init`.
+pub struct InitWriter<T> {
+    writer: Writer<T>,
+}
+
+/// A new buffer that has not been initialized yet.
+///
+/// Passed to the `init_op` of `InitWriter::write`, which has to turn it
+/// into the `InitBuf` it returns, proving that the buffer got initialized.
+pub struct UninitBuf<'a, T> {
+    slot: &'a mut MaybeUninit<T>,
+    _invariant: PhantomData<fn(&'a ()) -> &'a ()>,
+}
+
+/// Proof that the buffer of an `UninitBuf` has been initialized.
+pub struct InitBuf<'a, T> {
+    _slot: PhantomData<&'a mut T>,
+    _invariant: PhantomData<fn(&'a ()) -> &'a ()>,
+}
+
+impl<'a, T> UninitBuf<'a, T> {
+    /// Initialize the buffer with `value`.
+    pub fn write(self, value: T) -> InitBuf<'a, T> {
+        self.slot.write(value);
+        // SAFETY: The buffer was just initialized.
+        unsafe { self.assume_init() }
+    }
+
+    /// Get a pointer to the buffer, for initializing it in place.
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.slot.as_mut_ptr()
+    }
+
+    /// Claim that the buffer has been initialized through `as_mut_ptr`.
+    ///
+    /// # Safety
+    /// The buffer must contain a valid `T`.
+    pub unsafe fn assume_init(self) -> InitBuf<'a, T> {
+        InitBuf {
+            _slot: PhantomData,
+            _invariant: PhantomData,
+        }
+    }
+}
+
+impl<T> InitWriter<T> {
+    /// Write the next state into the buffer.
+    ///
+    /// If an unused buffer is available, this calls `write_op`
+    /// exactly like `Writer::write_new`. Otherwise, a new
+    /// uninitialized buffer gets allocated and passed to `init_op`
+    /// instead, which has to initialize it with the new state.
+    /// After that, it gets reused like any other buffer.
+    ///
+    /// Both closures receive the previous state as first argument.
+    pub fn write(
+        &mut self,
+        write_op: impl FnOnce(&T, &mut T),
+        init_op: impl for<'a> FnOnce(&T, UninitBuf<'a, T>) -> InitBuf<'a, T>,
+    ) {
+        let new_state = match self.writer.try_next_unused_buffer() {
+            Ok(mut new_state) => {
+                write_op(&self.writer.prev_buf, Buf::get_mut(&mut new_state).unwrap());
+                new_state
+            }
+            Err(PoolExhausted) => self.init_buffer(init_op),
+        };
+        self.writer.publish(new_state);
+    }
+
+    fn init_buffer(
+        &mut self,
+        init_op: impl for<'a> FnOnce(&T, UninitBuf<'a, T>) -> InitBuf<'a, T>,
+    ) -> Buf<T> {
+        let mut new_state = Buf::new_uninit();
+        let uninit = UninitBuf {
+            slot: Buf::get_mut(&mut new_state).unwrap(),
+            _invariant: PhantomData,
+        };
+        // The only way to get an `InitBuf` for this lifetime
+        // is through the `UninitBuf` of this buffer.
+        let InitBuf { .. } = init_op(&self.writer.prev_buf, uninit);
+        self.writer.created += 1;
+        // SAFETY: `init_op` proved that the buffer got initialized.
+        unsafe { new_state.assume_init() }
+    }
+
+    /// Check whether the `Reader` has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.writer.is_closed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::new_write_init;
+
+    /// A state that can not be cloned.
+    struct Big {
+        values: Box<[u64; 1024]>,
+        drops: Arc<AtomicUsize>,
+    }
+
+    impl Drop for Big {
+        fn drop(&mut self) {
+            self.drops.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    #[test]
+    fn test_fresh_buffers_get_initialized_once() {
+        let drops = Arc::new(AtomicUsize::new(0));
+        let init = Big {
+            values: Box::new([0; 1024]),
+            drops: drops.clone(),
+        };
+        let (mut w, mut r) = new_write_init(init);
+        let mut inits = 0;
+        for i in 1..=100 {
+            w.write(
+                |_, new| new.values.fill(i),
+                |prev, uninit| {
+                    inits += 1;
+                    uninit.write(Big {
+                        values: Box::new([i; 1024]),
+                        drops: prev.drops.clone(),
+                    })
+                },
+            );
+            if i % 3 == 0 {
+                assert!(r.read_newest().values.iter().all(|v| *v == i));
+            }
+        }
+        assert!(inits <= 2);
+        drop((w, r));
+        assert_eq!(drops.load(Ordering::Relaxed), inits + 1);
+    }
+
+    #[test]
+    fn test_init_in_place() {
+        let (mut w, mut r) = new_write_init([1u32; 256]);
+        w.write(
+            |_, _| unreachable!(),
+            |prev, mut uninit| {
+                let ptr = uninit.as_mut_ptr() as *mut u32;
+                for (i, v) in prev.iter().enumerate() {
+                    // SAFETY: `ptr` points to 256 writable `u32`s.
+                    unsafe { ptr.add(i).write(v + 1) };
+                }
+                // SAFETY: All elements were written.
+                unsafe { uninit.assume_init() }
+            },
+        );
+        assert_eq!(*r.read_newest(), [2; 256]);
+    }
+}
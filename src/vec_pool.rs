@@ -0,0 +1,214 @@
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as HashMap;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::{new_clone, Buf, Reader, Writer};
+
+fn key<T>(buf: &Buf<T>) -> usize {
+    Buf::as_ptr(buf) as usize
+}
+
+/// Create a new buffer pair for `Vec` states that keeps the
+/// capacities of its buffers uniform.
+///
+/// Every write reuses the capacity of the buffer it writes into, so
+/// without intervention a buffer that grew during a spike keeps its
+/// capacity forever while the others stay small. `VecWriter` can
+/// apply a capacity to all of them, and reports what they currently
+/// have with `VecWriter::capacities`.
+///
+/// # Example
+/// ```
+/// let (mut writer, mut reader) = simple_triple_buffer::new_vec_pool(vec![0u32; 4]);
+///
+/// writer.write(0..1_000_000);
+/// assert_eq!(reader.read_newest().len(), 1_000_000);
+///
+/// writer.shrink_to(64);
+/// for _ in 0..3 {
+///     writer.write(0..16);
+///     reader.read_newest();
+/// }
+/// assert!(writer.capacities().iter().all(|c| *c <= 64));
+/// ````
+pub fn new_vec_pool<Item: Clone>(initial: Vec<Item>) -> (VecWriter<Item>, Reader<Vec<Item>>) {
+    let (writer, reader) = new_clone(initial);
+    let mut capacities = HashMap::new();
+    capacities.insert(key(&writer.prev_buf), writer.prev_buf.capacity());
+    let writer = VecWriter {
+        writer,
+        policy: None,
+        capacities,
+    };
+    (writer, reader)
+}
+
+#[derive(Clone, Copy)]
+enum Policy {
+    Exact(usize),
+    AtMost(usize),
+}
+
+impl Policy {
+    /// Apply the policy to an empty buffer.
+    fn apply<Item>(self, buf: &mut Vec<Item>) {
+        match self {
+            Policy::Exact(n) => {
+                buf.shrink_to(n);
+                buf.reserve_exact(n);
+            }
+            Policy::AtMost(n) => buf.shrink_to(n),
+        }
+    }
+}
+
+/// Write side of a pair created with `new_vec_pool`.
+pub struct VecWriter<Item> {
+    writer: Writer<Vec<Item>>,
+    policy: Option<Policy>,
+    /// The capacity of each buffer when the writer last touched it, by address.
+    capacities: HashMap<usize, usize>,
+}
+
+impl<Item: Clone> VecWriter<Item> {
+    /// Publish a state consisting of `items`.
+    ///
+    /// The items get written into the capacity of an unused buffer.
+    pub fn write(&mut self, items: impl IntoIterator<Item = Item>) {
+        let mut new_state = self.next_buffer();
+        Buf::get_mut(&mut new_state).unwrap().extend(items);
+        self.publish(new_state);
+    }
+
+    /// Publish the previous state after modifying it with `update_op`,
+    /// see `Writer::write_update`.
+    pub fn write_update(&mut self, update_op: impl FnOnce(&mut Vec<Item>)) {
+        let mut new_state = self.next_buffer();
+        let new = Buf::get_mut(&mut new_state).unwrap();
+        new.extend_from_slice(&self.writer.prev_buf);
+        update_op(new);
+        self.publish(new_state);
+    }
+}
+
+impl<Item> VecWriter<Item> {
+    /// Give every buffer a capacity of `n`, growing
+    /// the smaller ones and shrinking the larger ones.
+    ///
+    /// This applies right away to unused buffers, and to the others
+    /// as soon as they get reused. It stays in effect for all
+    /// later writes, where a state longer than `n` temporarily
+    /// grows its buffer until the next time it gets reused.
+    pub fn set_capacity(&mut self, n: usize) {
+        self.set_policy(Policy::Exact(n));
+    }
+
+    /// Limit the capacity of every buffer to `n`, like `set_capacity`,
+    /// but without growing the smaller ones.
+    pub fn shrink_to(&mut self, n: usize) {
+        self.set_policy(Policy::AtMost(n));
+    }
+
+    /// Get the capacities of all buffers of the pair, in no particular order.
+    ///
+    /// Buffers held by the `Reader` are reported with
+    /// the capacity they had when they were published.
+    pub fn capacities(&self) -> Vec<usize> {
+        self.capacities.values().copied().collect()
+    }
+
+    /// Check whether the `Reader` has been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.writer.is_closed()
+    }
+
+    fn set_policy(&mut self, policy: Policy) {
+        self.policy = Some(policy);
+        let mut unused = Vec::new();
+        while let Some(buf) = self.writer.unused_bufs_rx.try_recv() {
+            unused.push(buf);
+        }
+        for mut buf in unused {
+            let vec = Buf::get_mut(&mut buf).unwrap();
+            vec.clear();
+            policy.apply(vec);
+            self.capacities.insert(key(&buf), buf.capacity());
+            self.writer.recycle(buf);
+        }
+    }
+
+    /// Get an empty buffer with the capacity policy applied.
+    fn next_buffer(&mut self) -> Buf<Vec<Item>> {
+        let mut buf = self.writer.next_unused_buffer();
+        let vec = Buf::get_mut(&mut buf).unwrap();
+        vec.clear();
+        if let Some(policy) = self.policy {
+            policy.apply(vec);
+        }
+        buf
+    }
+
+    fn publish(&mut self, new_state: Buf<Vec<Item>>) {
+        self.capacities
+            .insert(key(&new_state), new_state.capacity());
+        self.writer.publish(new_state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::new_vec_pool;
+
+    #[test]
+    fn test_write_reuses_capacity() {
+        let (mut w, mut r) = new_vec_pool(Vec::with_capacity(64));
+        for i in 0..10u32 {
+            w.write(0..i);
+            assert_eq!(*r.read_newest(), (0..i).collect::<Vec<_>>());
+        }
+        w.write_update(|v| v.push(42));
+        assert_eq!(r.read_newest().last(), Some(&42));
+        assert!(w.capacities().len() <= 3);
+    }
+
+    #[test]
+    fn test_shrink_after_spike() {
+        let (mut w, mut r) = new_vec_pool(vec![0u8; 8]);
+        w.write(vec![1; 100_000]);
+        r.read_newest();
+        w.write(0..8);
+        r.read_newest();
+        assert!(w.capacities().iter().any(|c| *c >= 100_000));
+
+        w.shrink_to(32);
+        for _ in 0..4 {
+            w.write(0..8);
+            r.read_newest();
+        }
+        let capacities = w.capacities();
+        assert!(capacities.iter().all(|c| *c <= 32), "{:?}", capacities);
+    }
+
+    #[test]
+    fn test_set_capacity_is_uniform() {
+        let (mut w, mut r) = new_vec_pool(Vec::<u64>::new());
+        // Grow three buffers to different capacities.
+        for n in [10, 1000, 100] {
+            w.write(0..n);
+            r.read_newest();
+        }
+        w.set_capacity(256);
+        for _ in 0..4 {
+            w.write(0..4);
+            r.read_newest();
+        }
+        let capacities = w.capacities();
+        assert!(
+            capacities.iter().all(|c| *c >= 256 && *c < 1000),
+            "{:?}",
+            capacities
+        );
+    }
+}
@@ -0,0 +1,124 @@
+//! Versions of published states.
+//!
+//! Every publish increments the version of the pair, starting from 0 for
+//! the initial state. Under `std`, the version gets stamped next to the
+//! publish time in the slot, so readers learn the exact version of each
+//! state they pick up, whichever intermediate ones they skipped.
+
+use crate::{Reader, Writer};
+
+/// What a publish did, returned by `Writer::write_new` and friends.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublishReceipt {
+    /// The version of the published state, which is what
+    /// `Reader::version` returns once a reader picked it up.
+    pub version: u64,
+    /// Whether a new buffer had to be created for it.
+    pub allocated: bool,
+}
+
+impl<T> Writer<T> {
+    /// Get the version of the last published state,
+    /// which is the number of states published so far.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, _reader) = simple_triple_buffer::new_clone(0);
+    /// assert_eq!(writer.version(), 0);
+    ///
+    /// writer.write_new(|_, new| *new = 1);
+    /// let receipt = writer.write_new(|_, new| *new = 2);
+    /// assert_eq!(receipt.version, 2);
+    /// assert_eq!(writer.version(), 2);
+    /// ````
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Describe the publish that just happened, which started
+    /// out with `created` buffers.
+    pub(crate) fn receipt(&self, created: usize) -> PublishReceipt {
+        PublishReceipt {
+            version: self.version,
+            allocated: self.created > created,
+        }
+    }
+}
+
+impl<T> Reader<T> {
+    /// Get the version of the state last returned by `read_newest`,
+    /// see `Writer::version`.
+    ///
+    /// Only available with the `std` feature.
+    ///
+    /// # Example
+    /// ```
+    /// let (mut writer, mut reader) = simple_triple_buffer::new_clone(0);
+    /// writer.write_new(|_, new| *new = 1);
+    /// let receipt = writer.write_new(|_, new| *new = 2);
+    ///
+    /// reader.read_newest();
+    /// assert_eq!(reader.version(), receipt.version);
+    /// ````
+    #[cfg(feature = "std")]
+    pub fn version(&self) -> u64 {
+        self.prev_version
+    }
+}
+
+#[cfg(all(test, feature = "std", not(loom)))]
+mod tests {
+    use core::sync::atomic::Ordering;
+
+    use crate::new_clone;
+
+    #[test]
+    fn test_receipts_match_readers() {
+        let (mut w, r) = new_clone(0u64);
+        let readers: Vec<_> = (0..3)
+            .map(|_| {
+                let mut r = r.clone();
+                std::thread::spawn(move || {
+                    while !r.is_disconnected() {
+                        let state = *r.read_newest();
+                        // Every state is its own version.
+                        assert_eq!(r.version(), state);
+                    }
+                })
+            })
+            .collect();
+        for i in 1..=100_000 {
+            let receipt = if i % 2 == 0 {
+                w.write_update(|v| *v = i)
+            } else {
+                w.write_new(|_, new| *new = i)
+            };
+            assert_eq!(receipt.version, i);
+        }
+        drop(w);
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_allocations_and_new_readers() {
+        let (mut w, mut r) = new_clone(0u32);
+        let allocated: Vec<_> = (1..=10)
+            .map(|i| {
+                let receipt = w.write_new(|_, new| *new = i);
+                if i < 10 {
+                    r.read_newest();
+                }
+                receipt.allocated
+            })
+            .collect();
+        assert!(allocated[0] && !allocated[9]);
+        assert_eq!(allocated.iter().filter(|a| **a).count(), w.created - 1);
+        // Clones start at the state of the reader, new readers at the newest.
+        assert_eq!(r.clone().version(), 9);
+        w.read_update.shared.readers.fetch_add(1, Ordering::AcqRel);
+        assert_eq!(w.new_reader().version(), 10);
+    }
+}
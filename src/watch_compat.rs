@@ -0,0 +1,222 @@
+//! Wrappers with the vocabulary of `tokio::sync::watch`.
+//!
+//! `channel` returns a `Sender` and `Receiver` with the method names of
+//! the watch channel, so code written against it carries over, without
+//! needing an async runtime. They are thin wrappers around `Writer` and
+//! `Reader`, and the differences show up as follows:
+//!
+//! - Sending takes `&mut self`, as there is only one writer. It does not
+//!   wait for any lock, though, and neither does `Receiver::borrow`.
+//! - `Receiver::borrow` marks the state it returns as seen, like
+//!   `borrow_and_update` does for watch channels: it returns the newest
+//!   state by picking it up, and readers only ever see states they
+//!   picked up. The reference it returns does not hold a lock.
+//! - `Sender::send_if_modified` modifies a copy of the previous state,
+//!   which only gets published if the closure reports a change. Watch
+//!   channels modify the shared value in place, so receivers see
+//!   unreported modifications along with the next change, which can not
+//!   happen here.
+//! - `Receiver::changed` blocks the thread instead of being `async`.
+//!   `Reader::wait_for_async` fills that role with the `async` feature.
+//! - Like with watch channels, receivers only see the newest state, and
+//!   intermediate ones sent in between get coalesced.
+//!
+//! # Example
+//! ```
+//! use simple_triple_buffer::watch_compat;
+//!
+//! let (mut tx, mut rx) = watch_compat::channel(0);
+//! assert_eq!(rx.has_changed(), Ok(false));
+//!
+//! tx.send(1).unwrap();
+//! tx.send_modify(|state| *state += 1);
+//! assert!(!tx.send_if_modified(|_| false));
+//! assert_eq!(rx.has_changed(), Ok(true));
+//! assert_eq!(*rx.borrow(), 2);
+//! assert_eq!(rx.has_changed(), Ok(false));
+//!
+//! drop(rx);
+//! assert!(tx.send(3).is_err());
+//! ````
+
+pub use crate::error::SendError;
+use crate::{new_clone, ReadError, Reader, WriteError, Writer};
+
+/// Create a new channel, starting out with `init`.
+pub fn channel<T: Clone>(init: T) -> (Sender<T>, Receiver<T>) {
+    let (writer, reader) = new_clone(init);
+    (Sender { writer }, Receiver { reader })
+}
+
+/// Sending half of a channel created with `channel`.
+pub struct Sender<T> {
+    writer: Writer<T>,
+}
+
+impl<T: Clone> Sender<T> {
+    /// Send a new state, or fail if all receivers are gone.
+    ///
+    /// Unlike `send_modify`, this does not send anything then.
+    pub fn send(&mut self, value: T) -> Result<(), SendError<T>> {
+        if self.writer.is_closed() {
+            return Err(SendError(value));
+        }
+        self.writer.write_new(|_, new| *new = value);
+        Ok(())
+    }
+
+    /// Modify a copy of the previous state, and send it.
+    pub fn send_modify(&mut self, modify: impl FnOnce(&mut T)) {
+        self.writer.write_update(modify);
+    }
+
+    /// Modify a copy of the previous state, and send it if `modify`
+    /// returns `true`, returning whether it did.
+    pub fn send_if_modified(&mut self, modify: impl FnOnce(&mut T) -> bool) -> bool {
+        let sent = self.writer.try_write_with(|old, new| {
+            new.clone_from(old);
+            if modify(new) {
+                Ok(())
+            } else {
+                Err(())
+            }
+        });
+        match sent {
+            Ok(()) => true,
+            Err(WriteError::User(())) => false,
+            Err(_) => unreachable!("new_clone can always create buffers"),
+        }
+    }
+}
+
+impl<T> Sender<T> {
+    /// Get the state sent last.
+    // Named after `watch::Sender::borrow`, which this stands in for.
+    #[allow(clippy::should_implement_trait)]
+    pub fn borrow(&self) -> &T {
+        &self.writer.prev_buf
+    }
+
+    /// Create a new receiver, which has seen the state sent last.
+    pub fn subscribe(&mut self) -> Receiver<T> {
+        Receiver {
+            reader: self.writer.subscribe(),
+        }
+    }
+
+    /// Get the number of receivers.
+    pub fn receiver_count(&self) -> usize {
+        self.writer.readers()
+    }
+
+    /// Check whether all receivers are gone.
+    pub fn is_closed(&self) -> bool {
+        self.writer.is_closed()
+    }
+
+    /// Get the underlying `Writer`.
+    pub fn into_writer(self) -> Writer<T> {
+        self.writer
+    }
+}
+
+/// Receiving half of a channel created with `channel`.
+///
+/// Cloning it creates another receiver, which has
+/// seen the same states as this one.
+pub struct Receiver<T> {
+    reader: Reader<T>,
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        Self {
+            reader: self.reader.clone(),
+        }
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Get the newest state, marking it as seen.
+    pub fn borrow(&mut self) -> &T {
+        self.reader.read_newest()
+    }
+
+    /// Get the newest state, marking it as seen, same as `borrow`.
+    pub fn borrow_and_update(&mut self) -> &T {
+        self.reader.read_newest()
+    }
+
+    /// Check whether a state has been sent that was not seen yet.
+    ///
+    /// Fails with `ReadError::Disconnected` once the
+    /// sender is gone and the last state was seen.
+    pub fn has_changed(&self) -> Result<bool, ReadError> {
+        // Checked first, as the sender might send a last state before going.
+        let disconnected = self.reader.is_disconnected();
+        match self.reader.has_update() {
+            false if disconnected => Err(ReadError::Disconnected),
+            changed => Ok(changed),
+        }
+    }
+
+    /// Block until a state has been sent that was not seen yet,
+    /// and mark it as seen.
+    ///
+    /// Fails with `ReadError::Disconnected` once the
+    /// sender is gone and the last state was seen.
+    ///
+    /// Only available with the `std` feature.
+    #[cfg(feature = "std")]
+    pub fn changed(&mut self) -> Result<(), ReadError> {
+        self.reader.read_blocking().map(drop)
+    }
+
+    /// Get the underlying `Reader`.
+    pub fn into_reader(self) -> Reader<T> {
+        self.reader
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::channel;
+    use crate::ReadError;
+
+    #[test]
+    fn test_changed_across_threads() {
+        let (mut tx, mut rx) = channel(0u32);
+        let sender = std::thread::spawn(move || {
+            for i in 1..=100 {
+                tx.send_modify(|state| *state = i);
+            }
+            tx.send_if_modified(|state| {
+                *state += 1;
+                true
+            })
+        });
+        let mut last = 0;
+        while rx.changed().is_ok() {
+            let state = *rx.borrow();
+            assert!(state > last);
+            last = state;
+        }
+        assert!(sender.join().unwrap());
+        assert_eq!(last, 101);
+        assert_eq!(rx.has_changed(), Err(ReadError::Disconnected));
+    }
+
+    #[test]
+    fn test_subscribe() {
+        let (mut tx, rx) = channel(vec![1]);
+        tx.send(vec![1, 2]).unwrap();
+        let mut rx2 = tx.subscribe();
+        assert_eq!(tx.receiver_count(), 2);
+        assert_eq!(rx2.has_changed(), Ok(false));
+        assert_eq!(*rx2.borrow(), [1, 2]);
+        assert_eq!(*tx.borrow(), [1, 2]);
+        drop((rx, rx2));
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(vec![3]).err().map(|e| e.0), Some(vec![3]));
+    }
+}
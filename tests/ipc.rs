@@ -0,0 +1,94 @@
+#![cfg(feature = "ipc")]
+
+use std::process::{Child, Command};
+use std::time::{Duration, Instant};
+
+use simple_triple_buffer::ipc;
+
+type State = [u64; 64];
+
+const WRITES: u64 = 10_000;
+
+/// Set for the child process, to `reader:<name>` or `crash:<name>`.
+const CHILD_ENV: &str = "SIMPLE_TRIPLE_BUFFER_IPC_CHILD";
+
+fn spawn_child(role: &str, name: &str) -> Child {
+    Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "child", "--nocapture"])
+        .env(CHILD_ENV, format!("{}:{}", role, name))
+        .spawn()
+        .unwrap()
+}
+
+fn is_consistent(state: &State) -> bool {
+    state.iter().all(|v| *v == state[0])
+}
+
+/// The child half of the tests below, doing nothing when run directly.
+#[test]
+fn child() {
+    let Ok(role) = std::env::var(CHILD_ENV) else {
+        return;
+    };
+    match role.split_once(':').unwrap() {
+        ("reader", name) => {
+            let mut reader = ipc::open::<State>(name).unwrap();
+            let mut last = 0;
+            loop {
+                let disconnected = reader.is_disconnected();
+                let state = reader.read_newest();
+                assert!(is_consistent(state));
+                assert!(state[0] >= last);
+                last = state[0];
+                if disconnected {
+                    break;
+                }
+            }
+            assert_eq!(last, WRITES);
+        }
+        ("crash", name) => {
+            let mut writer = ipc::create::<State>(name, [0; 64]).unwrap();
+            for i in 1..=WRITES {
+                writer.write([i; 64]);
+            }
+            writer.write_new(|_, new| {
+                new[..32].fill(u64::MAX);
+                // Exit without running any destructors.
+                std::process::exit(0);
+            });
+        }
+        (role, _) => panic!("unknown role {}", role),
+    }
+}
+
+#[test]
+fn test_reader_in_child_process() {
+    let name = format!("test-reader-{}", std::process::id());
+    let mut writer = ipc::create::<State>(&name, [0; 64]).unwrap();
+    let mut child = spawn_child("reader", &name);
+
+    let start = Instant::now();
+    while !writer.has_reader() {
+        assert!(child.try_wait().unwrap().is_none(), "child exited early");
+        assert!(start.elapsed() < Duration::from_secs(30));
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    for _ in 0..WRITES {
+        writer.write_new(|old, new| *new = [old[0] + 1; 64]);
+    }
+    drop(writer);
+    assert!(child.wait().unwrap().success());
+    ipc::remove(&name).unwrap();
+}
+
+#[test]
+fn test_writer_dies_mid_write() {
+    let name = format!("test-crash-{}", std::process::id());
+    assert!(spawn_child("crash", &name).wait().unwrap().success());
+
+    let mut reader = ipc::open::<State>(&name).unwrap();
+    assert!(!reader.is_disconnected());
+    assert_eq!(*reader.read_newest(), [WRITES; 64]);
+    assert!(!reader.has_update());
+    ipc::remove(&name).unwrap();
+}
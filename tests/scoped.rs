@@ -0,0 +1,69 @@
+//! Pairs over state types that borrow from the stack,
+//! shared between scoped threads.
+
+use simple_triple_buffer::{new_clone, new_scoped};
+
+#[derive(Clone)]
+struct Frame<'a> {
+    pixels: &'a [u8],
+    index: usize,
+}
+
+#[test]
+fn test_borrowed_state() {
+    let data: Vec<u8> = (0..64).collect();
+    let data = &data;
+    std::thread::scope(|s| {
+        let (mut w, mut r) = new_clone(Frame {
+            pixels: data,
+            index: 0,
+        });
+        s.spawn(move || {
+            for i in 0..1000 {
+                w.write_new(|_, new| {
+                    *new = Frame {
+                        pixels: &data[i % 64..],
+                        index: i,
+                    }
+                });
+            }
+        });
+        s.spawn(move || {
+            while !r.is_disconnected() {
+                let frame = r.read_newest();
+                assert_eq!(frame.pixels.len(), 64 - frame.index % 64);
+            }
+        });
+    });
+}
+
+#[test]
+fn test_borrowing_clone_function() {
+    let data: Vec<u8> = (0..64).collect();
+    let blank = [0u8; 64];
+    std::thread::scope(|s| {
+        let init = Frame {
+            pixels: &data,
+            index: 0,
+        };
+        // Starts every new buffer out blank, borrowing from the stack.
+        let (mut w, mut r) = new_scoped(init, |prev: &Frame<'_>| Frame {
+            pixels: &blank,
+            index: prev.index,
+        });
+        s.spawn(move || {
+            for i in 1..=1000 {
+                w.write_update(|frame| frame.index = i);
+            }
+        });
+        s.spawn(move || {
+            let mut last = 0;
+            while !r.is_disconnected() {
+                let frame = r.read_newest();
+                assert!(frame.index >= last);
+                last = frame.index;
+            }
+            assert_eq!(r.read_newest().index, 1000);
+        });
+    });
+}
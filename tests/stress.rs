@@ -0,0 +1,43 @@
+//! Short runs of the `stress` binary, which checks the invariants
+//! of a pair under load itself.
+#![cfg(feature = "stress")]
+
+use std::process::Command;
+
+fn stress(args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_stress"))
+        .args(["--duration", "0.2", "--payload", "64"])
+        .args(args)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "{:?}: {}", args, output.status);
+    assert!(stdout.contains("published:"), "{}", stdout);
+}
+
+#[test]
+fn test_writer_dropped_first() {
+    stress(&["--readers", "3"]);
+    stress(&["--backend", "mutex"]);
+}
+
+#[test]
+fn test_readers_dropped_first() {
+    stress(&["--readers", "3", "--readers-first"]);
+    stress(&["--backend", "mutex", "--readers-first"]);
+}
+
+#[test]
+fn test_throttled() {
+    stress(&["--write-rate", "1000", "--read-rate", "100", "--timestamps"]);
+}
+
+#[test]
+fn test_bad_options() {
+    let status = Command::new(env!("CARGO_BIN_EXE_stress"))
+        .args(["--backend", "mutex", "--readers", "2"])
+        .output()
+        .unwrap()
+        .status;
+    assert!(!status.success());
+}